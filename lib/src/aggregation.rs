@@ -0,0 +1,60 @@
+//! Aggregation of independently proven offers.
+//!
+//! Offer batches are proven in one guest run, which needs every offer up front. Offers proven
+//! separately, by different makers and at different times, can instead be aggregated: the guest
+//! verifies each compressed proof with `verify_sp1_proof` and commits an [`AggregatedOfferProofs`]
+//! listing the verification key and public values digest of every proof. A relayer then posts a
+//! single Groth16 proof of the aggregation in place of one proof per offer.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::hash::hash;
+
+use crate::zk_offers::PublicValuesStruct;
+
+/// The digest SP1 computes over the bytes a program commits, their SHA-256 hash.
+pub fn committed_values_digest(public_values: &[u8]) -> [u8; 32] {
+    hash(public_values).to_bytes()
+}
+
+/// One proof verified by the aggregation.
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize, serde::Serialize)]
+pub struct AggregatedProof {
+    /// Hash of the verification key of the program that produced the proof, as the eight words
+    /// `verify_sp1_proof` takes.
+    pub vkey: [u32; 8],
+    /// [`committed_values_digest`] of the public values of the proof.
+    pub public_values_digest: [u8; 32],
+}
+
+impl AggregatedProof {
+    pub fn new(vkey: [u32; 8], public_values: &[u8]) -> Self {
+        Self {
+            vkey,
+            public_values_digest: committed_values_digest(public_values),
+        }
+    }
+}
+
+/// What the zkVM program commits when aggregating proofs, in the order they were verified.
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize, serde::Serialize)]
+pub struct AggregatedOfferProofs {
+    pub proofs: Vec<AggregatedProof>,
+}
+
+impl AggregatedOfferProofs {
+    /// Whether the aggregation covers a proof by `vkey` committing `public_values`.
+    pub fn covers(&self, vkey: &[u32; 8], public_values: &[u8]) -> bool {
+        self.covers_digest(vkey, &committed_values_digest(public_values))
+    }
+
+    /// Whether the aggregation covers a proof by `vkey` of the offer `public_values`.
+    pub fn covers_offer(&self, vkey: &[u32; 8], public_values: &PublicValuesStruct) -> bool {
+        self.covers_digest(vkey, &public_values.public_values_digest())
+    }
+
+    fn covers_digest(&self, vkey: &[u32; 8], digest: &[u8; 32]) -> bool {
+        self.proofs
+            .iter()
+            .any(|proof| proof.vkey == *vkey && proof.public_values_digest == *digest)
+    }
+}
@@ -0,0 +1,68 @@
+//! Compute budget instructions for offer transactions.
+//!
+//! Fills that pay royalties run several creator transfers and PDA derivations, which can exceed
+//! the default compute unit limit. Clients measure the units a transaction needs, typically by
+//! simulating it, and prepend the instructions built here.
+
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+
+solana_program::declare_id!("ComputeBudget111111111111111111111111111111");
+
+/// Highest compute unit limit a transaction can request.
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// Request a compute unit limit of `units` for the transaction.
+pub fn set_compute_unit_limit(units: u32) -> Instruction {
+    let mut data = vec![2];
+    data.extend_from_slice(&units.to_le_bytes());
+    Instruction::new_with_bytes(id(), &data, vec![])
+}
+
+/// Pay `micro_lamports` per requested compute unit as a priority fee.
+pub fn set_compute_unit_price(micro_lamports: u64) -> Instruction {
+    let mut data = vec![3];
+    data.extend_from_slice(&micro_lamports.to_le_bytes());
+    Instruction::new_with_bytes(id(), &data, vec![])
+}
+
+/// The limit to request for a transaction that consumed `units_consumed` in simulation, with
+/// `margin_percent` of headroom.
+pub fn unit_limit_with_margin(units_consumed: u64, margin_percent: u32) -> u32 {
+    let units = units_consumed.saturating_mul(100 + margin_percent as u64) / 100;
+    units.min(MAX_COMPUTE_UNIT_LIMIT as u64) as u32
+}
+
+/// Prepend compute budget instructions to `instructions`.
+///
+/// Instructions already targeting the compute budget program are dropped, so this can be
+/// applied to a transaction built with placeholder values.
+pub fn with_compute_budget(
+    instructions: Vec<Instruction>,
+    unit_limit: Option<u32>,
+    micro_lamports: Option<u64>,
+) -> Vec<Instruction> {
+    unit_limit
+        .map(set_compute_unit_limit)
+        .into_iter()
+        .chain(micro_lamports.map(set_compute_unit_price))
+        .chain(
+            instructions
+                .into_iter()
+                .filter(|instruction| instruction.program_id != id()),
+        )
+        .collect()
+}
+
+/// The accounts an instruction list writes to, which is what recent priority fees are sampled
+/// for.
+pub fn writable_accounts(instructions: &[Instruction]) -> Vec<Pubkey> {
+    let mut accounts: Vec<Pubkey> = instructions
+        .iter()
+        .flat_map(|instruction| &instruction.accounts)
+        .filter(|meta| meta.is_writable)
+        .map(|meta| meta.pubkey)
+        .collect();
+    accounts.sort();
+    accounts.dedup();
+    accounts
+}
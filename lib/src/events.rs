@@ -0,0 +1,101 @@
+//! Structured events emitted by fills.
+//!
+//! Every fill logs one [`FillEvent`] with `sol_log_data`, in the layout Anchor's `emit!` uses:
+//! a single `Program data:` field holding an eight byte discriminator followed by the Borsh
+//! encoded event. [`parse_fill_events`] reads them back from the log messages of a transaction,
+//! keeping only events logged by the program itself so another program cannot forge a fill by
+//! logging the same bytes.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
+use solana_program::{log::sol_log_data, pubkey::Pubkey};
+
+/// `sha256("event:FillEvent")[..8]`, as Anchor derives event discriminators.
+pub const FILL_EVENT_DISCRIMINATOR: [u8; 8] = [13, 89, 41, 228, 105, 178, 45, 112];
+
+/// A fee paid out of a fill.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct FeePaid {
+    /// The creator or platform fee wallet, paid to its ATA for SPL fees.
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+/// A filled offer.
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct FillEvent {
+    /// The transfer authority of a stateless offer, or the escrow or routed offer account.
+    pub offer: Pubkey,
+    pub maker_wallet: Pubkey,
+    pub taker_wallet: Pubkey,
+    pub maker_mint: Pubkey,
+    /// The native mint when the taker paid in SOL.
+    pub taker_mint: Pubkey,
+    /// The sizes the offer committed to.
+    pub maker_size: u64,
+    pub taker_size: u64,
+    /// What the taker received of `maker_size` after fees.
+    pub taker_received: u64,
+    /// What the maker received of `taker_size` after fees.
+    pub maker_received: u64,
+    /// The mint fees were paid in, the mint of the leg opposite the NFT.
+    pub fee_mint: Pubkey,
+    /// Royalties, in metadata creator order.
+    pub creator_fees: Vec<FeePaid>,
+    pub platform_fee: Option<FeePaid>,
+}
+
+impl FillEvent {
+    /// The event as it appears base64 decoded in the `Program data:` log.
+    pub fn to_log_data(&self) -> Vec<u8> {
+        let mut data = FILL_EVENT_DISCRIMINATOR.to_vec();
+        data.extend(borsh::to_vec(self).expect("FillEvent serializes"));
+        data
+    }
+
+    /// Log the event from the program.
+    pub fn emit(&self) {
+        sol_log_data(&[&self.to_log_data()]);
+    }
+
+    /// Decode the data of one `Program data:` log, `None` if it is not a fill event.
+    pub fn from_log_data(data: &[u8]) -> Option<Self> {
+        let event = data.strip_prefix(&FILL_EVENT_DISCRIMINATOR)?;
+        Self::try_from_slice(event).ok()
+    }
+}
+
+/// Extract the fill events `program_id` logged from the log messages of a transaction.
+///
+/// The logs of a failed transaction can contain events of fills that were rolled back, so the
+/// caller should only index transactions that succeeded.
+pub fn parse_fill_events<S: AsRef<str>>(program_id: &Pubkey, logs: &[S]) -> Vec<FillEvent> {
+    let program_id = program_id.to_string();
+    // The programs currently executing, innermost last.
+    let mut invocations: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+    for log in logs {
+        let log = log.as_ref();
+        if let Some(data) = log.strip_prefix("Program data: ") {
+            if invocations.last() != Some(&program_id.as_str()) {
+                continue;
+            }
+            let event = data
+                .split_whitespace()
+                .next()
+                .and_then(|field| STANDARD.decode(field).ok())
+                .and_then(|data| FillEvent::from_log_data(&data));
+            events.extend(event);
+        } else if let Some(rest) = log.strip_prefix("Program ") {
+            let mut words = rest.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some(program), Some("invoke")) => invocations.push(program),
+                (Some(_), Some("success")) | (Some(_), Some("failed:")) => {
+                    invocations.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+    events
+}
@@ -0,0 +1,185 @@
+//! Client-side checks that a posted offer can still be filled.
+//!
+//! `process_accept_offer` trusts the maker's delegation as it finds it at fill time. A maker can
+//! revoke or replace the delegate, move tokens out or get frozen after advertising an offer, and
+//! a taker filling such an offer only finds out when the transaction fails. These checks let
+//! offer books drop stale offers before a taker ever sees them.
+
+use solana_program::{program_option::COption, program_pack::Pack, pubkey::Pubkey};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::state::{Account, AccountState};
+use std::fmt;
+
+use crate::zk_offers::{compute_offer_authority, PlatformFee};
+
+/// The terms of a posted offer, as committed in the transfer authority seeds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OfferTerms {
+    pub maker_wallet: Pubkey,
+    pub maker_mint: Pubkey,
+    pub taker_mint: Pubkey,
+    pub maker_size: u64,
+    pub taker_size: u64,
+    pub platform_fee: Option<PlatformFee>,
+}
+
+impl OfferTerms {
+    /// The token account the maker sells from, always the maker's ATA for `maker_mint`.
+    pub fn maker_src_account(&self) -> Pubkey {
+        get_associated_token_address(&self.maker_wallet, &self.maker_mint)
+    }
+
+    pub fn authority(&self, program_id: &Pubkey) -> (Pubkey, u8) {
+        compute_offer_authority(
+            program_id,
+            &self.maker_wallet,
+            &self.maker_mint,
+            &self.taker_mint,
+            self.maker_size,
+            self.taker_size,
+            self.platform_fee.as_ref(),
+        )
+    }
+}
+
+/// A reason an offer cannot be filled.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OfferIssue {
+    /// The maker source account does not exist.
+    MissingAccount,
+    /// The maker source account is not a valid SPL token account.
+    NotTokenAccount,
+    /// The maker source account is not the maker's ATA for `maker_mint`.
+    NotAssociatedTokenAccount { expected: Pubkey, actual: Pubkey },
+    /// The token account is owned by someone other than the maker.
+    WrongOwner { expected: Pubkey, actual: Pubkey },
+    /// The token account holds a different mint.
+    WrongMint { expected: Pubkey, actual: Pubkey },
+    /// The token account is frozen.
+    Frozen,
+    /// The delegate is not the offer's transfer authority.
+    WrongDelegate {
+        expected: Pubkey,
+        actual: Option<Pubkey>,
+    },
+    /// The delegated amount differs from `maker_size`.
+    DelegatedAmountMismatch { delegated: u64, maker_size: u64 },
+    /// The balance is below `maker_size`.
+    InsufficientBalance { amount: u64, maker_size: u64 },
+}
+
+impl fmt::Display for OfferIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OfferIssue::MissingAccount => write!(f, "maker source account does not exist"),
+            OfferIssue::NotTokenAccount => write!(f, "maker source account is not a token account"),
+            OfferIssue::NotAssociatedTokenAccount { expected, actual } => {
+                write!(f, "maker source account {} is not the ATA {}", actual, expected)
+            }
+            OfferIssue::WrongOwner { expected, actual } => {
+                write!(f, "token account owner is {}, expected {}", actual, expected)
+            }
+            OfferIssue::WrongMint { expected, actual } => {
+                write!(f, "token account mint is {}, expected {}", actual, expected)
+            }
+            OfferIssue::Frozen => write!(f, "token account is frozen"),
+            OfferIssue::WrongDelegate { expected, actual } => match actual {
+                Some(actual) => write!(f, "delegate is {}, expected {}", actual, expected),
+                None => write!(f, "no delegate, expected {}", expected),
+            },
+            OfferIssue::DelegatedAmountMismatch {
+                delegated,
+                maker_size,
+            } => write!(
+                f,
+                "delegated amount {} does not equal maker size {}",
+                delegated, maker_size
+            ),
+            OfferIssue::InsufficientBalance { amount, maker_size } => {
+                write!(f, "balance {} is below maker size {}", amount, maker_size)
+            }
+        }
+    }
+}
+
+/// The result of checking an offer against the maker's token account.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OfferHealth {
+    pub authority: Pubkey,
+    pub issues: Vec<OfferIssue>,
+}
+
+impl OfferHealth {
+    pub fn is_fillable(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Check whether an offer is fillable given the data of `maker_src_account`.
+///
+/// `maker_src_data` is `None` when the account does not exist.
+pub fn check_offer_health(
+    program_id: &Pubkey,
+    offer: &OfferTerms,
+    maker_src_account: &Pubkey,
+    maker_src_data: Option<&[u8]>,
+) -> OfferHealth {
+    let (authority, _) = offer.authority(program_id);
+    let mut issues = Vec::new();
+
+    let expected_ata = offer.maker_src_account();
+    if *maker_src_account != expected_ata {
+        issues.push(OfferIssue::NotAssociatedTokenAccount {
+            expected: expected_ata,
+            actual: *maker_src_account,
+        });
+    }
+
+    let account = match maker_src_data.map(Account::unpack) {
+        None => {
+            issues.push(OfferIssue::MissingAccount);
+            return OfferHealth { authority, issues };
+        }
+        Some(Err(_)) => {
+            issues.push(OfferIssue::NotTokenAccount);
+            return OfferHealth { authority, issues };
+        }
+        Some(Ok(account)) => account,
+    };
+
+    if account.owner != offer.maker_wallet {
+        issues.push(OfferIssue::WrongOwner {
+            expected: offer.maker_wallet,
+            actual: account.owner,
+        });
+    }
+    if account.mint != offer.maker_mint {
+        issues.push(OfferIssue::WrongMint {
+            expected: offer.maker_mint,
+            actual: account.mint,
+        });
+    }
+    if account.state == AccountState::Frozen {
+        issues.push(OfferIssue::Frozen);
+    }
+    if account.delegate != COption::Some(authority) {
+        issues.push(OfferIssue::WrongDelegate {
+            expected: authority,
+            actual: account.delegate.into(),
+        });
+    }
+    if account.delegated_amount != offer.maker_size {
+        issues.push(OfferIssue::DelegatedAmountMismatch {
+            delegated: account.delegated_amount,
+            maker_size: offer.maker_size,
+        });
+    }
+    if account.amount < offer.maker_size {
+        issues.push(OfferIssue::InsufficientBalance {
+            amount: account.amount,
+            maker_size: offer.maker_size,
+        });
+    }
+
+    OfferHealth { authority, issues }
+}
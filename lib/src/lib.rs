@@ -1,12 +1,19 @@
-use borsh::{BorshDeserialize, BorshSerialize};
-use solana_program::{
-    instruction::{AccountMeta, Instruction},
-    pubkey::Pubkey,
-    system_program, sysvar,
-};
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
 use spl_token::instruction::approve;
 use std::str::FromStr;
 
+pub mod aggregation;
+pub mod compute_budget;
+pub mod events;
+pub mod health;
+pub mod metadata;
+pub mod native;
+pub mod offer_batch;
+pub mod quote;
+pub mod sealed_bid;
+pub mod solvency;
+
 pub mod zk_offers {
     use super::*;
 
@@ -21,21 +28,206 @@ pub mod zk_offers {
         pub taker_dst_account: Pubkey,
         pub bump_seed: u8,
     }
-    #[derive(Clone, BorshSerialize, BorshDeserialize, Debug, serde::Serialize)]
+    #[derive(
+        Clone,
+        PartialEq,
+        Eq,
+        BorshSerialize,
+        BorshDeserialize,
+        BorshSchema,
+        Debug,
+        serde::Serialize,
+    )]
     pub struct PublicValuesStruct {
         pub maker_mint: Pubkey,
         pub taker_mint: Option<Pubkey>,
         pub is_native: bool,
         pub maker_size: u64,
         pub taker_size: Option<u64>,
+        pub platform_fee: Option<PlatformFee>,
+    }
+
+    /// What the zkVM program proves, read by the guest ahead of the inputs of that mode.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+    pub enum GuestMode {
+        /// Commit the [`PublicValuesStruct`] of an offer.
+        Offer,
+        /// Commit a [`SealedBidReveal`](crate::sealed_bid::SealedBidReveal) opening a bid
+        /// commitment.
+        SealedBidReveal,
+        /// Commit a [`MakerSolvency`](crate::solvency::MakerSolvency) from a snapshot of the
+        /// maker's token account.
+        Solvency,
+        /// Commit an [`OfferBatchRoot`](crate::offer_batch::OfferBatchRoot) over a batch of
+        /// offers.
+        OfferBatch,
+        /// Verify compressed proofs and commit their
+        /// [`AggregatedOfferProofs`](crate::aggregation::AggregatedOfferProofs).
+        Aggregate,
+    }
+
+    /// Highest platform fee the program accepts, in basis points.
+    pub const MAX_PLATFORM_FEE_BASIS_POINTS: u16 = 1000;
+
+    /// Verification key hash of the guest in `elf/`, the only program whose proofs the on-chain
+    /// program accepts for offer batches and sealed bids. The `vkey` script prints the hash of the
+    /// current ELF, which has to be copied here whenever the guest changes, and the script's
+    /// `vkey` test fails until it is.
+    pub const OFFER_VKEY_HASH: [u8; 32] = [0; 32];
+
+    /// A fee taken by the venue running the offer book, on top of creator royalties.
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        BorshSerialize,
+        BorshDeserialize,
+        BorshSchema,
+        serde::Serialize,
+    )]
+    pub struct PlatformFee {
+        /// Fee charged on the paying leg (0-`MAX_PLATFORM_FEE_BASIS_POINTS`).
+        pub basis_points: u16,
+        /// Wallet receiving the fee, paid to its ATA for SPL legs.
+        pub recipient: Pubkey,
+    }
+
+    impl PlatformFee {
+        /// The fee charging `basis_points` to `recipient`, `None` when nothing is charged so a zero
+        /// fee commits, seeds and fills the same way as no fee at all.
+        pub fn new(basis_points: u16, recipient: Pubkey) -> Option<Self> {
+            (basis_points > 0).then_some(PlatformFee {
+                basis_points,
+                recipient,
+            })
+        }
+
+        /// Whether the program accepts this fee schedule.
+        pub fn is_valid(&self) -> bool {
+            self.basis_points <= MAX_PLATFORM_FEE_BASIS_POINTS
+                && self.recipient != Pubkey::default()
+        }
+
+        /// The fee charged on `size`, `None` on overflow.
+        pub fn amount(&self, size: u64) -> Option<u64> {
+            (self.basis_points as u64)
+                .checked_mul(size)?
+                .checked_div(10000)
+        }
+    }
+
+    /// Length of the canonical encoding of [`PublicValuesStruct`].
+    pub const CANONICAL_PUBLIC_VALUES_LEN: usize = 118;
+
+    impl PublicValuesStruct {
+        /// The fixed-width encoding the guest commits for an offer.
+        ///
+        /// Borsh drops the bytes of a `None`, which moves every later field. Here each optional
+        /// field is a presence byte followed by its value, zeroed when absent, so every field sits
+        /// at the same offset:
+        ///
+        /// | Offset | Field |
+        /// |--------|-------|
+        /// | 0 | `maker_mint` |
+        /// | 32 | `taker_mint` presence, then the mint |
+        /// | 65 | `is_native` |
+        /// | 66 | `maker_size`, little-endian |
+        /// | 74 | `taker_size` presence, then the size, little-endian |
+        /// | 83 | `platform_fee` presence, then the basis points, little-endian, and the recipient |
+        pub fn to_canonical_bytes(&self) -> [u8; CANONICAL_PUBLIC_VALUES_LEN] {
+            let mut bytes = [0u8; CANONICAL_PUBLIC_VALUES_LEN];
+            bytes[0..32].copy_from_slice(self.maker_mint.as_ref());
+            if let Some(taker_mint) = &self.taker_mint {
+                bytes[32] = 1;
+                bytes[33..65].copy_from_slice(taker_mint.as_ref());
+            }
+            bytes[65] = self.is_native as u8;
+            bytes[66..74].copy_from_slice(&self.maker_size.to_le_bytes());
+            if let Some(taker_size) = self.taker_size {
+                bytes[74] = 1;
+                bytes[75..83].copy_from_slice(&taker_size.to_le_bytes());
+            }
+            if let Some(platform_fee) = &self.platform_fee {
+                bytes[83] = 1;
+                bytes[84..86].copy_from_slice(&platform_fee.basis_points.to_le_bytes());
+                bytes[86..118].copy_from_slice(platform_fee.recipient.as_ref());
+            }
+            bytes
+        }
+
+        /// Decode [`to_canonical_bytes`](Self::to_canonical_bytes), `None` unless `bytes` is
+        /// exactly a canonical encoding.
+        ///
+        /// Presence bytes other than 0 and 1 and non-zero bytes under an absent field are
+        /// rejected, so that every public values have a single encoding and a single digest.
+        pub fn from_canonical_bytes(bytes: &[u8]) -> Option<Self> {
+            let bytes: &[u8; CANONICAL_PUBLIC_VALUES_LEN] = bytes.try_into().ok()?;
+            let flag = |offset: usize, value_len: usize| -> Option<bool> {
+                let value = &bytes[offset + 1..offset + 1 + value_len];
+                match bytes[offset] {
+                    0 if value.iter().all(|byte| *byte == 0) => Some(false),
+                    1 => Some(true),
+                    _ => None,
+                }
+            };
+            let pubkey = |offset: usize| {
+                Pubkey::new_from_array(bytes[offset..offset + 32].try_into().unwrap())
+            };
+            let u64_at =
+                |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+
+            let is_native = match bytes[65] {
+                0 => false,
+                1 => true,
+                _ => return None,
+            };
+            Some(PublicValuesStruct {
+                maker_mint: pubkey(0),
+                taker_mint: flag(32, 32)?.then(|| pubkey(33)),
+                is_native,
+                maker_size: u64_at(66),
+                taker_size: flag(74, 8)?.then(|| u64_at(75)),
+                platform_fee: flag(83, 34)?.then(|| PlatformFee {
+                    basis_points: u16::from_le_bytes([bytes[84], bytes[85]]),
+                    recipient: pubkey(86),
+                }),
+            })
+        }
+
+        /// SHA-256 of the canonical encoding, the digest SP1 computes over the public values of an
+        /// offer proof.
+        pub fn public_values_digest(&self) -> [u8; 32] {
+            crate::aggregation::committed_values_digest(&self.to_canonical_bytes())
+        }
+
+        /// The terms of a fill of `maker_size` of `maker_mint` for `taker_size` of `taker_mint`,
+        /// with both legs committed and SOL taken as the native mint.
+        pub fn fill_terms(
+            maker_mint: Pubkey,
+            taker_mint: Pubkey,
+            maker_size: u64,
+            taker_size: u64,
+            platform_fee: Option<PlatformFee>,
+        ) -> Self {
+            PublicValuesStruct {
+                maker_mint,
+                taker_mint: Some(taker_mint),
+                is_native: taker_mint == spl_token::native_mint::id(),
+                maker_size,
+                taker_size: Some(taker_size),
+                platform_fee,
+            }
+        }
     }
 
     impl FromStr for PublicValuesStruct {
         type Err = String;
         fn from_str(s: &str) -> Result<Self, Self::Err> {
             let parts: Vec<&str> = s.split(',').collect();
-            if parts.len() != 5 {
-                return Err("Expected 5 comma-separated values".to_string());
+            if parts.len() != 5 && parts.len() != 7 {
+                return Err("Expected 5 or 7 comma-separated values".to_string());
             }
 
             let maker_mint = parts[0].parse::<Pubkey>().map_err(|e| e.to_string())?;
@@ -51,6 +243,14 @@ pub mod zk_offers {
             } else {
                 Some(parts[4].parse::<u64>().map_err(|e| e.to_string())?)
             };
+            let platform_fee = if parts.len() == 7 {
+                PlatformFee::new(
+                    parts[5].parse::<u16>().map_err(|e| e.to_string())?,
+                    parts[6].parse::<Pubkey>().map_err(|e| e.to_string())?,
+                )
+            } else {
+                None
+            };
 
             Ok(PublicValuesStruct {
                 maker_mint,
@@ -58,6 +258,7 @@ pub mod zk_offers {
                 is_native,
                 maker_size,
                 taker_size,
+                platform_fee,
             })
         }
     }
@@ -68,6 +269,25 @@ pub mod zk_offers {
         pub public_values: PublicValuesStruct,
     }
 
+    impl OfferStruct {
+        /// The accounts the offer was advertised with, labelled by their role in a fill.
+        pub fn accounts(&self) -> [(&'static str, Pubkey); 6] {
+            let private_offer = &self.private_offer;
+            [
+                ("maker_wallet", private_offer.maker_wallet),
+                ("taker_wallet", private_offer.taker_wallet),
+                ("maker_src_account", private_offer.maker_src_account),
+                ("maker_dst_account", private_offer.maker_dst_account),
+                ("taker_src_account", private_offer.taker_src_account),
+                ("taker_dst_account", private_offer.taker_dst_account),
+            ]
+        }
+
+        pub fn bump_seed(&self) -> u8 {
+            self.private_offer.bump_seed
+        }
+    }
+
     pub fn compute_offer_pda(
         program_id: &Pubkey,
         maker_wallet: &Pubkey,
@@ -88,6 +308,132 @@ pub mod zk_offers {
         pda
     }
 
+    /// Derive the transfer authority `process_accept_offer` signs with.
+    ///
+    /// The seeds hold the [`public_values_digest`](PublicValuesStruct::public_values_digest) of
+    /// the [`fill_terms`](PublicValuesStruct::fill_terms), so the authority only matches an offer
+    /// whose mints, sizes and platform fee are exactly the ones the maker delegated for.
+    pub fn compute_offer_authority(
+        program_id: &Pubkey,
+        maker_wallet: &Pubkey,
+        maker_mint: &Pubkey,
+        taker_mint: &Pubkey,
+        maker_size: u64,
+        taker_size: u64,
+        platform_fee: Option<&PlatformFee>,
+    ) -> (Pubkey, u8) {
+        find_offer_authority(
+            b"stateless_offer",
+            program_id,
+            maker_wallet,
+            maker_mint,
+            taker_mint,
+            maker_size,
+            taker_size,
+            platform_fee,
+        )
+    }
+
+    /// Derive the transfer authority of a collection-wide bid.
+    ///
+    /// The bid accepts any NFT that is a verified member of `collection`, which takes the place
+    /// of the taker mint in the digested terms.
+    pub fn compute_collection_offer_authority(
+        program_id: &Pubkey,
+        maker_wallet: &Pubkey,
+        maker_mint: &Pubkey,
+        collection: &Pubkey,
+        maker_size: u64,
+        taker_size: u64,
+        verified_creators_only: bool,
+        platform_fee: Option<&PlatformFee>,
+    ) -> (Pubkey, u8) {
+        find_offer_authority(
+            collection_offer_prefix(verified_creators_only),
+            program_id,
+            maker_wallet,
+            maker_mint,
+            collection,
+            maker_size,
+            taker_size,
+            platform_fee,
+        )
+    }
+
+    /// The first seed of a collection offer authority, which commits to whether royalties are
+    /// only paid to verified creators.
+    pub fn collection_offer_prefix(verified_creators_only: bool) -> &'static [u8] {
+        if verified_creators_only {
+            b"verified_collection_offer"
+        } else {
+            b"collection_offer"
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn find_offer_authority(
+        prefix: &[u8],
+        program_id: &Pubkey,
+        maker_wallet: &Pubkey,
+        maker_mint: &Pubkey,
+        taker_mint: &Pubkey,
+        maker_size: u64,
+        taker_size: u64,
+        platform_fee: Option<&PlatformFee>,
+    ) -> (Pubkey, u8) {
+        let terms_digest = PublicValuesStruct::fill_terms(
+            *maker_mint,
+            *taker_mint,
+            maker_size,
+            taker_size,
+            platform_fee.copied(),
+        )
+        .public_values_digest();
+        Pubkey::find_program_address(&[prefix, maker_wallet.as_ref(), &terms_digest], program_id)
+    }
+
+    /// Derive the escrow offer account holding the terms of an escrowed offer.
+    ///
+    /// Escrowed offers are keyed by a maker chosen nonce rather than their terms, so a maker can
+    /// run any number of them concurrently from the same token account.
+    pub fn compute_escrow_offer_address(
+        program_id: &Pubkey,
+        maker_wallet: &Pubkey,
+        nonce: u64,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[b"escrow_offer", maker_wallet.as_ref(), &nonce.to_le_bytes()],
+            program_id,
+        )
+    }
+
+    /// The vault of an escrowed offer, the escrow offer account's ATA for the maker mint.
+    pub fn compute_escrow_vault(escrow_offer: &Pubkey, maker_mint: &Pubkey) -> Pubkey {
+        spl_associated_token_account::get_associated_token_address(escrow_offer, maker_mint)
+    }
+
+    /// Derive the program-wide offer router.
+    ///
+    /// A maker delegates once to the router for the sum of their routed offers. Each routed offer
+    /// account then caps how much of that delegation its fill may consume, so any number of
+    /// offers can share one token account and its single delegate.
+    pub fn compute_offer_router(program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"offer_router"], program_id)
+    }
+
+    /// Derive the routed offer account recording the terms of one offer filled through the
+    /// router.
+    pub fn compute_routed_offer_address(
+        program_id: &Pubkey,
+        maker_wallet: &Pubkey,
+        nonce: u64,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[b"routed_offer", maker_wallet.as_ref(), &nonce.to_le_bytes()],
+            program_id,
+        )
+    }
+
     /// Approve token delegation to the PDA.
     pub fn approve_delegation(
         maker_wallet: &Pubkey,
@@ -107,38 +453,6 @@ pub mod zk_offers {
         .unwrap()
     }
 
-    /// Create an offer transaction for the taker.
-    pub fn create_offer_transaction(
-        program_id: &Pubkey,
-        offer: Vec<u8>,
-        pda: Pubkey,
-    ) -> Instruction {
-        let OfferStruct {
-            private_offer,
-            public_values,
-        } = OfferStruct::try_from_slice(&offer).expect("Failed to deserialize offer_info");
-
-        let accounts = vec![
-            AccountMeta::new(private_offer.maker_wallet, true),
-            AccountMeta::new(private_offer.taker_wallet, true),
-            AccountMeta::new(private_offer.maker_src_account, false),
-            AccountMeta::new(private_offer.maker_dst_account, false),
-            AccountMeta::new(private_offer.taker_src_account, false),
-            AccountMeta::new(private_offer.taker_dst_account, false),
-            AccountMeta::new_readonly(public_values.maker_mint, false),
-            AccountMeta::new_readonly(public_values.taker_mint.unwrap_or(Pubkey::default()), false),
-            AccountMeta::new_readonly(sysvar::rent::id(), false),
-            AccountMeta::new_readonly(system_program::id(), false),
-            AccountMeta::new_readonly(pda, false),
-        ];
-
-        Instruction {
-            program_id: *program_id,
-            accounts,
-            data: offer,
-        }
-    }
-
     /// Cancel the offer by revoking the delegation.
     pub fn cancel_delegation(
         maker_wallet: &Pubkey,
@@ -0,0 +1,154 @@
+//! The subset of the Metaplex Token Metadata account layout used by stateless offers.
+//!
+//! This is the layout `process_accept_offer` decodes royalties from, shared with clients so
+//! that quotes and account lists are derived from exactly what the program reads.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{instruction::AccountMeta, pubkey::Pubkey};
+use spl_associated_token_account::get_associated_token_address;
+
+use crate::quote::FeePayer;
+
+solana_program::declare_id!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct Metadata {
+    /// Account discriminator.
+    pub key: u8,
+    /// Address of the update authority.
+    pub update_authority: Pubkey,
+    /// Address of the mint.
+    pub mint: Pubkey,
+    /// Asset data.
+    pub data: Data,
+    /// Immutable, once flipped, all sales of this metadata are considered
+    /// secondary.
+    pub primary_sale_happened: bool,
+    /// Whether or not the data struct is mutable, default is not
+    pub is_mutable: bool,
+    /// nonce for easy calculation of editions, if present
+    pub edition_nonce: Option<u8>,
+    /// Token standard discriminator, `4` for programmable NFTs.
+    pub token_standard: Option<u8>,
+    /// Collection
+    pub collection: Option<Collection>,
+}
+
+impl Metadata {
+    /// Whether this asset is a verified member of `collection`.
+    pub fn is_verified_member_of(&self, collection: &Pubkey) -> bool {
+        matches!(&self.collection, Some(c) if c.verified && c.key == *collection)
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Default, PartialEq, Eq, Debug, Clone)]
+pub struct Data {
+    /// The name of the asset
+    pub name: String,
+    /// The symbol for the asset
+    pub symbol: String,
+    /// URI pointing to JSON representing the asset
+    pub uri: String,
+    /// Royalty basis points that goes to creators in secondary sales
+    /// (0-10000)
+    pub seller_fee_basis_points: u16,
+    /// Array of creators, optional
+    pub creators: Option<Vec<Creator>>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Eq, Hash)]
+pub struct Creator {
+    pub address: Pubkey,
+    pub verified: bool,
+    pub share: u8,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Eq, Hash)]
+pub struct Collection {
+    pub verified: bool,
+    pub key: Pubkey,
+}
+
+/// Decode metadata account data, ignoring the trailing fields this layout does not cover.
+pub fn decode_metadata(data: &[u8]) -> std::io::Result<Metadata> {
+    let mut data = data;
+    Metadata::deserialize(&mut data)
+}
+
+/// Derive the metadata account of `mint`.
+pub fn find_metadata_address(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"metadata", id().as_ref(), mint.as_ref()], &id())
+}
+
+/// The accounts `accept_offer_with_metadata` needs to pay royalties.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoyaltyAccounts {
+    pub metadata: Metadata,
+    /// The metadata PDA of the NFT leg.
+    pub metadata_key: Pubkey,
+    /// Bump of `metadata_key`, passed to the program so it does not search for it.
+    pub metadata_bump_seed: u8,
+    pub fee_payer: FeePayer,
+    /// The accounts following the metadata account, in the order `pay_creator_fees` reads them.
+    pub creator_accounts: Vec<AccountMeta>,
+}
+
+/// Resolve the royalty accounts of an offer from the raw metadata account data of its NFT leg.
+///
+/// Returns `None` when the data does not decode or describes neither `maker_mint` nor
+/// `taker_mint`.
+pub fn resolve_royalty_accounts(
+    metadata_data: &[u8],
+    maker_mint: &Pubkey,
+    taker_mint: &Pubkey,
+    is_native: bool,
+    verified_creators_only: bool,
+) -> Option<RoyaltyAccounts> {
+    let metadata = decode_metadata(metadata_data).ok()?;
+    let (fee_payer, fee_mint) = if metadata.mint == *maker_mint {
+        (FeePayer::Taker, taker_mint)
+    } else if metadata.mint == *taker_mint {
+        (FeePayer::Maker, maker_mint)
+    } else {
+        return None;
+    };
+    let (metadata_key, metadata_bump_seed) = find_metadata_address(&metadata.mint);
+    let creator_accounts =
+        creator_account_metas(&metadata, fee_mint, is_native, verified_creators_only);
+    Some(RoyaltyAccounts {
+        metadata,
+        metadata_key,
+        metadata_bump_seed,
+        fee_payer,
+        creator_accounts,
+    })
+}
+
+/// The creator accounts `pay_creator_fees` consumes for fees paid in `fee_mint`.
+///
+/// Native SOL fees go straight to each creator wallet. SPL fees go to each creator's ATA for
+/// `fee_mint`, which follows the creator wallet. Unverified creators are left out when
+/// `verified_creators_only` is set.
+pub fn creator_account_metas(
+    metadata: &Metadata,
+    fee_mint: &Pubkey,
+    is_native: bool,
+    verified_creators_only: bool,
+) -> Vec<AccountMeta> {
+    let mut accounts = Vec::new();
+    for creator in metadata.data.creators.iter().flatten() {
+        if verified_creators_only && !creator.verified {
+            continue;
+        }
+        if is_native {
+            accounts.push(AccountMeta::new(creator.address, false));
+        } else {
+            accounts.push(AccountMeta::new_readonly(creator.address, false));
+            accounts.push(AccountMeta::new(
+                get_associated_token_address(&creator.address, fee_mint),
+                false,
+            ));
+        }
+    }
+    accounts
+}
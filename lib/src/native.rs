@@ -0,0 +1,119 @@
+//! Helpers for offers whose maker leg is SOL.
+//!
+//! The program moves the maker leg with an SPL delegation, so a maker offering SOL holds it as
+//! wrapped SOL in their native mint ATA and delegates that to the offer authority. These helpers
+//! wrap and unwrap around the offer lifecycle so makers and takers never handle wSOL directly.
+
+use solana_program::{instruction::Instruction, pubkey::Pubkey, system_instruction};
+use spl_associated_token_account::{
+    get_associated_token_address, instruction::create_associated_token_account_idempotent,
+};
+use spl_token::{
+    instruction::{approve, close_account, revoke, sync_native},
+    native_mint,
+};
+
+/// The wrapped SOL account of `wallet`.
+pub fn wrapped_sol_account(wallet: &Pubkey) -> Pubkey {
+    get_associated_token_address(wallet, &native_mint::id())
+}
+
+/// Wrap `lamports` into the wrapped SOL account of `wallet`, creating it if needed.
+pub fn wrap_sol(wallet: &Pubkey, lamports: u64) -> Vec<Instruction> {
+    let wrapped = wrapped_sol_account(wallet);
+    vec![
+        create_associated_token_account_idempotent(
+            wallet,
+            wallet,
+            &native_mint::id(),
+            &spl_token::id(),
+        ),
+        system_instruction::transfer(wallet, &wrapped, lamports),
+        sync_native(&spl_token::id(), &wrapped).unwrap(),
+    ]
+}
+
+/// Close the wrapped SOL account of `wallet`, returning all of its lamports to `wallet`.
+pub fn unwrap_sol(wallet: &Pubkey) -> Instruction {
+    close_account(
+        &spl_token::id(),
+        &wrapped_sol_account(wallet),
+        wallet,
+        wallet,
+        &[],
+    )
+    .unwrap()
+}
+
+/// Post a SOL offer: wrap `maker_size` lamports and delegate them to the offer authority.
+///
+/// `authority` is the transfer authority derived with the native mint as maker mint.
+pub fn approve_native_delegation(
+    maker_wallet: &Pubkey,
+    authority: &Pubkey,
+    maker_size: u64,
+) -> Vec<Instruction> {
+    let mut instructions = wrap_sol(maker_wallet, maker_size);
+    instructions.push(
+        approve(
+            &spl_token::id(),
+            &wrapped_sol_account(maker_wallet),
+            authority,
+            maker_wallet,
+            &[],
+            maker_size,
+        )
+        .unwrap(),
+    );
+    instructions
+}
+
+/// Cancel a SOL offer: revoke the delegation and unwrap the remaining balance.
+pub fn cancel_native_delegation(maker_wallet: &Pubkey) -> Vec<Instruction> {
+    vec![
+        revoke(
+            &spl_token::id(),
+            &wrapped_sol_account(maker_wallet),
+            maker_wallet,
+            &[],
+        )
+        .unwrap(),
+        unwrap_sol(maker_wallet),
+    ]
+}
+
+/// Instructions a taker runs before filling a SOL offer.
+///
+/// The taker receives wrapped SOL, and creators and the platform fee recipient are paid in
+/// wrapped SOL, so their accounts are created with the taker as payer. Follow the fill with
+/// [`finish_native_fill`].
+pub fn prepare_native_fill(taker_wallet: &Pubkey, fee_recipients: &[Pubkey]) -> Vec<Instruction> {
+    std::iter::once(taker_wallet)
+        .chain(fee_recipients)
+        .map(|wallet| {
+            create_associated_token_account_idempotent(
+                taker_wallet,
+                wallet,
+                &native_mint::id(),
+                &spl_token::id(),
+            )
+        })
+        .collect()
+}
+
+/// Instructions a taker runs after filling a SOL offer.
+///
+/// When the taker's wrapped SOL account did not exist before the fill, [`prepare_native_fill`]
+/// created it for the proceeds alone, and closing it unwraps them. An account that already
+/// existed may hold wrapped SOL of the taker's own, so it is left open and the proceeds stay
+/// wrapped.
+pub fn finish_native_fill(
+    taker_wallet: &Pubkey,
+    wrapped_account_existed: bool,
+) -> Vec<Instruction> {
+    if wrapped_account_existed {
+        vec![]
+    } else {
+        vec![unwrap_sol(taker_wallet)]
+    }
+}
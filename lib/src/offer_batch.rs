@@ -0,0 +1,143 @@
+//! Offer batches proven in one guest run.
+//!
+//! Proving offers one at a time costs a full proof each. The guest can instead read a batch of
+//! offers, check each of them, and commit the Merkle root of their leaves as an
+//! [`OfferBatchRoot`]. The root is posted on-chain once, with a Groth16 proof of the batch, and
+//! each offer is then filled with a proof of inclusion against it.
+//!
+//! Leaves and nodes are hashed with distinct prefixes, so a node can never pass for an offer, and
+//! the two children of a node are hashed in sorted order, so an inclusion proof is only the list
+//! of siblings from the leaf up. A node without a sibling moves up a level unchanged.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{hash::hashv, pubkey::Pubkey};
+
+use crate::zk_offers::PublicValuesStruct;
+
+/// What the zkVM program commits when proving a batch of offers.
+///
+/// The on-chain program verifies the batch proof against the Borsh encoding of these values.
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize, serde::Serialize)]
+pub struct OfferBatchRoot {
+    /// Merkle root of the [`offer_leaf`] of every offer in the batch.
+    pub root: [u8; 32],
+    pub offer_count: u32,
+}
+
+/// Check that batched terms fully describe both legs, as the guest does for every offer.
+///
+/// Batched offers are filled from their committed terms alone, so the taker mint and size must be
+/// committed, and the platform fee must be one the program accepts.
+pub fn check_batch_offer(public_values: &PublicValuesStruct) -> Result<(), &'static str> {
+    let taker_mint_committed = match public_values.taker_mint {
+        Some(taker_mint) => !public_values.is_native || taker_mint == spl_token::native_mint::id(),
+        None => public_values.is_native,
+    };
+    if !taker_mint_committed || public_values.taker_size.is_none() {
+        return Err("Batched offers need a taker mint and size");
+    }
+    if public_values.maker_size == 0 {
+        return Err("Batched offers need a maker size");
+    }
+    if public_values.is_native && public_values.maker_mint == spl_token::native_mint::id() {
+        return Err("Maker and taker cannot both trade SOL");
+    }
+    if matches!(&public_values.platform_fee, Some(fee) if !fee.is_valid()) {
+        return Err("Platform fee exceeds the program cap");
+    }
+    Ok(())
+}
+
+/// The leaf of an offer, the hash of its [`PublicValuesStruct::public_values_digest`].
+pub fn offer_leaf(public_values: &PublicValuesStruct) -> [u8; 32] {
+    digest_leaf(&public_values.public_values_digest())
+}
+
+/// The leaf of the offer whose public values digest is `public_values_digest`.
+pub fn digest_leaf(public_values_digest: &[u8; 32]) -> [u8; 32] {
+    hashv(&[&[0], public_values_digest]).to_bytes()
+}
+
+fn hash_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[&[1], left, right]).to_bytes()
+}
+
+/// The levels of the tree over `leaves`, from the leaves up to the root.
+fn merkle_levels(leaves: &[[u8; 32]]) -> Vec<Vec<[u8; 32]>> {
+    let mut levels = vec![leaves.to_vec()];
+    while levels[levels.len() - 1].len() > 1 {
+        let next = levels[levels.len() - 1]
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => hash_pair(left, right),
+                [single] => *single,
+                _ => unreachable!(),
+            })
+            .collect();
+        levels.push(next);
+    }
+    levels
+}
+
+/// The Merkle root of `leaves`, `None` for an empty batch.
+pub fn merkle_root(leaves: &[[u8; 32]]) -> Option<[u8; 32]> {
+    if leaves.is_empty() {
+        return None;
+    }
+    merkle_levels(leaves).last().map(|level| level[0])
+}
+
+/// The siblings proving that `leaves[index]` is part of [`merkle_root`]`(leaves)`.
+pub fn merkle_proof(leaves: &[[u8; 32]], mut index: usize) -> Option<Vec<[u8; 32]>> {
+    if index >= leaves.len() {
+        return None;
+    }
+    let mut proof = Vec::new();
+    for level in merkle_levels(leaves) {
+        if let Some(sibling) = level.get(index ^ 1) {
+            proof.push(*sibling);
+        }
+        index /= 2;
+    }
+    Some(proof)
+}
+
+/// Whether `proof` leads from `leaf` to `root`.
+pub fn verify_merkle_proof(root: &[u8; 32], leaf: &[u8; 32], proof: &[[u8; 32]]) -> bool {
+    let computed = proof
+        .iter()
+        .fold(*leaf, |node, sibling| hash_pair(&node, sibling));
+    computed == *root
+}
+
+/// Derive the account of a posted batch, keyed by the program that proved it and its root.
+pub fn compute_offer_batch_address(
+    program_id: &Pubkey,
+    vkey_hash: &[u8; 32],
+    root: &[u8; 32],
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"offer_batch", vkey_hash, root], program_id)
+}
+
+/// Derive the transfer authority a maker delegates a batched offer to.
+///
+/// The root of the batch is part of the seeds, next to the digest of the offer's terms, so the
+/// delegation is only filled through `AcceptBatchedOffer` against that batch and never by a plain
+/// `AcceptOffer` of the same terms.
+pub fn compute_batched_offer_authority(
+    program_id: &Pubkey,
+    maker_wallet: &Pubkey,
+    root: &[u8; 32],
+    public_values: &PublicValuesStruct,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"batched_offer",
+            maker_wallet.as_ref(),
+            root,
+            &public_values.public_values_digest(),
+        ],
+        program_id,
+    )
+}
@@ -0,0 +1,166 @@
+//! Fee quotes for accepting an offer.
+//!
+//! `pay_creator_fees` charges `seller_fee_basis_points * size / 10000` on the leg paying for an
+//! NFT, splits it by creator share and returns any rounding dust to the counterparty. These
+//! functions reproduce that arithmetic exactly so a taker knows the net amounts before sending.
+
+use solana_program::pubkey::Pubkey;
+use std::fmt;
+
+use crate::{metadata::Metadata, zk_offers::PlatformFee};
+
+/// Which side of the offer pays the royalties.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeePayer {
+    /// The metadata belongs to the maker mint, royalties come out of `taker_size`.
+    Taker,
+    /// The metadata belongs to the taker mint, royalties come out of `maker_size`.
+    Maker,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuoteError {
+    /// The on-chain arithmetic overflows, so the fill would fail with `NumericalOverflow`.
+    NumericalOverflow,
+    /// The maker leg is native SOL and a creator fee is due, which the program rejects.
+    MakerCannotPayNative,
+}
+
+impl fmt::Display for QuoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuoteError::NumericalOverflow => write!(f, "NumericalOverflow"),
+            QuoteError::MakerCannotPayNative => write!(f, "Maker cannot pay with native SOL"),
+        }
+    }
+}
+
+impl std::error::Error for QuoteError {}
+
+/// The amount paid to one creator.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CreatorPayout {
+    pub address: Pubkey,
+    pub amount: u64,
+}
+
+/// Royalties charged on one leg.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeeQuote {
+    /// `seller_fee_basis_points * size / 10000`.
+    pub total_fee: u64,
+    /// Payouts in metadata creator order, one per paid creator including zero amounts.
+    pub payouts: Vec<CreatorPayout>,
+    /// Part of `total_fee` not paid to any creator.
+    pub dust: u64,
+    /// Amount transferred to the counterparty, `size - total_fee + dust`.
+    pub remaining_size: u64,
+}
+
+/// The net result of accepting an offer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AcceptQuote {
+    pub fee_payer: FeePayer,
+    pub fees: FeeQuote,
+    /// Platform fee charged on the same leg as the royalties.
+    pub platform_fee: u64,
+    /// Amount the taker receives from the maker.
+    pub maker_pay_size: u64,
+    /// Amount the maker receives from the taker.
+    pub taker_pay_size: u64,
+}
+
+/// Quote the royalties charged on `size` for `metadata`.
+///
+/// With `verified_creators_only`, unverified creators are paid nothing and their share is
+/// returned as dust.
+pub fn quote_fees(
+    metadata: &Metadata,
+    size: u64,
+    verified_creators_only: bool,
+) -> Result<FeeQuote, QuoteError> {
+    let total_fee = (metadata.data.seller_fee_basis_points as u64)
+        .checked_mul(size)
+        .ok_or(QuoteError::NumericalOverflow)?
+        .checked_div(10000)
+        .ok_or(QuoteError::NumericalOverflow)?;
+    let mut remaining_fee = total_fee;
+    let remaining_size = size
+        .checked_sub(total_fee)
+        .ok_or(QuoteError::NumericalOverflow)?;
+    let mut payouts = Vec::new();
+    if let Some(creators) = &metadata.data.creators {
+        for creator in creators {
+            if verified_creators_only && !creator.verified {
+                continue;
+            }
+            let amount = (creator.share as u64)
+                .checked_mul(total_fee)
+                .ok_or(QuoteError::NumericalOverflow)?
+                .checked_div(100)
+                .ok_or(QuoteError::NumericalOverflow)?;
+            remaining_fee = remaining_fee
+                .checked_sub(amount)
+                .ok_or(QuoteError::NumericalOverflow)?;
+            payouts.push(CreatorPayout {
+                address: creator.address,
+                amount,
+            });
+        }
+    }
+    Ok(FeeQuote {
+        total_fee,
+        payouts,
+        dust: remaining_fee,
+        remaining_size: remaining_size
+            .checked_add(remaining_fee)
+            .ok_or(QuoteError::NumericalOverflow)?,
+    })
+}
+
+/// Quote accepting an offer whose royalties are described by `metadata`.
+///
+/// `is_native` is whether the taker leg is native SOL, as for `accept_offer`. The platform fee
+/// is charged on the gross size of the leg paying royalties.
+pub fn quote_accept(
+    metadata: &Metadata,
+    fee_payer: FeePayer,
+    maker_size: u64,
+    taker_size: u64,
+    is_native: bool,
+    verified_creators_only: bool,
+    platform_fee: Option<&PlatformFee>,
+) -> Result<AcceptQuote, QuoteError> {
+    let size = match fee_payer {
+        FeePayer::Taker => taker_size,
+        FeePayer::Maker => maker_size,
+    };
+    let fees = quote_fees(metadata, size, verified_creators_only)?;
+    if fee_payer == FeePayer::Maker
+        && is_native
+        && fees.payouts.iter().any(|payout| payout.amount > 0)
+    {
+        return Err(QuoteError::MakerCannotPayNative);
+    }
+    let platform_fee = match platform_fee {
+        Some(platform_fee) => platform_fee
+            .amount(size)
+            .ok_or(QuoteError::NumericalOverflow)?,
+        None => 0,
+    };
+    let remaining_size = fees
+        .remaining_size
+        .checked_sub(platform_fee)
+        .ok_or(QuoteError::NumericalOverflow)?;
+    let (maker_pay_size, taker_pay_size) = match fee_payer {
+        FeePayer::Taker => (maker_size, remaining_size),
+        FeePayer::Maker => (remaining_size, taker_size),
+    };
+    Ok(AcceptQuote {
+        fee_payer,
+        fees,
+        platform_fee,
+        maker_pay_size,
+        taker_pay_size,
+    })
+}
@@ -0,0 +1,85 @@
+//! Sealed-bid auctions.
+//!
+//! A maker escrows `maker_size` in a sealed auction and sets the reserve as `taker_size`. During
+//! the commit phase each bidder posts `bid_commitment(auction, bidder, amount, salt)`, which
+//! reveals nothing about the amount. During the reveal phase the bidder posts the amount with an
+//! SP1 proof that it opens their commitment and meets the reserve, and escrows it. The best
+//! revealed bid is settled once the reveal phase ends.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{hash::hashv, pubkey::Pubkey};
+
+/// What the zkVM program commits when proving a bid reveal.
+///
+/// The on-chain program rebuilds these values from the auction, the bid account and the revealed
+/// amount, and verifies the proof against their Borsh encoding. Every field is fixed-width, the
+/// auction terms enter through their digest.
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize, serde::Serialize)]
+pub struct SealedBidReveal {
+    pub auction: Pubkey,
+    pub bidder: Pubkey,
+    /// The commitment posted during the commit phase.
+    pub commitment: [u8; 32],
+    /// [`public_values_digest`](crate::zk_offers::PublicValuesStruct::public_values_digest) of the
+    /// auction terms, whose `taker_size` is the reserve.
+    pub public_values_digest: [u8; 32],
+    pub amount: u64,
+}
+
+/// The commitment a bidder posts for `amount`, blinded by a secret `salt`.
+///
+/// The auction and the bidder are hashed in so that a commitment cannot be copied into another
+/// auction or by another bidder.
+pub fn bid_commitment(auction: &Pubkey, bidder: &Pubkey, amount: u64, salt: &[u8; 32]) -> [u8; 32] {
+    hashv(&[
+        b"sealed_bid",
+        auction.as_ref(),
+        bidder.as_ref(),
+        &amount.to_le_bytes(),
+        salt,
+    ])
+    .to_bytes()
+}
+
+/// The `0x` prefixed hex form of a verification key hash, as printed by the `vkey` script.
+pub fn format_vkey_hash(vkey_hash: &[u8; 32]) -> String {
+    let mut hex = String::with_capacity(2 + 64);
+    hex.push_str("0x");
+    for byte in vkey_hash {
+        hex.push_str(&format!("{:02x}", byte));
+    }
+    hex
+}
+
+/// Derive the account of a sealed auction.
+pub fn compute_sealed_auction_address(
+    program_id: &Pubkey,
+    maker_wallet: &Pubkey,
+    nonce: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"sealed_auction",
+            maker_wallet.as_ref(),
+            &nonce.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+/// Derive the account holding one bidder's commitment to a sealed auction.
+pub fn compute_sealed_bid_address(
+    program_id: &Pubkey,
+    auction: &Pubkey,
+    bidder: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"sealed_bid", auction.as_ref(), bidder.as_ref()],
+        program_id,
+    )
+}
+
+/// The vault of a sealed auction or bid, the account's ATA for the mint it escrows.
+pub fn compute_sealed_vault(account: &Pubkey, mint: &Pubkey) -> Pubkey {
+    spl_associated_token_account::get_associated_token_address(account, mint)
+}
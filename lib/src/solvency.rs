@@ -0,0 +1,74 @@
+//! Proofs that a maker holds the `maker_size` they offer.
+//!
+//! `approve` does not look at the balance, so a maker can delegate, and advertise, more than their
+//! token account holds. The guest takes a snapshot of the maker's token account at a slot, checks
+//! that it is an initialized token account of the maker for `maker_mint`, and commits a
+//! [`MakerSolvency`] recording whether it held `maker_size`. Offer books show such offers as proven
+//! funded at that slot.
+//!
+//! The snapshot is supplied by the prover and the guest cannot check it against the bank it names.
+//! `bank_hash` pins the slot instead: verifiers compare it with the SlotHashes sysvar, which only
+//! keeps the last 512 slots, so a solvency proof has to be submitted within minutes of its slot.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{program_pack::Pack, pubkey::Pubkey};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::state::{Account, AccountState};
+
+use crate::health::OfferIssue;
+
+/// What the zkVM program commits when proving a maker's balance.
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize, serde::Serialize)]
+pub struct MakerSolvency {
+    pub maker_wallet: Pubkey,
+    /// The maker's ATA for `maker_mint`, the account the snapshot is of.
+    pub token_account: Pubkey,
+    pub maker_mint: Pubkey,
+    pub maker_size: u64,
+    /// The slot the snapshot was taken at.
+    pub slot: u64,
+    /// The bank hash of `slot`, as listed by the SlotHashes sysvar.
+    pub bank_hash: [u8; 32],
+    /// Whether the account held at least `maker_size` and was not frozen.
+    pub solvent: bool,
+}
+
+impl MakerSolvency {
+    /// Check a snapshot of the maker's token account taken at `slot`.
+    ///
+    /// Snapshots that say nothing about the maker's balance of `maker_mint`, because they are not
+    /// an initialized token account or belong to another owner or mint, are rejected. A balance
+    /// below `maker_size` or a frozen account is not an error, it is committed as insolvent.
+    pub fn from_snapshot(
+        maker_wallet: &Pubkey,
+        maker_mint: &Pubkey,
+        maker_size: u64,
+        slot: u64,
+        bank_hash: [u8; 32],
+        token_account_data: &[u8],
+    ) -> Result<Self, OfferIssue> {
+        let account =
+            Account::unpack(token_account_data).map_err(|_| OfferIssue::NotTokenAccount)?;
+        if account.owner != *maker_wallet {
+            return Err(OfferIssue::WrongOwner {
+                expected: *maker_wallet,
+                actual: account.owner,
+            });
+        }
+        if account.mint != *maker_mint {
+            return Err(OfferIssue::WrongMint {
+                expected: *maker_mint,
+                actual: account.mint,
+            });
+        }
+        Ok(Self {
+            maker_wallet: *maker_wallet,
+            token_account: get_associated_token_address(maker_wallet, maker_mint),
+            maker_mint: *maker_mint,
+            maker_size,
+            slot,
+            bank_hash,
+            solvent: account.state != AccountState::Frozen && account.amount >= maker_size,
+        })
+    }
+}
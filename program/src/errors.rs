@@ -1,7 +1,8 @@
+use num_derive::FromPrimitive;
 use solana_program::program_error::ProgramError;
 use thiserror::Error;
 
-#[derive(Error, Debug, Copy, Clone)]
+#[derive(Error, Debug, Copy, Clone, FromPrimitive)]
 pub enum UtilError {
     #[error("PublicKeyMismatch")]
     PublicKeyMismatch,
@@ -19,6 +20,24 @@ pub enum UtilError {
     NotRentExempt,
     #[error("NumericalOverflow")]
     NumericalOverflow,
+    #[error("MakerReceivedBelowMinimum")]
+    MakerReceivedBelowMinimum,
+    #[error("TakerPaidAboveMaximum")]
+    TakerPaidAboveMaximum,
+    #[error("OutsideAuctionPhase")]
+    OutsideAuctionPhase,
+    #[error("InvalidBidProof")]
+    InvalidBidProof,
+    #[error("InvalidBatchProof")]
+    InvalidBatchProof,
+    #[error("OfferNotInBatch")]
+    OfferNotInBatch,
+    #[error("BidExceedsDeposit")]
+    BidExceedsDeposit,
+    #[error("UnknownVerificationKey")]
+    UnknownVerificationKey,
+    #[error("ProgrammableTakerLeg")]
+    ProgrammableTakerLeg,
 }
 
 impl From<UtilError> for ProgramError {
@@ -26,3 +45,10 @@ impl From<UtilError> for ProgramError {
         ProgramError::Custom(e as u32)
     }
 }
+
+impl UtilError {
+    /// The error with custom program error `code`, if there is one.
+    pub fn from_code(code: u32) -> Option<Self> {
+        num_traits::FromPrimitive::from_u32(code)
+    }
+}
@@ -1,10 +1,85 @@
 //! Instruction types
 
-use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
-use solana_program::{instruction::AccountMeta, instruction::Instruction, system_program};
+use crate::errors::UtilError;
+use borsh::{
+    schema::BorshSchemaContainer, schema_container_of, BorshDeserialize, BorshSchema,
+    BorshSerialize,
+};
+use solana_program::{
+    instruction::AccountMeta, instruction::Instruction, msg, program_error::ProgramError,
+    system_program,
+};
 use solana_pubkey::Pubkey;
+use solana_zk_offers::{
+    metadata::{self, resolve_royalty_accounts},
+    offer_batch::compute_batched_offer_authority,
+    quote::FeePayer,
+    sealed_bid::compute_sealed_vault,
+    zk_offers::{
+        compute_escrow_vault, compute_offer_authority, compute_offer_router, OfferStruct,
+        PlatformFee, PublicValuesStruct, CANONICAL_PUBLIC_VALUES_LEN,
+    },
+};
+use spl_associated_token_account::get_associated_token_address;
+
+/// Version of the instruction wire format written by [`StatelessOfferInstruction::pack`].
+///
+/// Version 2 appended the [`TakerGuard`] to the accept instructions.
+pub const INSTRUCTION_VERSION: u8 = 2;
+
+/// Header bytes after the discriminator and version, zero until a later version uses them.
+pub const INSTRUCTION_RESERVED_LEN: usize = 6;
+
+/// Length of the instruction header: discriminator, version and reserved bytes.
+pub const INSTRUCTION_HEADER_LEN: usize = 2 + INSTRUCTION_RESERVED_LEN;
+
+/// Bounds a taker puts on a fill, checked once the fees are known.
+///
+/// Royalties are read from the metadata at fill time, so a taker who quoted an offer beforehand
+/// sets these to fail the fill rather than receive less, or pay more, than quoted.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, BorshSerialize, BorshDeserialize, BorshSchema,
+)]
+pub struct TakerGuard {
+    /// Least of the maker leg the taker accepts to receive, after fees paid from it.
+    pub min_maker_received: Option<u64>,
+    /// Most of the taker leg the taker accepts to pay, fees included.
+    pub max_taker_paid: Option<u64>,
+}
+
+impl TakerGuard {
+    /// Check what a fill moves once its fees are paid.
+    pub fn check(&self, maker_received: u64, taker_paid: u64) -> Result<(), ProgramError> {
+        if let Some(min_maker_received) = self.min_maker_received {
+            if maker_received < min_maker_received {
+                msg!(
+                    "Taker would receive {}, below their minimum of {}",
+                    maker_received,
+                    min_maker_received
+                );
+                return Err(UtilError::MakerReceivedBelowMinimum.into());
+            }
+        }
+        if let Some(max_taker_paid) = self.max_taker_paid {
+            if taker_paid > max_taker_paid {
+                msg!(
+                    "Taker would pay {}, above their maximum of {}",
+                    taker_paid,
+                    max_taker_paid
+                );
+                return Err(UtilError::TakerPaidAboveMaximum.into());
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Instructions supported by the StatelessOffer program.
-#[repr(C)]
+///
+/// On the wire an instruction is an [`INSTRUCTION_HEADER_LEN`] byte header, holding the
+/// variant's [`discriminator`](Self::discriminator), [`INSTRUCTION_VERSION`] and zeroed reserved
+/// bytes, followed by the Borsh encoding of the variant's fields. Discriminators follow
+/// declaration order, so variants are only ever appended.
 #[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
 pub enum StatelessOfferInstruction {
     ///  Accept a StatelessOffer
@@ -20,16 +95,366 @@ pub enum StatelessOfferInstruction {
     ///
     ///  Bob initializes tkr_src_account (Token B) and tkr_dst_account (Token A)
     /// if they don't exist  Bob (or anyone) executes AcceptOffer
+    ///
+    ///  When royalties are paid, metadata_bump_seed is the bump of the
+    /// metadata account following the transfer accounts, so the program
+    /// checks it with create_program_address instead of searching for it.
+    ///
+    ///  Every accept instruction ends with the TakerGuard Bob fills under.
     AcceptOffer {
         #[allow(dead_code)]
-        has_metadata: bool,
+        metadata_bump_seed: Option<u8>,
+        #[allow(dead_code)]
+        maker_size: u64,
+        #[allow(dead_code)]
+        taker_size: u64,
+        #[allow(dead_code)]
+        bump_seed: u8,
+        #[allow(dead_code)]
+        platform_fee: Option<PlatformFee>,
+        #[allow(dead_code)]
+        taker_guard: TakerGuard,
+    },
+    ///  Accept a StatelessOffer whose maker leg is a Metaplex programmable NFT
+    ///
+    ///  Programmable NFT token accounts stay frozen and can only be moved by
+    /// Token Metadata's `Transfer`, so the maker delegates with Token
+    /// Metadata's `Delegate` (transfer or sale role) to the same
+    /// transfer_authority instead of calling SPL Approve. The maker leg is
+    /// then moved by a `Transfer` CPI signed by the transfer_authority, with
+    /// the token records and rule set accounts following the accounts of
+    /// AcceptOffer. Only the maker leg can be programmable. A frozen taker
+    /// source account, as every programmable NFT has, fails every accept
+    /// instruction with ProgrammableTakerLeg.
+    AcceptProgrammableOffer {
+        #[allow(dead_code)]
+        metadata_bump_seed: Option<u8>,
+        #[allow(dead_code)]
+        maker_size: u64,
+        #[allow(dead_code)]
+        taker_size: u64,
+        #[allow(dead_code)]
+        bump_seed: u8,
+        #[allow(dead_code)]
+        platform_fee: Option<PlatformFee>,
+        #[allow(dead_code)]
+        taker_guard: TakerGuard,
+    },
+    ///  Accept a collection-wide bid
+    ///
+    ///  The maker delegates maker_size of Token A to a transfer_authority
+    /// whose seeds commit to a verified collection instead of a taker mint,
+    /// and whether royalties are only paid to verified creators. Any taker
+    /// holding an NFT whose metadata carries that verified collection can
+    /// fill the bid. The accounts are the ones of AcceptOffer with the NFT
+    /// as taker mint, followed by the NFT metadata and its creator accounts.
+    /// Programmable NFTs cannot fill a collection bid, see
+    /// AcceptProgrammableOffer.
+    AcceptCollectionOffer {
+        #[allow(dead_code)]
+        collection: Pubkey,
+        #[allow(dead_code)]
+        verified_creators_only: bool,
+        #[allow(dead_code)]
+        metadata_bump_seed: u8,
         #[allow(dead_code)]
         maker_size: u64,
         #[allow(dead_code)]
         taker_size: u64,
         #[allow(dead_code)]
         bump_seed: u8,
+        #[allow(dead_code)]
+        platform_fee: Option<PlatformFee>,
+        #[allow(dead_code)]
+        taker_guard: TakerGuard,
+    },
+    ///  Initialize an escrowed offer
+    ///
+    ///  Instead of delegating, Alice moves maker_size of Token A into a
+    /// vault, the ATA of an escrow offer account owned by the program. The
+    /// escrow offer account records the offer terms, and is keyed by a nonce
+    /// so Alice can run any number of escrowed offers from the same token
+    /// account. Unlike a delegation, the offer stays backed until it is
+    /// filled or closed. The terms are passed in their canonical encoding,
+    /// as for every instruction carrying public_values.
+    InitEscrowOffer {
+        #[allow(dead_code)]
+        nonce: u64,
+        #[allow(dead_code)]
+        bump_seed: u8,
+        #[allow(dead_code)]
+        public_values: [u8; CANONICAL_PUBLIC_VALUES_LEN],
+    },
+    ///  Accept an escrowed offer
+    ///
+    ///  Bob fills the offer recorded in the escrow offer account. Royalties
+    /// and the platform fee are paid as for AcceptOffer, with the escrow
+    /// offer account signing for the maker leg held in the vault. The vault
+    /// and the escrow offer account are then closed, returning their rent to
+    /// Alice.
+    AcceptEscrowOffer {
+        #[allow(dead_code)]
+        metadata_bump_seed: Option<u8>,
+        #[allow(dead_code)]
+        taker_guard: TakerGuard,
+    },
+    ///  Close an escrowed offer
+    ///
+    ///  Alice takes the vault balance back into her token account and
+    /// reclaims the rent of the vault and the escrow offer account.
+    CloseEscrowOffer,
+    ///  Record an offer filled through the offer router
+    ///
+    ///  A token account has a single delegate, so a second Approve replaces
+    /// the first. Instead, Alice approves the program-wide offer router once
+    /// for the sum of her routed offers, and records each offer in a routed
+    /// offer account keyed by a nonce. The account caps the fill of the
+    /// offer at its maker_size.
+    InitRoutedOffer {
+        #[allow(dead_code)]
+        nonce: u64,
+        #[allow(dead_code)]
+        bump_seed: u8,
+        #[allow(dead_code)]
+        public_values: [u8; CANONICAL_PUBLIC_VALUES_LEN],
+    },
+    ///  Accept a routed offer
+    ///
+    ///  Bob fills the offer recorded in the routed offer account, with the
+    /// offer router signing for the maker leg. The routed offer account is
+    /// closed, so the offer cannot take more of the delegation.
+    AcceptRoutedOffer {
+        #[allow(dead_code)]
+        metadata_bump_seed: Option<u8>,
+        #[allow(dead_code)]
+        router_bump_seed: u8,
+        #[allow(dead_code)]
+        taker_guard: TakerGuard,
+    },
+    ///  Close a routed offer
+    ///
+    ///  Alice withdraws the offer and reclaims the rent of its account. The
+    /// router delegation is left for her to reduce or revoke.
+    CloseRoutedOffer,
+    ///  Open a sealed-bid auction
+    ///
+    ///  Alice escrows maker_size of Token A in the vault of a sealed auction
+    /// account, as for InitEscrowOffer, with taker_size as her reserve. Bids
+    /// are committed until commit_end_slot and revealed until
+    /// reveal_end_slot, with reveal proofs checked against bid_vkey_hash,
+    /// which must be the pinned OFFER_VKEY_HASH.
+    InitSealedAuction {
+        #[allow(dead_code)]
+        nonce: u64,
+        #[allow(dead_code)]
+        bump_seed: u8,
+        #[allow(dead_code)]
+        public_values: [u8; CANONICAL_PUBLIC_VALUES_LEN],
+        #[allow(dead_code)]
+        bid_vkey_hash: [u8; 32],
+        #[allow(dead_code)]
+        commit_end_slot: u64,
+        #[allow(dead_code)]
+        reveal_end_slot: u64,
+    },
+    ///  Commit to a sealed bid
+    ///
+    ///  Bob records the hash of his bid amount and a secret salt in a bid
+    /// account, and escrows a deposit of at least his bid in the vault of
+    /// the bid account. Nothing about the amount is on-chain until he reveals
+    /// it but the deposit bounding it, so there is no bid to front-run. A bid
+    /// left unrevealed forfeits its deposit to Alice.
+    CommitSealedBid {
+        #[allow(dead_code)]
+        bump_seed: u8,
+        #[allow(dead_code)]
+        commitment: [u8; 32],
+        #[allow(dead_code)]
+        deposit: u64,
+    },
+    ///  Reveal a sealed bid
+    ///
+    ///  Bob posts his bid amount with a Groth16 proof that it opens his
+    /// commitment and meets the reserve. The vault of his bid account keeps
+    /// the amount and returns the rest of his deposit. The auction keeps the
+    /// best revealed bid.
+    RevealSealedBid {
+        #[allow(dead_code)]
+        amount: u64,
+        #[allow(dead_code)]
+        proof: Vec<u8>,
+    },
+    ///  Settle a sealed-bid auction
+    ///
+    ///  Once the reveal phase is over, anyone settles the auction. The best
+    /// bid fills it like an escrowed offer, paying royalties and the platform
+    /// fee, and the vaults are closed. Without a bid the vault returns to
+    /// Alice.
+    SettleSealedAuction {
+        #[allow(dead_code)]
+        metadata_bump_seed: Option<u8>,
+    },
+    ///  Close a sealed bid
+    ///
+    ///  Bob takes back a revealed bid that is not the best, with the rent of
+    /// its accounts. Once the reveal phase is over anyone can close an
+    /// unrevealed bid, sending its deposit to Alice and the rent to Bob.
+    CloseSealedBid,
+    ///  Post a batch of offers
+    ///
+    ///  A relayer proves a batch of offers in one guest run and posts the
+    /// Merkle root of their terms with the Groth16 proof of the batch,
+    /// checked against vkey_hash, which must be the pinned OFFER_VKEY_HASH.
+    /// The root is recorded in an offer batch account derived from vkey_hash
+    /// and the root.
+    PostOfferBatch {
+        #[allow(dead_code)]
+        bump_seed: u8,
+        #[allow(dead_code)]
+        vkey_hash: [u8; 32],
+        #[allow(dead_code)]
+        root: [u8; 32],
+        #[allow(dead_code)]
+        offer_count: u32,
+        #[allow(dead_code)]
+        proof: Vec<u8>,
+    },
+    ///  Accept a batched offer
+    ///
+    ///  Bob fills a delegated offer whose terms are part of a posted batch.
+    /// The offer batch account comes first, followed by the accounts of
+    /// AcceptOffer, and merkle_proof leads from the leaf of public_values to
+    /// the root of the batch. Batches proven by a guest other than the
+    /// pinned one are rejected. The fill is then an AcceptOffer of those terms,
+    /// signed by an authority derived from the root and the digest of the
+    /// terms, so a batched delegation is only filled through this instruction.
+    AcceptBatchedOffer {
+        #[allow(dead_code)]
+        metadata_bump_seed: Option<u8>,
+        #[allow(dead_code)]
+        bump_seed: u8,
+        #[allow(dead_code)]
+        public_values: [u8; CANONICAL_PUBLIC_VALUES_LEN],
+        #[allow(dead_code)]
+        merkle_proof: Vec<[u8; 32]>,
+        #[allow(dead_code)]
+        taker_guard: TakerGuard,
     },
+    ///  Close an offer batch
+    ///
+    ///  The poster reclaims the rent of the batch account. Its offers can no
+    /// longer be filled through AcceptBatchedOffer.
+    CloseOfferBatch,
+}
+
+impl StatelessOfferInstruction {
+    /// The first byte of the instruction data of this variant.
+    pub fn discriminator(&self) -> u8 {
+        match self {
+            StatelessOfferInstruction::AcceptOffer { .. } => 0,
+            StatelessOfferInstruction::AcceptProgrammableOffer { .. } => 1,
+            StatelessOfferInstruction::AcceptCollectionOffer { .. } => 2,
+            StatelessOfferInstruction::InitEscrowOffer { .. } => 3,
+            StatelessOfferInstruction::AcceptEscrowOffer { .. } => 4,
+            StatelessOfferInstruction::CloseEscrowOffer => 5,
+            StatelessOfferInstruction::InitRoutedOffer { .. } => 6,
+            StatelessOfferInstruction::AcceptRoutedOffer { .. } => 7,
+            StatelessOfferInstruction::CloseRoutedOffer => 8,
+            StatelessOfferInstruction::InitSealedAuction { .. } => 9,
+            StatelessOfferInstruction::CommitSealedBid { .. } => 10,
+            StatelessOfferInstruction::RevealSealedBid { .. } => 11,
+            StatelessOfferInstruction::SettleSealedAuction { .. } => 12,
+            StatelessOfferInstruction::CloseSealedBid => 13,
+            StatelessOfferInstruction::PostOfferBatch { .. } => 14,
+            StatelessOfferInstruction::AcceptBatchedOffer { .. } => 15,
+            StatelessOfferInstruction::CloseOfferBatch => 16,
+        }
+    }
+
+    /// The taker guard of an accept instruction.
+    pub fn taker_guard_mut(&mut self) -> Option<&mut TakerGuard> {
+        match self {
+            StatelessOfferInstruction::AcceptOffer { taker_guard, .. }
+            | StatelessOfferInstruction::AcceptProgrammableOffer { taker_guard, .. }
+            | StatelessOfferInstruction::AcceptCollectionOffer { taker_guard, .. }
+            | StatelessOfferInstruction::AcceptEscrowOffer { taker_guard, .. }
+            | StatelessOfferInstruction::AcceptRoutedOffer { taker_guard, .. }
+            | StatelessOfferInstruction::AcceptBatchedOffer { taker_guard, .. } => {
+                Some(taker_guard)
+            }
+            _ => None,
+        }
+    }
+
+    /// The offer terms an instruction records or fills, decoded from their canonical encoding.
+    ///
+    /// `None` for variants without terms and for bytes that are not a canonical encoding.
+    pub fn public_values(&self) -> Option<PublicValuesStruct> {
+        match self {
+            StatelessOfferInstruction::InitEscrowOffer { public_values, .. }
+            | StatelessOfferInstruction::InitRoutedOffer { public_values, .. }
+            | StatelessOfferInstruction::InitSealedAuction { public_values, .. }
+            | StatelessOfferInstruction::AcceptBatchedOffer { public_values, .. } => {
+                PublicValuesStruct::from_canonical_bytes(public_values)
+            }
+            _ => None,
+        }
+    }
+
+    /// Encode the instruction data.
+    pub fn pack(&self) -> Vec<u8> {
+        // Borsh writes the variant index ahead of the fields, which the header replaces.
+        let encoded = borsh::to_vec(self).unwrap();
+        let mut data = vec![0; INSTRUCTION_HEADER_LEN];
+        data[0] = self.discriminator();
+        data[1] = INSTRUCTION_VERSION;
+        data.extend_from_slice(&encoded[1..]);
+        data
+    }
+
+    /// Decode instruction data written by [`pack`](Self::pack).
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() < INSTRUCTION_HEADER_LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let (header, fields) = input.split_at(INSTRUCTION_HEADER_LEN);
+        if header[1] != INSTRUCTION_VERSION || header[2..].iter().any(|byte| *byte != 0) {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let mut encoded = Vec::with_capacity(1 + fields.len());
+        encoded.push(header[0]);
+        encoded.extend_from_slice(fields);
+        let instruction = Self::try_from_slice(&encoded)
+            .map_err(|_| ProgramError::InvalidInstructionData)?;
+        if instruction.discriminator() != header[0] {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(instruction)
+    }
+}
+
+/// The Borsh schema of [`StatelessOfferInstruction`], from which clients in other languages
+/// generate the field encoders that follow the instruction header.
+pub fn instruction_schema() -> BorshSchemaContainer {
+    schema_container_of::<StatelessOfferInstruction>()
+}
+
+/// Token Auth Rules program, which evaluates programmable NFT rule sets.
+pub const TOKEN_AUTH_RULES_PROGRAM_ID: Pubkey =
+    solana_pubkey::pubkey!("auth9SigNpDKz4sJJ1DfCTuZrZNSAgh9sFD3rboVmgg");
+
+/// Guard an accept instruction built by this module, so that it fails unless the fill stays
+/// within `taker_guard`.
+pub fn set_taker_guard(
+    instruction: &mut Instruction,
+    taker_guard: TakerGuard,
+) -> Result<(), ProgramError> {
+    let mut data = StatelessOfferInstruction::unpack(&instruction.data)?;
+    *data
+        .taker_guard_mut()
+        .ok_or(ProgramError::InvalidInstructionData)? = taker_guard;
+    instruction.data = data.pack();
+    Ok(())
 }
 
 /// Creates an 'initialize' instruction.
@@ -47,17 +472,20 @@ pub fn accept_offer(
     authority: &Pubkey,
     token_program_id: &Pubkey,
     is_native: bool,
+    platform_fee: Option<&PlatformFee>,
     maker_size: u64,
     taker_size: u64,
     bump_seed: u8,
 ) -> Instruction {
     let init_data = StatelessOfferInstruction::AcceptOffer {
-        has_metadata: false,
+        metadata_bump_seed: None,
         maker_size,
         taker_size,
         bump_seed,
+        platform_fee: platform_fee.copied(),
+        taker_guard: TakerGuard::default(),
     };
-    let data = borsh::to_vec(&init_data).unwrap();
+    let data = init_data.pack();
     let mut accounts = vec![
         AccountMeta::new_readonly(*maker_wallet, false),
         AccountMeta::new_readonly(*taker_wallet, true),
@@ -73,6 +501,9 @@ pub fn accept_offer(
     if is_native {
         accounts.push(AccountMeta::new_readonly(system_program::id(), false));
     }
+    if let Some(platform_fee) = platform_fee {
+        accounts.push(platform_fee_account(platform_fee, taker_mint, is_native));
+    }
     Instruction {
         program_id: *program_id,
         accounts,
@@ -80,7 +511,44 @@ pub fn accept_offer(
     }
 }
 
+/// Creates an 'accept offer' instruction from a Borsh encoded [`OfferStruct`], with `pda` as its
+/// transfer authority.
+///
+/// The offer data used to be sent as is, which the program rejects.
+#[deprecated(note = "use `accept_offer`, with the authority of `compute_offer_authority`")]
+pub fn create_offer_transaction(program_id: &Pubkey, offer: Vec<u8>, pda: Pubkey) -> Instruction {
+    let offer = OfferStruct::try_from_slice(&offer).expect("Failed to deserialize offer_info");
+    let accounts = offer.accounts().map(|(_, account)| account);
+    let [maker_wallet, taker_wallet, maker_src, maker_dst, taker_src, taker_dst] = accounts;
+    let public_values = &offer.public_values;
+    accept_offer(
+        program_id,
+        &maker_wallet,
+        &taker_wallet,
+        &maker_src,
+        &maker_dst,
+        &taker_src,
+        &taker_dst,
+        &public_values.maker_mint,
+        &public_values
+            .taker_mint
+            .unwrap_or_else(spl_token::native_mint::id),
+        &pda,
+        &spl_token::id(),
+        public_values.is_native,
+        public_values.platform_fee.as_ref(),
+        public_values.maker_size,
+        public_values.taker_size.expect("Offer has no taker size"),
+        offer.bump_seed(),
+    )
+}
+
 /// Creates an 'initialize' instruction.
+///
+/// `creators` must already be in the order `pay_creator_fees` reads them, with each creator's
+/// ATA following it for SPL legs. Prefer [`accept_offer_with_metadata_account`], which derives
+/// them from the metadata account data. The platform fee account follows the creators, paid in
+/// the leg the metadata does not describe.
 #[allow(clippy::too_many_arguments)]
 pub fn accept_offer_with_metadata(
     program_id: &Pubkey,
@@ -95,19 +563,23 @@ pub fn accept_offer_with_metadata(
     authority: &Pubkey,
     token_program_id: &Pubkey,
     metadata: &Pubkey,
+    metadata_bump_seed: u8,
     creators: &[&Pubkey],
     is_native: bool,
+    platform_fee: Option<&PlatformFee>,
     maker_size: u64,
     taker_size: u64,
     bump_seed: u8,
 ) -> Instruction {
     let init_data = StatelessOfferInstruction::AcceptOffer {
-        has_metadata: true,
+        metadata_bump_seed: Some(metadata_bump_seed),
         maker_size,
         taker_size,
         bump_seed,
+        platform_fee: platform_fee.copied(),
+        taker_guard: TakerGuard::default(),
     };
-    let data = borsh::to_vec(&init_data).unwrap();
+    let data = init_data.pack();
     let mut accounts = vec![
         AccountMeta::new_readonly(*maker_wallet, false),
         AccountMeta::new_readonly(*taker_wallet, true),
@@ -127,9 +599,894 @@ pub fn accept_offer_with_metadata(
     for creator in creators.iter() {
         accounts.push(AccountMeta::new(**creator, false));
     }
+    if let Some(platform_fee) = platform_fee {
+        // The maker pays the fees when the metadata belongs to the taker mint.
+        let (fee_mint, fee_is_native) =
+            if metadata::find_metadata_address(taker_mint).0 == *metadata {
+                (maker_mint, false)
+            } else {
+                (taker_mint, is_native)
+            };
+        accounts.push(platform_fee_account(platform_fee, fee_mint, fee_is_native));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    }
+}
+
+/// Creates an 'accept offer' instruction that pays royalties described by raw metadata
+/// account data.
+///
+/// The metadata PDA and creator accounts are derived from the decoded metadata. For SPL legs
+/// each creator is followed by its ATA for the paying mint, as `pay_creator_fees` expects.
+#[allow(clippy::too_many_arguments)]
+pub fn accept_offer_with_metadata_account(
+    program_id: &Pubkey,
+    maker_wallet: &Pubkey,
+    taker_wallet: &Pubkey,
+    maker_src_account: &Pubkey,
+    maker_dst_account: &Pubkey,
+    taker_src_account: &Pubkey,
+    taker_dst_account: &Pubkey,
+    maker_mint: &Pubkey,
+    taker_mint: &Pubkey,
+    authority: &Pubkey,
+    token_program_id: &Pubkey,
+    metadata_data: &[u8],
+    is_native: bool,
+    platform_fee: Option<&PlatformFee>,
+    maker_size: u64,
+    taker_size: u64,
+    bump_seed: u8,
+) -> Result<Instruction, ProgramError> {
+    let royalty_accounts =
+        resolve_royalty_accounts(metadata_data, maker_mint, taker_mint, is_native, false)
+            .ok_or(ProgramError::InvalidAccountData)?;
+    let mut instruction = accept_offer(
+        program_id,
+        maker_wallet,
+        taker_wallet,
+        maker_src_account,
+        maker_dst_account,
+        taker_src_account,
+        taker_dst_account,
+        maker_mint,
+        taker_mint,
+        authority,
+        token_program_id,
+        is_native,
+        None,
+        maker_size,
+        taker_size,
+        bump_seed,
+    );
+    instruction.data = StatelessOfferInstruction::AcceptOffer {
+        metadata_bump_seed: Some(royalty_accounts.metadata_bump_seed),
+        maker_size,
+        taker_size,
+        bump_seed,
+        platform_fee: platform_fee.copied(),
+        taker_guard: TakerGuard::default(),
+    }
+    .pack();
+    instruction
+        .accounts
+        .push(AccountMeta::new_readonly(royalty_accounts.metadata_key, false));
+    instruction
+        .accounts
+        .extend(royalty_accounts.creator_accounts);
+    if let Some(platform_fee) = platform_fee {
+        let (fee_mint, fee_is_native) = match royalty_accounts.fee_payer {
+            FeePayer::Taker => (taker_mint, is_native),
+            FeePayer::Maker => (maker_mint, false),
+        };
+        instruction.accounts.push(platform_fee_account(
+            platform_fee,
+            fee_mint,
+            fee_is_native,
+        ));
+    }
+    Ok(instruction)
+}
+
+/// Creates an 'accept offer' instruction for a programmable NFT maker leg.
+///
+/// `authorization_rules` is the rule set of the NFT, if any. `metadata_data` is the raw metadata
+/// account data of the NFT when royalties should be paid.
+#[allow(clippy::too_many_arguments)]
+pub fn accept_programmable_offer(
+    program_id: &Pubkey,
+    maker_wallet: &Pubkey,
+    taker_wallet: &Pubkey,
+    maker_src_account: &Pubkey,
+    maker_dst_account: &Pubkey,
+    taker_src_account: &Pubkey,
+    taker_dst_account: &Pubkey,
+    maker_mint: &Pubkey,
+    taker_mint: &Pubkey,
+    authority: &Pubkey,
+    token_program_id: &Pubkey,
+    authorization_rules: Option<&Pubkey>,
+    metadata_data: Option<&[u8]>,
+    is_native: bool,
+    platform_fee: Option<&PlatformFee>,
+    maker_size: u64,
+    taker_size: u64,
+    bump_seed: u8,
+) -> Result<Instruction, ProgramError> {
+    let royalty_accounts = match metadata_data {
+        Some(data) => Some(
+            resolve_royalty_accounts(data, maker_mint, taker_mint, is_native, false)
+                .ok_or(ProgramError::InvalidAccountData)?,
+        ),
+        None => None,
+    };
+    let mut instruction = accept_offer(
+        program_id,
+        maker_wallet,
+        taker_wallet,
+        maker_src_account,
+        maker_dst_account,
+        taker_src_account,
+        taker_dst_account,
+        maker_mint,
+        taker_mint,
+        authority,
+        token_program_id,
+        is_native,
+        None,
+        maker_size,
+        taker_size,
+        bump_seed,
+    );
+    instruction.data = StatelessOfferInstruction::AcceptProgrammableOffer {
+        metadata_bump_seed: royalty_accounts
+            .as_ref()
+            .map(|royalty_accounts| royalty_accounts.metadata_bump_seed),
+        maker_size,
+        taker_size,
+        bump_seed,
+        platform_fee: platform_fee.copied(),
+        taker_guard: TakerGuard::default(),
+    }
+    .pack();
+    // The taker pays for the destination token record.
+    instruction.accounts[1] = AccountMeta::new(*taker_wallet, true);
+
+    let token_metadata_program = metadata::id();
+    let (maker_metadata, _) = metadata::find_metadata_address(maker_mint);
+    let (edition, _) = mpl_token_metadata::accounts::MasterEdition::find_pda(maker_mint);
+    let (owner_token_record, _) =
+        mpl_token_metadata::accounts::TokenRecord::find_pda(maker_mint, maker_src_account);
+    let (destination_token_record, _) =
+        mpl_token_metadata::accounts::TokenRecord::find_pda(maker_mint, taker_dst_account);
+    // Token Metadata takes its own program id in place of an absent optional account.
+    let (authorization_rules_program, authorization_rules) = match authorization_rules {
+        Some(rules) => (TOKEN_AUTH_RULES_PROGRAM_ID, *rules),
+        None => (token_metadata_program, token_metadata_program),
+    };
+    instruction.accounts.extend([
+        AccountMeta::new_readonly(token_metadata_program, false),
+        AccountMeta::new(maker_metadata, false),
+        AccountMeta::new_readonly(edition, false),
+        AccountMeta::new(owner_token_record, false),
+        AccountMeta::new(destination_token_record, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(solana_program::sysvar::instructions::id(), false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new_readonly(authorization_rules_program, false),
+        AccountMeta::new_readonly(authorization_rules, false),
+    ]);
+    if let Some(royalty_accounts) = royalty_accounts {
+        instruction
+            .accounts
+            .push(AccountMeta::new_readonly(royalty_accounts.metadata_key, false));
+        instruction
+            .accounts
+            .extend(royalty_accounts.creator_accounts);
+    }
+    // The maker leg is the NFT, so any platform fee is paid by the taker leg.
+    if let Some(platform_fee) = platform_fee {
+        instruction
+            .accounts
+            .push(platform_fee_account(platform_fee, taker_mint, is_native));
+    }
+    Ok(instruction)
+}
+
+/// Creates an 'accept collection offer' instruction filling a collection-wide bid with the NFT
+/// `taker_mint`.
+///
+/// `metadata_data` is the raw metadata account data of the NFT, which must be a verified member
+/// of `collection`.
+#[allow(clippy::too_many_arguments)]
+pub fn accept_collection_offer(
+    program_id: &Pubkey,
+    maker_wallet: &Pubkey,
+    taker_wallet: &Pubkey,
+    maker_src_account: &Pubkey,
+    maker_dst_account: &Pubkey,
+    taker_src_account: &Pubkey,
+    taker_dst_account: &Pubkey,
+    maker_mint: &Pubkey,
+    taker_mint: &Pubkey,
+    collection: &Pubkey,
+    authority: &Pubkey,
+    token_program_id: &Pubkey,
+    metadata_data: &[u8],
+    verified_creators_only: bool,
+    platform_fee: Option<&PlatformFee>,
+    maker_size: u64,
+    taker_size: u64,
+    bump_seed: u8,
+) -> Result<Instruction, ProgramError> {
+    let royalty_accounts = resolve_royalty_accounts(
+        metadata_data,
+        maker_mint,
+        taker_mint,
+        false,
+        verified_creators_only,
+    )
+    .ok_or(ProgramError::InvalidAccountData)?;
+    if !royalty_accounts.metadata.is_verified_member_of(collection) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let mut instruction = accept_offer(
+        program_id,
+        maker_wallet,
+        taker_wallet,
+        maker_src_account,
+        maker_dst_account,
+        taker_src_account,
+        taker_dst_account,
+        maker_mint,
+        taker_mint,
+        authority,
+        token_program_id,
+        false,
+        None,
+        maker_size,
+        taker_size,
+        bump_seed,
+    );
+    instruction.data = StatelessOfferInstruction::AcceptCollectionOffer {
+        collection: *collection,
+        verified_creators_only,
+        metadata_bump_seed: royalty_accounts.metadata_bump_seed,
+        maker_size,
+        taker_size,
+        bump_seed,
+        platform_fee: platform_fee.copied(),
+        taker_guard: TakerGuard::default(),
+    }
+    .pack();
+    instruction
+        .accounts
+        .push(AccountMeta::new_readonly(royalty_accounts.metadata_key, false));
+    instruction
+        .accounts
+        .extend(royalty_accounts.creator_accounts);
+    if let Some(platform_fee) = platform_fee {
+        instruction
+            .accounts
+            .push(platform_fee_account(platform_fee, maker_mint, false));
+    }
+    Ok(instruction)
+}
+
+/// Creates an 'init escrow offer' instruction moving `public_values.maker_size` from the
+/// maker's ATA into the vault of `escrow_offer`.
+pub fn init_escrow_offer(
+    program_id: &Pubkey,
+    maker_wallet: &Pubkey,
+    escrow_offer: &Pubkey,
+    token_program_id: &Pubkey,
+    public_values: PublicValuesStruct,
+    nonce: u64,
+    bump_seed: u8,
+) -> Instruction {
+    let maker_mint = public_values.maker_mint;
+    let data = StatelessOfferInstruction::InitEscrowOffer {
+        nonce,
+        bump_seed,
+        public_values: public_values.to_canonical_bytes(),
+    }
+    .pack();
+    let accounts = vec![
+        AccountMeta::new(*maker_wallet, true),
+        AccountMeta::new(
+            get_associated_token_address(maker_wallet, &maker_mint),
+            false,
+        ),
+        AccountMeta::new(*escrow_offer, false),
+        AccountMeta::new(compute_escrow_vault(escrow_offer, &maker_mint), false),
+        AccountMeta::new_readonly(maker_mint, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    }
+}
+
+/// Creates an 'accept escrow offer' instruction filling the offer recorded in `escrow_offer`.
+///
+/// `metadata_data` is the raw metadata account data of the NFT leg when royalties should be
+/// paid.
+#[allow(clippy::too_many_arguments)]
+pub fn accept_escrow_offer(
+    program_id: &Pubkey,
+    maker_wallet: &Pubkey,
+    taker_wallet: &Pubkey,
+    escrow_offer: &Pubkey,
+    maker_dst_account: &Pubkey,
+    taker_src_account: &Pubkey,
+    taker_dst_account: &Pubkey,
+    token_program_id: &Pubkey,
+    public_values: &PublicValuesStruct,
+    metadata_data: Option<&[u8]>,
+) -> Result<Instruction, ProgramError> {
+    let maker_mint = &public_values.maker_mint;
+    let taker_mint = public_values
+        .taker_mint
+        .unwrap_or_else(spl_token::native_mint::id);
+    let is_native = public_values.is_native;
+    let royalty_accounts = match metadata_data {
+        Some(data) => Some(
+            resolve_royalty_accounts(data, maker_mint, &taker_mint, is_native, false)
+                .ok_or(ProgramError::InvalidAccountData)?,
+        ),
+        None => None,
+    };
+    let data = StatelessOfferInstruction::AcceptEscrowOffer {
+        metadata_bump_seed: royalty_accounts
+            .as_ref()
+            .map(|royalty_accounts| royalty_accounts.metadata_bump_seed),
+        taker_guard: TakerGuard::default(),
+    }
+    .pack();
+    let mut accounts = vec![
+        AccountMeta::new(*maker_wallet, false),
+        AccountMeta::new(*taker_wallet, true),
+        AccountMeta::new(*escrow_offer, false),
+        AccountMeta::new(compute_escrow_vault(escrow_offer, maker_mint), false),
+        AccountMeta::new(*maker_dst_account, false),
+        AccountMeta::new(*taker_src_account, false),
+        AccountMeta::new(*taker_dst_account, false),
+        AccountMeta::new_readonly(*maker_mint, false),
+        AccountMeta::new_readonly(taker_mint, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    if is_native {
+        accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+    }
+    let fee_payer = match royalty_accounts {
+        Some(royalty_accounts) => {
+            accounts.push(AccountMeta::new_readonly(royalty_accounts.metadata_key, false));
+            accounts.extend(royalty_accounts.creator_accounts);
+            royalty_accounts.fee_payer
+        }
+        None => FeePayer::Taker,
+    };
+    if let Some(platform_fee) = &public_values.platform_fee {
+        let (fee_mint, fee_is_native) = match fee_payer {
+            FeePayer::Taker => (&taker_mint, is_native),
+            FeePayer::Maker => (maker_mint, false),
+        };
+        accounts.push(platform_fee_account(platform_fee, fee_mint, fee_is_native));
+    }
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'close escrow offer' instruction returning the vault balance to the maker's ATA.
+pub fn close_escrow_offer(
+    program_id: &Pubkey,
+    maker_wallet: &Pubkey,
+    escrow_offer: &Pubkey,
+    maker_mint: &Pubkey,
+    token_program_id: &Pubkey,
+) -> Instruction {
+    let data = StatelessOfferInstruction::CloseEscrowOffer.pack();
+    let accounts = vec![
+        AccountMeta::new(*maker_wallet, true),
+        AccountMeta::new(*escrow_offer, false),
+        AccountMeta::new(compute_escrow_vault(escrow_offer, maker_mint), false),
+        AccountMeta::new(get_associated_token_address(maker_wallet, maker_mint), false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    }
+}
+
+/// Creates an 'init routed offer' instruction recording an offer against the maker's router
+/// delegation.
+pub fn init_routed_offer(
+    program_id: &Pubkey,
+    maker_wallet: &Pubkey,
+    routed_offer: &Pubkey,
+    public_values: PublicValuesStruct,
+    nonce: u64,
+    bump_seed: u8,
+) -> Instruction {
+    let data = StatelessOfferInstruction::InitRoutedOffer {
+        nonce,
+        bump_seed,
+        public_values: public_values.to_canonical_bytes(),
+    }
+    .pack();
+    let accounts = vec![
+        AccountMeta::new(*maker_wallet, true),
+        AccountMeta::new(*routed_offer, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
     Instruction {
         program_id: *program_id,
         accounts,
         data,
     }
 }
+
+/// Creates an 'accept routed offer' instruction filling the offer recorded in `routed_offer`.
+///
+/// `metadata_data` is the raw metadata account data of the NFT leg when royalties should be
+/// paid.
+#[allow(clippy::too_many_arguments)]
+pub fn accept_routed_offer(
+    program_id: &Pubkey,
+    maker_wallet: &Pubkey,
+    taker_wallet: &Pubkey,
+    routed_offer: &Pubkey,
+    maker_dst_account: &Pubkey,
+    taker_src_account: &Pubkey,
+    taker_dst_account: &Pubkey,
+    token_program_id: &Pubkey,
+    public_values: &PublicValuesStruct,
+    metadata_data: Option<&[u8]>,
+) -> Result<Instruction, ProgramError> {
+    let (router, router_bump_seed) = compute_offer_router(program_id);
+    // Same fee accounts as an escrowed fill, with the maker's ATA and the router in place of the
+    // vault.
+    let mut instruction = accept_escrow_offer(
+        program_id,
+        maker_wallet,
+        taker_wallet,
+        routed_offer,
+        maker_dst_account,
+        taker_src_account,
+        taker_dst_account,
+        token_program_id,
+        public_values,
+        metadata_data,
+    )?;
+    let metadata_bump_seed = metadata_data
+        .and_then(|data| metadata::decode_metadata(data).ok())
+        .map(|metadata| metadata::find_metadata_address(&metadata.mint).1);
+    instruction.data = StatelessOfferInstruction::AcceptRoutedOffer {
+        metadata_bump_seed,
+        router_bump_seed,
+        taker_guard: TakerGuard::default(),
+    }
+    .pack();
+    let maker_mint = &public_values.maker_mint;
+    instruction.accounts[3] = AccountMeta::new(
+        get_associated_token_address(maker_wallet, maker_mint),
+        false,
+    );
+    instruction
+        .accounts
+        .insert(9, AccountMeta::new_readonly(router, false));
+    Ok(instruction)
+}
+
+/// Creates a 'close routed offer' instruction.
+pub fn close_routed_offer(
+    program_id: &Pubkey,
+    maker_wallet: &Pubkey,
+    routed_offer: &Pubkey,
+) -> Instruction {
+    let data = StatelessOfferInstruction::CloseRoutedOffer.pack();
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*maker_wallet, true),
+            AccountMeta::new(*routed_offer, false),
+        ],
+        data,
+    }
+}
+
+/// Creates an 'init sealed auction' instruction moving `public_values.maker_size` from the
+/// maker's ATA into the vault of `auction`.
+#[allow(clippy::too_many_arguments)]
+pub fn init_sealed_auction(
+    program_id: &Pubkey,
+    maker_wallet: &Pubkey,
+    auction: &Pubkey,
+    token_program_id: &Pubkey,
+    public_values: PublicValuesStruct,
+    nonce: u64,
+    bump_seed: u8,
+    bid_vkey_hash: [u8; 32],
+    commit_end_slot: u64,
+    reveal_end_slot: u64,
+) -> Instruction {
+    // Same accounts as an escrowed offer, with the auction in place of the escrow offer.
+    let mut instruction = init_escrow_offer(
+        program_id,
+        maker_wallet,
+        auction,
+        token_program_id,
+        public_values.clone(),
+        nonce,
+        bump_seed,
+    );
+    instruction.data = StatelessOfferInstruction::InitSealedAuction {
+        nonce,
+        bump_seed,
+        public_values: public_values.to_canonical_bytes(),
+        bid_vkey_hash,
+        commit_end_slot,
+        reveal_end_slot,
+    }
+    .pack();
+    instruction
+}
+
+/// Creates a 'commit sealed bid' instruction recording `commitment` in the bid account `bid`
+/// and escrowing `deposit` of `taker_mint` from the bidder's ATA.
+///
+/// The deposit must cover the bid. Depositing more than the bid keeps the amount hidden until
+/// the reveal.
+#[allow(clippy::too_many_arguments)]
+pub fn commit_sealed_bid(
+    program_id: &Pubkey,
+    bidder: &Pubkey,
+    auction: &Pubkey,
+    bid: &Pubkey,
+    taker_mint: &Pubkey,
+    token_program_id: &Pubkey,
+    bump_seed: u8,
+    commitment: [u8; 32],
+    deposit: u64,
+) -> Instruction {
+    let data = StatelessOfferInstruction::CommitSealedBid {
+        bump_seed,
+        commitment,
+        deposit,
+    }
+    .pack();
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*bidder, true),
+            AccountMeta::new_readonly(*auction, false),
+            AccountMeta::new(*bid, false),
+            AccountMeta::new(compute_sealed_vault(bid, taker_mint), false),
+            AccountMeta::new(get_associated_token_address(bidder, taker_mint), false),
+            AccountMeta::new_readonly(*taker_mint, false),
+            AccountMeta::new_readonly(*token_program_id, false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data,
+    }
+}
+
+/// Creates a 'reveal sealed bid' instruction returning the deposit above `amount` to the
+/// bidder's ATA.
+///
+/// `proof` holds the bytes of the Groth16 reveal proof, as returned by
+/// `SP1ProofWithPublicValues::bytes`.
+#[allow(clippy::too_many_arguments)]
+pub fn reveal_sealed_bid(
+    program_id: &Pubkey,
+    bidder: &Pubkey,
+    auction: &Pubkey,
+    bid: &Pubkey,
+    taker_mint: &Pubkey,
+    token_program_id: &Pubkey,
+    amount: u64,
+    proof: Vec<u8>,
+) -> Instruction {
+    let data = StatelessOfferInstruction::RevealSealedBid { amount, proof }.pack();
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*bidder, true),
+            AccountMeta::new(*auction, false),
+            AccountMeta::new(*bid, false),
+            AccountMeta::new(compute_sealed_vault(bid, taker_mint), false),
+            AccountMeta::new(get_associated_token_address(bidder, taker_mint), false),
+            AccountMeta::new_readonly(*taker_mint, false),
+            AccountMeta::new_readonly(*token_program_id, false),
+        ],
+        data,
+    }
+}
+
+/// Creates a 'settle sealed auction' instruction.
+///
+/// `best_bid` is the bidder and bid account recorded as the best bid of the auction, if any.
+/// `metadata_data` is the raw metadata account data of the NFT leg when royalties should be
+/// paid.
+#[allow(clippy::too_many_arguments)]
+pub fn settle_sealed_auction(
+    program_id: &Pubkey,
+    maker_wallet: &Pubkey,
+    auction: &Pubkey,
+    token_program_id: &Pubkey,
+    public_values: &PublicValuesStruct,
+    best_bid: Option<(&Pubkey, &Pubkey)>,
+    metadata_data: Option<&[u8]>,
+) -> Result<Instruction, ProgramError> {
+    let maker_mint = &public_values.maker_mint;
+    let taker_mint = public_values
+        .taker_mint
+        .ok_or(ProgramError::InvalidArgument)?;
+    let mut accounts = vec![
+        AccountMeta::new(*maker_wallet, false),
+        AccountMeta::new(*auction, false),
+        AccountMeta::new(compute_sealed_vault(auction, maker_mint), false),
+        AccountMeta::new(get_associated_token_address(maker_wallet, maker_mint), false),
+        AccountMeta::new(get_associated_token_address(maker_wallet, &taker_mint), false),
+        AccountMeta::new_readonly(*maker_mint, false),
+        AccountMeta::new_readonly(taker_mint, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    let mut metadata_bump_seed = None;
+    if let Some((bidder, bid)) = best_bid {
+        accounts.extend([
+            AccountMeta::new(*bidder, false),
+            AccountMeta::new(*bid, false),
+            AccountMeta::new(compute_sealed_vault(bid, &taker_mint), false),
+            AccountMeta::new(get_associated_token_address(bidder, maker_mint), false),
+        ]);
+        // The fee accounts are those of an escrowed fill of the same terms.
+        let fill = accept_escrow_offer(
+            program_id,
+            maker_wallet,
+            bidder,
+            auction,
+            &accounts[4].pubkey,
+            &accounts[10].pubkey,
+            &accounts[11].pubkey,
+            token_program_id,
+            public_values,
+            metadata_data,
+        )?;
+        metadata_bump_seed = metadata_data
+            .and_then(|data| metadata::decode_metadata(data).ok())
+            .map(|metadata| metadata::find_metadata_address(&metadata.mint).1);
+        accounts.extend(fill.accounts.into_iter().skip(10));
+    }
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: StatelessOfferInstruction::SettleSealedAuction { metadata_bump_seed }.pack(),
+    })
+}
+
+/// Creates a 'close sealed bid' instruction returning a revealed bid to the bidder's ATA.
+pub fn close_sealed_bid(
+    program_id: &Pubkey,
+    bidder: &Pubkey,
+    maker_wallet: &Pubkey,
+    auction: &Pubkey,
+    bid: &Pubkey,
+    taker_mint: &Pubkey,
+    token_program_id: &Pubkey,
+) -> Instruction {
+    let data = StatelessOfferInstruction::CloseSealedBid.pack();
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*bidder, true),
+            AccountMeta::new_readonly(*auction, false),
+            AccountMeta::new(*bid, false),
+            AccountMeta::new(compute_sealed_vault(bid, taker_mint), false),
+            AccountMeta::new(get_associated_token_address(bidder, taker_mint), false),
+            AccountMeta::new(get_associated_token_address(maker_wallet, taker_mint), false),
+            AccountMeta::new_readonly(*taker_mint, false),
+            AccountMeta::new_readonly(*token_program_id, false),
+        ],
+        data,
+    }
+}
+
+/// Creates a 'close sealed bid' instruction forfeiting the deposit of a bid left unrevealed to
+/// the maker's ATA, which anyone can send once the reveal phase is over.
+pub fn forfeit_sealed_bid(
+    program_id: &Pubkey,
+    bidder: &Pubkey,
+    maker_wallet: &Pubkey,
+    auction: &Pubkey,
+    bid: &Pubkey,
+    taker_mint: &Pubkey,
+    token_program_id: &Pubkey,
+) -> Instruction {
+    let mut instruction = close_sealed_bid(
+        program_id,
+        bidder,
+        maker_wallet,
+        auction,
+        bid,
+        taker_mint,
+        token_program_id,
+    );
+    instruction.accounts[0].is_signer = false;
+    instruction
+}
+
+/// Creates a 'post offer batch' instruction recording `root` in the batch account `offer_batch`.
+///
+/// `proof` holds the bytes of the Groth16 batch proof, as returned by
+/// `SP1ProofWithPublicValues::bytes`.
+#[allow(clippy::too_many_arguments)]
+pub fn post_offer_batch(
+    program_id: &Pubkey,
+    poster: &Pubkey,
+    offer_batch: &Pubkey,
+    bump_seed: u8,
+    vkey_hash: [u8; 32],
+    root: [u8; 32],
+    offer_count: u32,
+    proof: Vec<u8>,
+) -> Instruction {
+    let data = StatelessOfferInstruction::PostOfferBatch {
+        bump_seed,
+        vkey_hash,
+        root,
+        offer_count,
+        proof,
+    }
+    .pack();
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*poster, true),
+            AccountMeta::new(*offer_batch, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data,
+    }
+}
+
+/// Creates an 'accept batched offer' instruction filling the delegated offer of `maker_wallet`
+/// with the terms `public_values`, part of the batch with `root` posted at `offer_batch`.
+///
+/// The token accounts are the ATAs of both wallets, or the wallets themselves for a taker leg in
+/// SOL. `metadata_data` is the raw metadata account data of the NFT leg when royalties should be
+/// paid.
+#[allow(clippy::too_many_arguments)]
+pub fn accept_batched_offer(
+    program_id: &Pubkey,
+    offer_batch: &Pubkey,
+    root: &[u8; 32],
+    maker_wallet: &Pubkey,
+    taker_wallet: &Pubkey,
+    token_program_id: &Pubkey,
+    public_values: &PublicValuesStruct,
+    merkle_proof: Vec<[u8; 32]>,
+    metadata_data: Option<&[u8]>,
+) -> Result<Instruction, ProgramError> {
+    let maker_mint = &public_values.maker_mint;
+    let taker_mint = public_values
+        .taker_mint
+        .unwrap_or_else(spl_token::native_mint::id);
+    let taker_size = public_values
+        .taker_size
+        .ok_or(ProgramError::InvalidArgument)?;
+    let is_native = public_values.is_native;
+    let (authority, bump_seed) =
+        compute_batched_offer_authority(program_id, maker_wallet, root, public_values);
+    let (maker_dst_account, taker_src_account) = if is_native {
+        (*maker_wallet, *taker_wallet)
+    } else {
+        (
+            get_associated_token_address(maker_wallet, &taker_mint),
+            get_associated_token_address(taker_wallet, &taker_mint),
+        )
+    };
+    let maker_src_account = get_associated_token_address(maker_wallet, maker_mint);
+    let taker_dst_account = get_associated_token_address(taker_wallet, maker_mint);
+    let mut instruction = match metadata_data {
+        Some(metadata_data) => accept_offer_with_metadata_account(
+            program_id,
+            maker_wallet,
+            taker_wallet,
+            &maker_src_account,
+            &maker_dst_account,
+            &taker_src_account,
+            &taker_dst_account,
+            maker_mint,
+            &taker_mint,
+            &authority,
+            token_program_id,
+            metadata_data,
+            is_native,
+            public_values.platform_fee.as_ref(),
+            public_values.maker_size,
+            taker_size,
+            bump_seed,
+        )?,
+        None => accept_offer(
+            program_id,
+            maker_wallet,
+            taker_wallet,
+            &maker_src_account,
+            &maker_dst_account,
+            &taker_src_account,
+            &taker_dst_account,
+            maker_mint,
+            &taker_mint,
+            &authority,
+            token_program_id,
+            is_native,
+            public_values.platform_fee.as_ref(),
+            public_values.maker_size,
+            taker_size,
+            bump_seed,
+        ),
+    };
+    let metadata_bump_seed = metadata_data
+        .and_then(|data| metadata::decode_metadata(data).ok())
+        .map(|metadata| metadata::find_metadata_address(&metadata.mint).1);
+    instruction
+        .accounts
+        .insert(0, AccountMeta::new_readonly(*offer_batch, false));
+    instruction.data = StatelessOfferInstruction::AcceptBatchedOffer {
+        metadata_bump_seed,
+        bump_seed,
+        public_values: public_values.to_canonical_bytes(),
+        merkle_proof,
+        taker_guard: TakerGuard::default(),
+    }
+    .pack();
+    Ok(instruction)
+}
+
+/// Creates a 'close offer batch' instruction returning the rent of `offer_batch` to its poster.
+pub fn close_offer_batch(
+    program_id: &Pubkey,
+    poster: &Pubkey,
+    offer_batch: &Pubkey,
+) -> Instruction {
+    let data = StatelessOfferInstruction::CloseOfferBatch.pack();
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*poster, true),
+            AccountMeta::new(*offer_batch, false),
+        ],
+        data,
+    }
+}
+
+/// The account receiving the platform fee, the recipient wallet for native SOL and its ATA
+/// otherwise.
+fn platform_fee_account(
+    platform_fee: &PlatformFee,
+    fee_mint: &Pubkey,
+    is_native: bool,
+) -> AccountMeta {
+    if is_native {
+        AccountMeta::new(platform_fee.recipient, false)
+    } else {
+        AccountMeta::new(
+            get_associated_token_address(&platform_fee.recipient, fee_mint),
+            false,
+        )
+    }
+}
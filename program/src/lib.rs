@@ -1,6 +1,7 @@
 pub mod errors;
 pub mod instruction;
 pub mod processor;
+pub mod state;
 pub mod validation_utils;
 
 #[cfg(not(feature = "no-entrypoint"))]
@@ -3,9 +3,15 @@
 #![no_main]
 sp1_zkvm::entrypoint!(main);
 
-use borsh::BorshSerialize;
+use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::pubkey::Pubkey;
-use solana_zk_offers::zk_offers::PublicValuesStruct;
+use solana_zk_offers::{
+    aggregation::{AggregatedOfferProofs, AggregatedProof},
+    offer_batch::{check_batch_offer, merkle_root, offer_leaf, OfferBatchRoot},
+    sealed_bid::{bid_commitment, SealedBidReveal},
+    solvency::MakerSolvency,
+    zk_offers::{GuestMode, PlatformFee, PublicValuesStruct},
+};
 use std::process;
 
 fn read_pubkey() -> Pubkey {
@@ -32,6 +38,23 @@ fn read_input() -> u64 {
     sp1_zkvm::io::read::<u64>()
 }
 
+/// Read the platform fee basis points, followed by the recipient when non-zero.
+fn read_platform_fee() -> Option<PlatformFee> {
+    let basis_points = sp1_zkvm::io::read::<u16>();
+    if basis_points == 0 {
+        return None;
+    }
+    let platform_fee = PlatformFee {
+        basis_points,
+        recipient: read_pubkey(),
+    };
+    if !platform_fee.is_valid() {
+        eprintln!("Error: Platform fee exceeds the program cap");
+        process::exit(1);
+    }
+    Some(platform_fee)
+}
+
 fn serialize_public_values(public_values: &PublicValuesStruct) -> Vec<u8> {
     let mut bytes = Vec::new();
     public_values
@@ -51,10 +74,22 @@ pub fn main() {
     // Behind the scenes, this compiles down to a custom system call which handles reading inputs
     // from the prover.
 
+    match sp1_zkvm::io::read::<GuestMode>() {
+        GuestMode::Offer => prove_offer(),
+        GuestMode::SealedBidReveal => prove_sealed_bid_reveal(),
+        GuestMode::Solvency => prove_solvency(),
+        GuestMode::OfferBatch => prove_offer_batch(),
+        GuestMode::Aggregate => aggregate_offer_proofs(),
+    }
+}
+
+fn prove_offer() {
     let maker_mint: Pubkey = read_pubkey();
 
     let maker_size: u64 = read_input();
 
+    let platform_fee: Option<PlatformFee> = read_platform_fee();
+
     // Encode the public values of the program.
     let public_values: PublicValuesStruct = PublicValuesStruct {
         maker_mint,
@@ -62,13 +97,120 @@ pub fn main() {
         is_native: true,
         taker_mint: None,
         taker_size: None,
+        platform_fee,
     };
-    let mut bytes = Vec::new();
-    public_values
-        .serialize(&mut bytes)
-        .expect("Failed to serialize");
 
-    // Commit to the public values of the program. The final proof will have a commitment to all the
-    // bytes that were committed to.
-    sp1_zkvm::io::commit_slice(&bytes);
+    // Commit to the canonical encoding of the public values. The final proof will have a
+    // commitment to all the bytes that were committed to, whose digest is
+    // `public_values.public_values_digest()`.
+    sp1_zkvm::io::commit_slice(&public_values.to_canonical_bytes());
+}
+
+/// Open a sealed bid: the commitment is recomputed from the private salt, so the proof shows the
+/// revealed amount is the one committed to, and that it meets the reserve of the auction.
+fn prove_sealed_bid_reveal() {
+    let auction = read_pubkey();
+    let bidder = read_pubkey();
+    let public_values = PublicValuesStruct::from_canonical_bytes(&sp1_zkvm::io::read_vec())
+        .unwrap_or_else(|| {
+            eprintln!("Error: Failed to decode the auction terms");
+            process::exit(1);
+        });
+    let amount = read_input();
+    let salt = sp1_zkvm::io::read::<[u8; 32]>();
+
+    let reserve = public_values.taker_size.unwrap_or_else(|| {
+        eprintln!("Error: The auction has no reserve");
+        process::exit(1);
+    });
+    if amount < reserve {
+        eprintln!("Error: Bid is below the reserve");
+        process::exit(1);
+    }
+
+    let reveal = SealedBidReveal {
+        auction,
+        bidder,
+        commitment: bid_commitment(&auction, &bidder, amount, &salt),
+        public_values_digest: public_values.public_values_digest(),
+        amount,
+    };
+    commit_public_values(&borsh::to_vec(&reveal).expect("Failed to serialize reveal"));
+}
+
+/// Check a snapshot of the maker's token account and commit whether it held the maker size at the
+/// given slot. Snapshots of another owner's or mint's account, or of no token account at all, do
+/// not produce a proof.
+fn prove_solvency() {
+    let maker_wallet = read_pubkey();
+    let maker_mint = read_pubkey();
+    let maker_size = read_input();
+    let slot = read_input();
+    let bank_hash = sp1_zkvm::io::read::<[u8; 32]>();
+    let token_account_data = sp1_zkvm::io::read_vec();
+
+    let solvency = MakerSolvency::from_snapshot(
+        &maker_wallet,
+        &maker_mint,
+        maker_size,
+        slot,
+        bank_hash,
+        &token_account_data,
+    )
+    .unwrap_or_else(|issue| {
+        eprintln!("Error: {}", issue);
+        process::exit(1);
+    });
+    commit_public_values(&borsh::to_vec(&solvency).expect("Failed to serialize solvency"));
+}
+
+/// Check every offer of a batch and commit the Merkle root of their leaves, so that one proof
+/// covers the whole batch.
+fn prove_offer_batch() {
+    let offers = Vec::<PublicValuesStruct>::try_from_slice(&sp1_zkvm::io::read_vec())
+        .unwrap_or_else(|_| {
+            eprintln!("Error: Failed to decode the offers");
+            process::exit(1);
+        });
+    let mut leaves = Vec::with_capacity(offers.len());
+    for public_values in &offers {
+        if let Err(err) = check_batch_offer(public_values) {
+            eprintln!("Error: {}", err);
+            process::exit(1);
+        }
+        leaves.push(offer_leaf(public_values));
+    }
+    let root = merkle_root(&leaves).unwrap_or_else(|| {
+        eprintln!("Error: The batch is empty");
+        process::exit(1);
+    });
+    let offer_count = u32::try_from(leaves.len()).unwrap_or_else(|_| {
+        eprintln!("Error: The batch is too large");
+        process::exit(1);
+    });
+
+    let batch = OfferBatchRoot { root, offer_count };
+    commit_public_values(&borsh::to_vec(&batch).expect("Failed to serialize batch"));
+}
+
+/// Verify a list of compressed proofs, supplied by the prover alongside the verification keys and
+/// public values read here, and commit what each of them proved.
+fn aggregate_offer_proofs() {
+    let vkeys = sp1_zkvm::io::read::<Vec<[u32; 8]>>();
+    let public_values = sp1_zkvm::io::read::<Vec<Vec<u8>>>();
+    if vkeys.is_empty() || vkeys.len() != public_values.len() {
+        eprintln!("Error: Expected one set of public values per verification key");
+        process::exit(1);
+    }
+
+    let mut proofs = Vec::with_capacity(vkeys.len());
+    for (vkey, public_values) in vkeys.iter().zip(&public_values) {
+        let proof = AggregatedProof::new(*vkey, public_values);
+        // Fails the whole run unless the prover supplied a valid proof for this key and digest.
+        sp1_zkvm::lib::verify::verify_sp1_proof(&proof.vkey, &proof.public_values_digest);
+        proofs.push(proof);
+    }
+
+    let aggregated = AggregatedOfferProofs { proofs };
+    commit_public_values(&borsh::to_vec(&aggregated).expect("Failed to serialize aggregation"));
 }
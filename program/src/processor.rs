@@ -3,58 +3,37 @@
 use {
     crate::{
         errors::UtilError,
-        instruction::StatelessOfferInstruction,
-        validation_utils::{assert_is_ata, assert_keys_equal},
+        instruction::{StatelessOfferInstruction, TakerGuard},
+        state::{AccountKey, EscrowOffer, OfferBatch, RoutedOffer, SealedAuction, SealedBid},
+        validation_utils::{assert_is_ata, assert_keys_equal, assert_owned_by},
     },
-    borsh::{BorshDeserialize, BorshSerialize},
+    borsh::BorshSerialize,
+    mpl_token_metadata::instructions::TransferV1CpiBuilder,
     solana_program::{
         account_info::next_account_info, borsh1::try_from_slice_unchecked, entrypoint,
         entrypoint_deprecated::ProgramResult, instruction::AccountMeta, msg, program::invoke,
         program::invoke_signed, program_option::COption, program_pack::Pack, system_instruction,
         system_program, sysvar::slot_history::AccountInfo, sysvar::slot_history::ProgramError,
+        sysvar::{clock::Clock, rent::Rent, Sysvar},
     },
     solana_pubkey::Pubkey,
+    solana_zk_offers::{
+        aggregation::committed_values_digest,
+        events::{FeePaid, FillEvent},
+        offer_batch::{digest_leaf, verify_merkle_proof, OfferBatchRoot},
+        sealed_bid::{format_vkey_hash, SealedBidReveal},
+        zk_offers::{
+            collection_offer_prefix, PlatformFee, PublicValuesStruct, CANONICAL_PUBLIC_VALUES_LEN,
+            MAX_PLATFORM_FEE_BASIS_POINTS, OFFER_VKEY_HASH,
+        },
+    },
+    spl_associated_token_account::instruction::create_associated_token_account_idempotent,
     spl_token::state::Account,
     std::slice::Iter,
 };
 
-pub(crate) mod inline_mpl_token_metadata {
-    solana_program::declare_id!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
-    use borsh::{BorshDeserialize, BorshSerialize};
-    use solana_pubkey::Pubkey;
-    #[derive(Clone, Debug, PartialEq, Eq, BorshDeserialize)]
-    pub(crate) struct Metadata {
-        /// Account discriminator.
-        pub key: u8,
-        /// Address of the update authority.
-        pub update_authority: Pubkey,
-        /// Address of the mint.
-        pub mint: Pubkey,
-        /// Asset data.
-        pub data: Data,
-    }
-
-    #[derive(BorshDeserialize, Default, PartialEq, Eq, Debug, Clone)]
-    pub(crate) struct Data {
-        /// The name of the asset
-        pub name: String,
-        /// The symbol for the asset
-        pub symbol: String,
-        /// URI pointing to JSON representing the asset
-        pub uri: String,
-        /// Royalty basis points that goes to creators in secondary sales
-        /// (0-10000)
-        pub seller_fee_basis_points: u16,
-        /// Array of creators, optional
-        pub creators: Option<Vec<Creator>>,
-    }
-
-    #[derive(BorshDeserialize, PartialEq, Debug, Clone, Eq, Hash)]
-    pub(crate) struct Creator {
-        pub address: Pubkey,
-        pub verified: bool,
-        pub share: u8,
-    }
+pub mod inline_mpl_token_metadata {
+    pub use solana_zk_offers::metadata::{id, Collection, Creator, Data, Metadata, ID};
 }
 
 /// Program state handler.
@@ -62,32 +41,283 @@ pub struct Processor {}
 impl Processor {
     /// Processes [Instruction](enum.Instruction.html).
     pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
-        let instruction = StatelessOfferInstruction::try_from_slice(input)?;
+        let instruction = StatelessOfferInstruction::unpack(input)?;
         match instruction {
             StatelessOfferInstruction::AcceptOffer {
-                has_metadata,
+                metadata_bump_seed,
                 maker_size,
                 taker_size,
                 bump_seed,
+                platform_fee,
+                taker_guard,
             } => {
                 msg!("Instruction: accept offer");
                 process_accept_offer(
                     program_id,
                     accounts,
-                    has_metadata,
+                    AcceptOptions {
+                        metadata_bump_seed,
+                        platform_fee,
+                        taker_guard,
+                        ..AcceptOptions::default()
+                    },
+                    maker_size,
+                    taker_size,
+                    bump_seed,
+                )
+            }
+            StatelessOfferInstruction::AcceptProgrammableOffer {
+                metadata_bump_seed,
+                maker_size,
+                taker_size,
+                bump_seed,
+                platform_fee,
+                taker_guard,
+            } => {
+                msg!("Instruction: accept programmable offer");
+                process_accept_offer(
+                    program_id,
+                    accounts,
+                    AcceptOptions {
+                        metadata_bump_seed,
+                        programmable: true,
+                        platform_fee,
+                        taker_guard,
+                        ..AcceptOptions::default()
+                    },
+                    maker_size,
+                    taker_size,
+                    bump_seed,
+                )
+            }
+            StatelessOfferInstruction::AcceptCollectionOffer {
+                collection,
+                verified_creators_only,
+                metadata_bump_seed,
+                maker_size,
+                taker_size,
+                bump_seed,
+                platform_fee,
+                taker_guard,
+            } => {
+                msg!("Instruction: accept collection offer");
+                process_accept_offer(
+                    program_id,
+                    accounts,
+                    AcceptOptions {
+                        metadata_bump_seed: Some(metadata_bump_seed),
+                        collection: Some(collection),
+                        verified_creators_only,
+                        platform_fee,
+                        taker_guard,
+                        ..AcceptOptions::default()
+                    },
                     maker_size,
                     taker_size,
                     bump_seed,
                 )
             }
+            StatelessOfferInstruction::InitEscrowOffer {
+                nonce,
+                bump_seed,
+                public_values,
+            } => {
+                msg!("Instruction: init escrow offer");
+                let public_values = unpack_public_values(&public_values)?;
+                process_init_escrow_offer(program_id, accounts, nonce, bump_seed, public_values)
+            }
+            StatelessOfferInstruction::AcceptEscrowOffer {
+                metadata_bump_seed,
+                taker_guard,
+            } => {
+                msg!("Instruction: accept escrow offer");
+                process_accept_escrow_offer(program_id, accounts, metadata_bump_seed, taker_guard)
+            }
+            StatelessOfferInstruction::CloseEscrowOffer => {
+                msg!("Instruction: close escrow offer");
+                process_close_escrow_offer(program_id, accounts)
+            }
+            StatelessOfferInstruction::InitRoutedOffer {
+                nonce,
+                bump_seed,
+                public_values,
+            } => {
+                msg!("Instruction: init routed offer");
+                let public_values = unpack_public_values(&public_values)?;
+                process_init_routed_offer(program_id, accounts, nonce, bump_seed, public_values)
+            }
+            StatelessOfferInstruction::AcceptRoutedOffer {
+                metadata_bump_seed,
+                router_bump_seed,
+                taker_guard,
+            } => {
+                msg!("Instruction: accept routed offer");
+                process_accept_routed_offer(
+                    program_id,
+                    accounts,
+                    metadata_bump_seed,
+                    router_bump_seed,
+                    taker_guard,
+                )
+            }
+            StatelessOfferInstruction::CloseRoutedOffer => {
+                msg!("Instruction: close routed offer");
+                process_close_routed_offer(program_id, accounts)
+            }
+            StatelessOfferInstruction::InitSealedAuction {
+                nonce,
+                bump_seed,
+                public_values,
+                bid_vkey_hash,
+                commit_end_slot,
+                reveal_end_slot,
+            } => {
+                msg!("Instruction: init sealed auction");
+                let public_values = unpack_public_values(&public_values)?;
+                process_init_sealed_auction(
+                    program_id,
+                    accounts,
+                    nonce,
+                    bump_seed,
+                    public_values,
+                    bid_vkey_hash,
+                    commit_end_slot,
+                    reveal_end_slot,
+                )
+            }
+            StatelessOfferInstruction::CommitSealedBid {
+                bump_seed,
+                commitment,
+                deposit,
+            } => {
+                msg!("Instruction: commit sealed bid");
+                process_commit_sealed_bid(program_id, accounts, bump_seed, commitment, deposit)
+            }
+            StatelessOfferInstruction::RevealSealedBid { amount, proof } => {
+                msg!("Instruction: reveal sealed bid");
+                process_reveal_sealed_bid(program_id, accounts, amount, &proof)
+            }
+            StatelessOfferInstruction::SettleSealedAuction { metadata_bump_seed } => {
+                msg!("Instruction: settle sealed auction");
+                process_settle_sealed_auction(program_id, accounts, metadata_bump_seed)
+            }
+            StatelessOfferInstruction::CloseSealedBid => {
+                msg!("Instruction: close sealed bid");
+                process_close_sealed_bid(program_id, accounts)
+            }
+            StatelessOfferInstruction::PostOfferBatch {
+                bump_seed,
+                vkey_hash,
+                root,
+                offer_count,
+                proof,
+            } => {
+                msg!("Instruction: post offer batch");
+                process_post_offer_batch(
+                    program_id,
+                    accounts,
+                    bump_seed,
+                    vkey_hash,
+                    root,
+                    offer_count,
+                    &proof,
+                )
+            }
+            StatelessOfferInstruction::AcceptBatchedOffer {
+                metadata_bump_seed,
+                bump_seed,
+                public_values,
+                merkle_proof,
+                taker_guard,
+            } => {
+                msg!("Instruction: accept batched offer");
+                process_accept_batched_offer(
+                    program_id,
+                    accounts,
+                    metadata_bump_seed,
+                    bump_seed,
+                    &public_values,
+                    &merkle_proof,
+                    taker_guard,
+                )
+            }
+            StatelessOfferInstruction::CloseOfferBatch => {
+                msg!("Instruction: close offer batch");
+                process_close_offer_batch(program_id, accounts)
+            }
         }
     }
 }
 
+/// Variant specific behaviour of the accept instructions.
+#[derive(Default)]
+struct AcceptOptions {
+    /// Royalties are paid from the metadata account following the transfer accounts, whose bump
+    /// this is.
+    metadata_bump_seed: Option<u8>,
+    /// The maker leg is a programmable NFT moved through Token Metadata.
+    programmable: bool,
+    /// The taker leg is any verified member of this collection, which replaces the taker mint in
+    /// the authority seeds.
+    collection: Option<Pubkey>,
+    /// Only verified creators are paid royalties.
+    verified_creators_only: bool,
+    /// Fee paid to the venue, committed in the authority seeds.
+    platform_fee: Option<PlatformFee>,
+    /// Bounds the taker put on what the fill moves after fees.
+    taker_guard: TakerGuard,
+    /// The offer is part of a posted batch, whose authority seeds replace those of its terms.
+    batched_offer: Option<BatchedOffer>,
+}
+
+/// What the authority of a batched offer is derived from, besides its maker.
+struct BatchedOffer {
+    root: [u8; 32],
+    /// Digest of the canonical terms committed in the batch.
+    public_values_digest: [u8; 32],
+}
+
+/// Token Metadata accounts needed to move a programmable NFT.
+struct ProgrammableAccounts<'a, 'b> {
+    token_metadata_program: &'b AccountInfo<'a>,
+    metadata: &'b AccountInfo<'a>,
+    edition: &'b AccountInfo<'a>,
+    owner_token_record: &'b AccountInfo<'a>,
+    destination_token_record: &'b AccountInfo<'a>,
+    system_program: &'b AccountInfo<'a>,
+    sysvar_instructions: &'b AccountInfo<'a>,
+    ata_program: &'b AccountInfo<'a>,
+    authorization_rules_program: Option<&'b AccountInfo<'a>>,
+    authorization_rules: Option<&'b AccountInfo<'a>>,
+}
+
+impl<'a, 'b> ProgrammableAccounts<'a, 'b> {
+    fn next(account_info_iter: &mut Iter<'b, AccountInfo<'a>>) -> Result<Self, ProgramError> {
+        let token_metadata_program = next_account_info(account_info_iter)?;
+        assert_keys_equal(inline_mpl_token_metadata::id(), *token_metadata_program.key)?;
+        // Token Metadata takes its own program id in place of an absent optional account
+        let optional = |account: &'b AccountInfo<'a>| {
+            (*account.key != inline_mpl_token_metadata::id()).then_some(account)
+        };
+        Ok(Self {
+            token_metadata_program,
+            metadata: next_account_info(account_info_iter)?,
+            edition: next_account_info(account_info_iter)?,
+            owner_token_record: next_account_info(account_info_iter)?,
+            destination_token_record: next_account_info(account_info_iter)?,
+            system_program: next_account_info(account_info_iter)?,
+            sysvar_instructions: next_account_info(account_info_iter)?,
+            ata_program: next_account_info(account_info_iter)?,
+            authorization_rules_program: optional(next_account_info(account_info_iter)?),
+            authorization_rules: optional(next_account_info(account_info_iter)?),
+        })
+    }
+}
+
 fn process_accept_offer(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
-    has_metadata: bool,
+    options: AcceptOptions,
     maker_size: u64,
     taker_size: u64,
     bump_seed: u8,
@@ -105,75 +335,79 @@ fn process_accept_offer(
     let token_program_info = next_account_info(account_info_iter)?;
     let mut system_program_info: Option<&AccountInfo> = None;
     let is_native = *taker_src_mint.key == spl_token::native_mint::id();
+    // A maker offering SOL does so from a delegated wrapped SOL account, which is moved like
+    // any other SPL leg. Only the taker leg can be paid in native lamports.
+    if is_native && *maker_src_mint.key == spl_token::native_mint::id() {
+        msg!("Maker and taker cannot both trade SOL");
+        return Err(ProgramError::InvalidArgument);
+    }
     if is_native {
         assert_keys_equal(*taker_wallet.key, *taker_src_account.key)?;
         assert_keys_equal(*maker_wallet.key, *maker_dst_account.key)?;
         system_program_info = Some(next_account_info(account_info_iter)?);
     }
-    let seeds = &[
-        b"stateless_offer",
-        maker_wallet.key.as_ref(),
-        maker_src_mint.key.as_ref(),
-        taker_src_mint.key.as_ref(),
-        &maker_size.to_le_bytes(),
-        &taker_size.to_le_bytes(),
-        &[bump_seed],
-    ];
-    let (maker_pay_size, taker_pay_size) = if has_metadata {
-        let metadata_info = next_account_info(account_info_iter)?;
-        let (maker_metadata_key, _) = Pubkey::find_program_address(
-            &[
-                b"metadata",
-                inline_mpl_token_metadata::id().as_ref(),
-                maker_src_mint.key.as_ref(),
-            ],
-            &inline_mpl_token_metadata::id(),
-        );
-        let (taker_metadata_key, _) = Pubkey::find_program_address(
-            &[
-                b"metadata",
-                inline_mpl_token_metadata::id().as_ref(),
-                taker_src_mint.key.as_ref(),
-            ],
-            &inline_mpl_token_metadata::id(),
-        );
-        if *metadata_info.key == maker_metadata_key {
-            msg!("Taker pays for fees");
-            let taker_remaining_size = pay_creator_fees(
-                account_info_iter,
-                metadata_info,
-                taker_src_account,
-                taker_wallet,
-                token_program_info,
-                system_program_info,
-                taker_src_mint,
-                taker_size,
-                is_native,
-                &[],
-            )?;
-            (maker_size, taker_remaining_size)
-        } else if *metadata_info.key == taker_metadata_key {
-            msg!("Maker pays for fees");
-            let maker_remaining_size = pay_creator_fees(
-                account_info_iter,
-                metadata_info,
-                maker_src_account,
-                transfer_authority, // Delegate signs for transfer
-                token_program_info,
-                system_program_info,
-                maker_src_mint,
-                maker_size,
-                is_native,
-                seeds,
-            )?;
-            (maker_remaining_size, taker_size)
-        } else {
-            msg!("Neither maker nor taker metadata keys match");
-            return Err(ProgramError::InvalidAccountData);
+    if let Some(platform_fee) = &options.platform_fee {
+        if !platform_fee.is_valid() {
+            msg!("Platform fee exceeds {} bps", MAX_PLATFORM_FEE_BASIS_POINTS);
+            return Err(ProgramError::InvalidInstructionData);
         }
+    }
+    let programmable_accounts = if options.programmable {
+        Some(ProgrammableAccounts::next(account_info_iter)?)
     } else {
-        (maker_size, taker_size)
+        None
     };
+    let (seed_prefix, seed_taker_mint): (&[u8], &Pubkey) = match &options.collection {
+        Some(collection) => (
+            collection_offer_prefix(options.verified_creators_only),
+            collection,
+        ),
+        None => (b"stateless_offer", taker_src_mint.key),
+    };
+    // The delegation is to the digest of the terms, so every fill checks the digest of the terms
+    // it moves rather than the terms themselves.
+    let terms_digest = PublicValuesStruct::fill_terms(
+        *maker_src_mint.key,
+        *seed_taker_mint,
+        maker_size,
+        taker_size,
+        options.platform_fee,
+    )
+    .public_values_digest();
+    let bump_seed_bytes = [bump_seed];
+    let mut seeds: Vec<&[u8]> = match &options.batched_offer {
+        Some(batched_offer) => vec![
+            &b"batched_offer"[..],
+            maker_wallet.key.as_ref(),
+            &batched_offer.root,
+            &batched_offer.public_values_digest,
+        ],
+        None => vec![seed_prefix, maker_wallet.key.as_ref(), &terms_digest],
+    };
+    seeds.push(&bump_seed_bytes);
+    let seeds = seeds.as_slice();
+    let payout = pay_fees(
+        account_info_iter,
+        &options,
+        &Leg {
+            src_account: maker_src_account,
+            authority: transfer_authority, // Delegate signs for transfer
+            mint: maker_src_mint,
+            size: maker_size,
+            seeds,
+        },
+        &Leg {
+            src_account: taker_src_account,
+            authority: taker_wallet,
+            mint: taker_src_mint,
+            size: taker_size,
+            seeds: &[],
+        },
+        token_program_info,
+        system_program_info,
+        is_native,
+    )?;
+    let (maker_pay_size, taker_pay_size) = (payout.maker_pay_size, payout.taker_pay_size);
 
     let maker_src_token_account: Account = Account::unpack(&maker_src_account.data.borrow())?;
     // Ensure that the delegated amount is exactly equal to the maker_size
@@ -202,26 +436,53 @@ fn process_accept_offer(
     // Both of these transfers will fail if the `transfer_authority` is the delegate
     // of these ATA's One consideration is that the taker can get tricked in the
     // case that the maker size is greater than the token amount in the maker's
-    // ATA, but these stateless offers should just be invalidated in the client.
+    // ATA, but these stateless offers should just be invalidated in the client,
+    // or shown as funded only with a recent `solana_zk_offers::solvency` proof.
     assert_is_ata(maker_src_account, maker_wallet.key, maker_src_mint.key)?;
     assert_is_ata(taker_dst_account, taker_wallet.key, maker_src_mint.key)?;
-    invoke_signed(
-        &spl_token::instruction::transfer(
-            token_program_info.key,
-            maker_src_account.key,
-            taker_dst_account.key,
-            transfer_authority.key,
-            &[],
-            maker_pay_size,
-        )?,
-        &[
-            maker_src_account.clone(),
-            taker_dst_account.clone(),
-            transfer_authority.clone(),
-            token_program_info.clone(),
-        ],
-        &[seeds],
-    )?;
+    match &programmable_accounts {
+        Some(pnft) => {
+            TransferV1CpiBuilder::new(pnft.token_metadata_program)
+                .token(maker_src_account)
+                .token_owner(maker_wallet)
+                .destination_token(taker_dst_account)
+                .destination_owner(taker_wallet)
+                .mint(maker_src_mint)
+                .metadata(pnft.metadata)
+                .edition(Some(pnft.edition))
+                .token_record(Some(pnft.owner_token_record))
+                .destination_token_record(Some(pnft.destination_token_record))
+                .authority(transfer_authority)
+                .payer(taker_wallet)
+                .system_program(pnft.system_program)
+                .sysvar_instructions(pnft.sysvar_instructions)
+                .spl_token_program(token_program_info)
+                .spl_ata_program(pnft.ata_program)
+                .authorization_rules_program(pnft.authorization_rules_program)
+                .authorization_rules(pnft.authorization_rules)
+                .amount(maker_pay_size)
+                .invoke_signed(&[seeds])?;
+        }
+        None => {
+            invoke_signed(
+                &spl_token::instruction::transfer(
+                    token_program_info.key,
+                    maker_src_account.key,
+                    taker_dst_account.key,
+                    transfer_authority.key,
+                    &[],
+                    maker_pay_size,
+                )?,
+                &[
+                    maker_src_account.clone(),
+                    taker_dst_account.clone(),
+                    transfer_authority.clone(),
+                    token_program_info.clone(),
+                ],
+                &[seeds],
+            )?;
+        }
+    }
     msg!("done tx from maker to taker {}", maker_pay_size);
     if *taker_src_mint.key == spl_token::native_mint::id() {
         match system_program_info {
@@ -263,132 +524,1721 @@ fn process_accept_offer(
         )?;
     }
     msg!("done tx from taker to maker {}", taker_pay_size);
+    FillEvent {
+        offer: *transfer_authority.key,
+        maker_wallet: *maker_wallet.key,
+        taker_wallet: *taker_wallet.key,
+        maker_mint: *maker_src_mint.key,
+        taker_mint: *taker_src_mint.key,
+        maker_size,
+        taker_size,
+        taker_received: maker_pay_size,
+        maker_received: taker_pay_size,
+        fee_mint: payout.fee_mint,
+        creator_fees: payout.creator_fees,
+        platform_fee: payout.platform_fee,
+    }
+    .emit();
     msg!("done!");
     Ok(())
 }
 
-#[allow(clippy::too_many_arguments)]
-fn pay_creator_fees<'a>(
-    account_info_iter: &mut Iter<AccountInfo<'a>>,
-    metadata_info: &AccountInfo<'a>,
-    src_account_info: &AccountInfo<'a>,
-    src_authority_info: &AccountInfo<'a>,
-    token_program_info: &AccountInfo<'a>,
-    system_program_info: Option<&AccountInfo<'a>>,
-    fee_mint: &AccountInfo<'a>,
-    size: u64,
-    is_native: bool,
-    seeds: &[&[u8]],
-) -> Result<u64, ProgramError> {
-    if *metadata_info.owner != inline_mpl_token_metadata::id() {
-        return Err(ProgramError::InvalidAccountData);
-    }
-    let metadata = try_from_slice_unchecked::<inline_mpl_token_metadata::Metadata>(
-        &metadata_info.try_borrow_data()?,
+fn process_init_escrow_offer(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    nonce: u64,
+    bump_seed: u8,
+    public_values: PublicValuesStruct,
+) -> ProgramResult {
+    let escrow_accounts = InitEscrowAccounts::next(&mut accounts.iter())?;
+    let maker_wallet = escrow_accounts.maker_wallet;
+    assert_keys_equal(public_values.maker_mint, *escrow_accounts.maker_mint.key)?;
+    assert_recorded_terms(&public_values)?;
+    let nonce_bytes = nonce.to_le_bytes();
+    let bump_seed_bytes = [bump_seed];
+    let seeds = EscrowOffer::signer_seeds(maker_wallet.key, &nonce_bytes, &bump_seed_bytes);
+    escrow_accounts.escrow(
+        program_id,
+        &seeds,
+        EscrowOffer::LEN,
+        public_values.maker_size,
     )?;
-    let fees = metadata.data.seller_fee_basis_points;
-    let total_fee = (fees as u64)
-        .checked_mul(size)
-        .ok_or(UtilError::NumericalOverflow)?
-        .checked_div(10000)
-        .ok_or(UtilError::NumericalOverflow)?;
-    let mut remaining_fee = total_fee;
-    let remaining_size = size
-        .checked_sub(total_fee)
-        .ok_or(UtilError::NumericalOverflow)?;
-    match metadata.data.creators {
-        Some(creators) => {
-            for creator in creators {
-                let pct = creator.share as u64;
-                let creator_fee = pct
-                    .checked_mul(total_fee)
-                    .ok_or(UtilError::NumericalOverflow)?
-                    .checked_div(100)
-                    .ok_or(UtilError::NumericalOverflow)?;
-                remaining_fee = remaining_fee
-                    .checked_sub(creator_fee)
-                    .ok_or(UtilError::NumericalOverflow)?;
-                let current_creator_info = next_account_info(account_info_iter)?;
-                assert_keys_equal(creator.address, *current_creator_info.key)?;
-                if !is_native {
-                    let current_creator_token_account_info = next_account_info(account_info_iter)?;
-                    assert_is_ata(
-                        current_creator_token_account_info,
-                        current_creator_info.key,
-                        fee_mint.key,
-                    )?;
-                    if creator_fee > 0 {
-                        if seeds.is_empty() {
-                            invoke(
-                                &spl_token::instruction::transfer(
-                                    token_program_info.key,
-                                    src_account_info.key,
-                                    current_creator_token_account_info.key,
-                                    src_authority_info.key,
-                                    &[],
-                                    creator_fee,
-                                )?,
-                                &[
-                                    src_account_info.clone(),
-                                    current_creator_token_account_info.clone(),
-                                    src_authority_info.clone(),
-                                    token_program_info.clone(),
-                                ],
-                            )?;
-                        } else {
-                            invoke_signed(
-                                &spl_token::instruction::transfer(
-                                    token_program_info.key,
-                                    src_account_info.key,
-                                    current_creator_token_account_info.key,
-                                    src_authority_info.key,
-                                    &[],
-                                    creator_fee,
-                                )?,
-                                &[
-                                    src_account_info.clone(),
-                                    current_creator_token_account_info.clone(),
-                                    src_authority_info.clone(),
-                                    token_program_info.clone(),
-                                ],
-                                &[seeds],
-                            )?;
-                        }
-                    }
-                } else if creator_fee > 0 {
-                    if !seeds.is_empty() {
-                        msg!("Maker cannot pay with native SOL");
-                        return Err(ProgramError::InvalidAccountData);
-                    }
-                    match system_program_info {
-                        Some(sys_program_info) => {
-                            invoke(
-                                &system_instruction::transfer(
-                                    src_account_info.key,
-                                    current_creator_info.key,
-                                    creator_fee,
-                                ),
-                                &[
-                                    src_account_info.clone(),
-                                    current_creator_info.clone(),
-                                    sys_program_info.clone(),
-                                ],
-                            )?;
-                        }
-                        None => {
-                            msg!("Invalid System Program Info");
-                            return Err(ProgramError::IncorrectProgramId);
-                        }
-                    }
-                }
-            }
+    let escrow_offer = EscrowOffer {
+        key: AccountKey::EscrowOffer,
+        maker_wallet: *maker_wallet.key,
+        nonce,
+        bump_seed,
+        public_values,
+    };
+    escrow_offer.serialize(&mut &mut escrow_accounts.escrow.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+/// Accounts of an instruction escrowing the maker leg, in the order InitEscrowOffer lists them.
+struct InitEscrowAccounts<'a, 'b> {
+    maker_wallet: &'b AccountInfo<'a>,
+    maker_src_account: &'b AccountInfo<'a>,
+    escrow: &'b AccountInfo<'a>,
+    vault: &'b AccountInfo<'a>,
+    maker_mint: &'b AccountInfo<'a>,
+    token_program: &'b AccountInfo<'a>,
+    ata_program: &'b AccountInfo<'a>,
+    system_program: &'b AccountInfo<'a>,
+}
+
+impl<'a, 'b> InitEscrowAccounts<'a, 'b> {
+    fn next(account_info_iter: &mut Iter<'b, AccountInfo<'a>>) -> Result<Self, ProgramError> {
+        let escrow_accounts = Self {
+            maker_wallet: next_account_info(account_info_iter)?,
+            maker_src_account: next_account_info(account_info_iter)?,
+            escrow: next_account_info(account_info_iter)?,
+            vault: next_account_info(account_info_iter)?,
+            maker_mint: next_account_info(account_info_iter)?,
+            token_program: next_account_info(account_info_iter)?,
+            ata_program: next_account_info(account_info_iter)?,
+            system_program: next_account_info(account_info_iter)?,
+        };
+        if !escrow_accounts.maker_wallet.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
         }
-        None => {
-            msg!("No creators found in metadata");
+        assert_keys_equal(spl_token::id(), *escrow_accounts.token_program.key)?;
+        assert_keys_equal(
+            spl_associated_token_account::id(),
+            *escrow_accounts.ata_program.key,
+        )?;
+        assert_keys_equal(system_program::id(), *escrow_accounts.system_program.key)?;
+        Ok(escrow_accounts)
+    }
+
+    /// Create the escrow account of `len` bytes at `seeds`, and move `maker_size` from the
+    /// maker's ATA into its vault.
+    fn escrow(
+        &self,
+        program_id: &Pubkey,
+        seeds: &[&[u8]],
+        len: usize,
+        maker_size: u64,
+    ) -> ProgramResult {
+        let escrow_key = Pubkey::create_program_address(seeds, program_id)?;
+        assert_keys_equal(escrow_key, *self.escrow.key)?;
+        invoke_signed(
+            &system_instruction::create_account(
+                self.maker_wallet.key,
+                self.escrow.key,
+                Rent::get()?.minimum_balance(len),
+                len as u64,
+                program_id,
+            ),
+            &[
+                self.maker_wallet.clone(),
+                self.escrow.clone(),
+                self.system_program.clone(),
+            ],
+            &[seeds],
+        )?;
+        invoke(
+            &create_associated_token_account_idempotent(
+                self.maker_wallet.key,
+                self.escrow.key,
+                self.maker_mint.key,
+                self.token_program.key,
+            ),
+            &[
+                self.maker_wallet.clone(),
+                self.vault.clone(),
+                self.escrow.clone(),
+                self.maker_mint.clone(),
+                self.system_program.clone(),
+                self.token_program.clone(),
+                self.ata_program.clone(),
+            ],
+        )?;
+        assert_is_ata(self.vault, self.escrow.key, self.maker_mint.key)?;
+        assert_is_ata(
+            self.maker_src_account,
+            self.maker_wallet.key,
+            self.maker_mint.key,
+        )?;
+        invoke(
+            &spl_token::instruction::transfer(
+                self.token_program.key,
+                self.maker_src_account.key,
+                self.vault.key,
+                self.maker_wallet.key,
+                &[],
+                maker_size,
+            )?,
+            &[
+                self.maker_src_account.clone(),
+                self.vault.clone(),
+                self.maker_wallet.clone(),
+                self.token_program.clone(),
+            ],
+        )?;
+        msg!("Escrowed {}", maker_size);
+        Ok(())
+    }
+}
+
+fn process_accept_escrow_offer(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    metadata_bump_seed: Option<u8>,
+    taker_guard: TakerGuard,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let maker_wallet = next_account_info(account_info_iter)?;
+    let taker_wallet = next_account_info(account_info_iter)?;
+    let escrow_info = next_account_info(account_info_iter)?;
+    let vault_info = next_account_info(account_info_iter)?;
+    let maker_dst_account = next_account_info(account_info_iter)?;
+    let taker_src_account = next_account_info(account_info_iter)?;
+    let taker_dst_account = next_account_info(account_info_iter)?;
+    let maker_src_mint = next_account_info(account_info_iter)?;
+    let taker_src_mint = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+    let escrow_offer = load_escrow_offer(program_id, escrow_info)?;
+    let public_values = &escrow_offer.public_values;
+    assert_keys_equal(escrow_offer.maker_wallet, *maker_wallet.key)?;
+    assert_keys_equal(public_values.maker_mint, *maker_src_mint.key)?;
+    assert_keys_equal(escrow_offer.taker_mint(), *taker_src_mint.key)?;
+    assert_keys_equal(spl_token::id(), *token_program_info.key)?;
+    assert_is_ata(vault_info, escrow_info.key, maker_src_mint.key)?;
+    let taker_size = public_values
+        .taker_size
+        .ok_or(ProgramError::InvalidAccountData)?;
+    let is_native = public_values.is_native;
+    let mut system_program_info: Option<&AccountInfo> = None;
+    if is_native {
+        assert_keys_equal(*taker_wallet.key, *taker_src_account.key)?;
+        assert_keys_equal(*maker_wallet.key, *maker_dst_account.key)?;
+        system_program_info = Some(next_account_info(account_info_iter)?);
+    }
+    let nonce_bytes = escrow_offer.nonce.to_le_bytes();
+    let bump_seed_bytes = [escrow_offer.bump_seed];
+    let seeds = EscrowOffer::signer_seeds(maker_wallet.key, &nonce_bytes, &bump_seed_bytes);
+    let options = AcceptOptions {
+        metadata_bump_seed,
+        platform_fee: public_values.platform_fee,
+        taker_guard,
+        ..AcceptOptions::default()
+    };
+    let payout = pay_fees(
+        account_info_iter,
+        &options,
+        &Leg {
+            src_account: vault_info,
+            authority: escrow_info,
+            mint: maker_src_mint,
+            size: public_values.maker_size,
+            seeds: &seeds,
+        },
+        &Leg {
+            src_account: taker_src_account,
+            authority: taker_wallet,
+            mint: taker_src_mint,
+            size: taker_size,
+            seeds: &[],
+        },
+        token_program_info,
+        system_program_info,
+        is_native,
+    )?;
+    let (maker_pay_size, taker_pay_size) = (payout.maker_pay_size, payout.taker_pay_size);
+
+    assert_is_ata(taker_dst_account, taker_wallet.key, maker_src_mint.key)?;
+    transfer_fee(
+        vault_info,
+        escrow_info,
+        taker_dst_account,
+        token_program_info,
+        None,
+        maker_pay_size,
+        false,
+        &seeds,
+    )?;
+    msg!("done tx from maker to taker {}", maker_pay_size);
+    if !is_native {
+        assert_is_ata(maker_dst_account, maker_wallet.key, taker_src_mint.key)?;
+        assert_is_ata(taker_src_account, taker_wallet.key, taker_src_mint.key)?;
+    }
+    transfer_fee(
+        taker_src_account,
+        taker_wallet,
+        maker_dst_account,
+        token_program_info,
+        system_program_info,
+        taker_pay_size,
+        is_native,
+        &[],
+    )?;
+    msg!("done tx from taker to maker {}", taker_pay_size);
+    // Anything sent to the vault on top of the offer goes to the taker, so that a donation
+    // cannot keep the vault from closing.
+    close_escrow_account(
+        escrow_info,
+        vault_info,
+        maker_wallet,
+        taker_dst_account,
+        token_program_info,
+        &seeds,
+    )?;
+    FillEvent {
+        offer: *escrow_info.key,
+        maker_wallet: *maker_wallet.key,
+        taker_wallet: *taker_wallet.key,
+        maker_mint: *maker_src_mint.key,
+        taker_mint: *taker_src_mint.key,
+        maker_size: public_values.maker_size,
+        taker_size,
+        taker_received: maker_pay_size,
+        maker_received: taker_pay_size,
+        fee_mint: payout.fee_mint,
+        creator_fees: payout.creator_fees,
+        platform_fee: payout.platform_fee,
+    }
+    .emit();
+    msg!("done!");
+    Ok(())
+}
+
+fn process_close_escrow_offer(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let maker_wallet = next_account_info(account_info_iter)?;
+    let escrow_info = next_account_info(account_info_iter)?;
+    let vault_info = next_account_info(account_info_iter)?;
+    let maker_src_account = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !maker_wallet.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let escrow_offer = load_escrow_offer(program_id, escrow_info)?;
+    let maker_mint = &escrow_offer.public_values.maker_mint;
+    assert_keys_equal(escrow_offer.maker_wallet, *maker_wallet.key)?;
+    assert_keys_equal(spl_token::id(), *token_program_info.key)?;
+    assert_is_ata(vault_info, escrow_info.key, maker_mint)?;
+    assert_is_ata(maker_src_account, maker_wallet.key, maker_mint)?;
+    let nonce_bytes = escrow_offer.nonce.to_le_bytes();
+    let bump_seed_bytes = [escrow_offer.bump_seed];
+    let seeds = EscrowOffer::signer_seeds(maker_wallet.key, &nonce_bytes, &bump_seed_bytes);
+    close_escrow_account(
+        escrow_info,
+        vault_info,
+        maker_wallet,
+        maker_src_account,
+        token_program_info,
+        &seeds,
+    )
+}
+
+/// Read an escrow offer account owned by this program.
+fn load_escrow_offer(
+    program_id: &Pubkey,
+    escrow_info: &AccountInfo,
+) -> Result<EscrowOffer, ProgramError> {
+    assert_account_key(program_id, escrow_info, AccountKey::EscrowOffer)?;
+    let escrow_offer = try_from_slice_unchecked::<EscrowOffer>(&escrow_info.try_borrow_data()?)?;
+    let nonce_bytes = escrow_offer.nonce.to_le_bytes();
+    let bump_seed_bytes = [escrow_offer.bump_seed];
+    assert_program_address(
+        program_id,
+        escrow_info,
+        &EscrowOffer::signer_seeds(&escrow_offer.maker_wallet, &nonce_bytes, &bump_seed_bytes),
+    )?;
+    Ok(escrow_offer)
+}
+
+/// Ensure an account is owned by this program and holds an account of kind `key`.
+fn assert_account_key(
+    program_id: &Pubkey,
+    account_info: &AccountInfo,
+    key: AccountKey,
+) -> ProgramResult {
+    assert_owned_by(account_info, program_id)?;
+    let data = account_info.try_borrow_data()?;
+    match data.first().copied() {
+        Some(first) if first == key as u8 => Ok(()),
+        None | Some(0) => Err(ProgramError::UninitializedAccount),
+        Some(_) => {
+            msg!("{} is not a {:?} account", account_info.key, key);
+            Err(ProgramError::InvalidAccountData)
         }
     }
-    // Any dust is returned to the party posting the NFT
-    Ok(remaining_size
-        .checked_add(remaining_fee)
-        .ok_or(UtilError::NumericalOverflow)?)
+}
+
+/// Ensure an account is the program address of the seeds it records.
+fn assert_program_address(
+    program_id: &Pubkey,
+    account_info: &AccountInfo,
+    seeds: &[&[u8]],
+) -> ProgramResult {
+    let key = Pubkey::create_program_address(seeds, program_id)?;
+    assert_keys_equal(key, *account_info.key)
+}
+
+/// Sweep the vault into `dst_account`, then close the vault and the program account escrowing
+/// into it, returning their rent to `owner`.
+fn close_escrow_account<'a>(
+    escrow_info: &AccountInfo<'a>,
+    vault_info: &AccountInfo<'a>,
+    owner: &AccountInfo<'a>,
+    dst_account: &AccountInfo<'a>,
+    token_program_info: &AccountInfo<'a>,
+    seeds: &[&[u8]],
+) -> ProgramResult {
+    let remaining = Account::unpack(&vault_info.data.borrow())?.amount;
+    if remaining > 0 {
+        transfer_fee(
+            vault_info,
+            escrow_info,
+            dst_account,
+            token_program_info,
+            None,
+            remaining,
+            false,
+            seeds,
+        )?;
+        msg!("Swept {} from the vault", remaining);
+    }
+    invoke_signed(
+        &spl_token::instruction::close_account(
+            token_program_info.key,
+            vault_info.key,
+            owner.key,
+            escrow_info.key,
+            &[],
+        )?,
+        &[
+            vault_info.clone(),
+            owner.clone(),
+            escrow_info.clone(),
+            token_program_info.clone(),
+        ],
+        &[seeds],
+    )?;
+    close_program_account(escrow_info, owner)
+}
+
+/// Decode public values passed in instruction data in their canonical encoding.
+fn unpack_public_values(
+    bytes: &[u8; CANONICAL_PUBLIC_VALUES_LEN],
+) -> Result<PublicValuesStruct, ProgramError> {
+    PublicValuesStruct::from_canonical_bytes(bytes).ok_or_else(|| {
+        msg!("Public values are not canonically encoded");
+        ProgramError::InvalidInstructionData
+    })
+}
+
+/// Ensure terms recorded in an offer account fully describe the taker leg.
+fn assert_recorded_terms(public_values: &PublicValuesStruct) -> ProgramResult {
+    let taker_size_committed = public_values.taker_size.is_some();
+    let taker_mint_committed = match public_values.taker_mint {
+        Some(taker_mint) => {
+            !public_values.is_native || taker_mint == spl_token::native_mint::id()
+        }
+        None => public_values.is_native,
+    };
+    if !taker_size_committed || !taker_mint_committed || public_values.maker_size == 0 {
+        msg!("Recorded offers need a taker mint and size");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if public_values.is_native && public_values.maker_mint == spl_token::native_mint::id() {
+        msg!("Maker and taker cannot both trade SOL");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if let Some(platform_fee) = &public_values.platform_fee {
+        if !platform_fee.is_valid() {
+            msg!("Platform fee exceeds {} bps", MAX_PLATFORM_FEE_BASIS_POINTS);
+            return Err(ProgramError::InvalidInstructionData);
+        }
+    }
+    Ok(())
+}
+
+/// Close an account owned by this program, moving its rent to `destination`.
+fn close_program_account(account_info: &AccountInfo, destination: &AccountInfo) -> ProgramResult {
+    let lamports = account_info.lamports();
+    **destination.lamports.borrow_mut() = destination
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(UtilError::NumericalOverflow)?;
+    **account_info.lamports.borrow_mut() = 0;
+    account_info.try_borrow_mut_data()?.fill(0);
+    Ok(())
+}
+
+fn process_init_routed_offer(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    nonce: u64,
+    bump_seed: u8,
+    public_values: PublicValuesStruct,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let maker_wallet = next_account_info(account_info_iter)?;
+    let routed_offer_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !maker_wallet.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    assert_keys_equal(system_program::id(), *system_program_info.key)?;
+    assert_recorded_terms(&public_values)?;
+    let nonce_bytes = nonce.to_le_bytes();
+    let bump_seed_bytes = [bump_seed];
+    let seeds = RoutedOffer::signer_seeds(maker_wallet.key, &nonce_bytes, &bump_seed_bytes);
+    let routed_offer_key = Pubkey::create_program_address(&seeds, program_id)?;
+    assert_keys_equal(routed_offer_key, *routed_offer_info.key)?;
+    invoke_signed(
+        &system_instruction::create_account(
+            maker_wallet.key,
+            routed_offer_info.key,
+            Rent::get()?.minimum_balance(RoutedOffer::LEN),
+            RoutedOffer::LEN as u64,
+            program_id,
+        ),
+        &[
+            maker_wallet.clone(),
+            routed_offer_info.clone(),
+            system_program_info.clone(),
+        ],
+        &[&seeds],
+    )?;
+    let routed_offer = RoutedOffer {
+        key: AccountKey::RoutedOffer,
+        maker_wallet: *maker_wallet.key,
+        nonce,
+        bump_seed,
+        public_values,
+    };
+    routed_offer.serialize(&mut &mut routed_offer_info.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+fn process_accept_routed_offer(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    metadata_bump_seed: Option<u8>,
+    router_bump_seed: u8,
+    taker_guard: TakerGuard,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let maker_wallet = next_account_info(account_info_iter)?;
+    let taker_wallet = next_account_info(account_info_iter)?;
+    let routed_offer_info = next_account_info(account_info_iter)?;
+    let maker_src_account = next_account_info(account_info_iter)?;
+    let maker_dst_account = next_account_info(account_info_iter)?;
+    let taker_src_account = next_account_info(account_info_iter)?;
+    let taker_dst_account = next_account_info(account_info_iter)?;
+    let maker_src_mint = next_account_info(account_info_iter)?;
+    let taker_src_mint = next_account_info(account_info_iter)?;
+    let router_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+    let routed_offer = load_routed_offer(program_id, routed_offer_info)?;
+    let public_values = &routed_offer.public_values;
+    assert_keys_equal(routed_offer.maker_wallet, *maker_wallet.key)?;
+    assert_keys_equal(public_values.maker_mint, *maker_src_mint.key)?;
+    assert_keys_equal(routed_offer.taker_mint(), *taker_src_mint.key)?;
+    assert_keys_equal(spl_token::id(), *token_program_info.key)?;
+    let router_bump_seed_bytes = [router_bump_seed];
+    let router_seeds: &[&[u8]] = &[b"offer_router", &router_bump_seed_bytes];
+    let router_key = Pubkey::create_program_address(router_seeds, program_id)?;
+    assert_keys_equal(router_key, *router_info.key)?;
+    assert_is_ata(maker_src_account, maker_wallet.key, maker_src_mint.key)?;
+    // The router delegation is shared by all of the maker's routed offers, so it only has to
+    // cover this one. The routed offer account caps what this fill takes from it.
+    let maker_src_token_account: Account = Account::unpack(&maker_src_account.data.borrow())?;
+    if maker_src_token_account.delegate != COption::Some(router_key)
+        || maker_src_token_account.delegated_amount < public_values.maker_size
+    {
+        msg!("Router delegation does not cover {}", public_values.maker_size);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let taker_size = public_values
+        .taker_size
+        .ok_or(ProgramError::InvalidAccountData)?;
+    let is_native = public_values.is_native;
+    let mut system_program_info: Option<&AccountInfo> = None;
+    if is_native {
+        assert_keys_equal(*taker_wallet.key, *taker_src_account.key)?;
+        assert_keys_equal(*maker_wallet.key, *maker_dst_account.key)?;
+        system_program_info = Some(next_account_info(account_info_iter)?);
+    }
+    let options = AcceptOptions {
+        metadata_bump_seed,
+        platform_fee: public_values.platform_fee,
+        taker_guard,
+        ..AcceptOptions::default()
+    };
+    let payout = pay_fees(
+        account_info_iter,
+        &options,
+        &Leg {
+            src_account: maker_src_account,
+            authority: router_info,
+            mint: maker_src_mint,
+            size: public_values.maker_size,
+            seeds: router_seeds,
+        },
+        &Leg {
+            src_account: taker_src_account,
+            authority: taker_wallet,
+            mint: taker_src_mint,
+            size: taker_size,
+            seeds: &[],
+        },
+        token_program_info,
+        system_program_info,
+        is_native,
+    )?;
+    let (maker_pay_size, taker_pay_size) = (payout.maker_pay_size, payout.taker_pay_size);
+
+    assert_is_ata(taker_dst_account, taker_wallet.key, maker_src_mint.key)?;
+    transfer_fee(
+        maker_src_account,
+        router_info,
+        taker_dst_account,
+        token_program_info,
+        None,
+        maker_pay_size,
+        false,
+        router_seeds,
+    )?;
+    msg!("done tx from maker to taker {}", maker_pay_size);
+    if !is_native {
+        assert_is_ata(maker_dst_account, maker_wallet.key, taker_src_mint.key)?;
+        assert_is_ata(taker_src_account, taker_wallet.key, taker_src_mint.key)?;
+    }
+    transfer_fee(
+        taker_src_account,
+        taker_wallet,
+        maker_dst_account,
+        token_program_info,
+        system_program_info,
+        taker_pay_size,
+        is_native,
+        &[],
+    )?;
+    msg!("done tx from taker to maker {}", taker_pay_size);
+    // Closing the routed offer account is what keeps the fill from being replayed against the
+    // rest of the delegation.
+    close_program_account(routed_offer_info, maker_wallet)?;
+    FillEvent {
+        offer: *routed_offer_info.key,
+        maker_wallet: *maker_wallet.key,
+        taker_wallet: *taker_wallet.key,
+        maker_mint: *maker_src_mint.key,
+        taker_mint: *taker_src_mint.key,
+        maker_size: public_values.maker_size,
+        taker_size,
+        taker_received: maker_pay_size,
+        maker_received: taker_pay_size,
+        fee_mint: payout.fee_mint,
+        creator_fees: payout.creator_fees,
+        platform_fee: payout.platform_fee,
+    }
+    .emit();
+    msg!("done!");
+    Ok(())
+}
+
+fn process_close_routed_offer(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let maker_wallet = next_account_info(account_info_iter)?;
+    let routed_offer_info = next_account_info(account_info_iter)?;
+    if !maker_wallet.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let routed_offer = load_routed_offer(program_id, routed_offer_info)?;
+    assert_keys_equal(routed_offer.maker_wallet, *maker_wallet.key)?;
+    close_program_account(routed_offer_info, maker_wallet)
+}
+
+/// Read a routed offer account owned by this program.
+fn load_routed_offer(
+    program_id: &Pubkey,
+    routed_offer_info: &AccountInfo,
+) -> Result<RoutedOffer, ProgramError> {
+    assert_account_key(program_id, routed_offer_info, AccountKey::RoutedOffer)?;
+    let routed_offer =
+        try_from_slice_unchecked::<RoutedOffer>(&routed_offer_info.try_borrow_data()?)?;
+    let nonce_bytes = routed_offer.nonce.to_le_bytes();
+    let bump_seed_bytes = [routed_offer.bump_seed];
+    assert_program_address(
+        program_id,
+        routed_offer_info,
+        &RoutedOffer::signer_seeds(&routed_offer.maker_wallet, &nonce_bytes, &bump_seed_bytes),
+    )?;
+    Ok(routed_offer)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn process_init_sealed_auction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    nonce: u64,
+    bump_seed: u8,
+    public_values: PublicValuesStruct,
+    bid_vkey_hash: [u8; 32],
+    commit_end_slot: u64,
+    reveal_end_slot: u64,
+) -> ProgramResult {
+    let escrow_accounts = InitEscrowAccounts::next(&mut accounts.iter())?;
+    let maker_wallet = escrow_accounts.maker_wallet;
+    assert_keys_equal(public_values.maker_mint, *escrow_accounts.maker_mint.key)?;
+    assert_recorded_terms(&public_values)?;
+    assert_offer_vkey_hash(&bid_vkey_hash)?;
+    if public_values.is_native {
+        msg!("Sealed bids are escrowed as tokens, use wrapped SOL instead");
+        return Err(ProgramError::InvalidArgument);
+    }
+    let slot = Clock::get()?.slot;
+    if slot >= commit_end_slot || commit_end_slot >= reveal_end_slot {
+        msg!(
+            "The commit phase must end after slot {} and before the reveal phase",
+            slot
+        );
+        return Err(ProgramError::InvalidArgument);
+    }
+    let nonce_bytes = nonce.to_le_bytes();
+    let bump_seed_bytes = [bump_seed];
+    let seeds = SealedAuction::signer_seeds(maker_wallet.key, &nonce_bytes, &bump_seed_bytes);
+    escrow_accounts.escrow(
+        program_id,
+        &seeds,
+        SealedAuction::LEN,
+        public_values.maker_size,
+    )?;
+    let auction = SealedAuction {
+        key: AccountKey::SealedAuction,
+        maker_wallet: *maker_wallet.key,
+        nonce,
+        bump_seed,
+        public_values,
+        bid_vkey_hash,
+        commit_end_slot,
+        reveal_end_slot,
+        best_bid: None,
+        best_amount: 0,
+    };
+    auction.serialize(&mut &mut escrow_accounts.escrow.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+fn process_commit_sealed_bid(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    bump_seed: u8,
+    commitment: [u8; 32],
+    deposit: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let bidder = next_account_info(account_info_iter)?;
+    let auction_info = next_account_info(account_info_iter)?;
+    let bid_info = next_account_info(account_info_iter)?;
+    let bid_vault_info = next_account_info(account_info_iter)?;
+    let bidder_src_account = next_account_info(account_info_iter)?;
+    let taker_mint = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+    let ata_program_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !bidder.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    assert_keys_equal(spl_token::id(), *token_program_info.key)?;
+    assert_keys_equal(spl_associated_token_account::id(), *ata_program_info.key)?;
+    assert_keys_equal(system_program::id(), *system_program_info.key)?;
+    let auction = load_sealed_auction(program_id, auction_info)?;
+    assert_keys_equal(auction.taker_mint(), *taker_mint.key)?;
+    if Clock::get()?.slot >= auction.commit_end_slot {
+        msg!("Bids closed at slot {}", auction.commit_end_slot);
+        return Err(UtilError::OutsideAuctionPhase.into());
+    }
+    // No bid below the reserve reveals, so a smaller deposit could not back one.
+    let reserve = auction
+        .public_values
+        .taker_size
+        .ok_or(ProgramError::InvalidAccountData)?;
+    if deposit < reserve {
+        msg!("The deposit must cover the reserve of {}", reserve);
+        return Err(UtilError::BidExceedsDeposit.into());
+    }
+    let bump_seed_bytes = [bump_seed];
+    let seeds = SealedBid::signer_seeds(auction_info.key, bidder.key, &bump_seed_bytes);
+    let bid_key = Pubkey::create_program_address(&seeds, program_id)?;
+    assert_keys_equal(bid_key, *bid_info.key)?;
+    invoke_signed(
+        &system_instruction::create_account(
+            bidder.key,
+            bid_info.key,
+            Rent::get()?.minimum_balance(SealedBid::LEN),
+            SealedBid::LEN as u64,
+            program_id,
+        ),
+        &[
+            bidder.clone(),
+            bid_info.clone(),
+            system_program_info.clone(),
+        ],
+        &[&seeds],
+    )?;
+    invoke(
+        &create_associated_token_account_idempotent(
+            bidder.key,
+            bid_info.key,
+            taker_mint.key,
+            token_program_info.key,
+        ),
+        &[
+            bidder.clone(),
+            bid_vault_info.clone(),
+            bid_info.clone(),
+            taker_mint.clone(),
+            system_program_info.clone(),
+            token_program_info.clone(),
+            ata_program_info.clone(),
+        ],
+    )?;
+    assert_is_ata(bid_vault_info, bid_info.key, taker_mint.key)?;
+    assert_is_ata(bidder_src_account, bidder.key, taker_mint.key)?;
+    transfer_fee(
+        bidder_src_account,
+        bidder,
+        bid_vault_info,
+        token_program_info,
+        None,
+        deposit,
+        false,
+        &[],
+    )?;
+    msg!("Committed with a deposit of {}", deposit);
+    let bid = SealedBid {
+        key: AccountKey::SealedBid,
+        auction: *auction_info.key,
+        bidder: *bidder.key,
+        maker_wallet: auction.maker_wallet,
+        bump_seed,
+        commitment,
+        deposit,
+        revealed_amount: None,
+    };
+    bid.serialize(&mut &mut bid_info.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+fn process_reveal_sealed_bid(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+    proof: &[u8],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let bidder = next_account_info(account_info_iter)?;
+    let auction_info = next_account_info(account_info_iter)?;
+    let bid_info = next_account_info(account_info_iter)?;
+    let bid_vault_info = next_account_info(account_info_iter)?;
+    let bidder_src_account = next_account_info(account_info_iter)?;
+    let taker_mint = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !bidder.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    assert_keys_equal(spl_token::id(), *token_program_info.key)?;
+    let mut auction = load_sealed_auction(program_id, auction_info)?;
+    let mut bid = load_sealed_bid(program_id, bid_info)?;
+    assert_keys_equal(bid.auction, *auction_info.key)?;
+    assert_keys_equal(bid.bidder, *bidder.key)?;
+    assert_keys_equal(auction.taker_mint(), *taker_mint.key)?;
+    let slot = Clock::get()?.slot;
+    if slot < auction.commit_end_slot || slot >= auction.reveal_end_slot {
+        msg!(
+            "Bids are revealed from slot {} to slot {}",
+            auction.commit_end_slot,
+            auction.reveal_end_slot
+        );
+        return Err(UtilError::OutsideAuctionPhase.into());
+    }
+    if bid.revealed_amount.is_some() {
+        msg!("Bid already revealed");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if amount > bid.deposit {
+        msg!("Bid of {} exceeds its deposit of {}", amount, bid.deposit);
+        return Err(UtilError::BidExceedsDeposit.into());
+    }
+    // The committed values are rebuilt from on-chain state, so the proof only verifies if the
+    // guest opened this bidder's commitment against these exact terms.
+    let reveal = SealedBidReveal {
+        auction: *auction_info.key,
+        bidder: *bidder.key,
+        commitment: bid.commitment,
+        public_values_digest: auction.public_values.public_values_digest(),
+        amount,
+    };
+    verify_groth16_proof(
+        proof,
+        &reveal,
+        &auction.bid_vkey_hash,
+        UtilError::InvalidBidProof,
+    )?;
+    assert_is_ata(bid_vault_info, bid_info.key, taker_mint.key)?;
+    assert_is_ata(bidder_src_account, bidder.key, taker_mint.key)?;
+    // The vault keeps exactly the bid, which settling pays out in full.
+    let refund = bid.deposit - amount;
+    if refund > 0 {
+        let bump_seed_bytes = [bid.bump_seed];
+        let seeds = SealedBid::signer_seeds(auction_info.key, bidder.key, &bump_seed_bytes);
+        transfer_fee(
+            bid_vault_info,
+            bid_info,
+            bidder_src_account,
+            token_program_info,
+            None,
+            refund,
+            false,
+            &seeds,
+        )?;
+    }
+    msg!("Revealed {}, returning {} of the deposit", amount, refund);
+    bid.revealed_amount = Some(amount);
+    bid.serialize(&mut &mut bid_info.try_borrow_mut_data()?[..])?;
+    // Ties go to the bid revealed first.
+    if auction.best_bid.is_none() || amount > auction.best_amount {
+        auction.best_bid = Some(*bid_info.key);
+        auction.best_amount = amount;
+        auction.serialize(&mut &mut auction_info.try_borrow_mut_data()?[..])?;
+    }
+    Ok(())
+}
+
+fn process_settle_sealed_auction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    metadata_bump_seed: Option<u8>,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let maker_wallet = next_account_info(account_info_iter)?;
+    let auction_info = next_account_info(account_info_iter)?;
+    let vault_info = next_account_info(account_info_iter)?;
+    let maker_src_account = next_account_info(account_info_iter)?;
+    let maker_dst_account = next_account_info(account_info_iter)?;
+    let maker_src_mint = next_account_info(account_info_iter)?;
+    let taker_src_mint = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+    let auction = load_sealed_auction(program_id, auction_info)?;
+    let public_values = &auction.public_values;
+    assert_keys_equal(auction.maker_wallet, *maker_wallet.key)?;
+    assert_keys_equal(public_values.maker_mint, *maker_src_mint.key)?;
+    assert_keys_equal(auction.taker_mint(), *taker_src_mint.key)?;
+    assert_keys_equal(spl_token::id(), *token_program_info.key)?;
+    assert_is_ata(vault_info, auction_info.key, maker_src_mint.key)?;
+    if Clock::get()?.slot < auction.reveal_end_slot {
+        msg!("Bids are revealed until slot {}", auction.reveal_end_slot);
+        return Err(UtilError::OutsideAuctionPhase.into());
+    }
+    let nonce_bytes = auction.nonce.to_le_bytes();
+    let bump_seed_bytes = [auction.bump_seed];
+    let seeds = SealedAuction::signer_seeds(maker_wallet.key, &nonce_bytes, &bump_seed_bytes);
+    let best_bid = match auction.best_bid {
+        Some(best_bid) => best_bid,
+        None => {
+            msg!("No bid was revealed, returning the vault to the maker");
+            assert_is_ata(maker_src_account, maker_wallet.key, maker_src_mint.key)?;
+            return close_escrow_account(
+                auction_info,
+                vault_info,
+                maker_wallet,
+                maker_src_account,
+                token_program_info,
+                &seeds,
+            );
+        }
+    };
+    let bidder = next_account_info(account_info_iter)?;
+    let bid_info = next_account_info(account_info_iter)?;
+    let bid_vault_info = next_account_info(account_info_iter)?;
+    let bidder_dst_account = next_account_info(account_info_iter)?;
+    assert_keys_equal(best_bid, *bid_info.key)?;
+    let bid = load_sealed_bid(program_id, bid_info)?;
+    assert_keys_equal(bid.bidder, *bidder.key)?;
+    assert_is_ata(bid_vault_info, bid_info.key, taker_src_mint.key)?;
+    let bid_bump_seed_bytes = [bid.bump_seed];
+    let bid_seeds = SealedBid::signer_seeds(auction_info.key, bidder.key, &bid_bump_seed_bytes);
+    let options = AcceptOptions {
+        metadata_bump_seed,
+        platform_fee: public_values.platform_fee,
+        ..AcceptOptions::default()
+    };
+    let payout = pay_fees(
+        account_info_iter,
+        &options,
+        &Leg {
+            src_account: vault_info,
+            authority: auction_info,
+            mint: maker_src_mint,
+            size: public_values.maker_size,
+            seeds: &seeds,
+        },
+        &Leg {
+            src_account: bid_vault_info,
+            authority: bid_info,
+            mint: taker_src_mint,
+            size: auction.best_amount,
+            seeds: &bid_seeds,
+        },
+        token_program_info,
+        None,
+        false,
+    )?;
+    let (maker_pay_size, taker_pay_size) = (payout.maker_pay_size, payout.taker_pay_size);
+
+    assert_is_ata(bidder_dst_account, bidder.key, maker_src_mint.key)?;
+    transfer_fee(
+        vault_info,
+        auction_info,
+        bidder_dst_account,
+        token_program_info,
+        None,
+        maker_pay_size,
+        false,
+        &seeds,
+    )?;
+    msg!("done tx from maker to taker {}", maker_pay_size);
+    assert_is_ata(maker_dst_account, maker_wallet.key, taker_src_mint.key)?;
+    transfer_fee(
+        bid_vault_info,
+        bid_info,
+        maker_dst_account,
+        token_program_info,
+        None,
+        taker_pay_size,
+        false,
+        &bid_seeds,
+    )?;
+    msg!("done tx from taker to maker {}", taker_pay_size);
+    close_escrow_account(
+        auction_info,
+        vault_info,
+        maker_wallet,
+        bidder_dst_account,
+        token_program_info,
+        &seeds,
+    )?;
+    close_escrow_account(
+        bid_info,
+        bid_vault_info,
+        bidder,
+        maker_dst_account,
+        token_program_info,
+        &bid_seeds,
+    )?;
+    FillEvent {
+        offer: *auction_info.key,
+        maker_wallet: *maker_wallet.key,
+        taker_wallet: *bidder.key,
+        maker_mint: *maker_src_mint.key,
+        taker_mint: *taker_src_mint.key,
+        maker_size: public_values.maker_size,
+        taker_size: auction.best_amount,
+        taker_received: maker_pay_size,
+        maker_received: taker_pay_size,
+        fee_mint: payout.fee_mint,
+        creator_fees: payout.creator_fees,
+        platform_fee: payout.platform_fee,
+    }
+    .emit();
+    msg!("done!");
+    Ok(())
+}
+
+fn process_close_sealed_bid(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let bidder = next_account_info(account_info_iter)?;
+    let auction_info = next_account_info(account_info_iter)?;
+    let bid_info = next_account_info(account_info_iter)?;
+    let bid_vault_info = next_account_info(account_info_iter)?;
+    let bidder_src_account = next_account_info(account_info_iter)?;
+    let maker_dst_account = next_account_info(account_info_iter)?;
+    let taker_mint = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+    assert_keys_equal(spl_token::id(), *token_program_info.key)?;
+    let bid = load_sealed_bid(program_id, bid_info)?;
+    assert_keys_equal(bid.bidder, *bidder.key)?;
+    assert_keys_equal(bid.auction, *auction_info.key)?;
+    assert_is_ata(bid_vault_info, bid_info.key, taker_mint.key)?;
+    // Settling closes the auction account, releasing the best bid along with it.
+    let revealing = if auction_info.lamports() > 0 {
+        let auction = load_sealed_auction(program_id, auction_info)?;
+        if auction.best_bid == Some(*bid_info.key) {
+            msg!("The best bid is held until the auction settles");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Clock::get()?.slot < auction.reveal_end_slot
+    } else {
+        false
+    };
+    let bump_seed_bytes = [bid.bump_seed];
+    let seeds = SealedBid::signer_seeds(auction_info.key, bidder.key, &bump_seed_bytes);
+    if bid.revealed_amount.is_none() {
+        // Forfeiting the deposit keeps a bidder from committing to several amounts and only
+        // revealing the one that suits them. Anyone can close the bid once it can no longer be
+        // revealed, the rent still going to the bidder.
+        if revealing {
+            msg!("Unrevealed bids are held until the reveal phase ends");
+            return Err(UtilError::OutsideAuctionPhase.into());
+        }
+        assert_is_ata(maker_dst_account, &bid.maker_wallet, taker_mint.key)?;
+        msg!("Bid was not revealed, forfeiting its deposit");
+        return close_escrow_account(
+            bid_info,
+            bid_vault_info,
+            bidder,
+            maker_dst_account,
+            token_program_info,
+            &seeds,
+        );
+    }
+    if !bidder.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    assert_is_ata(bidder_src_account, bidder.key, taker_mint.key)?;
+    close_escrow_account(
+        bid_info,
+        bid_vault_info,
+        bidder,
+        bidder_src_account,
+        token_program_info,
+        &seeds,
+    )
+}
+
+/// Read a sealed auction account owned by this program.
+fn load_sealed_auction(
+    program_id: &Pubkey,
+    auction_info: &AccountInfo,
+) -> Result<SealedAuction, ProgramError> {
+    assert_account_key(program_id, auction_info, AccountKey::SealedAuction)?;
+    let auction = try_from_slice_unchecked::<SealedAuction>(&auction_info.try_borrow_data()?)?;
+    let nonce_bytes = auction.nonce.to_le_bytes();
+    let bump_seed_bytes = [auction.bump_seed];
+    assert_program_address(
+        program_id,
+        auction_info,
+        &SealedAuction::signer_seeds(&auction.maker_wallet, &nonce_bytes, &bump_seed_bytes),
+    )?;
+    Ok(auction)
+}
+
+/// Read a sealed bid account owned by this program.
+fn load_sealed_bid(program_id: &Pubkey, bid_info: &AccountInfo) -> Result<SealedBid, ProgramError> {
+    assert_account_key(program_id, bid_info, AccountKey::SealedBid)?;
+    let bid = try_from_slice_unchecked::<SealedBid>(&bid_info.try_borrow_data()?)?;
+    let bump_seed_bytes = [bid.bump_seed];
+    assert_program_address(
+        program_id,
+        bid_info,
+        &SealedBid::signer_seeds(&bid.auction, &bid.bidder, &bump_seed_bytes),
+    )?;
+    Ok(bid)
+}
+
+/// Check that `vkey_hash` is the pinned offer guest, so that proofs cannot come from a program of
+/// the caller's choosing.
+fn assert_offer_vkey_hash(vkey_hash: &[u8; 32]) -> ProgramResult {
+    if *vkey_hash != OFFER_VKEY_HASH {
+        msg!(
+            "Verification key {} is not the offer guest",
+            format_vkey_hash(vkey_hash)
+        );
+        return Err(UtilError::UnknownVerificationKey.into());
+    }
+    Ok(())
+}
+
+/// Verify a Groth16 proof that the program with hash `vkey_hash` committed `committed`, failing
+/// with `error` otherwise.
+///
+/// The key is the one of the SP1 3.x Groth16 circuit, which the script proves with through sp1-sdk
+/// 3.1; the two have to be bumped together.
+fn verify_groth16_proof<T: BorshSerialize>(
+    proof: &[u8],
+    committed: &T,
+    vkey_hash: &[u8; 32],
+    error: UtilError,
+) -> ProgramResult {
+    let public_inputs = borsh::to_vec(committed)?;
+    sp1_solana::verify_proof(
+        proof,
+        &public_inputs,
+        &format_vkey_hash(vkey_hash),
+        sp1_solana::GROTH16_VK_3_0_0_BYTES,
+    )
+    .map_err(|_| {
+        msg!("Proof does not verify");
+        error.into()
+    })
+}
+
+fn process_post_offer_batch(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    bump_seed: u8,
+    vkey_hash: [u8; 32],
+    root: [u8; 32],
+    offer_count: u32,
+    proof: &[u8],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let poster = next_account_info(account_info_iter)?;
+    let offer_batch_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !poster.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    assert_keys_equal(system_program::id(), *system_program_info.key)?;
+    assert_offer_vkey_hash(&vkey_hash)?;
+    verify_groth16_proof(
+        proof,
+        &OfferBatchRoot { root, offer_count },
+        &vkey_hash,
+        UtilError::InvalidBatchProof,
+    )?;
+    let bump_seed_bytes = [bump_seed];
+    let seeds = OfferBatch::signer_seeds(&vkey_hash, &root, &bump_seed_bytes);
+    let offer_batch_key = Pubkey::create_program_address(&seeds, program_id)?;
+    assert_keys_equal(offer_batch_key, *offer_batch_info.key)?;
+    invoke_signed(
+        &system_instruction::create_account(
+            poster.key,
+            offer_batch_info.key,
+            Rent::get()?.minimum_balance(OfferBatch::LEN),
+            OfferBatch::LEN as u64,
+            program_id,
+        ),
+        &[
+            poster.clone(),
+            offer_batch_info.clone(),
+            system_program_info.clone(),
+        ],
+        &[&seeds],
+    )?;
+    let offer_batch = OfferBatch {
+        key: AccountKey::OfferBatch,
+        poster: *poster.key,
+        bump_seed,
+        vkey_hash,
+        root,
+        offer_count,
+    };
+    offer_batch.serialize(&mut &mut offer_batch_info.try_borrow_mut_data()?[..])?;
+    msg!("Posted a batch of {} offers", offer_count);
+    Ok(())
+}
+
+fn process_accept_batched_offer(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    metadata_bump_seed: Option<u8>,
+    bump_seed: u8,
+    public_values: &[u8; CANONICAL_PUBLIC_VALUES_LEN],
+    merkle_proof: &[[u8; 32]],
+    taker_guard: TakerGuard,
+) -> ProgramResult {
+    let (offer_batch_info, fill_accounts) = accounts
+        .split_first()
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let offer_batch = load_offer_batch(program_id, offer_batch_info)?;
+    // Batches posted before the guest was last upgraded were proven by another program.
+    assert_offer_vkey_hash(&offer_batch.vkey_hash)?;
+    // The leaf is the digest of the bytes the guest committed, so inclusion is checked before
+    // the terms are decoded.
+    let public_values_digest = committed_values_digest(public_values);
+    let leaf = digest_leaf(&public_values_digest);
+    if !verify_merkle_proof(&offer_batch.root, &leaf, merkle_proof) {
+        msg!("Offer is not part of batch {}", offer_batch_info.key);
+        return Err(UtilError::OfferNotInBatch.into());
+    }
+    let public_values = unpack_public_values(public_values)?;
+    assert_recorded_terms(&public_values)?;
+    // The authority of the fill is derived from the batched terms rather than its mint accounts,
+    // which must then be checked against the terms.
+    let maker_mint = fill_accounts
+        .get(6)
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let taker_mint = fill_accounts
+        .get(7)
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    assert_keys_equal(public_values.maker_mint, *maker_mint.key)?;
+    assert_keys_equal(
+        public_values
+            .taker_mint
+            .unwrap_or_else(spl_token::native_mint::id),
+        *taker_mint.key,
+    )?;
+    let taker_size = public_values
+        .taker_size
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    process_accept_offer(
+        program_id,
+        fill_accounts,
+        AcceptOptions {
+            metadata_bump_seed,
+            platform_fee: public_values.platform_fee,
+            taker_guard,
+            batched_offer: Some(BatchedOffer {
+                root: offer_batch.root,
+                public_values_digest,
+            }),
+            ..AcceptOptions::default()
+        },
+        public_values.maker_size,
+        taker_size,
+        bump_seed,
+    )
+}
+
+fn process_close_offer_batch(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let poster = next_account_info(account_info_iter)?;
+    let offer_batch_info = next_account_info(account_info_iter)?;
+    if !poster.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let offer_batch = load_offer_batch(program_id, offer_batch_info)?;
+    assert_keys_equal(offer_batch.poster, *poster.key)?;
+    close_program_account(offer_batch_info, poster)
+}
+
+/// Read an offer batch account owned by this program.
+fn load_offer_batch(
+    program_id: &Pubkey,
+    offer_batch_info: &AccountInfo,
+) -> Result<OfferBatch, ProgramError> {
+    assert_account_key(program_id, offer_batch_info, AccountKey::OfferBatch)?;
+    let offer_batch = try_from_slice_unchecked::<OfferBatch>(&offer_batch_info.try_borrow_data()?)?;
+    let bump_seed_bytes = [offer_batch.bump_seed];
+    assert_program_address(
+        program_id,
+        offer_batch_info,
+        &OfferBatch::signer_seeds(&offer_batch.vkey_hash, &offer_batch.root, &bump_seed_bytes),
+    )?;
+    Ok(offer_batch)
+}
+
+/// One leg of a fill, as seen by the fee logic.
+struct Leg<'a, 'b> {
+    /// Account the leg is paid from, the wallet itself for native SOL.
+    src_account: &'b AccountInfo<'a>,
+    /// Signer moving the leg.
+    authority: &'b AccountInfo<'a>,
+    mint: &'b AccountInfo<'a>,
+    size: u64,
+    /// Seeds signing for `authority` when it is a program address, empty otherwise.
+    seeds: &'b [&'b [u8]],
+}
+
+/// What the fee logic paid out of a fill.
+struct Payout {
+    /// What is left of `maker_size` for the taker.
+    maker_pay_size: u64,
+    /// What is left of `taker_size` for the maker.
+    taker_pay_size: u64,
+    fee_mint: Pubkey,
+    creator_fees: Vec<FeePaid>,
+    platform_fee: Option<FeePaid>,
+}
+
+impl Payout {
+    /// What leaves the taker's source account: the maker's part of the taker leg, plus the
+    /// royalties and platform fee paid from it when the taker bears them.
+    fn taker_paid(&self, maker_pays_fees: bool) -> Result<u64, ProgramError> {
+        if maker_pays_fees {
+            return Ok(self.taker_pay_size);
+        }
+        self.creator_fees
+            .iter()
+            .chain(&self.platform_fee)
+            .try_fold(self.taker_pay_size, |paid, fee| {
+                paid.checked_add(fee.amount)
+            })
+            .ok_or_else(|| UtilError::NumericalOverflow.into())
+    }
+}
+
+/// Pay royalties and the platform fee out of the leg opposite the NFT.
+fn pay_fees<'a>(
+    account_info_iter: &mut Iter<AccountInfo<'a>>,
+    options: &AcceptOptions,
+    maker: &Leg<'a, '_>,
+    taker: &Leg<'a, '_>,
+    token_program_info: &AccountInfo<'a>,
+    system_program_info: Option<&AccountInfo<'a>>,
+    is_native: bool,
+) -> Result<Payout, ProgramError> {
+    let mut payout = Payout {
+        maker_pay_size: maker.size,
+        taker_pay_size: taker.size,
+        fee_mint: *taker.mint.key,
+        creator_fees: Vec::new(),
+        platform_fee: None,
+    };
+    if !is_native {
+        assert_taker_leg_transferable(taker.src_account)?;
+    }
+    let mut maker_pays_fees = false;
+    if let Some(metadata_bump_seed) = options.metadata_bump_seed {
+        let metadata_info = next_account_info(account_info_iter)?;
+        // The bump is supplied, so each candidate costs a create_program_address rather than a
+        // find_program_address search.
+        let is_maker_metadata = is_metadata_of(metadata_info, maker.mint.key, metadata_bump_seed);
+        let is_taker_metadata = is_metadata_of(metadata_info, taker.mint.key, metadata_bump_seed);
+        if let Some(collection) = &options.collection {
+            if !is_taker_metadata {
+                msg!("Metadata does not belong to the taker mint");
+                return Err(ProgramError::InvalidAccountData);
+            }
+            assert_collection_member(metadata_info, collection)?;
+        }
+        if is_maker_metadata {
+            msg!("Taker pays for fees");
+            let (taker_remaining_size, creator_fees) = pay_creator_fees(
+                account_info_iter,
+                metadata_info,
+                taker.src_account,
+                taker.authority,
+                token_program_info,
+                system_program_info,
+                taker.mint,
+                taker.size,
+                is_native,
+                options.verified_creators_only,
+                taker.seeds,
+            )?;
+            payout.taker_pay_size = taker_remaining_size;
+            payout.creator_fees = creator_fees;
+        } else if is_taker_metadata {
+            msg!("Maker pays for fees");
+            let (maker_remaining_size, creator_fees) = pay_creator_fees(
+                account_info_iter,
+                metadata_info,
+                maker.src_account,
+                maker.authority,
+                token_program_info,
+                system_program_info,
+                maker.mint,
+                maker.size,
+                is_native,
+                options.verified_creators_only,
+                maker.seeds,
+            )?;
+            payout.maker_pay_size = maker_remaining_size;
+            payout.fee_mint = *maker.mint.key;
+            payout.creator_fees = creator_fees;
+            maker_pays_fees = true;
+        } else {
+            msg!("Neither maker nor taker metadata keys match");
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+    match &options.platform_fee {
+        // The venue is paid from the same leg as the creators
+        Some(platform_fee) if maker_pays_fees => {
+            let platform_fee_info = next_account_info(account_info_iter)?;
+            let (maker_remaining_size, fee_paid) = pay_platform_fee(
+                platform_fee,
+                platform_fee_info,
+                maker.src_account,
+                maker.authority,
+                token_program_info,
+                system_program_info,
+                maker.mint,
+                maker.size,
+                payout.maker_pay_size,
+                false,
+                maker.seeds,
+            )?;
+            payout.maker_pay_size = maker_remaining_size;
+            payout.platform_fee = Some(fee_paid);
+        }
+        Some(platform_fee) => {
+            let platform_fee_info = next_account_info(account_info_iter)?;
+            let (taker_remaining_size, fee_paid) = pay_platform_fee(
+                platform_fee,
+                platform_fee_info,
+                taker.src_account,
+                taker.authority,
+                token_program_info,
+                system_program_info,
+                taker.mint,
+                taker.size,
+                payout.taker_pay_size,
+                is_native,
+                taker.seeds,
+            )?;
+            payout.taker_pay_size = taker_remaining_size;
+            payout.platform_fee = Some(fee_paid);
+        }
+        None => {}
+    }
+    options
+        .taker_guard
+        .check(payout.maker_pay_size, payout.taker_paid(maker_pays_fees)?)?;
+    Ok(payout)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn pay_creator_fees<'a>(
+    account_info_iter: &mut Iter<AccountInfo<'a>>,
+    metadata_info: &AccountInfo<'a>,
+    src_account_info: &AccountInfo<'a>,
+    src_authority_info: &AccountInfo<'a>,
+    token_program_info: &AccountInfo<'a>,
+    system_program_info: Option<&AccountInfo<'a>>,
+    fee_mint: &AccountInfo<'a>,
+    size: u64,
+    is_native: bool,
+    verified_creators_only: bool,
+    seeds: &[&[u8]],
+) -> Result<(u64, Vec<FeePaid>), ProgramError> {
+    if *metadata_info.owner != inline_mpl_token_metadata::id() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let metadata = try_from_slice_unchecked::<inline_mpl_token_metadata::Metadata>(
+        &metadata_info.try_borrow_data()?,
+    )?;
+    let (creator_fees, remaining_size) =
+        creator_fee_amounts(&metadata, size, verified_creators_only)?;
+    let mut fees_paid = Vec::with_capacity(creator_fees.len());
+    match metadata.data.creators {
+        Some(creators) => {
+            let paid_creators = creators
+                .into_iter()
+                .filter(|creator| !verified_creators_only || creator.verified);
+            for (creator, creator_fee) in paid_creators.zip(creator_fees) {
+                let current_creator_info = next_account_info(account_info_iter)?;
+                assert_keys_equal(creator.address, *current_creator_info.key)?;
+                let creator_dst_info = if !is_native {
+                    let current_creator_token_account_info = next_account_info(account_info_iter)?;
+                    assert_is_ata(
+                        current_creator_token_account_info,
+                        current_creator_info.key,
+                        fee_mint.key,
+                    )?;
+                    current_creator_token_account_info
+                } else {
+                    current_creator_info
+                };
+                if creator_fee > 0 {
+                    transfer_fee(
+                        src_account_info,
+                        src_authority_info,
+                        creator_dst_info,
+                        token_program_info,
+                        system_program_info,
+                        creator_fee,
+                        is_native,
+                        seeds,
+                    )?;
+                }
+                fees_paid.push(FeePaid {
+                    recipient: creator.address,
+                    amount: creator_fee,
+                });
+            }
+        }
+        None => {
+            msg!("No creators found in metadata");
+        }
+    }
+    Ok((remaining_size, fees_paid))
+}
+
+/// Pay the platform fee charged on `size` out of `pay_size`.
+///
+/// Returns what is left of `pay_size` for the counterparty and the fee paid.
+#[allow(clippy::too_many_arguments)]
+fn pay_platform_fee<'a>(
+    platform_fee: &PlatformFee,
+    platform_fee_info: &AccountInfo<'a>,
+    src_account_info: &AccountInfo<'a>,
+    src_authority_info: &AccountInfo<'a>,
+    token_program_info: &AccountInfo<'a>,
+    system_program_info: Option<&AccountInfo<'a>>,
+    fee_mint: &AccountInfo<'a>,
+    size: u64,
+    pay_size: u64,
+    is_native: bool,
+    seeds: &[&[u8]],
+) -> Result<(u64, FeePaid), ProgramError> {
+    let fee = platform_fee
+        .amount(size)
+        .ok_or(UtilError::NumericalOverflow)?;
+    let remaining_size = pay_size
+        .checked_sub(fee)
+        .ok_or(UtilError::NumericalOverflow)?;
+    if is_native {
+        assert_keys_equal(platform_fee.recipient, *platform_fee_info.key)?;
+    } else {
+        assert_is_ata(platform_fee_info, &platform_fee.recipient, fee_mint.key)?;
+    }
+    if fee > 0 {
+        transfer_fee(
+            src_account_info,
+            src_authority_info,
+            platform_fee_info,
+            token_program_info,
+            system_program_info,
+            fee,
+            is_native,
+            seeds,
+        )?;
+    }
+    msg!("Platform fee {}", fee);
+    Ok((
+        remaining_size,
+        FeePaid {
+            recipient: platform_fee.recipient,
+            amount: fee,
+        },
+    ))
+}
+
+/// Move a fee out of the paying leg.
+///
+/// The transfer is signed with `seeds` when the transfer authority pays on behalf of the maker.
+#[allow(clippy::too_many_arguments)]
+fn transfer_fee<'a>(
+    src_account_info: &AccountInfo<'a>,
+    src_authority_info: &AccountInfo<'a>,
+    dst_info: &AccountInfo<'a>,
+    token_program_info: &AccountInfo<'a>,
+    system_program_info: Option<&AccountInfo<'a>>,
+    amount: u64,
+    is_native: bool,
+    seeds: &[&[u8]],
+) -> ProgramResult {
+    if is_native {
+        if !seeds.is_empty() {
+            msg!("Maker cannot pay with native SOL, use wrapped SOL instead");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        match system_program_info {
+            Some(sys_program_info) => invoke(
+                &system_instruction::transfer(src_account_info.key, dst_info.key, amount),
+                &[
+                    src_account_info.clone(),
+                    dst_info.clone(),
+                    sys_program_info.clone(),
+                ],
+            ),
+            None => {
+                msg!("Invalid System Program Info");
+                Err(ProgramError::IncorrectProgramId)
+            }
+        }
+    } else {
+        let instruction = spl_token::instruction::transfer(
+            token_program_info.key,
+            src_account_info.key,
+            dst_info.key,
+            src_authority_info.key,
+            &[],
+            amount,
+        )?;
+        let account_infos = &[
+            src_account_info.clone(),
+            dst_info.clone(),
+            src_authority_info.clone(),
+            token_program_info.clone(),
+        ];
+        if seeds.is_empty() {
+            invoke(&instruction, account_infos)
+        } else {
+            invoke_signed(&instruction, account_infos, &[seeds])
+        }
+    }
+}
+
+/// Compute the royalty owed to each paid metadata creator on `size`.
+///
+/// Returns the per-creator amounts in metadata order and the size left for the counterparty.
+/// Unverified creators are skipped when `verified_creators_only` is set. Any dust is returned
+/// to the party posting the NFT.
+pub fn creator_fee_amounts(
+    metadata: &inline_mpl_token_metadata::Metadata,
+    size: u64,
+    verified_creators_only: bool,
+) -> Result<(Vec<u64>, u64), ProgramError> {
+    let fees = metadata.data.seller_fee_basis_points;
+    let total_fee = (fees as u64)
+        .checked_mul(size)
+        .ok_or(UtilError::NumericalOverflow)?
+        .checked_div(10000)
+        .ok_or(UtilError::NumericalOverflow)?;
+    let mut remaining_fee = total_fee;
+    let remaining_size = size
+        .checked_sub(total_fee)
+        .ok_or(UtilError::NumericalOverflow)?;
+    let mut creator_fees = Vec::new();
+    if let Some(creators) = &metadata.data.creators {
+        for creator in creators {
+            if verified_creators_only && !creator.verified {
+                continue;
+            }
+            let pct = creator.share as u64;
+            let creator_fee = pct
+                .checked_mul(total_fee)
+                .ok_or(UtilError::NumericalOverflow)?
+                .checked_div(100)
+                .ok_or(UtilError::NumericalOverflow)?;
+            remaining_fee = remaining_fee
+                .checked_sub(creator_fee)
+                .ok_or(UtilError::NumericalOverflow)?;
+            creator_fees.push(creator_fee);
+        }
+    }
+    Ok((
+        creator_fees,
+        remaining_size
+            .checked_add(remaining_fee)
+            .ok_or(UtilError::NumericalOverflow)?,
+    ))
+}
+
+/// Reject a frozen taker leg before anything moves. Token Metadata keeps the token account of
+/// every programmable NFT frozen, and only the maker leg goes through its `Transfer`.
+fn assert_taker_leg_transferable(src_account: &AccountInfo) -> ProgramResult {
+    if Account::unpack(&src_account.data.borrow())?.is_frozen() {
+        msg!("Taker source account is frozen, a programmable NFT can only be the maker leg");
+        return Err(UtilError::ProgrammableTakerLeg.into());
+    }
+    Ok(())
+}
+
+/// Whether `metadata_info` is the metadata PDA of `mint` with bump `bump_seed`.
+fn is_metadata_of(metadata_info: &AccountInfo, mint: &Pubkey, bump_seed: u8) -> bool {
+    Pubkey::create_program_address(
+        &[
+            b"metadata",
+            inline_mpl_token_metadata::id().as_ref(),
+            mint.as_ref(),
+            &[bump_seed],
+        ],
+        &inline_mpl_token_metadata::id(),
+    )
+    .map_or(false, |key| key == *metadata_info.key)
+}
+
+/// Ensure the metadata describes a verified member of `collection`.
+fn assert_collection_member(metadata_info: &AccountInfo, collection: &Pubkey) -> ProgramResult {
+    if *metadata_info.owner != inline_mpl_token_metadata::id() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let metadata = try_from_slice_unchecked::<inline_mpl_token_metadata::Metadata>(
+        &metadata_info.try_borrow_data()?,
+    )?;
+    if !metadata.is_verified_member_of(collection) {
+        msg!("NFT is not a verified member of collection {}", collection);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
 }
@@ -0,0 +1,191 @@
+//! Program accounts
+
+use {
+    borsh::{BorshDeserialize, BorshSchema, BorshSerialize},
+    solana_pubkey::Pubkey,
+    solana_zk_offers::zk_offers::PublicValuesStruct,
+};
+
+/// Leading byte of every program account, telling the kinds of account apart.
+///
+/// The offer accounts share a layout, so without it one kind of account could be passed where
+/// another is expected. Closed accounts are zeroed and read as `Uninitialized`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub enum AccountKey {
+    Uninitialized,
+    EscrowOffer,
+    RoutedOffer,
+    SealedAuction,
+    SealedBid,
+    OfferBatch,
+}
+
+/// An escrowed offer, holding the terms its vault is released against.
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct EscrowOffer {
+    pub key: AccountKey,
+    pub maker_wallet: Pubkey,
+    pub nonce: u64,
+    pub bump_seed: u8,
+    /// The same terms a stateless offer commits in its authority seeds.
+    pub public_values: PublicValuesStruct,
+}
+
+impl EscrowOffer {
+    /// Space allocated for the account, with every optional term present.
+    pub const LEN: usize = 1 + 32 + 8 + 1 + (32 + 33 + 1 + 8 + 9 + (1 + 2 + 32));
+
+    /// The mint the taker pays with, the native mint for SOL.
+    pub fn taker_mint(&self) -> Pubkey {
+        self.public_values
+            .taker_mint
+            .unwrap_or_else(spl_token::native_mint::id)
+    }
+
+    /// The seeds the escrow offer account signs for its vault with.
+    pub fn signer_seeds<'a>(
+        maker_wallet: &'a Pubkey,
+        nonce_bytes: &'a [u8; 8],
+        bump_seed: &'a [u8; 1],
+    ) -> [&'a [u8]; 4] {
+        [b"escrow_offer", maker_wallet.as_ref(), nonce_bytes, bump_seed]
+    }
+}
+
+/// An offer filled through the program-wide offer router.
+///
+/// The maker's token account is delegated to the router, and this account caps the fill of one
+/// offer at `public_values.maker_size` of that delegation. It is closed when the offer fills.
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct RoutedOffer {
+    pub key: AccountKey,
+    pub maker_wallet: Pubkey,
+    pub nonce: u64,
+    pub bump_seed: u8,
+    pub public_values: PublicValuesStruct,
+}
+
+impl RoutedOffer {
+    /// Space allocated for the account, with every optional term present.
+    pub const LEN: usize = EscrowOffer::LEN;
+
+    /// The mint the taker pays with, the native mint for SOL.
+    pub fn taker_mint(&self) -> Pubkey {
+        self.public_values
+            .taker_mint
+            .unwrap_or_else(spl_token::native_mint::id)
+    }
+
+    /// The seeds of the routed offer account.
+    pub fn signer_seeds<'a>(
+        maker_wallet: &'a Pubkey,
+        nonce_bytes: &'a [u8; 8],
+        bump_seed: &'a [u8; 1],
+    ) -> [&'a [u8]; 4] {
+        [b"routed_offer", maker_wallet.as_ref(), nonce_bytes, bump_seed]
+    }
+}
+
+/// A sealed-bid auction of an escrowed `public_values.maker_size`.
+///
+/// `public_values.taker_size` is the reserve. Bids are committed until `commit_end_slot` and
+/// revealed until `reveal_end_slot`, after which the best revealed bid is settled.
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct SealedAuction {
+    pub key: AccountKey,
+    pub maker_wallet: Pubkey,
+    pub nonce: u64,
+    pub bump_seed: u8,
+    pub public_values: PublicValuesStruct,
+    /// Hash of the verification key that reveal proofs are checked against.
+    pub bid_vkey_hash: [u8; 32],
+    pub commit_end_slot: u64,
+    pub reveal_end_slot: u64,
+    /// The bid account of the best revealed bid so far.
+    pub best_bid: Option<Pubkey>,
+    pub best_amount: u64,
+}
+
+impl SealedAuction {
+    /// Space allocated for the account, with every optional term present.
+    pub const LEN: usize = EscrowOffer::LEN + 32 + 8 + 8 + 33 + 8;
+
+    /// The mint bids are paid in.
+    pub fn taker_mint(&self) -> Pubkey {
+        self.public_values
+            .taker_mint
+            .unwrap_or_else(spl_token::native_mint::id)
+    }
+
+    /// The seeds the auction account signs for its vault with.
+    pub fn signer_seeds<'a>(
+        maker_wallet: &'a Pubkey,
+        nonce_bytes: &'a [u8; 8],
+        bump_seed: &'a [u8; 1],
+    ) -> [&'a [u8]; 4] {
+        [b"sealed_auction", maker_wallet.as_ref(), nonce_bytes, bump_seed]
+    }
+}
+
+/// One bidder's commitment to a sealed auction, holding its deposit in its vault.
+///
+/// The deposit bounds the bid. Revealing returns what the deposit holds above the bid, and a bid
+/// left unrevealed forfeits the deposit to the maker.
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct SealedBid {
+    pub key: AccountKey,
+    pub auction: Pubkey,
+    pub bidder: Pubkey,
+    /// The maker of the auction, receiving the deposit if the bid is not revealed.
+    pub maker_wallet: Pubkey,
+    pub bump_seed: u8,
+    pub commitment: [u8; 32],
+    /// The amount escrowed in the vault when the bid was committed.
+    pub deposit: u64,
+    /// The amount left in the vault, set when the bid is revealed.
+    pub revealed_amount: Option<u64>,
+}
+
+impl SealedBid {
+    /// Space allocated for the account.
+    pub const LEN: usize = 1 + 32 + 32 + 32 + 1 + 32 + 8 + 9;
+
+    /// The seeds the bid account signs for its vault with.
+    pub fn signer_seeds<'a>(
+        auction: &'a Pubkey,
+        bidder: &'a Pubkey,
+        bump_seed: &'a [u8; 1],
+    ) -> [&'a [u8]; 4] {
+        [b"sealed_bid", auction.as_ref(), bidder.as_ref(), bump_seed]
+    }
+}
+
+/// A batch of offers proven in one guest run, recorded by the Merkle root of their terms.
+///
+/// Offers are filled with a proof of inclusion against `root`. The account is derived from the
+/// verification key hash and the root, so a root can only be posted once per proving program.
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct OfferBatch {
+    pub key: AccountKey,
+    /// The wallet that posted the batch and gets the rent back when closing it.
+    pub poster: Pubkey,
+    pub bump_seed: u8,
+    /// Hash of the verification key the batch proof was checked against.
+    pub vkey_hash: [u8; 32],
+    pub root: [u8; 32],
+    pub offer_count: u32,
+}
+
+impl OfferBatch {
+    /// Space allocated for the account.
+    pub const LEN: usize = 1 + 32 + 1 + 32 + 32 + 4;
+
+    /// The seeds of the batch account.
+    pub fn signer_seeds<'a>(
+        vkey_hash: &'a [u8; 32],
+        root: &'a [u8; 32],
+        bump_seed: &'a [u8; 1],
+    ) -> [&'a [u8]; 4] {
+        [b"offer_batch", vkey_hash, root, bump_seed]
+    }
+}
@@ -0,0 +1,38 @@
+//! Checks the digests committed when aggregating offer proofs.
+
+use solana_zk_offers::aggregation::{
+    committed_values_digest, AggregatedOfferProofs, AggregatedProof,
+};
+
+#[test]
+fn digest_is_sha256_of_the_committed_bytes() {
+    let expected = [
+        0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae, 0x22,
+        0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61, 0xf2, 0x00,
+        0x15, 0xad,
+    ];
+    assert_eq!(committed_values_digest(b"abc"), expected);
+}
+
+#[test]
+fn aggregation_covers_only_its_proofs() {
+    let offer_vkey = [1; 8];
+    let other_vkey = [2; 8];
+    let aggregated = AggregatedOfferProofs {
+        proofs: vec![
+            AggregatedProof::new(offer_vkey, b"first offer"),
+            AggregatedProof::new(offer_vkey, b"second offer"),
+        ],
+    };
+
+    assert!(aggregated.covers(&offer_vkey, b"first offer"));
+    assert!(aggregated.covers(&offer_vkey, b"second offer"));
+    assert!(!aggregated.covers(&offer_vkey, b"third offer"));
+    assert!(!aggregated.covers(&other_vkey, b"first offer"));
+
+    let encoded = borsh::to_vec(&aggregated).unwrap();
+    assert_eq!(
+        borsh::from_slice::<AggregatedOfferProofs>(&encoded).unwrap(),
+        aggregated
+    );
+}
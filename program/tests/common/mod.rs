@@ -0,0 +1,121 @@
+//! Accounts shared by the program tests.
+#![allow(dead_code)]
+
+use solana_program::{program_option::COption, program_pack::Pack, pubkey::Pubkey};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{account::Account, native_token::LAMPORTS_PER_SOL};
+use solana_zk_offers::zk_offers::{PlatformFee, PublicValuesStruct};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::state::{AccountState, Mint};
+
+/// Terms trading `maker_size` of a fresh mint for 1_000_000 of another, with a 1% platform fee.
+pub fn public_values(maker_size: u64) -> PublicValuesStruct {
+    PublicValuesStruct {
+        maker_mint: Pubkey::new_unique(),
+        taker_mint: Some(Pubkey::new_unique()),
+        is_native: false,
+        maker_size,
+        taker_size: Some(1_000_000),
+        platform_fee: Some(PlatformFee {
+            basis_points: 100,
+            recipient: Pubkey::new_unique(),
+        }),
+    }
+}
+
+pub fn system_account(lamports: u64) -> Account {
+    Account::new(lamports, 0, &solana_program::system_program::id())
+}
+
+pub fn mint_account(supply: u64) -> Account {
+    let mut data = vec![0; Mint::LEN];
+    Mint {
+        mint_authority: COption::None,
+        supply,
+        decimals: 0,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    Account {
+        lamports: LAMPORTS_PER_SOL,
+        data,
+        owner: spl_token::id(),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// Add the ATA of `owner` for `mint`, holding `amount` all delegated to `delegate` if any.
+pub fn add_token_account(
+    program_test: &mut ProgramTest,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    amount: u64,
+    delegate: Option<&Pubkey>,
+) {
+    add_token_account_in_state(
+        program_test,
+        owner,
+        mint,
+        amount,
+        delegate,
+        AccountState::Initialized,
+    );
+}
+
+/// [`add_token_account`] in `state`, such as the frozen account of a programmable NFT.
+pub fn add_token_account_in_state(
+    program_test: &mut ProgramTest,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    amount: u64,
+    delegate: Option<&Pubkey>,
+    state: AccountState,
+) {
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        delegate: delegate.copied().into(),
+        state,
+        is_native: COption::None,
+        delegated_amount: delegate.map_or(0, |_| amount),
+        close_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    program_test.add_account(
+        get_associated_token_address(owner, mint),
+        Account {
+            lamports: LAMPORTS_PER_SOL,
+            data,
+            owner: spl_token::id(),
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
+
+/// The balance of the token account at `account`, which must exist.
+pub async fn token_balance(context: &mut ProgramTestContext, account: Pubkey) -> u64 {
+    let account = context
+        .banks_client
+        .get_account(account)
+        .await
+        .unwrap()
+        .unwrap();
+    spl_token::state::Account::unpack(&account.data)
+        .unwrap()
+        .amount
+}
+
+/// The lamports held at `account`, zero once it is closed.
+pub async fn lamports(context: &mut ProgramTestContext, account: Pubkey) -> u64 {
+    context
+        .banks_client
+        .get_account(account)
+        .await
+        .unwrap()
+        .map_or(0, |account| account.lamports)
+}
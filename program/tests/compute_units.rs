@@ -0,0 +1,361 @@
+//! Reports the compute units `AcceptOffer` consumes on its SPL, native and metadata paths, and
+//! compares the metadata path against the `find_program_address` search it replaced.
+//!
+//! Runs against the SBF build of the program:
+//! ```shell
+//! cargo build-sbf && cargo test-sbf --test compute_units -- --nocapture
+//! ```
+//!
+//! The search is measured on the program as it was before fills supplied the metadata bump, built
+//! into a fixture from the parent of the commit that added the bump:
+//! ```shell
+//! git worktree add /tmp/metadata-search 1214846^
+//! (cd /tmp/metadata-search/program && cargo build-sbf)
+//! cp /tmp/metadata-search/target/deploy/stateless_asks.so \
+//!     tests/fixtures/stateless_asks_metadata_search.so
+//! ```
+#![cfg(feature = "test-sbf")]
+
+mod common;
+
+use common::{add_token_account, mint_account, system_account};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::ProgramTest;
+use solana_sdk::{
+    account::Account,
+    instruction::Instruction,
+    native_token::LAMPORTS_PER_SOL,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use solana_zk_offers::{
+    metadata::{self, find_metadata_address, Creator, Data, Metadata},
+    zk_offers::compute_offer_authority,
+};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::native_mint;
+use stateless_asks::instruction::{accept_offer, accept_offer_with_metadata_account};
+
+const MAKER_SIZE: u64 = 1;
+// Large enough that native creator payouts leave new creator wallets rent exempt.
+const TAKER_SIZE: u64 = LAMPORTS_PER_SOL;
+
+/// The build of the program a fill runs against.
+#[derive(Clone, Copy, PartialEq)]
+enum Build {
+    /// The program in this tree, checking the metadata PDAs against the supplied bump.
+    Current,
+    /// The program before fills supplied the bump, finding the metadata PDAs with
+    /// `find_program_address`.
+    MetadataSearch,
+}
+
+impl Build {
+    fn program_name(self) -> &'static str {
+        match self {
+            Build::Current => "stateless_asks",
+            Build::MetadataSearch => "stateless_asks_metadata_search",
+        }
+    }
+}
+
+struct Fill {
+    program_id: Pubkey,
+    maker: Pubkey,
+    taker: Keypair,
+    maker_mint: Pubkey,
+    taker_mint: Pubkey,
+    authority: Pubkey,
+    bump_seed: u8,
+    build: Build,
+    program_test: ProgramTest,
+}
+
+impl Fill {
+    /// A maker delegating `MAKER_SIZE` of a fresh mint for `TAKER_SIZE` of `taker_mint`.
+    fn new(taker_mint: Option<Pubkey>) -> Self {
+        Self::with_maker_mint(Pubkey::new_unique(), taker_mint, Build::Current)
+    }
+
+    /// A maker delegating `MAKER_SIZE` of `maker_mint` for `TAKER_SIZE` of `taker_mint`, to the
+    /// offer authority of `build`.
+    fn with_maker_mint(maker_mint: Pubkey, taker_mint: Option<Pubkey>, build: Build) -> Self {
+        let program_id = Pubkey::new_unique();
+        let mut program_test = ProgramTest::new(build.program_name(), program_id, None);
+        program_test.prefer_bpf(true);
+        let maker = Pubkey::new_unique();
+        let taker = Keypair::new();
+        let is_native = taker_mint.is_none();
+        let taker_mint = taker_mint.unwrap_or_else(native_mint::id);
+        let (authority, bump_seed) = match build {
+            Build::Current => compute_offer_authority(
+                &program_id,
+                &maker,
+                &maker_mint,
+                &taker_mint,
+                MAKER_SIZE,
+                TAKER_SIZE,
+                None,
+            ),
+            // That build committed the terms themselves in the seeds.
+            Build::MetadataSearch => Pubkey::find_program_address(
+                &[
+                    b"stateless_offer",
+                    maker.as_ref(),
+                    maker_mint.as_ref(),
+                    taker_mint.as_ref(),
+                    &MAKER_SIZE.to_le_bytes(),
+                    &TAKER_SIZE.to_le_bytes(),
+                ],
+                &program_id,
+            ),
+        };
+        for wallet in [maker, taker.pubkey()] {
+            program_test.add_account(wallet, system_account(10 * LAMPORTS_PER_SOL));
+        }
+        program_test.add_account(maker_mint, mint_account(MAKER_SIZE));
+        add_token_account(
+            &mut program_test,
+            &maker,
+            &maker_mint,
+            MAKER_SIZE,
+            Some(&authority),
+        );
+        add_token_account(&mut program_test, &taker.pubkey(), &maker_mint, 0, None);
+        if !is_native {
+            program_test.add_account(taker_mint, mint_account(TAKER_SIZE));
+            add_token_account(&mut program_test, &maker, &taker_mint, 0, None);
+            add_token_account(
+                &mut program_test,
+                &taker.pubkey(),
+                &taker_mint,
+                TAKER_SIZE,
+                None,
+            );
+        }
+        Self {
+            program_id,
+            maker,
+            taker,
+            maker_mint,
+            taker_mint,
+            authority,
+            bump_seed,
+            build,
+            program_test,
+        }
+    }
+
+    fn is_native(&self) -> bool {
+        self.taker_mint == native_mint::id()
+    }
+
+    fn maker_dst_account(&self) -> Pubkey {
+        if self.is_native() {
+            self.maker
+        } else {
+            get_associated_token_address(&self.maker, &self.taker_mint)
+        }
+    }
+
+    fn taker_src_account(&self) -> Pubkey {
+        if self.is_native() {
+            self.taker.pubkey()
+        } else {
+            get_associated_token_address(&self.taker.pubkey(), &self.taker_mint)
+        }
+    }
+
+    fn accept_offer(&self) -> Instruction {
+        accept_offer(
+            &self.program_id,
+            &self.maker,
+            &self.taker.pubkey(),
+            &get_associated_token_address(&self.maker, &self.maker_mint),
+            &self.maker_dst_account(),
+            &self.taker_src_account(),
+            &get_associated_token_address(&self.taker.pubkey(), &self.maker_mint),
+            &self.maker_mint,
+            &self.taker_mint,
+            &self.authority,
+            &spl_token::id(),
+            self.is_native(),
+            None,
+            MAKER_SIZE,
+            TAKER_SIZE,
+            self.bump_seed,
+        )
+    }
+
+    /// Give the maker mint royalty-paying metadata with `creators` creators.
+    fn add_metadata(&mut self, creators: u8) -> Vec<u8> {
+        let creators: Vec<Creator> = (0..creators)
+            .map(|_| Creator {
+                address: Pubkey::new_unique(),
+                verified: true,
+                share: 100 / creators,
+            })
+            .collect();
+        for creator in &creators {
+            if !self.is_native() {
+                add_token_account(
+                    &mut self.program_test,
+                    &creator.address,
+                    &self.taker_mint,
+                    0,
+                    None,
+                );
+            }
+        }
+        let data = borsh::to_vec(&Metadata {
+            key: 4,
+            update_authority: Pubkey::new_unique(),
+            mint: self.maker_mint,
+            data: Data {
+                name: String::new(),
+                symbol: String::new(),
+                uri: String::new(),
+                seller_fee_basis_points: 500,
+                creators: Some(creators),
+            },
+            primary_sale_happened: true,
+            is_mutable: false,
+            edition_nonce: None,
+            token_standard: None,
+            collection: None,
+        })
+        .unwrap();
+        self.program_test.add_account(
+            find_metadata_address(&self.maker_mint).0,
+            Account {
+                lamports: LAMPORTS_PER_SOL,
+                data: data.clone(),
+                owner: metadata::id(),
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+        data
+    }
+
+    fn accept_offer_with_metadata(&self, metadata_data: &[u8]) -> Instruction {
+        let mut instruction = accept_offer_with_metadata_account(
+            &self.program_id,
+            &self.maker,
+            &self.taker.pubkey(),
+            &get_associated_token_address(&self.maker, &self.maker_mint),
+            &self.maker_dst_account(),
+            &self.taker_src_account(),
+            &get_associated_token_address(&self.taker.pubkey(), &self.maker_mint),
+            &self.maker_mint,
+            &self.taker_mint,
+            &self.authority,
+            &spl_token::id(),
+            metadata_data,
+            self.is_native(),
+            None,
+            MAKER_SIZE,
+            TAKER_SIZE,
+            self.bump_seed,
+        )
+        .unwrap();
+        if self.build == Build::MetadataSearch {
+            // The accounts are unchanged. The data was the Borsh encoded `AcceptOffer` variant,
+            // flagging the metadata account instead of carrying its bump, without a platform fee.
+            let mut data = vec![0, 1];
+            data.extend(MAKER_SIZE.to_le_bytes());
+            data.extend(TAKER_SIZE.to_le_bytes());
+            data.extend([self.bump_seed, 0]);
+            instruction.data = data;
+        }
+        instruction
+    }
+
+    /// Simulate `instruction` and return the compute units it consumed.
+    async fn units_consumed(self, instruction: Instruction) -> u64 {
+        let mut context = self.program_test.start_with_context().await;
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&self.taker.pubkey()),
+            &[&self.taker],
+            context.last_blockhash,
+        );
+        let simulation = context
+            .banks_client
+            .simulate_transaction(transaction)
+            .await
+            .unwrap();
+        simulation.result.unwrap().unwrap();
+        simulation.simulation_details.unwrap().units_consumed
+    }
+}
+
+#[tokio::test]
+async fn report_accept_offer_compute_units() {
+    let spl = Fill::new(Some(Pubkey::new_unique()));
+    let instruction = spl.accept_offer();
+    let spl_units = spl.units_consumed(instruction).await;
+
+    let native = Fill::new(None);
+    let instruction = native.accept_offer();
+    let native_units = native.units_consumed(instruction).await;
+
+    let mut metadata = Fill::new(Some(Pubkey::new_unique()));
+    let metadata_data = metadata.add_metadata(2);
+    let instruction = metadata.accept_offer_with_metadata(&metadata_data);
+    let metadata_units = metadata.units_consumed(instruction).await;
+
+    let mut native_metadata = Fill::new(None);
+    let metadata_data = native_metadata.add_metadata(2);
+    let instruction = native_metadata.accept_offer_with_metadata(&metadata_data);
+    let native_metadata_units = native_metadata.units_consumed(instruction).await;
+
+    println!("AcceptOffer compute units");
+    println!("  spl:               {}", spl_units);
+    println!("  native:            {}", native_units);
+    println!("  spl + metadata:    {}", metadata_units);
+    println!("  native + metadata: {}", native_metadata_units);
+
+    // Two creator transfers and a metadata decode, but no PDA search.
+    assert!(metadata_units > spl_units);
+}
+
+/// A fresh mint whose metadata PDA has a bump accepted by `matches`.
+fn mint_with_metadata_bump(matches: impl Fn(u8) -> bool) -> Pubkey {
+    loop {
+        let mint = Pubkey::new_unique();
+        if matches(find_metadata_address(&mint).1) {
+            return mint;
+        }
+    }
+}
+
+/// The units of a native fill of `maker_mint` with royalty-paying metadata on `build`.
+async fn metadata_fill_units(maker_mint: Pubkey, build: Build) -> u64 {
+    let mut fill = Fill::with_maker_mint(maker_mint, None, build);
+    let metadata_data = fill.add_metadata(2);
+    let instruction = fill.accept_offer_with_metadata(&metadata_data);
+    fill.units_consumed(instruction).await
+}
+
+#[tokio::test]
+async fn supplied_metadata_bump_is_cheaper_than_the_search() {
+    // The same fill of a metadata PDA `find_program_address` finds on its first bump and of one
+    // it has to walk at least four bumps down to, on both builds.
+    let shallow = mint_with_metadata_bump(|bump| bump == u8::MAX);
+    let deep = mint_with_metadata_bump(|bump| bump <= u8::MAX - 3);
+    let shallow_units = metadata_fill_units(shallow, Build::Current).await;
+    let deep_units = metadata_fill_units(deep, Build::Current).await;
+    let shallow_search = metadata_fill_units(shallow, Build::MetadataSearch).await;
+    let deep_search = metadata_fill_units(deep, Build::MetadataSearch).await;
+
+    println!("AcceptOffer + metadata compute units, supplied bump / find_program_address");
+    println!("  shallow search: {} / {}", shallow_units, shallow_search);
+    println!("  deep search:    {} / {}", deep_units, deep_search);
+
+    // The search costs more the further down the bump is, where the supplied bump checks each PDA
+    // exactly once whatever its bump, and the deep fill is cheaper for it.
+    assert!(deep_search > shallow_search);
+    assert!(deep_units.abs_diff(shallow_units) < deep_search - shallow_search);
+    assert!(deep_units < deep_search);
+}
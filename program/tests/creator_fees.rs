@@ -0,0 +1,494 @@
+//! Checks that the creator accounts the builders derive from metadata are the ones, in the order,
+//! `pay_creator_fees` reads and pays, and that collection offers only fill with verified members
+//! of their collection, against the SBF build of the program:
+//! ```shell
+//! cargo build-sbf && cargo test-sbf --test creator_fees
+//! ```
+#![cfg(feature = "test-sbf")]
+
+mod common;
+
+use common::{add_token_account, mint_account, system_account};
+use solana_program::{instruction::AccountMeta, program_pack::Pack, pubkey::Pubkey};
+use solana_program_test::{BanksClientError, ProgramTest};
+use solana_sdk::{
+    account::Account,
+    instruction::{Instruction, InstructionError},
+    native_token::LAMPORTS_PER_SOL,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use solana_zk_offers::{
+    metadata::{
+        self, creator_account_metas, decode_metadata, find_metadata_address, Collection, Creator,
+        Data, Metadata,
+    },
+    zk_offers::compute_offer_authority,
+};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::native_mint;
+use stateless_asks::{
+    errors::UtilError,
+    instruction::{
+        accept_collection_offer, accept_offer_with_metadata_account, set_taker_guard, TakerGuard,
+    },
+};
+
+/// Size of the leg paying royalties, large enough that native payouts leave new creator wallets
+/// rent exempt.
+const PAY_SIZE: u64 = LAMPORTS_PER_SOL;
+const SELLER_FEE_BASIS_POINTS: u16 = 500;
+/// Verification and share of each creator, with an unverified creator between verified ones.
+const CREATORS: [(bool, u8); 3] = [(true, 50), (false, 30), (true, 20)];
+
+/// Which leg of the fill is the NFT whose metadata pays royalties.
+#[derive(Clone, Copy, PartialEq)]
+enum NftLeg {
+    /// An offer of the NFT, the taker paying royalties.
+    Maker,
+    /// A collection offer for the NFT, the maker paying royalties.
+    Taker,
+}
+
+struct Royalties {
+    program_id: Pubkey,
+    maker: Pubkey,
+    taker: Keypair,
+    maker_mint: Pubkey,
+    taker_mint: Pubkey,
+    maker_size: u64,
+    taker_size: u64,
+    nft_leg: NftLeg,
+    nft_mint: Pubkey,
+    collection: Pubkey,
+    authority: Pubkey,
+    bump_seed: u8,
+    creators: Vec<Creator>,
+    metadata_data: Vec<u8>,
+    program_test: ProgramTest,
+}
+
+impl Royalties {
+    /// A fill trading one NFT for `PAY_SIZE` of a fresh mint, or of SOL when `is_native`.
+    fn new(nft_leg: NftLeg, is_native: bool) -> Self {
+        let program_id = Pubkey::new_unique();
+        let mut program_test = ProgramTest::new("stateless_asks", program_id, None);
+        program_test.prefer_bpf(true);
+        let maker = Pubkey::new_unique();
+        let taker = Keypair::new();
+        let maker_mint = Pubkey::new_unique();
+        let taker_mint = if is_native {
+            native_mint::id()
+        } else {
+            Pubkey::new_unique()
+        };
+        let (maker_size, taker_size, nft_mint, fee_mint) = match nft_leg {
+            NftLeg::Maker => (1, PAY_SIZE, maker_mint, taker_mint),
+            NftLeg::Taker => (PAY_SIZE, 1, taker_mint, maker_mint),
+        };
+        let (authority, bump_seed) = compute_offer_authority(
+            &program_id,
+            &maker,
+            &maker_mint,
+            &taker_mint,
+            maker_size,
+            taker_size,
+            None,
+        );
+        for wallet in [maker, taker.pubkey()] {
+            program_test.add_account(wallet, system_account(10 * LAMPORTS_PER_SOL));
+        }
+        program_test.add_account(maker_mint, mint_account(maker_size));
+        add_token_account(
+            &mut program_test,
+            &maker,
+            &maker_mint,
+            maker_size,
+            Some(&authority),
+        );
+        add_token_account(&mut program_test, &taker.pubkey(), &maker_mint, 0, None);
+        if !is_native {
+            program_test.add_account(taker_mint, mint_account(taker_size));
+            add_token_account(&mut program_test, &maker, &taker_mint, 0, None);
+            add_token_account(
+                &mut program_test,
+                &taker.pubkey(),
+                &taker_mint,
+                taker_size,
+                None,
+            );
+        }
+
+        let creators: Vec<Creator> = CREATORS
+            .iter()
+            .map(|&(verified, share)| Creator {
+                address: Pubkey::new_unique(),
+                verified,
+                share,
+            })
+            .collect();
+        for creator in &creators {
+            program_test.add_account(creator.address, system_account(LAMPORTS_PER_SOL));
+            if !is_native {
+                add_token_account(&mut program_test, &creator.address, &fee_mint, 0, None);
+            }
+        }
+        let collection = Pubkey::new_unique();
+        let metadata_data = borsh::to_vec(&Metadata {
+            key: 4,
+            update_authority: Pubkey::new_unique(),
+            mint: nft_mint,
+            data: Data {
+                name: String::new(),
+                symbol: String::new(),
+                uri: String::new(),
+                seller_fee_basis_points: SELLER_FEE_BASIS_POINTS,
+                creators: Some(creators.clone()),
+            },
+            primary_sale_happened: true,
+            is_mutable: false,
+            edition_nonce: None,
+            token_standard: None,
+            collection: Some(Collection {
+                verified: true,
+                key: collection,
+            }),
+        })
+        .unwrap();
+        program_test.add_account(
+            find_metadata_address(&nft_mint).0,
+            Account {
+                lamports: LAMPORTS_PER_SOL,
+                data: metadata_data.clone(),
+                owner: metadata::id(),
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+        Self {
+            program_id,
+            maker,
+            taker,
+            maker_mint,
+            taker_mint,
+            maker_size,
+            taker_size,
+            nft_leg,
+            nft_mint,
+            collection,
+            authority,
+            bump_seed,
+            creators,
+            metadata_data,
+            program_test,
+        }
+    }
+
+    fn is_native(&self) -> bool {
+        self.taker_mint == native_mint::id()
+    }
+
+    /// The mint royalties are paid in, the leg that is not the NFT.
+    fn fee_mint(&self) -> Pubkey {
+        match self.nft_leg {
+            NftLeg::Maker => self.taker_mint,
+            NftLeg::Taker => self.maker_mint,
+        }
+    }
+
+    /// The account each creator is paid into, the wallet for SOL and its ATA otherwise.
+    fn creator_dst_account(&self, creator: &Creator) -> Pubkey {
+        if self.is_native() {
+            creator.address
+        } else {
+            get_associated_token_address(&creator.address, &self.fee_mint())
+        }
+    }
+
+    /// The creator accounts `pay_creator_fees` reads, written out from the metadata by hand.
+    fn expected_creator_accounts(&self, verified_creators_only: bool) -> Vec<AccountMeta> {
+        let mut accounts = Vec::new();
+        for creator in &self.creators {
+            if verified_creators_only && !creator.verified {
+                continue;
+            }
+            if self.is_native() {
+                accounts.push(AccountMeta::new(creator.address, false));
+            } else {
+                accounts.push(AccountMeta::new_readonly(creator.address, false));
+                accounts.push(AccountMeta::new(self.creator_dst_account(creator), false));
+            }
+        }
+        accounts
+    }
+
+    /// What each creator is owed on a fill, zero for the creators the fill skips.
+    fn expected_payouts(&self, verified_creators_only: bool) -> Vec<u64> {
+        let total_fee = PAY_SIZE * u64::from(SELLER_FEE_BASIS_POINTS) / 10_000;
+        self.creators
+            .iter()
+            .map(|creator| {
+                if verified_creators_only && !creator.verified {
+                    0
+                } else {
+                    total_fee * u64::from(creator.share) / 100
+                }
+            })
+            .collect()
+    }
+
+    fn accept(&self, verified_creators_only: bool) -> Instruction {
+        let maker_dst_account = if self.is_native() {
+            self.maker
+        } else {
+            get_associated_token_address(&self.maker, &self.taker_mint)
+        };
+        let taker_src_account = if self.is_native() {
+            self.taker.pubkey()
+        } else {
+            get_associated_token_address(&self.taker.pubkey(), &self.taker_mint)
+        };
+        let maker_src_account = get_associated_token_address(&self.maker, &self.maker_mint);
+        let taker_dst_account =
+            get_associated_token_address(&self.taker.pubkey(), &self.maker_mint);
+        match self.nft_leg {
+            NftLeg::Maker => {
+                assert!(!verified_creators_only, "offers of an NFT pay all creators");
+                accept_offer_with_metadata_account(
+                    &self.program_id,
+                    &self.maker,
+                    &self.taker.pubkey(),
+                    &maker_src_account,
+                    &maker_dst_account,
+                    &taker_src_account,
+                    &taker_dst_account,
+                    &self.maker_mint,
+                    &self.taker_mint,
+                    &self.authority,
+                    &spl_token::id(),
+                    &self.metadata_data,
+                    self.is_native(),
+                    None,
+                    self.maker_size,
+                    self.taker_size,
+                    self.bump_seed,
+                )
+            }
+            NftLeg::Taker => accept_collection_offer(
+                &self.program_id,
+                &self.maker,
+                &self.taker.pubkey(),
+                &maker_src_account,
+                &maker_dst_account,
+                &taker_src_account,
+                &taker_dst_account,
+                &self.maker_mint,
+                &self.taker_mint,
+                &self.collection,
+                &self.authority,
+                &spl_token::id(),
+                &self.metadata_data,
+                verified_creators_only,
+                None,
+                self.maker_size,
+                self.taker_size,
+                self.bump_seed,
+            ),
+        }
+        .unwrap()
+    }
+
+    /// Replace the metadata account the program reads with one naming `collection`, leaving the
+    /// metadata the instruction is built from a verified member.
+    fn with_metadata_collection(mut self, collection: Collection) -> Self {
+        let mut metadata = decode_metadata(&self.metadata_data).unwrap();
+        metadata.collection = Some(collection);
+        self.program_test.add_account(
+            find_metadata_address(&self.nft_mint).0,
+            Account {
+                lamports: LAMPORTS_PER_SOL,
+                data: borsh::to_vec(&metadata).unwrap(),
+                owner: metadata::id(),
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+        self
+    }
+
+    /// Run the fill paying every creator, with `taker_guard` set.
+    async fn fill_with_guard(self, taker_guard: TakerGuard) -> Result<(), BanksClientError> {
+        let mut instruction = self.accept(false);
+        set_taker_guard(&mut instruction, taker_guard).unwrap();
+        let mut context = self.program_test.start_with_context().await;
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&self.taker.pubkey()),
+            &[&self.taker],
+            context.last_blockhash,
+        );
+        context.banks_client.process_transaction(transaction).await
+    }
+
+    /// Check the creator accounts of the fill, run it and compare what each creator received
+    /// with what it is owed.
+    async fn assert_creators_paid(self, verified_creators_only: bool) {
+        let instruction = self.accept(verified_creators_only);
+        let (metadata_key, _) = find_metadata_address(&self.nft_mint);
+        let metadata_index = instruction
+            .accounts
+            .iter()
+            .position(|account| account.pubkey == metadata_key)
+            .unwrap();
+        let expected_accounts = self.expected_creator_accounts(verified_creators_only);
+        assert_eq!(
+            instruction.accounts[metadata_index + 1..],
+            expected_accounts[..]
+        );
+
+        let expected_payouts = self.expected_payouts(verified_creators_only);
+        let creator_dst_accounts: Vec<Pubkey> = self
+            .creators
+            .iter()
+            .map(|creator| self.creator_dst_account(creator))
+            .collect();
+        let is_native = self.is_native();
+        let mut context = self.program_test.start_with_context().await;
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&self.taker.pubkey()),
+            &[&self.taker],
+            context.last_blockhash,
+        );
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+        for (account, expected_payout) in creator_dst_accounts.into_iter().zip(expected_payouts) {
+            let account = context
+                .banks_client
+                .get_account(account)
+                .await
+                .unwrap()
+                .unwrap();
+            let payout = if is_native {
+                account.lamports - LAMPORTS_PER_SOL
+            } else {
+                spl_token::state::Account::unpack(&account.data)
+                    .unwrap()
+                    .amount
+            };
+            assert_eq!(payout, expected_payout);
+        }
+    }
+}
+
+#[tokio::test]
+async fn offers_of_an_nft_pay_every_creator_in_spl() {
+    Royalties::new(NftLeg::Maker, false)
+        .assert_creators_paid(false)
+        .await;
+}
+
+#[tokio::test]
+async fn offers_of_an_nft_pay_every_creator_in_sol() {
+    Royalties::new(NftLeg::Maker, true)
+        .assert_creators_paid(false)
+        .await;
+}
+
+#[tokio::test]
+async fn collection_offers_pay_every_creator() {
+    Royalties::new(NftLeg::Taker, false)
+        .assert_creators_paid(false)
+        .await;
+}
+
+#[tokio::test]
+async fn collection_offers_can_pay_verified_creators_only() {
+    Royalties::new(NftLeg::Taker, false)
+        .assert_creators_paid(true)
+        .await;
+}
+
+#[tokio::test]
+async fn taker_guards_count_the_royalties_the_taker_pays() {
+    // The royalties come out of the taker leg, so a cap at the maker's part of it is exceeded.
+    let royalties = PAY_SIZE * u64::from(SELLER_FEE_BASIS_POINTS) / 10_000;
+    let err = Royalties::new(NftLeg::Maker, false)
+        .fill_with_guard(TakerGuard {
+            min_maker_received: None,
+            max_taker_paid: Some(PAY_SIZE - royalties),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(UtilError::TakerPaidAboveMaximum as u32)
+        )
+    );
+
+    Royalties::new(NftLeg::Maker, false)
+        .fill_with_guard(TakerGuard {
+            min_maker_received: Some(1),
+            max_taker_paid: Some(PAY_SIZE),
+        })
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn collection_offers_fill_with_verified_members_only() {
+    Royalties::new(NftLeg::Taker, false)
+        .fill_with_guard(TakerGuard::default())
+        .await
+        .unwrap();
+
+    // The instructions are built from the verified metadata, so only the program sees an
+    // unverified membership or a foreign collection.
+    for is_foreign in [false, true] {
+        let royalties = Royalties::new(NftLeg::Taker, false);
+        let collection = if is_foreign {
+            Collection {
+                verified: true,
+                key: Pubkey::new_unique(),
+            }
+        } else {
+            Collection {
+                verified: false,
+                key: royalties.collection,
+            }
+        };
+        let err = royalties
+            .with_metadata_collection(collection)
+            .fill_with_guard(TakerGuard::default())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.unwrap(),
+            TransactionError::InstructionError(0, InstructionError::InvalidAccountData)
+        );
+    }
+}
+
+/// No instruction pays royalties in SOL to verified creators only, so the native order is only
+/// checked against the accounts written out by hand.
+#[test]
+fn native_creator_accounts_skip_unverified_creators() {
+    let royalties = Royalties::new(NftLeg::Maker, true);
+    let metadata = decode_metadata(&royalties.metadata_data).unwrap();
+    for verified_creators_only in [false, true] {
+        assert_eq!(
+            creator_account_metas(
+                &metadata,
+                &royalties.fee_mint(),
+                true,
+                verified_creators_only
+            ),
+            royalties.expected_creator_accounts(verified_creators_only)
+        );
+    }
+}
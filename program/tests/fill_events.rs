@@ -0,0 +1,85 @@
+//! Checks that fill events round trip through the program logs and that only events logged by
+//! the program itself are parsed.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_program::{hash::hashv, pubkey::Pubkey};
+use solana_zk_offers::events::{parse_fill_events, FeePaid, FillEvent, FILL_EVENT_DISCRIMINATOR};
+
+fn fill_event() -> FillEvent {
+    FillEvent {
+        offer: Pubkey::new_unique(),
+        maker_wallet: Pubkey::new_unique(),
+        taker_wallet: Pubkey::new_unique(),
+        maker_mint: Pubkey::new_unique(),
+        taker_mint: Pubkey::new_unique(),
+        maker_size: 1,
+        taker_size: 1_000,
+        taker_received: 1,
+        maker_received: 940,
+        fee_mint: Pubkey::new_unique(),
+        creator_fees: vec![
+            FeePaid {
+                recipient: Pubkey::new_unique(),
+                amount: 30,
+            },
+            FeePaid {
+                recipient: Pubkey::new_unique(),
+                amount: 20,
+            },
+        ],
+        platform_fee: Some(FeePaid {
+            recipient: Pubkey::new_unique(),
+            amount: 10,
+        }),
+    }
+}
+
+fn data_log(event: &FillEvent) -> String {
+    format!("Program data: {}", STANDARD.encode(event.to_log_data()))
+}
+
+#[test]
+fn discriminator_matches_anchor() {
+    assert_eq!(
+        FILL_EVENT_DISCRIMINATOR,
+        hashv(&[b"event:FillEvent"]).to_bytes()[..8]
+    );
+}
+
+#[test]
+fn event_round_trips_through_log_data() {
+    let event = fill_event();
+    assert_eq!(FillEvent::from_log_data(&event.to_log_data()), Some(event));
+    assert_eq!(FillEvent::from_log_data(&[0; 8]), None);
+}
+
+#[test]
+fn only_events_logged_by_the_program_are_parsed() {
+    let program_id = Pubkey::new_unique();
+    let other = Pubkey::new_unique();
+    let event = fill_event();
+    let forged = fill_event();
+    let logs = vec![
+        format!("Program {} invoke [1]", other),
+        data_log(&forged),
+        format!("Program {} success", other),
+        format!("Program {} invoke [1]", program_id),
+        "Program log: Instruction: accept offer".to_string(),
+        format!("Program {} invoke [2]", spl_token::id()),
+        data_log(&forged),
+        format!(
+            "Program {} consumed 4645 of 180000 compute units",
+            spl_token::id()
+        ),
+        format!("Program {} success", spl_token::id()),
+        data_log(&event),
+        "Program log: done!".to_string(),
+        format!(
+            "Program {} consumed 40000 of 200000 compute units",
+            program_id
+        ),
+        format!("Program {} success", program_id),
+        data_log(&forged),
+    ];
+    assert_eq!(parse_fill_events(&program_id, &logs), [event]);
+}
@@ -0,0 +1,305 @@
+//! Checks the versioned instruction wire format.
+
+mod common;
+
+use common::public_values;
+use solana_program::{instruction::Instruction, program_error::ProgramError, pubkey::Pubkey};
+use solana_zk_offers::{metadata::find_metadata_address, zk_offers::PublicValuesStruct};
+use spl_associated_token_account::get_associated_token_address;
+use stateless_asks::{
+    errors::UtilError,
+    instruction::{
+        accept_offer, accept_offer_with_metadata, create_offer_transaction, set_taker_guard,
+        StatelessOfferInstruction, TakerGuard, INSTRUCTION_HEADER_LEN, INSTRUCTION_VERSION,
+    },
+};
+
+/// One instance of every variant, in declaration order.
+fn instructions() -> Vec<StatelessOfferInstruction> {
+    vec![
+        StatelessOfferInstruction::AcceptOffer {
+            metadata_bump_seed: Some(254),
+            maker_size: 1,
+            taker_size: 2,
+            bump_seed: 255,
+            platform_fee: None,
+            taker_guard: TakerGuard {
+                min_maker_received: Some(1),
+                max_taker_paid: None,
+            },
+        },
+        StatelessOfferInstruction::AcceptProgrammableOffer {
+            metadata_bump_seed: None,
+            maker_size: 1,
+            taker_size: 2,
+            bump_seed: 255,
+            platform_fee: None,
+            taker_guard: TakerGuard::default(),
+        },
+        StatelessOfferInstruction::AcceptCollectionOffer {
+            collection: Pubkey::new_unique(),
+            verified_creators_only: true,
+            metadata_bump_seed: 253,
+            maker_size: 1,
+            taker_size: 2,
+            bump_seed: 255,
+            platform_fee: public_values(1).platform_fee,
+            taker_guard: TakerGuard {
+                min_maker_received: None,
+                max_taker_paid: Some(2),
+            },
+        },
+        StatelessOfferInstruction::InitEscrowOffer {
+            nonce: 7,
+            bump_seed: 255,
+            public_values: public_values(1).to_canonical_bytes(),
+        },
+        StatelessOfferInstruction::AcceptEscrowOffer {
+            metadata_bump_seed: None,
+            taker_guard: TakerGuard::default(),
+        },
+        StatelessOfferInstruction::CloseEscrowOffer,
+        StatelessOfferInstruction::InitRoutedOffer {
+            nonce: 7,
+            bump_seed: 255,
+            public_values: public_values(1).to_canonical_bytes(),
+        },
+        StatelessOfferInstruction::AcceptRoutedOffer {
+            metadata_bump_seed: Some(254),
+            router_bump_seed: 255,
+            taker_guard: TakerGuard {
+                min_maker_received: Some(1),
+                max_taker_paid: Some(2),
+            },
+        },
+        StatelessOfferInstruction::CloseRoutedOffer,
+        StatelessOfferInstruction::InitSealedAuction {
+            nonce: 7,
+            bump_seed: 255,
+            public_values: public_values(1).to_canonical_bytes(),
+            bid_vkey_hash: [1; 32],
+            commit_end_slot: 100,
+            reveal_end_slot: 200,
+        },
+        StatelessOfferInstruction::CommitSealedBid {
+            bump_seed: 254,
+            commitment: [2; 32],
+            deposit: 2_000_000,
+        },
+        StatelessOfferInstruction::RevealSealedBid {
+            amount: 1_000_000,
+            proof: vec![3; 260],
+        },
+        StatelessOfferInstruction::SettleSealedAuction {
+            metadata_bump_seed: Some(253),
+        },
+        StatelessOfferInstruction::CloseSealedBid,
+        StatelessOfferInstruction::PostOfferBatch {
+            bump_seed: 252,
+            vkey_hash: [4; 32],
+            root: [5; 32],
+            offer_count: 300,
+            proof: vec![6; 260],
+        },
+        StatelessOfferInstruction::AcceptBatchedOffer {
+            metadata_bump_seed: None,
+            bump_seed: 251,
+            public_values: public_values(1).to_canonical_bytes(),
+            merkle_proof: vec![[7; 32], [8; 32]],
+            taker_guard: TakerGuard::default(),
+        },
+        StatelessOfferInstruction::CloseOfferBatch,
+    ]
+}
+
+#[test]
+fn instructions_round_trip_with_header() {
+    for (index, instruction) in instructions().into_iter().enumerate() {
+        let data = instruction.pack();
+        assert_eq!(data[0], index as u8, "{:?}", instruction);
+        assert_eq!(data[0], instruction.discriminator());
+        assert_eq!(data[1], INSTRUCTION_VERSION);
+        assert!(data[2..INSTRUCTION_HEADER_LEN].iter().all(|byte| *byte == 0));
+        assert_eq!(
+            StatelessOfferInstruction::unpack(&data).unwrap(),
+            instruction
+        );
+    }
+}
+
+#[test]
+fn unpack_rejects_unknown_headers() {
+    let data = StatelessOfferInstruction::CloseEscrowOffer.pack();
+    let mut unknown_version = data.clone();
+    unknown_version[1] = INSTRUCTION_VERSION + 1;
+    let mut reserved_set = data.clone();
+    reserved_set[INSTRUCTION_HEADER_LEN - 1] = 1;
+    let mut unknown_discriminator = data.clone();
+    unknown_discriminator[0] = instructions().len() as u8;
+    for data in [
+        unknown_version,
+        reserved_set,
+        unknown_discriminator,
+        data[..INSTRUCTION_HEADER_LEN - 1].to_vec(),
+    ] {
+        assert_eq!(
+            StatelessOfferInstruction::unpack(&data),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
+}
+
+#[test]
+fn taker_guard_is_set_on_accept_instructions_only() {
+    let taker_guard = TakerGuard {
+        min_maker_received: Some(1),
+        max_taker_paid: Some(2),
+    };
+    for mut instruction in instructions() {
+        let mut built =
+            Instruction::new_with_bytes(Pubkey::new_unique(), &instruction.pack(), vec![]);
+        let result = set_taker_guard(&mut built, taker_guard);
+        match instruction.taker_guard_mut() {
+            Some(guard) => {
+                result.unwrap();
+                *guard = taker_guard;
+                assert_eq!(
+                    StatelessOfferInstruction::unpack(&built.data).unwrap(),
+                    instruction
+                );
+            }
+            None => assert_eq!(result, Err(ProgramError::InvalidInstructionData)),
+        }
+    }
+}
+
+#[test]
+fn taker_guard_bounds_the_fill_after_fees() {
+    let taker_guard = TakerGuard {
+        min_maker_received: Some(95),
+        max_taker_paid: Some(1_000),
+    };
+    assert_eq!(taker_guard.check(95, 1_000), Ok(()));
+    assert_eq!(TakerGuard::default().check(0, u64::MAX), Ok(()));
+    assert_eq!(
+        taker_guard.check(94, 1_000),
+        Err(UtilError::MakerReceivedBelowMinimum.into())
+    );
+    assert_eq!(
+        taker_guard.check(95, 1_001),
+        Err(UtilError::TakerPaidAboveMaximum.into())
+    );
+}
+
+#[test]
+fn public_values_keep_their_offsets_in_instruction_data() {
+    let with_fee = public_values(1);
+    let without_fee = PublicValuesStruct {
+        taker_mint: None,
+        taker_size: None,
+        platform_fee: None,
+        ..with_fee.clone()
+    };
+    let init_escrow_offer = |public_values: &PublicValuesStruct| {
+        StatelessOfferInstruction::InitEscrowOffer {
+            nonce: 7,
+            bump_seed: 255,
+            public_values: public_values.to_canonical_bytes(),
+        }
+        .pack()
+    };
+    assert_eq!(
+        init_escrow_offer(&with_fee).len(),
+        init_escrow_offer(&without_fee).len()
+    );
+}
+
+#[test]
+fn accept_offer_with_metadata_passes_the_platform_fee() {
+    let public_values = public_values(1);
+    let platform_fee = public_values.platform_fee.unwrap();
+    let maker_mint = public_values.maker_mint;
+    let taker_mint = public_values.taker_mint.unwrap();
+    let creator = Pubkey::new_unique();
+    let accept = |nft_mint: &Pubkey| {
+        let (metadata, metadata_bump_seed) = find_metadata_address(nft_mint);
+        accept_offer_with_metadata(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &maker_mint,
+            &taker_mint,
+            &Pubkey::new_unique(),
+            &spl_token::id(),
+            &metadata,
+            metadata_bump_seed,
+            &[&creator],
+            false,
+            Some(&platform_fee),
+            1,
+            2,
+            255,
+        )
+    };
+
+    // The fee account follows the creators, in the leg the metadata does not describe.
+    for (nft_mint, fee_mint) in [(maker_mint, taker_mint), (taker_mint, maker_mint)] {
+        let instruction = accept(&nft_mint);
+        let StatelessOfferInstruction::AcceptOffer {
+            platform_fee: Some(packed_fee),
+            ..
+        } = StatelessOfferInstruction::unpack(&instruction.data).unwrap()
+        else {
+            panic!("expected an accept offer with a platform fee");
+        };
+        assert_eq!(packed_fee, platform_fee);
+        let accounts = &instruction.accounts;
+        assert_eq!(accounts[accounts.len() - 2].pubkey, creator);
+        assert_eq!(
+            accounts.last().unwrap().pubkey,
+            get_associated_token_address(&platform_fee.recipient, &fee_mint)
+        );
+    }
+}
+
+#[test]
+#[allow(deprecated)]
+fn create_offer_transaction_accepts_the_encoded_offer() {
+    let program_id = Pubkey::new_unique();
+    let authority = Pubkey::new_unique();
+    let public_values = public_values(1);
+    let accounts: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+    // An `OfferStruct` is its six accounts and bump seed, then its public values.
+    let mut offer: Vec<u8> = accounts
+        .iter()
+        .flat_map(|account| account.to_bytes())
+        .collect();
+    offer.push(254);
+    offer.extend(borsh::to_vec(&public_values).unwrap());
+
+    assert_eq!(
+        create_offer_transaction(&program_id, offer, authority),
+        accept_offer(
+            &program_id,
+            &accounts[0],
+            &accounts[1],
+            &accounts[2],
+            &accounts[3],
+            &accounts[4],
+            &accounts[5],
+            &public_values.maker_mint,
+            &public_values.taker_mint.unwrap(),
+            &authority,
+            &spl_token::id(),
+            false,
+            public_values.platform_fee.as_ref(),
+            1,
+            public_values.taker_size.unwrap(),
+            254,
+        )
+    );
+}
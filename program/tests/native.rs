@@ -0,0 +1,107 @@
+//! Checks the instructions built around offers whose maker leg is SOL, running them against the
+//! SPL programs `ProgramTest` loads.
+
+mod common;
+
+use common::system_account;
+use solana_program::{program_pack::Pack, pubkey::Pubkey};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::Instruction,
+    native_token::LAMPORTS_PER_SOL,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use solana_zk_offers::native::{
+    approve_native_delegation, cancel_native_delegation, finish_native_fill, prepare_native_fill,
+    unwrap_sol, wrap_sol, wrapped_sol_account,
+};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::{native_mint, state::Account};
+
+async fn start(wallet: &Keypair) -> ProgramTestContext {
+    let mut program_test = ProgramTest::default();
+    program_test.add_account(wallet.pubkey(), system_account(10 * LAMPORTS_PER_SOL));
+    program_test.start_with_context().await
+}
+
+async fn send(context: &mut ProgramTestContext, wallet: &Keypair, instructions: &[Instruction]) {
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&wallet.pubkey()),
+        &[wallet],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+}
+
+async fn wrapped_sol(context: &mut ProgramTestContext, wallet: &Pubkey) -> Option<Account> {
+    context
+        .banks_client
+        .get_account(wrapped_sol_account(wallet))
+        .await
+        .unwrap()
+        .map(|account| Account::unpack(&account.data).unwrap())
+}
+
+#[tokio::test]
+async fn native_delegation_wraps_and_cancelling_unwraps() {
+    let maker = Keypair::new();
+    let authority = Pubkey::new_unique();
+    let mut context = start(&maker).await;
+
+    let approve = approve_native_delegation(&maker.pubkey(), &authority, LAMPORTS_PER_SOL);
+    send(&mut context, &maker, &approve).await;
+    let wrapped = wrapped_sol(&mut context, &maker.pubkey()).await.unwrap();
+    assert_eq!(wrapped.mint, native_mint::id());
+    assert_eq!(wrapped.amount, LAMPORTS_PER_SOL);
+    assert_eq!(wrapped.delegate, Some(authority).into());
+    assert_eq!(wrapped.delegated_amount, LAMPORTS_PER_SOL);
+
+    let cancel = cancel_native_delegation(&maker.pubkey());
+    send(&mut context, &maker, &cancel).await;
+    assert!(wrapped_sol(&mut context, &maker.pubkey()).await.is_none());
+}
+
+#[tokio::test]
+async fn finishing_a_fill_only_closes_a_wrapped_account_it_created() {
+    let taker = Keypair::new();
+    let mut context = start(&taker).await;
+    let wrap = wrap_sol(&taker.pubkey(), LAMPORTS_PER_SOL);
+    send(&mut context, &taker, &wrap).await;
+
+    // The account existed before the fill, so it is not closed and keeps its balance.
+    let finish = finish_native_fill(&taker.pubkey(), true);
+    assert!(finish.is_empty());
+    let wrapped = wrapped_sol(&mut context, &taker.pubkey()).await.unwrap();
+    assert_eq!(wrapped.amount, LAMPORTS_PER_SOL);
+
+    // An account the fill created holds the proceeds alone, which the close unwraps.
+    let finish = finish_native_fill(&taker.pubkey(), false);
+    assert_eq!(finish, vec![unwrap_sol(&taker.pubkey())]);
+    send(&mut context, &taker, &finish).await;
+    assert!(wrapped_sol(&mut context, &taker.pubkey()).await.is_none());
+}
+
+#[test]
+fn native_fills_create_every_wrapped_sol_account_paid_by_the_taker() {
+    let taker = Pubkey::new_unique();
+    let recipients = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let instructions = prepare_native_fill(&taker, &recipients);
+    assert_eq!(instructions.len(), 3);
+    let wallets = [taker, recipients[0], recipients[1]];
+    for (instruction, wallet) in instructions.iter().zip(wallets) {
+        assert_eq!(instruction.program_id, spl_associated_token_account::id());
+        assert_eq!(instruction.accounts[0].pubkey, taker);
+        assert!(instruction.accounts[0].is_signer);
+        assert_eq!(
+            instruction.accounts[1].pubkey,
+            get_associated_token_address(&wallet, &native_mint::id())
+        );
+        assert_eq!(instruction.accounts[2].pubkey, wallet);
+    }
+}
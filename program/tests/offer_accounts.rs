@@ -0,0 +1,460 @@
+//! Runs the escrowed, routed and batched offer instructions against the SBF build of the program:
+//! ```shell
+//! cargo build-sbf && cargo test-sbf --test offer_accounts
+//! ```
+#![cfg(feature = "test-sbf")]
+
+mod common;
+
+use common::{
+    add_token_account, add_token_account_in_state, lamports, mint_account, system_account,
+    token_balance,
+};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::{BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::{Instruction, InstructionError},
+    native_token::LAMPORTS_PER_SOL,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use solana_zk_offers::{
+    offer_batch::compute_offer_batch_address,
+    zk_offers::{
+        compute_escrow_offer_address, compute_escrow_vault, compute_offer_router,
+        compute_routed_offer_address, PublicValuesStruct, OFFER_VKEY_HASH,
+    },
+};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::{native_mint, state::AccountState};
+use stateless_asks::{
+    errors::UtilError,
+    instruction::{
+        accept_escrow_offer, accept_routed_offer, close_escrow_offer, close_routed_offer,
+        init_escrow_offer, init_routed_offer, post_offer_batch,
+    },
+};
+
+const MAKER_SIZE: u64 = 1;
+const TAKER_SIZE: u64 = 1_000;
+
+/// A maker holding twice `MAKER_SIZE` of a fresh mint, all delegated to the offer router, and a
+/// taker holding `TAKER_SIZE` of another, or SOL for native offers.
+struct Offers {
+    program_id: Pubkey,
+    maker: Keypair,
+    taker: Keypair,
+    maker_mint: Pubkey,
+    taker_mint: Pubkey,
+    program_test: ProgramTest,
+}
+
+impl Offers {
+    fn new() -> Self {
+        Self::with_taker_account_state(AccountState::Initialized)
+    }
+
+    /// [`new`](Self::new) with the taker's source account in `state`.
+    fn with_taker_account_state(state: AccountState) -> Self {
+        let mut offers = Self::with_taker_mint(Pubkey::new_unique());
+        offers
+            .program_test
+            .add_account(offers.taker_mint, mint_account(TAKER_SIZE));
+        add_token_account(
+            &mut offers.program_test,
+            &offers.maker.pubkey(),
+            &offers.taker_mint,
+            0,
+            None,
+        );
+        add_token_account_in_state(
+            &mut offers.program_test,
+            &offers.taker.pubkey(),
+            &offers.taker_mint,
+            TAKER_SIZE,
+            None,
+            state,
+        );
+        offers
+    }
+
+    /// Offers paid for in SOL, out of the taker's wallet.
+    fn native() -> Self {
+        Self::with_taker_mint(native_mint::id())
+    }
+
+    /// The maker and taker, without any account for `taker_mint`.
+    fn with_taker_mint(taker_mint: Pubkey) -> Self {
+        let program_id = Pubkey::new_unique();
+        let mut program_test = ProgramTest::new("stateless_asks", program_id, None);
+        program_test.prefer_bpf(true);
+        let (router, _) = compute_offer_router(&program_id);
+        let maker = Keypair::new();
+        let taker = Keypair::new();
+        let maker_mint = Pubkey::new_unique();
+        for wallet in [maker.pubkey(), taker.pubkey()] {
+            program_test.add_account(wallet, system_account(10 * LAMPORTS_PER_SOL));
+        }
+        program_test.add_account(maker_mint, mint_account(2 * MAKER_SIZE));
+        add_token_account(
+            &mut program_test,
+            &maker.pubkey(),
+            &maker_mint,
+            2 * MAKER_SIZE,
+            Some(&router),
+        );
+        add_token_account(&mut program_test, &taker.pubkey(), &maker_mint, 0, None);
+        Self {
+            program_id,
+            maker,
+            taker,
+            maker_mint,
+            taker_mint,
+            program_test,
+        }
+    }
+
+    fn is_native(&self) -> bool {
+        self.taker_mint == native_mint::id()
+    }
+
+    fn public_values(&self) -> PublicValuesStruct {
+        PublicValuesStruct {
+            maker_mint: self.maker_mint,
+            taker_mint: Some(self.taker_mint),
+            is_native: self.is_native(),
+            maker_size: MAKER_SIZE,
+            taker_size: Some(TAKER_SIZE),
+            platform_fee: None,
+        }
+    }
+
+    /// Where the maker is paid, its wallet for SOL and its ATA otherwise.
+    fn maker_dst_account(&self) -> Pubkey {
+        if self.is_native() {
+            self.maker.pubkey()
+        } else {
+            get_associated_token_address(&self.maker.pubkey(), &self.taker_mint)
+        }
+    }
+
+    /// What the taker pays from, its wallet for SOL and its ATA otherwise.
+    fn taker_src_account(&self) -> Pubkey {
+        if self.is_native() {
+            self.taker.pubkey()
+        } else {
+            get_associated_token_address(&self.taker.pubkey(), &self.taker_mint)
+        }
+    }
+
+    fn taker_dst_account(&self) -> Pubkey {
+        get_associated_token_address(&self.taker.pubkey(), &self.maker_mint)
+    }
+
+    fn maker_src_account(&self) -> Pubkey {
+        get_associated_token_address(&self.maker.pubkey(), &self.maker_mint)
+    }
+
+    /// Record the escrow offer with `nonce`, returning its address and the instruction.
+    fn init_escrow_offer(&self, nonce: u64) -> (Pubkey, Instruction) {
+        let maker = self.maker.pubkey();
+        let (escrow_offer, bump_seed) =
+            compute_escrow_offer_address(&self.program_id, &maker, nonce);
+        let init = init_escrow_offer(
+            &self.program_id,
+            &maker,
+            &escrow_offer,
+            &spl_token::id(),
+            self.public_values(),
+            nonce,
+            bump_seed,
+        );
+        (escrow_offer, init)
+    }
+
+    fn accept_escrow_offer(&self, escrow_offer: &Pubkey) -> Instruction {
+        accept_escrow_offer(
+            &self.program_id,
+            &self.maker.pubkey(),
+            &self.taker.pubkey(),
+            escrow_offer,
+            &self.maker_dst_account(),
+            &self.taker_src_account(),
+            &self.taker_dst_account(),
+            &spl_token::id(),
+            &self.public_values(),
+            None,
+        )
+        .unwrap()
+    }
+
+    /// Record the routed offer with `nonce`, returning its address and the instruction.
+    fn init_routed_offer(&self, nonce: u64) -> (Pubkey, Instruction) {
+        let maker = self.maker.pubkey();
+        let (routed_offer, bump_seed) =
+            compute_routed_offer_address(&self.program_id, &maker, nonce);
+        let init = init_routed_offer(
+            &self.program_id,
+            &maker,
+            &routed_offer,
+            self.public_values(),
+            nonce,
+            bump_seed,
+        );
+        (routed_offer, init)
+    }
+
+    fn accept_routed_offer(&self, routed_offer: &Pubkey) -> Instruction {
+        accept_routed_offer(
+            &self.program_id,
+            &self.maker.pubkey(),
+            &self.taker.pubkey(),
+            routed_offer,
+            &self.maker_dst_account(),
+            &self.taker_src_account(),
+            &self.taker_dst_account(),
+            &spl_token::id(),
+            &self.public_values(),
+            None,
+        )
+        .unwrap()
+    }
+
+    async fn start(&mut self) -> ProgramTestContext {
+        std::mem::take(&mut self.program_test)
+            .start_with_context()
+            .await
+    }
+
+    /// What the maker has been paid with, in lamports for SOL.
+    async fn maker_proceeds(&self, context: &mut ProgramTestContext) -> u64 {
+        if self.is_native() {
+            lamports(context, self.maker.pubkey()).await
+        } else {
+            token_balance(context, self.maker_dst_account()).await
+        }
+    }
+}
+
+async fn send(
+    context: &mut ProgramTestContext,
+    signer: &Keypair,
+    instruction: Instruction,
+) -> Result<(), BanksClientError> {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&signer.pubkey()),
+        &[signer],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(transaction).await
+}
+
+#[tokio::test]
+async fn escrow_offer_cannot_fill_as_a_routed_offer() {
+    let mut offers = Offers::new();
+    let (escrow_offer, init) = offers.init_escrow_offer(0);
+    let accept = offers.accept_routed_offer(&escrow_offer);
+    let mut context = offers.start().await;
+    send(&mut context, &offers.maker, init).await.unwrap();
+
+    // What is left in the maker's ATA is delegated to the router, so only the kind of account
+    // keeps the escrow offer from being filled, and closed, through it.
+    assert!(send(&mut context, &offers.taker, accept).await.is_err());
+    let vault = compute_escrow_vault(&escrow_offer, &offers.maker_mint);
+    assert_eq!(token_balance(&mut context, vault).await, MAKER_SIZE);
+    assert!(context
+        .banks_client
+        .get_account(escrow_offer)
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[tokio::test]
+async fn batches_must_be_proven_by_the_pinned_guest() {
+    let mut offers = Offers::new();
+    let poster = offers.maker.pubkey();
+    let vkey_hash = [OFFER_VKEY_HASH[0].wrapping_add(1); 32];
+    let root = [2; 32];
+    let (offer_batch, bump_seed) =
+        compute_offer_batch_address(&offers.program_id, &vkey_hash, &root);
+    let post = post_offer_batch(
+        &offers.program_id,
+        &poster,
+        &offer_batch,
+        bump_seed,
+        vkey_hash,
+        root,
+        1,
+        vec![0; 260],
+    );
+    let mut context = offers.start().await;
+
+    // The proof is never looked at: a batch of another guest is rejected for its key alone.
+    let err = send(&mut context, &offers.maker, post).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(UtilError::UnknownVerificationKey as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn frozen_taker_legs_are_rejected() {
+    let mut offers = Offers::with_taker_account_state(AccountState::Frozen);
+    let (routed_offer, init) = offers.init_routed_offer(0);
+    let accept = offers.accept_routed_offer(&routed_offer);
+    let mut context = offers.start().await;
+    send(&mut context, &offers.maker, init).await.unwrap();
+
+    // A programmable NFT taker leg is frozen, and only the maker leg can go through Token
+    // Metadata, so the fill fails before any fee moves.
+    let err = send(&mut context, &offers.taker, accept).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(UtilError::ProgrammableTakerLeg as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn escrow_offers_fill_and_close() {
+    for mut offers in [Offers::new(), Offers::native()] {
+        let (filled, init_filled) = offers.init_escrow_offer(0);
+        let (closed, init_closed) = offers.init_escrow_offer(1);
+        let accept = offers.accept_escrow_offer(&filled);
+        let close = close_escrow_offer(
+            &offers.program_id,
+            &offers.maker.pubkey(),
+            &closed,
+            &offers.maker_mint,
+            &spl_token::id(),
+        );
+        let filled_vault = compute_escrow_vault(&filled, &offers.maker_mint);
+        let closed_vault = compute_escrow_vault(&closed, &offers.maker_mint);
+        let mut context = offers.start().await;
+        send(&mut context, &offers.maker, init_filled)
+            .await
+            .unwrap();
+        send(&mut context, &offers.maker, init_closed)
+            .await
+            .unwrap();
+        assert_eq!(
+            token_balance(&mut context, offers.maker_src_account()).await,
+            0
+        );
+
+        // The fill swaps the legs and returns the rent of both accounts to the maker.
+        let proceeds = offers.maker_proceeds(&mut context).await;
+        let rent =
+            lamports(&mut context, filled).await + lamports(&mut context, filled_vault).await;
+        send(&mut context, &offers.taker, accept).await.unwrap();
+        let taker_dst_account = offers.taker_dst_account();
+        assert_eq!(
+            token_balance(&mut context, taker_dst_account).await,
+            MAKER_SIZE
+        );
+        let paid = offers.maker_proceeds(&mut context).await - proceeds;
+        let expected_paid = if offers.is_native() {
+            TAKER_SIZE + rent
+        } else {
+            TAKER_SIZE
+        };
+        assert_eq!(paid, expected_paid);
+        assert_eq!(lamports(&mut context, filled).await, 0);
+        assert_eq!(lamports(&mut context, filled_vault).await, 0);
+
+        // Closing hands the escrowed leg back.
+        send(&mut context, &offers.maker, close).await.unwrap();
+        let maker_src_account = offers.maker_src_account();
+        assert_eq!(
+            token_balance(&mut context, maker_src_account).await,
+            MAKER_SIZE
+        );
+        assert_eq!(lamports(&mut context, closed).await, 0);
+        assert_eq!(lamports(&mut context, closed_vault).await, 0);
+    }
+}
+
+#[tokio::test]
+async fn routed_offers_fill_and_close() {
+    for mut offers in [Offers::new(), Offers::native()] {
+        let (filled, init_filled) = offers.init_routed_offer(0);
+        let (closed, init_closed) = offers.init_routed_offer(1);
+        let accept = offers.accept_routed_offer(&filled);
+        let close = close_routed_offer(&offers.program_id, &offers.maker.pubkey(), &closed);
+        let mut context = offers.start().await;
+        send(&mut context, &offers.maker, init_filled)
+            .await
+            .unwrap();
+        send(&mut context, &offers.maker, init_closed)
+            .await
+            .unwrap();
+
+        // The fill takes its leg through the router and returns the rent of the offer account.
+        let proceeds = offers.maker_proceeds(&mut context).await;
+        let rent = lamports(&mut context, filled).await;
+        send(&mut context, &offers.taker, accept).await.unwrap();
+        let taker_dst_account = offers.taker_dst_account();
+        assert_eq!(
+            token_balance(&mut context, taker_dst_account).await,
+            MAKER_SIZE
+        );
+        let maker_src_account = offers.maker_src_account();
+        assert_eq!(
+            token_balance(&mut context, maker_src_account).await,
+            MAKER_SIZE
+        );
+        let paid = offers.maker_proceeds(&mut context).await - proceeds;
+        let expected_paid = if offers.is_native() {
+            TAKER_SIZE + rent
+        } else {
+            TAKER_SIZE
+        };
+        assert_eq!(paid, expected_paid);
+        assert_eq!(lamports(&mut context, filled).await, 0);
+
+        // Closing only drops the offer account, the tokens never left the maker's ATA.
+        send(&mut context, &offers.maker, close).await.unwrap();
+        assert_eq!(lamports(&mut context, closed).await, 0);
+        assert_eq!(
+            token_balance(&mut context, maker_src_account).await,
+            MAKER_SIZE
+        );
+    }
+}
+
+#[tokio::test]
+async fn fills_and_closes_check_their_accounts() {
+    let mut offers = Offers::new();
+    let (escrow_offer, init_escrow) = offers.init_escrow_offer(0);
+    let (routed_offer, init_routed) = offers.init_routed_offer(1);
+    // The taker pays itself in place of the maker.
+    let mut accept = offers.accept_escrow_offer(&escrow_offer);
+    accept.accounts[4].pubkey = offers.taker_src_account();
+    // Only the maker closes its offers.
+    let close = close_routed_offer(&offers.program_id, &offers.taker.pubkey(), &routed_offer);
+    let vault = compute_escrow_vault(&escrow_offer, &offers.maker_mint);
+    let mut context = offers.start().await;
+    send(&mut context, &offers.maker, init_escrow)
+        .await
+        .unwrap();
+    send(&mut context, &offers.maker, init_routed)
+        .await
+        .unwrap();
+
+    assert!(send(&mut context, &offers.taker, accept).await.is_err());
+    assert!(send(&mut context, &offers.taker, close).await.is_err());
+    assert_eq!(token_balance(&mut context, vault).await, MAKER_SIZE);
+    let taker_src_account = offers.taker_src_account();
+    assert_eq!(
+        token_balance(&mut context, taker_src_account).await,
+        TAKER_SIZE
+    );
+    assert!(lamports(&mut context, routed_offer).await > 0);
+}
@@ -0,0 +1,126 @@
+//! Checks the Merkle tree of offer batches and the batched fill builder.
+
+mod common;
+
+use common::public_values;
+use solana_program::pubkey::Pubkey;
+use solana_zk_offers::{
+    offer_batch::{
+        check_batch_offer, compute_batched_offer_authority, merkle_proof, merkle_root, offer_leaf,
+        verify_merkle_proof,
+    },
+    zk_offers::{compute_offer_authority, PlatformFee, PublicValuesStruct},
+};
+use stateless_asks::instruction::{accept_batched_offer, StatelessOfferInstruction};
+
+#[test]
+fn every_leaf_proves_inclusion() {
+    for size in 1..=9 {
+        let leaves: Vec<[u8; 32]> = (1..=size).map(|i| offer_leaf(&public_values(i))).collect();
+        let root = merkle_root(&leaves).unwrap();
+        for (index, leaf) in leaves.iter().enumerate() {
+            let proof = merkle_proof(&leaves, index).unwrap();
+            assert!(
+                verify_merkle_proof(&root, leaf, &proof),
+                "{index} of {size}"
+            );
+        }
+        assert_eq!(merkle_proof(&leaves, leaves.len()), None);
+    }
+    assert_eq!(merkle_root(&[]), None);
+}
+
+#[test]
+fn other_leaves_and_nodes_are_rejected() {
+    let leaves: Vec<[u8; 32]> = (1..=5).map(|i| offer_leaf(&public_values(i))).collect();
+    let root = merkle_root(&leaves).unwrap();
+    let proof = merkle_proof(&leaves, 2).unwrap();
+
+    let outsider = offer_leaf(&public_values(6));
+    assert!(!verify_merkle_proof(&root, &outsider, &proof));
+    assert!(!verify_merkle_proof(&root, &leaves[3], &proof));
+}
+
+#[test]
+fn batched_offers_commit_both_legs() {
+    assert_eq!(check_batch_offer(&public_values(1)), Ok(()));
+
+    let mut open_size = public_values(1);
+    open_size.taker_size = None;
+    assert!(check_batch_offer(&open_size).is_err());
+
+    let mut open_mint = public_values(1);
+    open_mint.taker_mint = None;
+    assert!(check_batch_offer(&open_mint).is_err());
+
+    assert!(check_batch_offer(&public_values(0)).is_err());
+
+    let mut greedy = public_values(1);
+    greedy.platform_fee = Some(PlatformFee {
+        basis_points: 10_000,
+        recipient: Pubkey::new_unique(),
+    });
+    assert!(check_batch_offer(&greedy).is_err());
+}
+
+#[test]
+fn batched_fill_prepends_the_batch() {
+    let program_id = Pubkey::new_unique();
+    let offer_batch = Pubkey::new_unique();
+    let root = [9; 32];
+    let maker_wallet = Pubkey::new_unique();
+    let taker_wallet = Pubkey::new_unique();
+    let public_values = public_values(7);
+    let proof = vec![[3; 32], [4; 32]];
+
+    let instruction = accept_batched_offer(
+        &program_id,
+        &offer_batch,
+        &root,
+        &maker_wallet,
+        &taker_wallet,
+        &spl_token::id(),
+        &public_values,
+        proof.clone(),
+        None,
+    )
+    .unwrap();
+    assert_eq!(instruction.accounts[0].pubkey, offer_batch);
+    assert!(!instruction.accounts[0].is_writable);
+    assert!(!instruction.accounts[0].is_signer);
+    assert_eq!(instruction.accounts[1].pubkey, maker_wallet);
+
+    // The batch has its own authority, which a plain AcceptOffer of the same terms cannot use.
+    let (authority, bump_seed) =
+        compute_batched_offer_authority(&program_id, &maker_wallet, &root, &public_values);
+    let (offer_authority, _) = compute_offer_authority(
+        &program_id,
+        &maker_wallet,
+        &public_values.maker_mint,
+        &public_values.taker_mint.unwrap(),
+        public_values.maker_size,
+        public_values.taker_size.unwrap(),
+        public_values.platform_fee.as_ref(),
+    );
+    assert_ne!(authority, offer_authority);
+    assert_eq!(instruction.accounts[9].pubkey, authority);
+    assert_ne!(
+        compute_batched_offer_authority(&program_id, &maker_wallet, &[8; 32], &public_values).0,
+        authority
+    );
+    match StatelessOfferInstruction::unpack(&instruction.data).unwrap() {
+        StatelessOfferInstruction::AcceptBatchedOffer {
+            metadata_bump_seed,
+            bump_seed: packed_bump_seed,
+            public_values: packed_public_values,
+            merkle_proof,
+            ..
+        } => {
+            assert_eq!(metadata_bump_seed, None);
+            assert_eq!(packed_bump_seed, bump_seed);
+            assert_eq!(packed_public_values, public_values.to_canonical_bytes());
+            assert_eq!(merkle_proof, proof);
+        }
+        other => panic!("unexpected instruction {:?}", other),
+    }
+}
@@ -0,0 +1,305 @@
+//! Fills offers of programmable NFTs minted by Token Metadata, against the SBF build of the
+//! program. Token Metadata is loaded from a dump of the mainnet program:
+//! ```shell
+//! solana program dump -u m metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s \
+//!     tests/fixtures/mpl_token_metadata.so
+//! cargo build-sbf && cargo test-sbf --test programmable
+//! ```
+#![cfg(feature = "test-sbf")]
+
+mod common;
+
+use common::{add_token_account, mint_account, system_account, token_balance};
+use mpl_token_metadata::{
+    accounts::{MasterEdition, Metadata, TokenRecord},
+    instructions::{CreateV1Builder, DelegateTransferV1Builder, MintV1Builder},
+    types::{PrintSupply, TokenStandard},
+};
+use solana_program::{program_pack::Pack, pubkey::Pubkey};
+use solana_program_test::{BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction,
+    instruction::{Instruction, InstructionError},
+    native_token::LAMPORTS_PER_SOL,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use solana_zk_offers::{metadata, zk_offers::compute_offer_authority};
+use spl_associated_token_account::get_associated_token_address;
+use stateless_asks::{
+    errors::UtilError,
+    instruction::{accept_offer, accept_programmable_offer},
+};
+
+const TAKER_SIZE: u64 = 1_000;
+
+/// A maker and a taker, each funded and holding `TAKER_SIZE` of the fungible mint on one side
+/// of the fill, the programmable NFT on the other side being minted once the test has started.
+struct Programmable {
+    program_id: Pubkey,
+    maker: Keypair,
+    taker: Keypair,
+    nft_mint: Keypair,
+    fungible_mint: Pubkey,
+    program_test: ProgramTest,
+}
+
+impl Programmable {
+    fn new() -> Self {
+        let program_id = Pubkey::new_unique();
+        let mut program_test = ProgramTest::new("stateless_asks", program_id, None);
+        program_test.prefer_bpf(true);
+        program_test.add_program("mpl_token_metadata", metadata::id(), None);
+        let maker = Keypair::new();
+        let taker = Keypair::new();
+        for wallet in [maker.pubkey(), taker.pubkey()] {
+            program_test.add_account(wallet, system_account(10 * LAMPORTS_PER_SOL));
+        }
+        let fungible_mint = Pubkey::new_unique();
+        program_test.add_account(fungible_mint, mint_account(TAKER_SIZE));
+        Self {
+            program_id,
+            maker,
+            taker,
+            nft_mint: Keypair::new(),
+            fungible_mint,
+            program_test,
+        }
+    }
+
+    async fn start(&mut self) -> ProgramTestContext {
+        std::mem::take(&mut self.program_test)
+            .start_with_context()
+            .await
+    }
+}
+
+/// Create a programmable NFT through Token Metadata and mint it to `owner`, whose token account
+/// Token Metadata leaves frozen.
+async fn mint_programmable_nft(context: &mut ProgramTestContext, mint: &Keypair, owner: &Keypair) {
+    let (metadata, _) = Metadata::find_pda(&mint.pubkey());
+    let (edition, _) = MasterEdition::find_pda(&mint.pubkey());
+    let token = get_associated_token_address(&owner.pubkey(), &mint.pubkey());
+    let (token_record, _) = TokenRecord::find_pda(&mint.pubkey(), &token);
+    let create = CreateV1Builder::new()
+        .metadata(metadata)
+        .master_edition(Some(edition))
+        .mint(mint.pubkey(), true)
+        .authority(owner.pubkey())
+        .payer(owner.pubkey())
+        .update_authority(owner.pubkey(), true)
+        .spl_token_program(Some(spl_token::id()))
+        .name(String::from("Programmable"))
+        .uri(String::new())
+        .seller_fee_basis_points(0)
+        .token_standard(TokenStandard::ProgrammableNonFungible)
+        .print_supply(PrintSupply::Zero)
+        .instruction();
+    let mint_to = MintV1Builder::new()
+        .token(token)
+        .token_owner(Some(owner.pubkey()))
+        .metadata(metadata)
+        .master_edition(Some(edition))
+        .token_record(Some(token_record))
+        .mint(mint.pubkey())
+        .authority(owner.pubkey())
+        .payer(owner.pubkey())
+        .amount(1)
+        .instruction();
+    let transaction = Transaction::new_signed_with_payer(
+        &[create, mint_to],
+        Some(&owner.pubkey()),
+        &[owner, mint],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+}
+
+async fn send(
+    context: &mut ProgramTestContext,
+    signer: &Keypair,
+    instructions: &[Instruction],
+) -> Result<(), BanksClientError> {
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&signer.pubkey()),
+        &[signer],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(transaction).await
+}
+
+#[tokio::test]
+async fn programmable_nfts_fill_through_token_metadata() {
+    let mut programmable = Programmable::new();
+    let maker = programmable.maker.pubkey();
+    let taker = programmable.taker.pubkey();
+    let nft_mint = programmable.nft_mint.pubkey();
+    let fungible_mint = programmable.fungible_mint;
+    add_token_account(
+        &mut programmable.program_test,
+        &maker,
+        &fungible_mint,
+        0,
+        None,
+    );
+    add_token_account(
+        &mut programmable.program_test,
+        &taker,
+        &fungible_mint,
+        TAKER_SIZE,
+        None,
+    );
+    add_token_account(&mut programmable.program_test, &taker, &nft_mint, 0, None);
+    let mut context = programmable.start().await;
+    mint_programmable_nft(&mut context, &programmable.nft_mint, &programmable.maker).await;
+
+    // The maker delegates the NFT to the offer authority through Token Metadata, since its
+    // token account is frozen for SPL Approve.
+    let (authority, bump_seed) = compute_offer_authority(
+        &programmable.program_id,
+        &maker,
+        &nft_mint,
+        &fungible_mint,
+        1,
+        TAKER_SIZE,
+        None,
+    );
+    let maker_src_account = get_associated_token_address(&maker, &nft_mint);
+    let delegate = DelegateTransferV1Builder::new()
+        .delegate(authority)
+        .metadata(Metadata::find_pda(&nft_mint).0)
+        .master_edition(Some(MasterEdition::find_pda(&nft_mint).0))
+        .token_record(Some(TokenRecord::find_pda(&nft_mint, &maker_src_account).0))
+        .mint(nft_mint)
+        .token(maker_src_account)
+        .authority(maker)
+        .payer(maker)
+        .spl_token_program(Some(spl_token::id()))
+        .amount(1)
+        .instruction();
+    send(&mut context, &programmable.maker, &[delegate])
+        .await
+        .unwrap();
+
+    let taker_dst_account = get_associated_token_address(&taker, &nft_mint);
+    let accept = accept_programmable_offer(
+        &programmable.program_id,
+        &maker,
+        &taker,
+        &maker_src_account,
+        &get_associated_token_address(&maker, &fungible_mint),
+        &get_associated_token_address(&taker, &fungible_mint),
+        &taker_dst_account,
+        &nft_mint,
+        &fungible_mint,
+        &authority,
+        &spl_token::id(),
+        None,
+        None,
+        false,
+        None,
+        1,
+        TAKER_SIZE,
+        bump_seed,
+    )
+    .unwrap();
+    send(
+        &mut context,
+        &programmable.taker,
+        &[
+            ComputeBudgetInstruction::set_compute_unit_limit(400_000),
+            accept,
+        ],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(token_balance(&mut context, maker_src_account).await, 0);
+    assert_eq!(
+        token_balance(
+            &mut context,
+            get_associated_token_address(&maker, &fungible_mint)
+        )
+        .await,
+        TAKER_SIZE
+    );
+    // Token Metadata moved the NFT and froze it again in the taker's account.
+    let taker_dst = context
+        .banks_client
+        .get_account(taker_dst_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let taker_dst = spl_token::state::Account::unpack(&taker_dst.data).unwrap();
+    assert_eq!(taker_dst.amount, 1);
+    assert!(taker_dst.is_frozen());
+}
+
+#[tokio::test]
+async fn programmable_nfts_cannot_be_the_taker_leg() {
+    let mut programmable = Programmable::new();
+    let maker = programmable.maker.pubkey();
+    let taker = programmable.taker.pubkey();
+    let nft_mint = programmable.nft_mint.pubkey();
+    let fungible_mint = programmable.fungible_mint;
+    let (authority, bump_seed) = compute_offer_authority(
+        &programmable.program_id,
+        &maker,
+        &fungible_mint,
+        &nft_mint,
+        TAKER_SIZE,
+        1,
+        None,
+    );
+    add_token_account(
+        &mut programmable.program_test,
+        &maker,
+        &fungible_mint,
+        TAKER_SIZE,
+        Some(&authority),
+    );
+    add_token_account(&mut programmable.program_test, &maker, &nft_mint, 0, None);
+    add_token_account(
+        &mut programmable.program_test,
+        &taker,
+        &fungible_mint,
+        0,
+        None,
+    );
+    let mut context = programmable.start().await;
+    mint_programmable_nft(&mut context, &programmable.nft_mint, &programmable.taker).await;
+
+    let accept = accept_offer(
+        &programmable.program_id,
+        &maker,
+        &taker,
+        &get_associated_token_address(&maker, &fungible_mint),
+        &get_associated_token_address(&maker, &nft_mint),
+        &get_associated_token_address(&taker, &nft_mint),
+        &get_associated_token_address(&taker, &fungible_mint),
+        &fungible_mint,
+        &nft_mint,
+        &authority,
+        &spl_token::id(),
+        false,
+        None,
+        TAKER_SIZE,
+        1,
+        bump_seed,
+    );
+    let err = send(&mut context, &programmable.taker, &[accept])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(UtilError::ProgrammableTakerLeg as u32)
+        )
+    );
+}
@@ -0,0 +1,156 @@
+//! Checks the canonical encoding and digest of committed public values.
+
+mod common;
+
+use common::public_values;
+use solana_program::pubkey::Pubkey;
+use solana_zk_offers::{
+    aggregation::{committed_values_digest, AggregatedOfferProofs, AggregatedProof},
+    zk_offers::{
+        compute_offer_authority, PlatformFee, PublicValuesStruct, CANONICAL_PUBLIC_VALUES_LEN,
+    },
+};
+
+#[test]
+fn canonical_encoding_round_trips() {
+    let full = public_values(1);
+    let bare = PublicValuesStruct {
+        taker_mint: None,
+        is_native: true,
+        taker_size: None,
+        platform_fee: None,
+        ..full.clone()
+    };
+    for public_values in [full, bare] {
+        let bytes = public_values.to_canonical_bytes();
+        assert_eq!(bytes.len(), CANONICAL_PUBLIC_VALUES_LEN);
+        assert_eq!(
+            PublicValuesStruct::from_canonical_bytes(&bytes),
+            Some(public_values)
+        );
+    }
+}
+
+#[test]
+fn fields_keep_their_offsets() {
+    let full = public_values(1);
+    let without_taker_mint = PublicValuesStruct {
+        taker_mint: None,
+        ..full.clone()
+    };
+    let full_bytes = full.to_canonical_bytes();
+    let bytes = without_taker_mint.to_canonical_bytes();
+    assert_eq!(bytes[..32], full_bytes[..32]);
+    assert_eq!(bytes[32..65], [0; 33]);
+    assert_eq!(bytes[65..], full_bytes[65..]);
+}
+
+#[test]
+fn non_canonical_bytes_are_rejected() {
+    let bytes = public_values(1).to_canonical_bytes();
+    assert_eq!(PublicValuesStruct::from_canonical_bytes(&bytes[1..]), None);
+    assert_eq!(
+        PublicValuesStruct::from_canonical_bytes(&[bytes.as_slice(), &[0]].concat()),
+        None
+    );
+
+    let mut bad_flag = bytes;
+    bad_flag[74] = 2;
+    assert_eq!(PublicValuesStruct::from_canonical_bytes(&bad_flag), None);
+
+    let mut bad_bool = bytes;
+    bad_bool[65] = 2;
+    assert_eq!(PublicValuesStruct::from_canonical_bytes(&bad_bool), None);
+
+    // An absent taker mint with leftover mint bytes would give a second encoding.
+    let mut padded = bytes;
+    padded[32] = 0;
+    assert_eq!(PublicValuesStruct::from_canonical_bytes(&padded), None);
+}
+
+#[test]
+fn digest_is_taken_over_the_canonical_encoding() {
+    let public_values = public_values(1);
+    assert_eq!(
+        public_values.public_values_digest(),
+        committed_values_digest(&public_values.to_canonical_bytes())
+    );
+
+    let vkey = [5; 8];
+    let aggregated = AggregatedOfferProofs {
+        proofs: vec![AggregatedProof::new(
+            vkey,
+            &public_values.to_canonical_bytes(),
+        )],
+    };
+    assert!(aggregated.covers_offer(&vkey, &public_values));
+    assert!(!aggregated.covers_offer(
+        &vkey,
+        &PublicValuesStruct {
+            maker_size: 2,
+            ..public_values
+        }
+    ));
+}
+
+#[test]
+fn zero_platform_fees_parse_as_no_fee() {
+    let public_values = public_values(1);
+    let recipient = Pubkey::new_unique();
+    let terms = format!(
+        "{},{},false,1,1000000",
+        public_values.maker_mint,
+        public_values.taker_mint.unwrap()
+    );
+    let without_fee: PublicValuesStruct = terms.parse().unwrap();
+    let zero_fee: PublicValuesStruct = format!("{terms},0,{recipient}").parse().unwrap();
+    assert_eq!(zero_fee.platform_fee, None);
+    assert_eq!(
+        zero_fee.to_canonical_bytes(),
+        without_fee.to_canonical_bytes()
+    );
+
+    let with_fee: PublicValuesStruct = format!("{terms},100,{recipient}").parse().unwrap();
+    assert_eq!(with_fee.platform_fee, PlatformFee::new(100, recipient));
+    assert_eq!(PlatformFee::new(0, recipient), None);
+}
+
+#[test]
+fn offer_authorities_are_derived_from_the_digest_of_the_fill_terms() {
+    let program_id = Pubkey::new_unique();
+    let maker = Pubkey::new_unique();
+    let public_values = public_values(1);
+    let (maker_mint, taker_mint) = (public_values.maker_mint, public_values.taker_mint.unwrap());
+    let authority = |platform_fee: Option<&PlatformFee>| {
+        compute_offer_authority(
+            &program_id,
+            &maker,
+            &maker_mint,
+            &taker_mint,
+            1,
+            1_000_000,
+            platform_fee,
+        )
+    };
+
+    let terms_digest = PublicValuesStruct::fill_terms(
+        maker_mint,
+        taker_mint,
+        1,
+        1_000_000,
+        public_values.platform_fee,
+    )
+    .public_values_digest();
+    assert_eq!(terms_digest, public_values.public_values_digest());
+    assert_eq!(
+        authority(public_values.platform_fee.as_ref()),
+        Pubkey::find_program_address(
+            &[b"stateless_offer", maker.as_ref(), &terms_digest],
+            &program_id
+        )
+    );
+    assert_ne!(
+        authority(public_values.platform_fee.as_ref()),
+        authority(None)
+    );
+}
@@ -0,0 +1,123 @@
+//! Checks that the client-side fee quote matches the processor's royalty arithmetic.
+
+use proptest::prelude::*;
+use solana_program::pubkey::Pubkey;
+use solana_zk_offers::{
+    metadata::{Creator, Data, Metadata},
+    quote::{quote_accept, quote_fees, FeePayer},
+};
+use stateless_asks::processor::creator_fee_amounts;
+
+fn metadata(seller_fee_basis_points: u16, shares: Option<Vec<(u8, bool)>>) -> Metadata {
+    Metadata {
+        key: 4,
+        update_authority: Pubkey::new_unique(),
+        mint: Pubkey::new_unique(),
+        data: Data {
+            name: String::new(),
+            symbol: String::new(),
+            uri: String::new(),
+            seller_fee_basis_points,
+            creators: shares.map(|shares| {
+                shares
+                    .into_iter()
+                    .map(|(share, verified)| Creator {
+                        address: Pubkey::new_unique(),
+                        verified,
+                        share,
+                    })
+                    .collect()
+            }),
+        },
+        primary_sale_happened: true,
+        is_mutable: false,
+        edition_nonce: None,
+        token_standard: None,
+        collection: None,
+    }
+}
+
+fn shares() -> impl Strategy<Value = Option<Vec<(u8, bool)>>> {
+    prop_oneof![
+        Just(None),
+        prop::collection::vec((0u8..=100, any::<bool>()), 0..5).prop_map(Some),
+        // Shares that always sum to 100, as Token Metadata enforces.
+        prop::collection::vec((1u32..100, any::<bool>()), 1..5).prop_map(|weights| {
+            let total: u32 = weights.iter().map(|(w, _)| w).sum();
+            let mut shares: Vec<(u8, bool)> = weights
+                .iter()
+                .map(|(w, verified)| ((w * 100 / total) as u8, *verified))
+                .collect();
+            let assigned: u8 = shares.iter().map(|(share, _)| share).sum();
+            shares[0].0 += 100 - assigned;
+            Some(shares)
+        }),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn quote_fees_matches_processor(
+        bps in prop_oneof![0u16..=10000, any::<u16>()],
+        size in prop_oneof![0u64..1_000_000_000_000, any::<u64>()],
+        shares in shares(),
+        verified_only in any::<bool>(),
+    ) {
+        let metadata = metadata(bps, shares);
+        let onchain = creator_fee_amounts(&metadata, size, verified_only);
+        let quote = quote_fees(&metadata, size, verified_only);
+        match (onchain, quote) {
+            (Ok((creator_fees, remaining_size)), Ok(quote)) => {
+                let amounts: Vec<u64> = quote.payouts.iter().map(|p| p.amount).collect();
+                prop_assert_eq!(amounts, creator_fees);
+                prop_assert_eq!(quote.remaining_size, remaining_size);
+                prop_assert_eq!(
+                    quote.total_fee,
+                    quote.payouts.iter().map(|p| p.amount).sum::<u64>() + quote.dust
+                );
+            }
+            (Err(_), Err(_)) => {}
+            (onchain, quote) => {
+                prop_assert!(false, "processor {:?} != quote {:?}", onchain, quote);
+            }
+        }
+    }
+
+    #[test]
+    fn quote_accept_deducts_from_fee_payer(
+        bps in 0u16..=10000,
+        maker_size in 0u64..1_000_000_000_000,
+        taker_size in 0u64..1_000_000_000_000,
+        shares in shares(),
+    ) {
+        let metadata = metadata(bps, shares);
+        let quote = quote_accept(
+            &metadata,
+            FeePayer::Taker,
+            maker_size,
+            taker_size,
+            true,
+            false,
+            None,
+        );
+        if let Ok(quote) = quote {
+            prop_assert_eq!(quote.maker_pay_size, maker_size);
+            let (_, remaining) = creator_fee_amounts(&metadata, taker_size, false).unwrap();
+            prop_assert_eq!(quote.taker_pay_size, remaining);
+        }
+        let quote = quote_accept(
+            &metadata,
+            FeePayer::Maker,
+            maker_size,
+            taker_size,
+            false,
+            false,
+            None,
+        );
+        if let Ok(quote) = quote {
+            prop_assert_eq!(quote.taker_pay_size, taker_size);
+            let (_, remaining) = creator_fee_amounts(&metadata, maker_size, false).unwrap();
+            prop_assert_eq!(quote.maker_pay_size, remaining);
+        }
+    }
+}
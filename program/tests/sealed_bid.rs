@@ -0,0 +1,182 @@
+//! Checks the sealed-bid commitments and the accounts of the auction instructions.
+
+mod common;
+
+use common::public_values;
+use solana_program::pubkey::Pubkey;
+use solana_zk_offers::{
+    sealed_bid::{
+        bid_commitment, compute_sealed_auction_address, compute_sealed_bid_address,
+        compute_sealed_vault, format_vkey_hash,
+    },
+    zk_offers::PublicValuesStruct,
+};
+use spl_associated_token_account::get_associated_token_address;
+use stateless_asks::instruction::{
+    close_sealed_bid, commit_sealed_bid, forfeit_sealed_bid, reveal_sealed_bid,
+    settle_sealed_auction, StatelessOfferInstruction,
+};
+
+#[test]
+fn commitments_bind_every_input() {
+    let auction = Pubkey::new_unique();
+    let bidder = Pubkey::new_unique();
+    let salt = [7; 32];
+    let commitment = bid_commitment(&auction, &bidder, 1_000, &salt);
+    assert_eq!(commitment, bid_commitment(&auction, &bidder, 1_000, &salt));
+    for other in [
+        bid_commitment(&auction, &bidder, 1_001, &salt),
+        bid_commitment(&auction, &bidder, 1_000, &[8; 32]),
+        bid_commitment(&auction, &Pubkey::new_unique(), 1_000, &salt),
+        bid_commitment(&Pubkey::new_unique(), &bidder, 1_000, &salt),
+    ] {
+        assert_ne!(commitment, other);
+    }
+    assert_eq!(
+        format_vkey_hash(&[0xab; 32]),
+        format!("0x{}", "ab".repeat(32))
+    );
+}
+
+#[test]
+fn settlement_lists_the_best_bid_when_there_is_one() {
+    let program_id = Pubkey::new_unique();
+    let maker = Pubkey::new_unique();
+    let bidder = Pubkey::new_unique();
+    let public_values = PublicValuesStruct {
+        taker_size: Some(1_000),
+        platform_fee: None,
+        ..public_values(1)
+    };
+    let maker_mint = public_values.maker_mint;
+    let taker_mint = public_values.taker_mint.unwrap();
+    let (auction, _) = compute_sealed_auction_address(&program_id, &maker, 7);
+    let (bid, _) = compute_sealed_bid_address(&program_id, &auction, &bidder);
+
+    let unsold = settle_sealed_auction(
+        &program_id,
+        &maker,
+        &auction,
+        &spl_token::id(),
+        &public_values,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(unsold.accounts.len(), 8);
+    assert_eq!(
+        unsold.accounts[2].pubkey,
+        compute_sealed_vault(&auction, &maker_mint)
+    );
+
+    let sold = settle_sealed_auction(
+        &program_id,
+        &maker,
+        &auction,
+        &spl_token::id(),
+        &public_values,
+        Some((&bidder, &bid)),
+        None,
+    )
+    .unwrap();
+    let keys: Vec<Pubkey> = sold.accounts.iter().map(|meta| meta.pubkey).collect();
+    assert!(unsold
+        .accounts
+        .iter()
+        .zip(&keys)
+        .all(|(meta, key)| meta.pubkey == *key));
+    assert_eq!(
+        keys[8..],
+        [
+            bidder,
+            bid,
+            compute_sealed_vault(&bid, &taker_mint),
+            get_associated_token_address(&bidder, &maker_mint),
+        ]
+    );
+    assert_eq!(
+        StatelessOfferInstruction::unpack(&sold.data).unwrap(),
+        StatelessOfferInstruction::SettleSealedAuction {
+            metadata_bump_seed: None
+        }
+    );
+
+    let reveal = reveal_sealed_bid(
+        &program_id,
+        &bidder,
+        &auction,
+        &bid,
+        &taker_mint,
+        &spl_token::id(),
+        1_000,
+        vec![0; 260],
+    );
+    assert!(reveal.accounts[0].is_signer);
+    assert_eq!(
+        reveal.accounts[4].pubkey,
+        get_associated_token_address(&bidder, &taker_mint)
+    );
+}
+
+#[test]
+fn deposits_are_escrowed_at_commit_and_forfeited_to_the_maker() {
+    let program_id = Pubkey::new_unique();
+    let maker = Pubkey::new_unique();
+    let bidder = Pubkey::new_unique();
+    let taker_mint = Pubkey::new_unique();
+    let (auction, _) = compute_sealed_auction_address(&program_id, &maker, 7);
+    let (bid, bump_seed) = compute_sealed_bid_address(&program_id, &auction, &bidder);
+    let bid_vault = compute_sealed_vault(&bid, &taker_mint);
+
+    let commit = commit_sealed_bid(
+        &program_id,
+        &bidder,
+        &auction,
+        &bid,
+        &taker_mint,
+        &spl_token::id(),
+        bump_seed,
+        [1; 32],
+        5_000,
+    );
+    assert!(commit.accounts[0].is_signer);
+    assert_eq!(commit.accounts[3].pubkey, bid_vault);
+    assert_eq!(
+        commit.accounts[4].pubkey,
+        get_associated_token_address(&bidder, &taker_mint)
+    );
+    assert_eq!(
+        StatelessOfferInstruction::unpack(&commit.data).unwrap(),
+        StatelessOfferInstruction::CommitSealedBid {
+            bump_seed,
+            commitment: [1; 32],
+            deposit: 5_000,
+        }
+    );
+
+    let close = close_sealed_bid(
+        &program_id,
+        &bidder,
+        &maker,
+        &auction,
+        &bid,
+        &taker_mint,
+        &spl_token::id(),
+    );
+    let forfeit = forfeit_sealed_bid(
+        &program_id,
+        &bidder,
+        &maker,
+        &auction,
+        &bid,
+        &taker_mint,
+        &spl_token::id(),
+    );
+    assert!(close.accounts[0].is_signer);
+    assert!(!forfeit.accounts[0].is_signer);
+    assert_eq!(forfeit.data, close.data);
+    assert_eq!(
+        forfeit.accounts[5].pubkey,
+        get_associated_token_address(&maker, &taker_mint)
+    );
+}
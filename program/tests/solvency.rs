@@ -0,0 +1,77 @@
+//! Checks which token account snapshots prove a maker solvent.
+
+use solana_program::{program_option::COption, program_pack::Pack, pubkey::Pubkey};
+use solana_zk_offers::{health::OfferIssue, solvency::MakerSolvency};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::state::{Account, AccountState};
+
+fn token_account(owner: Pubkey, mint: Pubkey, amount: u64, state: AccountState) -> Vec<u8> {
+    let account = Account {
+        mint,
+        owner,
+        amount,
+        delegate: COption::None,
+        state,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    };
+    let mut data = vec![0; Account::LEN];
+    account.pack_into_slice(&mut data);
+    data
+}
+
+#[test]
+fn balance_and_state_decide_solvency() {
+    let maker = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let solvency = |amount, state| {
+        MakerSolvency::from_snapshot(
+            &maker,
+            &mint,
+            100,
+            42,
+            [1; 32],
+            &token_account(maker, mint, amount, state),
+        )
+        .unwrap()
+    };
+
+    let funded = solvency(100, AccountState::Initialized);
+    assert!(funded.solvent);
+    assert_eq!(funded.slot, 42);
+    assert_eq!(
+        funded.token_account,
+        get_associated_token_address(&maker, &mint)
+    );
+    assert!(!solvency(99, AccountState::Initialized).solvent);
+    assert!(!solvency(100, AccountState::Frozen).solvent);
+}
+
+#[test]
+fn snapshots_of_other_accounts_are_rejected() {
+    let maker = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let other = Pubkey::new_unique();
+    let check = |data: &[u8]| MakerSolvency::from_snapshot(&maker, &mint, 1, 0, [0; 32], data);
+
+    assert_eq!(
+        check(&token_account(other, mint, 1, AccountState::Initialized)),
+        Err(OfferIssue::WrongOwner {
+            expected: maker,
+            actual: other
+        })
+    );
+    assert_eq!(
+        check(&token_account(maker, other, 1, AccountState::Initialized)),
+        Err(OfferIssue::WrongMint {
+            expected: mint,
+            actual: other
+        })
+    );
+    assert_eq!(
+        check(&token_account(maker, mint, 1, AccountState::Uninitialized)),
+        Err(OfferIssue::NotTokenAccount)
+    );
+    assert_eq!(check(&[0; 82]), Err(OfferIssue::NotTokenAccount));
+}
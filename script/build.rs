@@ -1,5 +1,12 @@
-use sp1_helper::build_program_with_args;
+use sp1_helper::{build_program_with_args, BuildArgs};
 
 fn main() {
-    build_program_with_args("../program", Default::default())
+    // The binaries include the ELF from the elf directory at the repository root.
+    build_program_with_args(
+        "../program",
+        BuildArgs {
+            output_directory: concat!(env!("CARGO_MANIFEST_DIR"), "/../elf").to_string(),
+            ..Default::default()
+        },
+    )
 }
@@ -0,0 +1,71 @@
+//! Sources of on-chain account data used to validate offers off-chain.
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_program::pubkey::Pubkey;
+use solana_rpc_client::rpc_client::RpcClient;
+use std::{collections::HashMap, fs, path::Path, str::FromStr};
+
+/// Anything that can return the raw data of an account.
+pub trait AccountSource: Send + Sync {
+    /// Returns `None` when the account does not exist.
+    fn account_data(&self, key: &Pubkey) -> Result<Option<Vec<u8>>>;
+}
+
+/// Reads accounts from a Solana JSON-RPC endpoint.
+pub struct RpcAccountSource {
+    client: RpcClient,
+}
+
+impl RpcAccountSource {
+    pub fn new(url: String) -> Self {
+        Self {
+            client: RpcClient::new(url),
+        }
+    }
+}
+
+impl AccountSource for RpcAccountSource {
+    fn account_data(&self, key: &Pubkey) -> Result<Option<Vec<u8>>> {
+        let response = self
+            .client
+            .get_account_with_commitment(key, self.client.commitment())
+            .with_context(|| format!("failed to fetch account {}", key))?;
+        Ok(response.value.map(|account| account.data))
+    }
+}
+
+/// A fixed set of accounts, e.g. a snapshot loaded from disk.
+#[derive(Default)]
+pub struct StaticAccountSource {
+    accounts: HashMap<Pubkey, Vec<u8>>,
+}
+
+impl StaticAccountSource {
+    /// Load a JSON object mapping base58 addresses to base64 account data.
+    pub fn from_json_file(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let entries: HashMap<String, String> =
+            serde_json::from_str(&contents).context("invalid accounts file")?;
+        let mut accounts = HashMap::with_capacity(entries.len());
+        for (key, data) in entries {
+            let key = Pubkey::from_str(&key).map_err(|e| anyhow::anyhow!("{}: {}", key, e))?;
+            let data = STANDARD
+                .decode(data)
+                .with_context(|| format!("invalid base64 data for {}", key))?;
+            accounts.insert(key, data);
+        }
+        Ok(Self { accounts })
+    }
+
+    pub fn insert(&mut self, key: Pubkey, data: Vec<u8>) {
+        self.accounts.insert(key, data);
+    }
+}
+
+impl AccountSource for StaticAccountSource {
+    fn account_data(&self, key: &Pubkey) -> Result<Option<Vec<u8>>> {
+        Ok(self.accounts.get(key).cloned())
+    }
+}
@@ -0,0 +1,119 @@
+//! Prove offers as compressed proofs and aggregate them into one Groth16 proof.
+//!
+//! You can run this script using the following commands:
+//! ```shell
+//! RUST_LOG=info cargo run --release --bin aggregate -- offer <PUBLIC_VALUES> >> proofs.txt
+//! RUST_LOG=info cargo run --release --bin aggregate -- prove --proofs proofs.txt
+//! ```
+//!
+//! `offer` proves one offer on its own and prints the proof as base64 bincode. `prove` reads such
+//! proofs, one per line, verifies them all inside the guest and prints the Groth16 proof of the
+//! aggregation with the public values it commits, ready for a relayer to post.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use borsh::BorshDeserialize;
+use clap::{Parser, Subcommand};
+use solana_zk_offers::{aggregation::AggregatedOfferProofs, zk_offers::PublicValuesStruct};
+use sp1_sdk::{HashableKey, SP1ProofWithPublicValues};
+use std::{fs, path::PathBuf};
+use zk_offers_script::{
+    guest::{aggregation_stdin, offer_stdin},
+    prover::{OfferProver, ProofKind, ProverArgs},
+};
+
+/// The ELF (executable and linkable format) file for the Succinct RISC-V zkVM.
+pub const ZKVM_ELF: &[u8] = include_bytes!("../../../elf/riscv32im-succinct-zkvm-elf");
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Prove one offer as a compressed proof that can be aggregated.
+    Offer { public_values: PublicValuesStruct },
+    /// Aggregate compressed offer proofs into one Groth16 proof.
+    Prove {
+        /// File listing one base64 bincode-encoded compressed proof per line.
+        #[clap(long)]
+        proofs: PathBuf,
+    },
+}
+
+/// The arguments for the aggregate command.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    #[clap(subcommand)]
+    command: Command,
+
+    #[clap(flatten)]
+    prover: ProverArgs,
+}
+
+fn read_proofs(path: &PathBuf) -> Vec<SP1ProofWithPublicValues> {
+    let contents = fs::read_to_string(path).expect("Failed to read proofs file");
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let bytes = STANDARD.decode(line).expect("Failed to decode proof");
+            bincode::deserialize(&bytes).expect("Failed to deserialize proof")
+        })
+        .collect()
+}
+
+fn main() {
+    sp1_sdk::utils::setup_logger();
+    let args = Args::parse();
+    let prover = OfferProver::new(&args.prover);
+    let (pk, vk) = prover.setup(ZKVM_ELF);
+
+    match args.command {
+        Command::Offer { public_values } => {
+            let proof = prover
+                .prove(&pk, offer_stdin(&public_values), ProofKind::Compressed)
+                .expect("Failed to generate proof");
+            prover.verify(&proof, &vk).expect("Failed to verify proof");
+            let proof = bincode::serialize(&proof).expect("Failed to serialize proof");
+            println!("{}", STANDARD.encode(proof));
+        }
+        Command::Prove { proofs } => {
+            let proofs: Vec<_> = read_proofs(&proofs)
+                .into_iter()
+                .map(|proof| (proof, vk.clone()))
+                .collect();
+            // Check every proof here first, a bad one would only fail deep inside the prover.
+            for (index, (proof, vk)) in proofs.iter().enumerate() {
+                if let Err(err) = prover.verify(proof, vk) {
+                    eprintln!("Error: Proof {} does not verify: {:#}", index, err);
+                    std::process::exit(1);
+                }
+            }
+            let stdin = aggregation_stdin(&proofs).unwrap_or_else(|err| {
+                eprintln!("Error: {:#}", err);
+                std::process::exit(1);
+            });
+            let proof = prover
+                .prove(&pk, stdin, ProofKind::Groth16)
+                .expect("Failed to generate proof");
+            prover.verify(&proof, &vk).expect("Failed to verify proof");
+
+            let aggregated = AggregatedOfferProofs::try_from_slice(proof.public_values.as_slice())
+                .expect("Failed to decode the aggregation");
+            for ((offer, _), aggregated) in proofs.iter().zip(&aggregated.proofs) {
+                match PublicValuesStruct::from_canonical_bytes(offer.public_values.as_slice()) {
+                    Some(public_values) => println!(
+                        "Offer {}: {:?}",
+                        hex::encode(aggregated.public_values_digest),
+                        public_values
+                    ),
+                    None => println!("Proof {}", hex::encode(aggregated.public_values_digest)),
+                }
+            }
+            println!("Verification Key: {}", vk.bytes32());
+            println!(
+                "Public Values: {}",
+                hex::encode(proof.public_values.as_slice())
+            );
+            println!("Proof Bytes: {}", hex::encode(proof.bytes()));
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Decode a stateless-asks transaction or instruction and check its accounts.
+//!
+//! You can run this script using the following command:
+//! ```shell
+//! cargo run --release --bin decode -- --program-id <PROGRAM_ID> <BASE58_OR_BASE64>
+//! ```
+//!
+//! The input is either a bincode serialized transaction, as returned by `getTransaction` with the
+//! `base64` encoding, or raw instruction data, whose accounts can be passed with `--accounts`.
+//! Each account is labelled with its role and checked against the PDAs and ATAs recomputed from
+//! the instruction.
+
+use clap::Parser;
+use solana_program::pubkey::Pubkey;
+use std::str::FromStr;
+use zk_offers_script::decode::{
+    decode_bytes, decode_instruction, decode_transaction, DecodedInstruction, Encoding,
+};
+
+fn parse_pubkey(s: &str) -> Result<Pubkey, String> {
+    Pubkey::from_str(s).map_err(|e| format!("Invalid Pubkey: {}", e))
+}
+
+/// The arguments for the decode command.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// The encoded transaction or instruction data.
+    input: String,
+
+    /// Only decode instructions of this program, and derive PDAs with it.
+    #[clap(long, value_parser = parse_pubkey)]
+    program_id: Option<Pubkey>,
+
+    /// Accounts of raw instruction data, in order.
+    #[clap(long, value_delimiter = ',', value_parser = parse_pubkey)]
+    accounts: Vec<Pubkey>,
+
+    #[clap(long, value_enum, default_value = "auto")]
+    encoding: Encoding,
+}
+
+fn main() {
+    let args = Args::parse();
+    let bytes = decode_bytes(&args.input, args.encoding).expect("Failed to decode input");
+
+    let accounts: Vec<Option<Pubkey>> = args.accounts.iter().copied().map(Some).collect();
+    let decoded: Vec<DecodedInstruction> =
+        match decode_instruction(args.program_id.as_ref(), &bytes, &accounts) {
+            Ok(instruction) => vec![instruction],
+            Err(_) => decode_transaction(args.program_id.as_ref(), &bytes)
+                .expect("Input is neither a stateless-asks instruction nor a transaction"),
+        };
+    if decoded.is_empty() {
+        println!("No stateless-asks instructions found");
+        return;
+    }
+    let mut inconsistent = 0;
+    for instruction in &decoded {
+        print!("{}", instruction);
+        if !instruction.is_consistent() {
+            inconsistent += 1;
+        }
+    }
+    if inconsistent > 0 {
+        eprintln!(
+            "{} of {} instructions look wrong",
+            inconsistent,
+            decoded.len()
+        );
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,133 @@
+//! Sweep a list of posted offers and print the ones that can no longer be filled.
+//!
+//! You can run this script using the following command:
+//! ```shell
+//! cargo run --release --bin health -- --program-id <PROGRAM_ID> --offers offers.json
+//! ```
+//!
+//! `offers.json` is a JSON array of objects with `maker_wallet`, `maker_mint`, `taker_mint`,
+//! `maker_size`, `taker_size` and optionally `platform_fee_basis_points` and
+//! `platform_fee_recipient`. `taker_mint` is the native mint for offers paid in SOL.
+
+use clap::Parser;
+use serde::Deserialize;
+use solana_program::pubkey::Pubkey;
+use solana_zk_offers::{
+    health::{check_offer_health, OfferTerms},
+    zk_offers::PlatformFee,
+};
+use std::{fs, path::PathBuf, str::FromStr};
+use zk_offers_script::accounts::{AccountSource, RpcAccountSource, StaticAccountSource};
+
+fn parse_pubkey(s: &str) -> Result<Pubkey, String> {
+    Pubkey::from_str(s).map_err(|e| format!("Invalid Pubkey: {}", e))
+}
+
+/// The arguments for the health command.
+#[derive(Clone, Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    #[clap(long, value_parser = parse_pubkey)]
+    program_id: Pubkey,
+
+    /// JSON file listing the offers to check.
+    #[clap(long)]
+    offers: PathBuf,
+
+    /// JSON-RPC endpoint used to fetch token accounts.
+    #[clap(long, env = "SOLANA_RPC_URL", conflicts_with = "accounts_file")]
+    rpc_url: Option<String>,
+
+    /// JSON file of base58 address to base64 account data, used instead of RPC.
+    #[clap(long)]
+    accounts_file: Option<PathBuf>,
+
+    /// Also print offers that are still fillable.
+    #[clap(long)]
+    all: bool,
+}
+
+#[derive(Deserialize)]
+struct OfferEntry {
+    maker_wallet: String,
+    maker_mint: String,
+    taker_mint: String,
+    maker_size: u64,
+    taker_size: u64,
+    platform_fee_basis_points: Option<u16>,
+    platform_fee_recipient: Option<String>,
+}
+
+impl OfferEntry {
+    fn terms(&self) -> Result<OfferTerms, String> {
+        let platform_fee = match (&self.platform_fee_basis_points, &self.platform_fee_recipient) {
+            (Some(basis_points), Some(recipient)) => Some(PlatformFee {
+                basis_points: *basis_points,
+                recipient: parse_pubkey(recipient)?,
+            }),
+            (None, None) => None,
+            _ => return Err("Incomplete platform fee".to_string()),
+        };
+        Ok(OfferTerms {
+            maker_wallet: parse_pubkey(&self.maker_wallet)?,
+            maker_mint: parse_pubkey(&self.maker_mint)?,
+            taker_mint: parse_pubkey(&self.taker_mint)?,
+            maker_size: self.maker_size,
+            taker_size: self.taker_size,
+            platform_fee,
+        })
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let accounts: Box<dyn AccountSource> = match (&args.rpc_url, &args.accounts_file) {
+        (_, Some(path)) => Box::new(
+            StaticAccountSource::from_json_file(path).expect("Failed to load accounts file"),
+        ),
+        (Some(url), None) => Box::new(RpcAccountSource::new(url.clone())),
+        (None, None) => {
+            eprintln!("Error: You must specify either --rpc-url or --accounts-file");
+            std::process::exit(1);
+        }
+    };
+
+    let contents = fs::read_to_string(&args.offers).expect("Failed to read offers file");
+    let entries: Vec<OfferEntry> =
+        serde_json::from_str(&contents).expect("Failed to parse offers file");
+
+    let mut stale = 0;
+    for (index, entry) in entries.iter().enumerate() {
+        let terms = match entry.terms() {
+            Ok(terms) => terms,
+            Err(err) => {
+                println!("offer #{}: invalid entry: {}", index, err);
+                stale += 1;
+                continue;
+            }
+        };
+        let maker_src_account = terms.maker_src_account();
+        let data = match accounts.account_data(&maker_src_account) {
+            Ok(data) => data,
+            Err(err) => {
+                eprintln!("offer #{}: {:#}", index, err);
+                continue;
+            }
+        };
+        let health =
+            check_offer_health(&args.program_id, &terms, &maker_src_account, data.as_deref());
+        if health.is_fillable() {
+            if args.all {
+                println!("offer #{} ({}): fillable", index, health.authority);
+            }
+            continue;
+        }
+        stale += 1;
+        println!("offer #{} ({}): stale", index, health.authority);
+        for issue in &health.issues {
+            println!("  - {}", issue);
+        }
+    }
+    println!("{} of {} offers are stale", stale, entries.len());
+}
@@ -0,0 +1,30 @@
+//! A script to write the Anchor-compatible IDL of the stateless-asks program.
+//!
+//! You can run this script using the following command:
+//! ```shell
+//! cargo run --bin idl --release
+//! ```
+//! `tests/idl.rs` fails until the checked-in IDL is regenerated after a change to the program
+//! interface.
+
+use clap::Parser;
+use std::{fs, path::PathBuf};
+use zk_offers_script::idl::{idl_json, IDL_PATH};
+
+/// The arguments for the idl command.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Where to write the IDL, the checked-in copy by default.
+    #[clap(long)]
+    out: Option<PathBuf>,
+}
+
+fn main() {
+    let args = Args::parse();
+    let out = args
+        .out
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("..").join(IDL_PATH));
+    fs::write(&out, idl_json()).expect("Failed to write IDL");
+    println!("Wrote {}", out.display());
+}
@@ -0,0 +1,365 @@
+//! Build a SQLite database of offer history from `getTransaction` responses, and query it.
+//!
+//! You can run this script using the following commands:
+//! ```shell
+//! cargo run --release --features indexer --bin indexer -- ingest \
+//!     --program-id <PROGRAM_ID> --db offers.db <DUMP_DIR>
+//! cargo run --release --features indexer --bin indexer -- query \
+//!     --db offers.db --wallet <WALLET> --mint <MINT>
+//! ```
+//!
+//! `<DUMP_DIR>` holds one `.json` file per transaction, each a `getTransaction` response with the
+//! `base64` or `json` encoding, with or without its JSON-RPC envelope. Ingesting a transaction
+//! again replaces its records, so a directory can be re-ingested as it grows. An approval or
+//! revoke outside a program transaction is only recorded once a fill in the directory ties it to
+//! an offer.
+
+use clap::{Parser, Subcommand};
+use rusqlite::{params, Connection};
+use solana_program::pubkey::Pubkey;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use zk_offers_script::history::{
+    match_offer_approvals, parse_transaction_dump, transaction_history, TransactionHistory,
+};
+
+fn parse_pubkey(s: &str) -> Result<Pubkey, String> {
+    Pubkey::from_str(s).map_err(|e| format!("Invalid Pubkey: {}", e))
+}
+
+/// The arguments for the indexer command.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// The SQLite database, created if missing.
+    #[clap(long, default_value = "offers.db")]
+    db: PathBuf,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Add the transactions of a directory of `getTransaction` dumps to the database.
+    Ingest {
+        #[clap(long, value_parser = parse_pubkey)]
+        program_id: Pubkey,
+
+        dir: PathBuf,
+    },
+    /// Print the fills, cancellations and delegations of a wallet or mint, oldest first.
+    Query {
+        #[clap(long, value_parser = parse_pubkey)]
+        wallet: Option<Pubkey>,
+
+        #[clap(long, value_parser = parse_pubkey)]
+        mint: Option<Pubkey>,
+    },
+}
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS transactions (
+    signature TEXT PRIMARY KEY,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    err TEXT
+);
+CREATE TABLE IF NOT EXISTS fills (
+    id INTEGER PRIMARY KEY,
+    signature TEXT NOT NULL REFERENCES transactions (signature),
+    offer TEXT NOT NULL,
+    maker_wallet TEXT NOT NULL,
+    taker_wallet TEXT NOT NULL,
+    maker_mint TEXT NOT NULL,
+    taker_mint TEXT NOT NULL,
+    maker_size INTEGER,
+    taker_size INTEGER,
+    taker_received INTEGER,
+    maker_received INTEGER,
+    fee_mint TEXT
+);
+CREATE TABLE IF NOT EXISTS fill_fees (
+    fill_id INTEGER NOT NULL REFERENCES fills (id),
+    kind TEXT NOT NULL,
+    recipient TEXT NOT NULL,
+    amount INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS cancellations (
+    signature TEXT NOT NULL REFERENCES transactions (signature),
+    kind TEXT NOT NULL,
+    wallet TEXT NOT NULL,
+    account TEXT NOT NULL,
+    mint TEXT
+);
+CREATE TABLE IF NOT EXISTS delegations (
+    signature TEXT NOT NULL REFERENCES transactions (signature),
+    wallet TEXT NOT NULL,
+    token_account TEXT NOT NULL,
+    delegate TEXT NOT NULL,
+    mint TEXT,
+    amount INTEGER NOT NULL,
+    to_router INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS fills_maker_wallet ON fills (maker_wallet);
+CREATE INDEX IF NOT EXISTS fills_taker_wallet ON fills (taker_wallet);
+CREATE INDEX IF NOT EXISTS fills_maker_mint ON fills (maker_mint);
+CREATE INDEX IF NOT EXISTS fills_taker_mint ON fills (taker_mint);
+CREATE INDEX IF NOT EXISTS fill_fees_fill_id ON fill_fees (fill_id);
+CREATE INDEX IF NOT EXISTS cancellations_wallet ON cancellations (wallet);
+CREATE INDEX IF NOT EXISTS cancellations_mint ON cancellations (mint);
+CREATE INDEX IF NOT EXISTS delegations_wallet ON delegations (wallet);
+CREATE INDEX IF NOT EXISTS delegations_mint ON delegations (mint);
+";
+
+/// Replace the records of a transaction.
+fn store(db: &mut Connection, history: &TransactionHistory) -> rusqlite::Result<()> {
+    let tx = db.transaction()?;
+    tx.execute(
+        "DELETE FROM fill_fees WHERE fill_id IN (SELECT id FROM fills WHERE signature = ?1)",
+        [&history.signature],
+    )?;
+    for table in ["fills", "cancellations", "delegations"] {
+        tx.execute(
+            &format!("DELETE FROM {} WHERE signature = ?1", table),
+            [&history.signature],
+        )?;
+    }
+    tx.execute(
+        "INSERT OR REPLACE INTO transactions (signature, slot, block_time, err)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![
+            history.signature,
+            history.slot,
+            history.block_time,
+            history.err
+        ],
+    )?;
+    for fill in &history.fills {
+        let payout = fill.payout.as_ref();
+        tx.execute(
+            "INSERT INTO fills (signature, offer, maker_wallet, taker_wallet, maker_mint,
+                 taker_mint, maker_size, taker_size, taker_received, maker_received, fee_mint)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                history.signature,
+                fill.offer.to_string(),
+                fill.maker_wallet.to_string(),
+                fill.taker_wallet.to_string(),
+                fill.maker_mint.to_string(),
+                fill.taker_mint.to_string(),
+                fill.maker_size,
+                fill.taker_size,
+                payout.map(|payout| payout.taker_received),
+                payout.map(|payout| payout.maker_received),
+                payout.map(|payout| payout.fee_mint.to_string()),
+            ],
+        )?;
+        let fill_id = tx.last_insert_rowid();
+        let Some(payout) = payout else {
+            continue;
+        };
+        let fees = payout
+            .creator_fees
+            .iter()
+            .map(|fee| ("creator", fee))
+            .chain(payout.platform_fee.iter().map(|fee| ("platform", fee)));
+        for (kind, fee) in fees {
+            tx.execute(
+                "INSERT INTO fill_fees (fill_id, kind, recipient, amount) VALUES (?1, ?2, ?3, ?4)",
+                params![fill_id, kind, fee.recipient.to_string(), fee.amount],
+            )?;
+        }
+    }
+    for cancellation in &history.cancellations {
+        tx.execute(
+            "INSERT INTO cancellations (signature, kind, wallet, account, mint)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                history.signature,
+                cancellation.kind.as_str(),
+                cancellation.wallet.to_string(),
+                cancellation.account.to_string(),
+                cancellation.mint.map(|mint| mint.to_string()),
+            ],
+        )?;
+    }
+    for delegation in &history.delegations {
+        tx.execute(
+            "INSERT INTO delegations (signature, wallet, token_account, delegate, mint, amount,
+                 to_router)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                history.signature,
+                delegation.wallet.to_string(),
+                delegation.token_account.to_string(),
+                delegation.delegate.to_string(),
+                delegation.mint.map(|mint| mint.to_string()),
+                delegation.amount,
+                delegation.to_router,
+            ],
+        )?;
+    }
+    tx.commit()
+}
+
+fn ingest(db: &mut Connection, program_id: &Pubkey, dir: &Path) {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .expect("Failed to read dump directory")
+        .map(|entry| entry.expect("Failed to read dump directory").path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .collect();
+    paths.sort();
+
+    let mut histories = Vec::new();
+    for path in &paths {
+        let history = fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| parse_transaction_dump(&json))
+            .and_then(|transaction| transaction_history(program_id, &transaction));
+        match history {
+            Ok(history) => histories.push(history),
+            Err(err) => eprintln!("{}: {:#}", path.display(), err),
+        }
+    }
+    // Approvals outside program transactions are offers once a fill goes through their delegate,
+    // which may be in any of the dumps.
+    match_offer_approvals(&mut histories);
+
+    let (mut fills, mut cancellations, mut delegations) = (0, 0, 0);
+    for history in &histories {
+        store(db, history).expect("Failed to write to the database");
+        fills += history.fills.len();
+        cancellations += history.cancellations.len();
+        delegations += history.delegations.len();
+    }
+    println!(
+        "Ingested {} of {} transactions: {} fills, {} cancellations, {} delegations",
+        histories.len(),
+        paths.len(),
+        fills,
+        cancellations,
+        delegations
+    );
+}
+
+fn or_unknown(value: Option<impl ToString>) -> String {
+    value.map_or_else(|| "?".to_string(), |value| value.to_string())
+}
+
+fn query(db: &Connection, wallet: Option<Pubkey>, mint: Option<Pubkey>) -> rusqlite::Result<()> {
+    let wallet = wallet.map(|wallet| wallet.to_string());
+    let mint = mint.map(|mint| mint.to_string());
+    // Every record as its slot and the line printed for it.
+    let mut records: Vec<(i64, String)> = Vec::new();
+
+    let mut fills = db.prepare(
+        "SELECT t.slot, f.signature, f.offer, f.maker_wallet, f.taker_wallet, f.maker_mint,
+             f.taker_mint, f.maker_size, f.taker_size, f.taker_received, f.maker_received
+         FROM fills f JOIN transactions t USING (signature)
+         WHERE (?1 IS NULL OR ?1 IN (f.maker_wallet, f.taker_wallet))
+             AND (?2 IS NULL OR ?2 IN (f.maker_mint, f.taker_mint))",
+    )?;
+    let rows = fills.query_map(params![wallet, mint], |row| {
+        let line = format!(
+            "fill {}: maker {} gave {} {} for {} {} from taker {} (offer {})",
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(3)?,
+            or_unknown(row.get::<_, Option<u64>>(7)?),
+            row.get::<_, String>(5)?,
+            or_unknown(row.get::<_, Option<u64>>(8)?),
+            row.get::<_, String>(6)?,
+            row.get::<_, String>(4)?,
+            row.get::<_, String>(2)?,
+        );
+        let received = match (
+            row.get::<_, Option<u64>>(9)?,
+            row.get::<_, Option<u64>>(10)?,
+        ) {
+            (Some(taker_received), Some(maker_received)) => format!(
+                ", after fees the taker received {} and the maker {}",
+                taker_received, maker_received
+            ),
+            _ => String::new(),
+        };
+        Ok((row.get(0)?, line + &received))
+    })?;
+    for row in rows {
+        records.push(row?);
+    }
+
+    let mut cancellations = db.prepare(
+        "SELECT t.slot, c.signature, c.kind, c.wallet, c.account, c.mint
+         FROM cancellations c JOIN transactions t USING (signature)
+         WHERE (?1 IS NULL OR c.wallet = ?1) AND (?2 IS NULL OR c.mint = ?2)",
+    )?;
+    let rows = cancellations.query_map(params![wallet, mint], |row| {
+        let line = format!(
+            "cancellation {}: {} of {} by {} ({})",
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(4)?,
+            row.get::<_, String>(3)?,
+            or_unknown(row.get::<_, Option<String>>(5)?),
+        );
+        Ok((row.get(0)?, line))
+    })?;
+    for row in rows {
+        records.push(row?);
+    }
+
+    let mut delegations = db.prepare(
+        "SELECT t.slot, d.signature, d.wallet, d.token_account, d.delegate, d.mint, d.amount,
+             d.to_router
+         FROM delegations d JOIN transactions t USING (signature)
+         WHERE (?1 IS NULL OR d.wallet = ?1) AND (?2 IS NULL OR d.mint = ?2)",
+    )?;
+    let rows = delegations.query_map(params![wallet, mint], |row| {
+        let delegate = if row.get::<_, bool>(7)? {
+            "the offer router".to_string()
+        } else {
+            row.get::<_, String>(4)?
+        };
+        let line = format!(
+            "delegation {}: {} approved {} {} from {} to {}",
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, u64>(6)?,
+            or_unknown(row.get::<_, Option<String>>(5)?),
+            row.get::<_, String>(3)?,
+            delegate,
+        );
+        Ok((row.get(0)?, line))
+    })?;
+    for row in rows {
+        records.push(row?);
+    }
+
+    records.sort_by_key(|(slot, _)| *slot);
+    for (slot, line) in &records {
+        println!("slot {} {}", slot, line);
+    }
+    println!("{} records", records.len());
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let mut db = Connection::open(&args.db).expect("Failed to open the database");
+    db.execute_batch(SCHEMA)
+        .expect("Failed to create the database schema");
+
+    match args.command {
+        Command::Ingest { program_id, dir } => ingest(&mut db, &program_id, &dir),
+        Command::Query { wallet, mint } => {
+            query(&db, wallet, mint).expect("Failed to query the database")
+        }
+    }
+}
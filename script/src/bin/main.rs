@@ -1,4 +1,3 @@
-use borsh::{BorshDeserialize, BorshSerialize};
 use clap::Parser;
 use hex::{encode, ToHex};
 use serde::Serialize;
@@ -8,13 +7,18 @@ use solana_program::{hash::Hash, instruction::Instruction, pubkey::Pubkey};
 use solana_pubkey::Pubkey as V2_pubkey;
 use solana_transaction::{versioned::VersionedTransaction, Transaction};
 use solana_zk_offers::zk_offers::{
-    approve_delegation, cancel_delegation, compute_maker_src_account, compute_offer_pda,
-    create_offer_transaction, PublicValuesStruct,
+    approve_delegation, cancel_delegation, compute_offer_authority, PlatformFee, PublicValuesStruct,
 };
 
-use sp1_sdk::{ProverClient, SP1Stdin};
-use spl_token::ID as TOKEN_PROGRAM_ID;
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::{native_mint, ID as TOKEN_PROGRAM_ID};
+use stateless_asks::instruction::accept_offer;
 use std::str::FromStr;
+use zk_offers_script::{
+    compute_budget::{ComputeBudgetArgs, ComputeBudgeter},
+    guest::offer_stdin,
+    prover::{OfferProver, ProofKind, ProverArgs},
+};
 
 /// The ELF (executable and linkable format) file for the Succinct RISC-V zkVM.
 pub const ZKVM_ELF: &[u8] = include_bytes!("../../../elf/riscv32im-succinct-zkvm-elf");
@@ -25,8 +29,8 @@ fn parse_pubkey(s: &str) -> Result<Pubkey, String> {
 
 fn parse_public_values(s: &str) -> Result<PublicValuesStruct, String> {
     let parts: Vec<&str> = s.split(',').map(|s| s.trim()).collect();
-    if parts.len() != 5 {
-        return Err("Expected 5 comma-separated values".to_string());
+    if parts.len() != 5 && parts.len() != 7 {
+        return Err("Expected 5 or 7 comma-separated values".to_string());
     }
 
     let maker_mint = parse_pubkey(parts[0])?;
@@ -51,17 +55,29 @@ fn parse_public_values(s: &str) -> Result<PublicValuesStruct, String> {
         )
     };
 
+    let platform_fee = if parts.len() == 7 {
+        PlatformFee::new(
+            parts[5]
+                .parse::<u16>()
+                .map_err(|_| "Invalid integer value for platform fee basis points".to_string())?,
+            parse_pubkey(parts[6])?,
+        )
+    } else {
+        None
+    };
+
     Ok(PublicValuesStruct {
         maker_mint,
         taker_mint,
         is_native,
         maker_size,
         taker_size,
+        platform_fee,
     })
 }
 
 /// The arguments for the command.
-#[derive(Clone, Parser, Debug, BorshDeserialize, BorshSerialize, serde::Serialize)]
+#[derive(Clone, Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
     #[clap(long)]
@@ -76,8 +92,22 @@ struct Args {
     #[clap(long, value_parser = parse_pubkey)]
     maker_wallet: Pubkey,
 
+    /// Wallet filling the offer, for which the fill instruction is built.
+    #[clap(long, value_parser = parse_pubkey)]
+    taker_wallet: Option<Pubkey>,
+
     #[clap(long, default_value = "So11111111111111111111111111111111111111112")]
     program_id: Pubkey,
+
+    /// JSON-RPC endpoint used to simulate the transaction and sample priority fees.
+    #[clap(long, env = "SOLANA_RPC_URL")]
+    rpc_url: Option<String>,
+
+    #[clap(flatten)]
+    prover: ProverArgs,
+
+    #[clap(flatten)]
+    compute_budget: ComputeBudgetArgs,
 }
 
 struct Application {
@@ -102,66 +132,124 @@ impl Application {
         Self { args }
     }
 
-    /// Compute the PDA
-    fn compute_pda(&self) -> Pubkey {
-        compute_offer_pda(
+    /// The taker mint, the native mint for offers paid in SOL.
+    fn taker_mint(&self) -> Pubkey {
+        self.args
+            .public_values
+            .taker_mint
+            .unwrap_or_else(native_mint::id)
+    }
+
+    /// Compute the transfer authority the maker delegates to and the fill signs with. The offer
+    /// terms are part of its seeds, so there is none without a taker size.
+    fn offer_authority(&self) -> Option<(Pubkey, u8)> {
+        let public_values = &self.args.public_values;
+        Some(compute_offer_authority(
             &self.args.program_id,
             &self.args.maker_wallet,
-            &self.args.public_values.maker_mint,
-        )
+            &public_values.maker_mint,
+            &self.taker_mint(),
+            public_values.maker_size,
+            public_values.taker_size?,
+            public_values.platform_fee.as_ref(),
+        ))
     }
 
-    fn approve_listing(&self) -> Instruction {
-        // Compute maker_src_account from maker wallet and maker mint
-
-        let maker_src_account: Pubkey = compute_maker_src_account(
-            &self.args.program_id,
+    fn approve_listing(&self) -> Option<Instruction> {
+        let (authority, _) = self.offer_authority()?;
+        let maker_src_account = get_associated_token_address(
             &self.args.maker_wallet,
             &self.args.public_values.maker_mint,
         );
-
-        let pda: &Pubkey = &self.compute_pda();
-        approve_delegation(
+        Some(approve_delegation(
             &self.args.maker_wallet,
             &maker_src_account,
-            pda,
+            &authority,
             self.args.public_values.maker_size,
             &TOKEN_PROGRAM_ID,
-        )
+        ))
     }
 
-    /// Build the transaction
-    fn build_transaction(&self, offer: Vec<u8>) -> Instruction {
-        let pda: Pubkey = self.compute_pda();
-        create_offer_transaction(&self.args.program_id, offer, pda)
+    /// Build the instruction filling the offer for `taker_wallet`.
+    fn build_transaction(&self, taker_wallet: &Pubkey) -> Option<Instruction> {
+        let public_values = &self.args.public_values;
+        let maker_wallet = &self.args.maker_wallet;
+        let maker_mint = &public_values.maker_mint;
+        let taker_mint = self.taker_mint();
+        let (authority, bump_seed) = self.offer_authority()?;
+        let (maker_dst_account, taker_src_account) = if public_values.is_native {
+            (*maker_wallet, *taker_wallet)
+        } else {
+            (
+                get_associated_token_address(maker_wallet, &taker_mint),
+                get_associated_token_address(taker_wallet, &taker_mint),
+            )
+        };
+        Some(accept_offer(
+            &self.args.program_id,
+            maker_wallet,
+            taker_wallet,
+            &get_associated_token_address(maker_wallet, maker_mint),
+            &maker_dst_account,
+            &taker_src_account,
+            &get_associated_token_address(taker_wallet, maker_mint),
+            maker_mint,
+            &taker_mint,
+            &authority,
+            &TOKEN_PROGRAM_ID,
+            public_values.is_native,
+            public_values.platform_fee.as_ref(),
+            public_values.maker_size,
+            public_values.taker_size?,
+            bump_seed,
+        ))
     }
 
     /// Run the application
     fn run(&self) {
-        let offer = self.approve_listing();
-        // let transaction = self.build_transaction(pda, offer);
+        let Some(offer) = self.approve_listing() else {
+            println!("The public values have no taker size, so there is no offer to list");
+            return;
+        };
 
         let data_buffer = hex::encode(&offer.data);
         println!("Created Offer Instruction: {:?}", data_buffer);
 
-        // For a dummy transaction, use a dummy signer
-        let dummy_signer = Pubkey::new_unique();
+        // The fill is the transaction the taker signs and pays for.
+        let Some(taker_wallet) = self.args.taker_wallet else {
+            return;
+        };
+        let Some(fill) = self.build_transaction(&taker_wallet) else {
+            return;
+        };
         let recent_blockhash = Hash::from_str("11111111111111111111111111111111").unwrap(); // Replace with actual blockhash
 
-        let v2_instruction = V2_instruction {
-            program_id: offer.program_id,
-            accounts: offer
-                .accounts
-                .iter()
-                .map(|account| AccountMeta {
-                    pubkey: account.pubkey,
-                    is_signer: account.is_signer,
-                    is_writable: account.is_writable,
-                })
-                .collect(),
-            data: offer.data.clone(),
+        let compute_budget =
+            ComputeBudgeter::new(&self.args.compute_budget, self.args.rpc_url.clone());
+        let instructions = match compute_budget.apply(&taker_wallet, vec![fill]) {
+            Ok(instructions) => instructions,
+            Err(err) => {
+                eprintln!("Failed to budget compute units: {:#}", err);
+                return;
+            }
         };
-        let result = Message::try_compile(&dummy_signer, &[v2_instruction], &[], recent_blockhash);
+        let v2_instructions: Vec<V2_instruction> = instructions
+            .iter()
+            .map(|instruction| V2_instruction {
+                program_id: instruction.program_id,
+                accounts: instruction
+                    .accounts
+                    .iter()
+                    .map(|account| AccountMeta {
+                        pubkey: account.pubkey,
+                        is_signer: account.is_signer,
+                        is_writable: account.is_writable,
+                    })
+                    .collect(),
+                data: instruction.data.clone(),
+            })
+            .collect();
+        let result = Message::try_compile(&taker_wallet, &v2_instructions, &[], recent_blockhash);
 
         // Handle the Result
         let message: Message = match result {
@@ -187,35 +275,32 @@ fn main() {
     let app = Application::new(); // Initialize application
     app.run(); // Execute main logic
 
-    let client = ProverClient::new();
+    let prover = OfferProver::new(&app.args.prover);
 
     // Serialize the public values into the SP1Stdin format.
-    let mut stdin = SP1Stdin::new();
+    let stdin = offer_stdin(&app.args.public_values);
 
     if app.args.execute {
         // Execute the program
-        let (output, report) = client.execute(ZKVM_ELF, stdin).run().unwrap();
+        let (output, report) = prover.client().execute(ZKVM_ELF, stdin).run().unwrap();
         println!("Program executed successfully.");
-        let mut output_reader: &[u8] = output.as_slice();
-
-        // Deserialize the output into PublicValuesStruct
-        let decoded: PublicValuesStruct = BorshDeserialize::deserialize(&mut output_reader)
-            .expect("Failed to deserialize with Borsh");
+        // Decode the canonical encoding of the PublicValuesStruct
+        let decoded = PublicValuesStruct::from_canonical_bytes(output.as_slice())
+            .expect("Failed to decode the public values");
         println!("Decoded PublicValuesStruct: {:?}", decoded);
     } else {
         // Setup the program for proving.
-        let (pk, vk) = client.setup(ZKVM_ELF);
+        let (pk, vk) = prover.setup(ZKVM_ELF);
 
         // Generate the proof
-        let proof = client
-            .prove(&pk, stdin)
-            .run()
+        let proof = prover
+            .prove(&pk, stdin, ProofKind::Core)
             .expect("Failed to generate proof");
 
         println!("Successfully generated proof!");
 
         // Verify the proof.
-        client.verify(&proof, &vk).expect("Failed to verify proof");
+        prover.verify(&proof, &vk).expect("Failed to verify proof");
         println!("Successfully verified proof!");
     }
 }
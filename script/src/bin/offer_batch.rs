@@ -0,0 +1,117 @@
+//! Prove batches of offers and the inclusion of one offer in a batch.
+//!
+//! You can run this script using the following commands:
+//! ```shell
+//! RUST_LOG=info cargo run --release --bin offer-batch -- prove --program-id <PROGRAM_ID> \
+//!     --offers offers.txt
+//! cargo run --release --bin offer-batch -- inclusion --offers offers.txt --index <INDEX>
+//! ```
+//!
+//! `offers.txt` lists the public values of one offer per line, in the format of `--public-values`,
+//! with the taker mint and size set. `prove` proves the whole batch with one Groth16 proof, checked
+//! on-chain by PostOfferBatch. `inclusion` prints the Merkle proof AcceptBatchedOffer takes to
+//! fill the offer on line `index`, counted from zero.
+
+use borsh::BorshDeserialize;
+use clap::{Parser, Subcommand};
+use solana_program::pubkey::Pubkey;
+use solana_zk_offers::{
+    offer_batch::{compute_offer_batch_address, merkle_proof, offer_leaf, OfferBatchRoot},
+    zk_offers::PublicValuesStruct,
+};
+use sp1_sdk::HashableKey;
+use std::{fs, path::PathBuf, str::FromStr};
+use zk_offers_script::{
+    guest::offer_batch_stdin,
+    prover::{OfferProver, ProofKind, ProverArgs},
+};
+
+/// The ELF (executable and linkable format) file for the Succinct RISC-V zkVM.
+pub const ZKVM_ELF: &[u8] = include_bytes!("../../../elf/riscv32im-succinct-zkvm-elf");
+
+fn parse_pubkey(s: &str) -> Result<Pubkey, String> {
+    Pubkey::from_str(s).map_err(|e| format!("Invalid Pubkey: {}", e))
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Prove every offer of the batch and print the root to post.
+    Prove {
+        #[clap(long, value_parser = parse_pubkey)]
+        program_id: Pubkey,
+
+        #[clap(flatten)]
+        prover: ProverArgs,
+    },
+    /// Print the Merkle proof of one offer of the batch.
+    Inclusion {
+        #[clap(long)]
+        index: usize,
+    },
+}
+
+/// The arguments for the offer-batch command.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// File listing the public values of one offer per line.
+    #[clap(long)]
+    offers: PathBuf,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+fn read_offers(path: &PathBuf) -> Vec<PublicValuesStruct> {
+    let contents = fs::read_to_string(path).expect("Failed to read offers file");
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            PublicValuesStruct::from_str(line)
+                .unwrap_or_else(|e| panic!("Invalid public values {}: {}", line, e))
+        })
+        .collect()
+}
+
+fn main() {
+    let args = Args::parse();
+    let offers = read_offers(&args.offers);
+    match args.command {
+        Command::Prove { program_id, prover } => {
+            sp1_sdk::utils::setup_logger();
+            let prover = OfferProver::new(&prover);
+            let (pk, vk) = prover.setup(ZKVM_ELF);
+            let proof = prover
+                .prove(&pk, offer_batch_stdin(&offers), ProofKind::Groth16)
+                .expect("Failed to generate proof");
+            prover.verify(&proof, &vk).expect("Failed to verify proof");
+
+            let batch = OfferBatchRoot::try_from_slice(proof.public_values.as_slice())
+                .expect("Failed to decode the batch root");
+            let vkey = vk.bytes32();
+            let mut vkey_hash = [0u8; 32];
+            hex::decode_to_slice(vkey.trim_start_matches("0x"), &mut vkey_hash)
+                .expect("Failed to decode the verification key");
+            let (offer_batch, bump_seed) =
+                compute_offer_batch_address(&program_id, &vkey_hash, &batch.root);
+            println!("Verification Key: {}", vkey);
+            println!("Root: {}", hex::encode(batch.root));
+            println!("Offer count: {}", batch.offer_count);
+            println!("Offer batch account: {} (bump {})", offer_batch, bump_seed);
+            println!("Proof Bytes: {}", hex::encode(proof.bytes()));
+        }
+        Command::Inclusion { index } => {
+            let leaves: Vec<[u8; 32]> = offers.iter().map(offer_leaf).collect();
+            let proof = merkle_proof(&leaves, index).unwrap_or_else(|| {
+                eprintln!("Error: The batch has {} offers", leaves.len());
+                std::process::exit(1);
+            });
+            println!("Leaf: {}", hex::encode(leaves[index]));
+            for sibling in proof {
+                println!("Sibling: {}", hex::encode(sibling));
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Commit to and reveal sealed bids.
+//!
+//! You can run this script using the following commands:
+//! ```shell
+//! cargo run --release --bin sealed-bid -- commit --program-id <PROGRAM_ID> \
+//!     --auction <AUCTION> --bidder <BIDDER> --amount <AMOUNT> --salt <SALT_HEX>
+//! RUST_LOG=info cargo run --release --bin sealed-bid -- reveal --auction <AUCTION> \
+//!     --bidder <BIDDER> --amount <AMOUNT> --salt <SALT_HEX> <PUBLIC_VALUES>
+//! ```
+//!
+//! `commit` prints the commitment to post with CommitSealedBid. `reveal` proves, as a Groth16
+//! proof checked on-chain by RevealSealedBid, that the amount opens the commitment and meets the
+//! reserve of the auction. The salt must be kept secret until the reveal, for instance generated
+//! with `openssl rand -hex 32`.
+
+use borsh::BorshDeserialize;
+use clap::{Parser, Subcommand};
+use solana_program::pubkey::Pubkey;
+use solana_zk_offers::{
+    sealed_bid::{bid_commitment, compute_sealed_bid_address, SealedBidReveal},
+    zk_offers::PublicValuesStruct,
+};
+use sp1_sdk::HashableKey;
+use std::str::FromStr;
+use zk_offers_script::{
+    guest::sealed_bid_reveal_stdin,
+    prover::{OfferProver, ProofKind, ProverArgs},
+};
+
+/// The ELF (executable and linkable format) file for the Succinct RISC-V zkVM.
+pub const ZKVM_ELF: &[u8] = include_bytes!("../../../elf/riscv32im-succinct-zkvm-elf");
+
+fn parse_pubkey(s: &str) -> Result<Pubkey, String> {
+    Pubkey::from_str(s).map_err(|e| format!("Invalid Pubkey: {}", e))
+}
+
+fn parse_salt(s: &str) -> Result<[u8; 32], String> {
+    let bytes = hex::decode(s.trim_start_matches("0x")).map_err(|e| e.to_string())?;
+    bytes
+        .try_into()
+        .map_err(|_| "Expected a 32 byte salt".to_string())
+}
+
+/// The bid being committed or revealed.
+#[derive(Parser, Debug)]
+struct Bid {
+    #[clap(long, value_parser = parse_pubkey)]
+    auction: Pubkey,
+
+    #[clap(long, value_parser = parse_pubkey)]
+    bidder: Pubkey,
+
+    #[clap(long)]
+    amount: u64,
+
+    /// Hex encoded secret blinding the amount.
+    #[clap(long, value_parser = parse_salt)]
+    salt: [u8; 32],
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the commitment and bid account of a bid.
+    Commit {
+        #[clap(long, value_parser = parse_pubkey)]
+        program_id: Pubkey,
+
+        #[clap(flatten)]
+        bid: Bid,
+    },
+    /// Prove that a bid opens its commitment and meets the reserve.
+    Reveal {
+        #[clap(flatten)]
+        bid: Bid,
+
+        /// Terms of the auction, whose taker size is the reserve.
+        public_values: PublicValuesStruct,
+
+        #[clap(flatten)]
+        prover: ProverArgs,
+    },
+}
+
+/// The arguments for the sealed-bid command.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+fn main() {
+    let args = Args::parse();
+    match args.command {
+        Command::Commit { program_id, bid } => {
+            let commitment = bid_commitment(&bid.auction, &bid.bidder, bid.amount, &bid.salt);
+            let (bid_account, bump_seed) =
+                compute_sealed_bid_address(&program_id, &bid.auction, &bid.bidder);
+            println!("Commitment: {}", hex::encode(commitment));
+            println!("Bid account: {} (bump {})", bid_account, bump_seed);
+        }
+        Command::Reveal {
+            bid,
+            public_values,
+            prover,
+        } => {
+            sp1_sdk::utils::setup_logger();
+            let prover = OfferProver::new(&prover);
+            let (pk, vk) = prover.setup(ZKVM_ELF);
+            let stdin = sealed_bid_reveal_stdin(
+                &bid.auction,
+                &bid.bidder,
+                &public_values,
+                bid.amount,
+                &bid.salt,
+            );
+            let proof = prover
+                .prove(&pk, stdin, ProofKind::Groth16)
+                .expect("Failed to generate proof");
+            prover.verify(&proof, &vk).expect("Failed to verify proof");
+
+            let reveal = SealedBidReveal::try_from_slice(proof.public_values.as_slice())
+                .expect("Failed to decode the reveal");
+            println!("Verification Key: {}", vk.bytes32());
+            println!("Commitment: {}", hex::encode(reveal.commitment));
+            println!("Amount: {}", reveal.amount);
+            println!("Proof Bytes: {}", hex::encode(proof.bytes()));
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! An HTTP offer book in front of `solana-zk-offers`.
+//!
+//! You can run the server using the following command:
+//! ```shell
+//! RUST_LOG=info cargo run --release --features server --bin server -- \
+//!     --program-id <PROGRAM_ID> --rpc-url https://api.mainnet-beta.solana.com
+//! ```
+//!
+//! The routes are listed in [`zk_offers_script::server`].
+
+use clap::Parser;
+use solana_program::pubkey::Pubkey;
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+use zk_offers_script::{
+    accounts::{AccountSource, RpcAccountSource, StaticAccountSource},
+    compute_budget::{ComputeBudgetArgs, ComputeBudgeter},
+    offer_book::{parse_pubkey, OfferBook},
+    prover::{OfferProver, ProverArgs},
+    server::router,
+};
+
+/// The ELF (executable and linkable format) file for the Succinct RISC-V zkVM.
+pub const ZKVM_ELF: &[u8] = include_bytes!("../../../elf/riscv32im-succinct-zkvm-elf");
+
+/// The arguments for the server command.
+#[derive(Clone, Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    #[clap(long, default_value = "127.0.0.1:8080")]
+    bind: SocketAddr,
+
+    #[clap(long, value_parser = parse_program_id)]
+    program_id: Pubkey,
+
+    /// JSON-RPC endpoint used to check delegations.
+    #[clap(long, env = "SOLANA_RPC_URL", conflicts_with = "accounts_file")]
+    rpc_url: Option<String>,
+
+    /// JSON file of base58 address to base64 account data, used instead of RPC.
+    #[clap(long)]
+    accounts_file: Option<PathBuf>,
+
+    #[clap(flatten)]
+    prover: ProverArgs,
+
+    #[clap(flatten)]
+    compute_budget: ComputeBudgetArgs,
+}
+
+fn parse_program_id(s: &str) -> Result<Pubkey, String> {
+    parse_pubkey(s).map_err(|e| e.to_string())
+}
+
+#[tokio::main]
+async fn main() {
+    // Setup the logger.
+    sp1_sdk::utils::setup_logger();
+
+    let args = Args::parse();
+
+    let accounts: Box<dyn AccountSource> = match (&args.rpc_url, &args.accounts_file) {
+        (_, Some(path)) => Box::new(
+            StaticAccountSource::from_json_file(path).expect("Failed to load accounts file"),
+        ),
+        (Some(url), None) => Box::new(RpcAccountSource::new(url.clone())),
+        (None, None) => {
+            eprintln!("Error: You must specify either --rpc-url or --accounts-file");
+            std::process::exit(1);
+        }
+    };
+
+    let prover = OfferProver::new(&args.prover);
+    let (_, vk) = prover.setup(ZKVM_ELF);
+    // Accept messages are simulated against the same endpoint the delegations are checked on.
+    let compute_budget = ComputeBudgeter::new(&args.compute_budget, args.rpc_url.clone());
+    let book = Arc::new(
+        OfferBook::new(args.program_id, prover, vk, accounts).with_compute_budget(compute_budget),
+    );
+
+    let listener = tokio::net::TcpListener::bind(args.bind)
+        .await
+        .expect("Failed to bind");
+    tracing::info!("Offer book listening on {}", args.bind);
+    axum::serve(listener, router(book))
+        .await
+        .expect("Server failed");
+}
@@ -0,0 +1,134 @@
+//! Prove that a maker holds the maker size of their offers.
+//!
+//! You can run this script using the following command:
+//! ```shell
+//! RUST_LOG=info cargo run --release --bin solvency -- --maker-wallet <WALLET> \
+//!     --maker-mint <MINT> --maker-size <SIZE> --rpc-url https://api.mainnet-beta.solana.com
+//! ```
+//!
+//! The maker's token account is read at a confirmed slot and the bank hash of that slot is looked
+//! up in the SlotHashes sysvar. The printed proof is the `proof` of `POST /offers/<id>/solvency`
+//! on the offer book server, which only accepts it while the slot is among the last 512.
+
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::Parser;
+use solana_program::{pubkey::Pubkey, slot_hashes::SlotHashes, sysvar::slot_hashes};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_zk_offers::solvency::MakerSolvency;
+use spl_associated_token_account::get_associated_token_address;
+use std::{str::FromStr, thread, time::Duration};
+use zk_offers_script::{
+    guest::solvency_stdin,
+    prover::{OfferProver, ProofKind, ProverArgs},
+};
+
+/// The ELF (executable and linkable format) file for the Succinct RISC-V zkVM.
+pub const ZKVM_ELF: &[u8] = include_bytes!("../../../elf/riscv32im-succinct-zkvm-elf");
+
+/// How many times the SlotHashes sysvar is polled for the slot of the snapshot.
+const SLOT_HASH_ATTEMPTS: usize = 10;
+
+fn parse_pubkey(s: &str) -> Result<Pubkey, String> {
+    Pubkey::from_str(s).map_err(|e| format!("Invalid Pubkey: {}", e))
+}
+
+/// The arguments for the solvency command.
+#[derive(Clone, Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    #[clap(long, value_parser = parse_pubkey)]
+    maker_wallet: Pubkey,
+
+    #[clap(long, value_parser = parse_pubkey)]
+    maker_mint: Pubkey,
+
+    #[clap(long)]
+    maker_size: u64,
+
+    /// JSON-RPC endpoint the snapshot is taken from.
+    #[clap(long, env = "SOLANA_RPC_URL")]
+    rpc_url: String,
+
+    #[clap(flatten)]
+    prover: ProverArgs,
+}
+
+/// Read the maker's token account and the bank hash of the slot it was read at.
+fn snapshot(client: &RpcClient, token_account: &Pubkey) -> Result<(u64, [u8; 32], Vec<u8>)> {
+    let response = client
+        .get_account_with_commitment(token_account, CommitmentConfig::confirmed())
+        .with_context(|| format!("failed to fetch account {}", token_account))?;
+    let slot = response.context.slot;
+    let account = response
+        .value
+        .ok_or_else(|| anyhow!("account {} not found", token_account))?;
+
+    // A slot's bank hash is listed once the next bank is created, so poll until it shows up.
+    for _ in 0..SLOT_HASH_ATTEMPTS {
+        let data = client
+            .get_account_with_commitment(&slot_hashes::id(), CommitmentConfig::confirmed())
+            .context("failed to fetch the SlotHashes sysvar")?
+            .value
+            .ok_or_else(|| anyhow!("SlotHashes sysvar not found"))?
+            .data;
+        let slot_hashes: SlotHashes =
+            bincode::deserialize(&data).context("invalid SlotHashes sysvar")?;
+        if let Some(hash) = slot_hashes.get(&slot) {
+            return Ok((slot, hash.to_bytes(), account.data));
+        }
+        thread::sleep(Duration::from_millis(400));
+    }
+    Err(anyhow!("bank hash of slot {} not found", slot))
+}
+
+fn main() {
+    sp1_sdk::utils::setup_logger();
+    let args = Args::parse();
+
+    let client = RpcClient::new(args.rpc_url.clone());
+    let token_account = get_associated_token_address(&args.maker_wallet, &args.maker_mint);
+    let (slot, bank_hash, data) =
+        snapshot(&client, &token_account).expect("Failed to snapshot the token account");
+
+    // Check the snapshot before proving, the guest rejects the same snapshots.
+    let solvency = MakerSolvency::from_snapshot(
+        &args.maker_wallet,
+        &args.maker_mint,
+        args.maker_size,
+        slot,
+        bank_hash,
+        &data,
+    )
+    .unwrap_or_else(|issue| {
+        eprintln!("Error: {}", issue);
+        std::process::exit(1);
+    });
+    if !solvency.solvent {
+        eprintln!(
+            "Error: {} does not hold {} at slot {}",
+            token_account, args.maker_size, slot
+        );
+        std::process::exit(1);
+    }
+
+    let prover = OfferProver::new(&args.prover);
+    let (pk, vk) = prover.setup(ZKVM_ELF);
+    let stdin = solvency_stdin(
+        &args.maker_wallet,
+        &args.maker_mint,
+        args.maker_size,
+        slot,
+        &bank_hash,
+        data,
+    );
+    let proof = prover
+        .prove(&pk, stdin, ProofKind::Core)
+        .expect("Failed to generate proof");
+    prover.verify(&proof, &vk).expect("Failed to verify proof");
+
+    let proof = bincode::serialize(&proof).expect("Failed to serialize proof");
+    println!("Proven funded at slot {}", slot);
+    println!("Proof: {}", STANDARD.encode(proof));
+}
@@ -10,20 +10,27 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use clap::Parser;
 use solana_program::pubkey::Pubkey;
 use solana_zk_offers::zk_offers::PublicValuesStruct;
-use sp1_sdk::{HashableKey, ProverClient, SP1ProofWithPublicValues, SP1Stdin, SP1VerifyingKey};
+use sp1_sdk::{HashableKey, SP1ProofWithPublicValues, SP1Stdin, SP1VerifyingKey};
 use std::path::PathBuf;
 use std::{fs, io::stdin};
+use zk_offers_script::{
+    guest::offer_stdin,
+    prover::{OfferProver, ProofKind, ProverArgs},
+};
 ///
 ///  linkable format) file for the Succinct RISC-V zkVM.
 pub const ZKVM_ELF: &[u8] = include_bytes!("../../../elf/riscv32im-succinct-zkvm-elf");
 
 /// The arguments for the offer command.
-#[derive(Parser, Debug, Clone, BorshDeserialize, BorshSerialize)]
+#[derive(Parser, Debug, Clone)]
 #[clap()]
 pub struct ZKAskArgs {
     pub public_values: PublicValuesStruct,
     maker_wallet: Pubkey,
     maker_size: u64,
+
+    #[clap(flatten)]
+    prover: ProverArgs,
 }
 
 #[derive(Debug, Clone, BorshDeserialize, BorshSerialize, serde::Serialize)]
@@ -41,10 +48,10 @@ fn main() {
     let args: ZKAskArgs = ZKAskArgs::parse();
 
     // Setup the prover client.
-    let client = ProverClient::new();
+    let prover = OfferProver::new(&args.prover);
 
     // Setup the program.
-    let (pk, vk) = client.setup(ZKVM_ELF);
+    let (pk, vk) = prover.setup(ZKVM_ELF);
 
     // Serialize the public values into the SP1Stdin format.
     // let mut stdin: SP1Stdin = SP1Stdin::new();
@@ -56,13 +63,11 @@ fn main() {
 
     println!("ZK Offer for Mint: {:#?}", args.public_values);
 
-    let sp1_stdin: SP1Stdin = SP1Stdin::new(); // Wrap Stdin in SP1Stdin
+    let sp1_stdin: SP1Stdin = offer_stdin(&args.public_values);
 
     // Generate the proof.
-    let proof = client
-        .prove(&pk, sp1_stdin)
-        .plonk()
-        .run()
+    let proof = prover
+        .prove(&pk, sp1_stdin, ProofKind::Plonk)
         .expect("failed to generate proof");
 
     create_plonk_fixture(&proof, &vk);
@@ -73,7 +78,7 @@ fn create_plonk_fixture(proof: &SP1ProofWithPublicValues, vk: &SP1VerifyingKey)
     // Deserialize the public values.
     let bytes: &[u8] = proof.public_values.as_slice();
     let serialized_data = bytes.to_vec();
-    let data = PublicValuesStruct::try_from_slice(&serialized_data).unwrap();
+    let data = PublicValuesStruct::from_canonical_bytes(&serialized_data).unwrap();
 
     // Create the testing fixture so we can test things end-to-end.
     let fixture = SP1ZK {
@@ -5,6 +5,7 @@
 //! RUST_LOG=info cargo run --bin vkey --release
 //! ```
 
+use solana_zk_offers::{sealed_bid::format_vkey_hash, zk_offers::OFFER_VKEY_HASH};
 use sp1_sdk::{HashableKey, ProverClient};
 
 /// The ELF (executable and linkable format) file for the Succinct RISC-V zkVM.
@@ -24,4 +25,13 @@ fn main() {
 
     // Print the verification key.
     println!("Program Verification Key: {}", vk.bytes32());
+
+    // The on-chain program only accepts proofs of the guest pinned in `OFFER_VKEY_HASH`.
+    if vk.bytes32() != format_vkey_hash(&OFFER_VKEY_HASH) {
+        eprintln!(
+            "Error: OFFER_VKEY_HASH is {}, update it in lib/src/lib.rs",
+            format_vkey_hash(&OFFER_VKEY_HASH)
+        );
+        std::process::exit(1);
+    }
 }
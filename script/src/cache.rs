@@ -0,0 +1,110 @@
+//! Content-addressed cache for proving keys and proofs.
+//!
+//! Proving keys are stored under the SHA-256 of the ELF they were set up from, and proofs under
+//! the verification key hash plus a digest of the serialized `SP1Stdin`. Rebuilding
+//! `elf/riscv32im-succinct-zkvm-elf` changes both, and [`ProofCache::open`] prunes the entries of
+//! other ELFs. Only directories carrying the cache's own marker are pruned, so pointing the cache
+//! at a directory holding anything else leaves it alone.
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use sp1_sdk::{HashableKey, SP1ProofWithPublicValues, SP1ProvingKey, SP1Stdin, SP1VerifyingKey};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::prover::ProofKind;
+
+const KEYS_FILE: &str = "keys.bin";
+/// Written into the directory of each ELF, holding the ELF hash the directory is named after.
+const ENTRY_MARKER: &str = ".proof-cache-entry";
+
+pub struct ProofCache {
+    dir: PathBuf,
+}
+
+impl ProofCache {
+    /// Open the cache for `elf`, creating `root` if needed and removing the entries it created
+    /// for other ELFs.
+    pub fn open(root: &Path, elf: &[u8]) -> Result<Self> {
+        let elf_hash = hex::encode(Sha256::digest(elf));
+        fs::create_dir_all(root).context("failed to create proof cache directory")?;
+        for entry in fs::read_dir(root)? {
+            let entry = entry?;
+            if entry.file_name() != elf_hash.as_str() && is_cache_entry(&entry.path()) {
+                tracing::info!("Pruning stale proof cache entry {:?}", entry.file_name());
+                fs::remove_dir_all(entry.path())?;
+            }
+        }
+        let dir = root.join(&elf_hash);
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(ENTRY_MARKER), &elf_hash).context("failed to mark proof cache entry")?;
+        Ok(Self { dir })
+    }
+
+    pub fn load_keys(&self) -> Option<(SP1ProvingKey, SP1VerifyingKey)> {
+        let bytes = fs::read(self.dir.join(KEYS_FILE)).ok()?;
+        bincode::deserialize(&bytes).ok()
+    }
+
+    pub fn store_keys(&self, pk: &SP1ProvingKey, vk: &SP1VerifyingKey) -> Result<()> {
+        let bytes = bincode::serialize(&(pk, vk)).context("failed to serialize keys")?;
+        fs::write(self.dir.join(KEYS_FILE), bytes).context("failed to write cached keys")?;
+        Ok(())
+    }
+
+    pub fn load_proof(
+        &self,
+        vk: &SP1VerifyingKey,
+        stdin: &SP1Stdin,
+        kind: ProofKind,
+        mock: bool,
+    ) -> Result<Option<SP1ProofWithPublicValues>> {
+        let path = self.proof_path(vk, stdin, kind, mock)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(SP1ProofWithPublicValues::load(&path).ok())
+    }
+
+    pub fn store_proof(
+        &self,
+        vk: &SP1VerifyingKey,
+        stdin: &SP1Stdin,
+        kind: ProofKind,
+        mock: bool,
+        proof: &SP1ProofWithPublicValues,
+    ) -> Result<()> {
+        let path = self.proof_path(vk, stdin, kind, mock)?;
+        fs::create_dir_all(path.parent().expect("proof path has a parent"))?;
+        proof.save(&path).context("failed to write cached proof")?;
+        Ok(())
+    }
+
+    fn proof_path(
+        &self,
+        vk: &SP1VerifyingKey,
+        stdin: &SP1Stdin,
+        kind: ProofKind,
+        mock: bool,
+    ) -> Result<PathBuf> {
+        let stdin_digest = hex::encode(Sha256::digest(
+            bincode::serialize(stdin).context("failed to serialize stdin")?,
+        ));
+        let suffix = if mock { "mock" } else { "proof" };
+        Ok(self
+            .dir
+            .join(vk.bytes32())
+            .join(format!("{}-{:?}.{}", stdin_digest, kind, suffix).to_lowercase()))
+    }
+}
+
+/// Whether `path` is a directory the cache created for some ELF, named after the hash in its
+/// marker.
+fn is_cache_entry(path: &Path) -> bool {
+    match fs::read_to_string(path.join(ENTRY_MARKER)) {
+        Ok(elf_hash) => path.file_name() == Some(elf_hash.as_ref()),
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,156 @@
+//! Compute unit limits and priority fees for the transactions the scripts build.
+//!
+//! The limit is measured by simulating the transaction against an RPC endpoint, with a margin on
+//! top, unless one is given on the command line. The priority fee is either fixed or sampled from
+//! the recent prioritization fees paid on the accounts the transaction writes to.
+
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use solana_program::{instruction::Instruction, message::Message, pubkey::Pubkey};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_rpc_client_api::config::RpcSimulateTransactionConfig;
+use solana_sdk::transaction::Transaction;
+use solana_zk_offers::compute_budget::{
+    unit_limit_with_margin, with_compute_budget, writable_accounts, MAX_COMPUTE_UNIT_LIMIT,
+};
+use std::str::FromStr;
+
+/// How the priority fee of a transaction is chosen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PriorityFee {
+    /// No priority fee.
+    None,
+    /// A fixed price in micro-lamports per compute unit.
+    Fixed(u64),
+    /// The given percentile of recent prioritization fees on the written accounts.
+    Recent(u8),
+}
+
+impl FromStr for PriorityFee {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "none" => Ok(PriorityFee::None),
+            None if s == "recent" => Ok(PriorityFee::Recent(50)),
+            Some(("fixed", price)) => price
+                .parse()
+                .map(PriorityFee::Fixed)
+                .map_err(|e| format!("invalid priority fee price: {}", e)),
+            Some(("recent", percentile)) => match percentile.parse::<u8>() {
+                Ok(percentile) if percentile <= 100 => Ok(PriorityFee::Recent(percentile)),
+                _ => Err(format!("invalid percentile {}", percentile)),
+            },
+            _ => Err("expected none, fixed:<micro-lamports> or recent[:<percentile>]".to_string()),
+        }
+    }
+}
+
+/// Command-line arguments shared by every binary that builds transactions.
+#[derive(Clone, Debug, Args)]
+pub struct ComputeBudgetArgs {
+    /// Priority fee strategy, `none`, `fixed:<micro-lamports per CU>` or `recent[:<percentile>]`.
+    #[clap(long, env = "ZK_OFFERS_PRIORITY_FEE", default_value = "none")]
+    pub priority_fee: PriorityFee,
+
+    /// Compute unit limit to request instead of simulating the transaction.
+    #[clap(long)]
+    pub compute_unit_limit: Option<u32>,
+
+    /// Headroom added to the simulated compute units, in percent.
+    #[clap(long, default_value_t = 10)]
+    pub compute_unit_margin: u32,
+}
+
+/// Prepends compute budget instructions to transactions.
+pub struct ComputeBudgeter {
+    args: ComputeBudgetArgs,
+    client: Option<RpcClient>,
+}
+
+impl ComputeBudgeter {
+    /// Without an RPC endpoint only a fixed limit and price can be applied.
+    pub fn new(args: &ComputeBudgetArgs, rpc_url: Option<String>) -> Self {
+        Self {
+            args: args.clone(),
+            client: rpc_url.map(RpcClient::new),
+        }
+    }
+
+    /// Prepend the compute unit limit and price for a transaction paid by `payer`.
+    pub fn apply(&self, payer: &Pubkey, instructions: Vec<Instruction>) -> Result<Vec<Instruction>> {
+        let micro_lamports = self.unit_price(&instructions)?;
+        let unit_limit = match (self.args.compute_unit_limit, &self.client) {
+            (Some(limit), _) => Some(limit),
+            (None, Some(client)) => Some(self.simulate_unit_limit(client, payer, &instructions)?),
+            (None, None) => None,
+        };
+        Ok(with_compute_budget(instructions, unit_limit, micro_lamports))
+    }
+
+    fn unit_price(&self, instructions: &[Instruction]) -> Result<Option<u64>> {
+        match self.args.priority_fee {
+            PriorityFee::None => Ok(None),
+            PriorityFee::Fixed(price) => Ok(Some(price)),
+            PriorityFee::Recent(percentile) => {
+                let Some(client) = &self.client else {
+                    bail!("recent priority fees need an RPC endpoint");
+                };
+                let mut fees: Vec<u64> = client
+                    .get_recent_prioritization_fees(&writable_accounts(instructions))
+                    .context("failed to fetch recent prioritization fees")?
+                    .into_iter()
+                    .map(|fee| fee.prioritization_fee)
+                    .collect();
+                fees.sort_unstable();
+                Ok(percentile_of(&fees, percentile))
+            }
+        }
+    }
+
+    fn simulate_unit_limit(
+        &self,
+        client: &RpcClient,
+        payer: &Pubkey,
+        instructions: &[Instruction],
+    ) -> Result<u32> {
+        // Simulate with the highest limit so the measurement is not cut short.
+        let instructions =
+            with_compute_budget(instructions.to_vec(), Some(MAX_COMPUTE_UNIT_LIMIT), None);
+        let transaction = Transaction::new_unsigned(Message::new(&instructions, Some(payer)));
+        let simulation = client
+            .simulate_transaction_with_config(
+                &transaction,
+                RpcSimulateTransactionConfig {
+                    sig_verify: false,
+                    replace_recent_blockhash: true,
+                    ..RpcSimulateTransactionConfig::default()
+                },
+            )
+            .context("failed to simulate transaction")?
+            .value;
+        if let Some(err) = simulation.err {
+            bail!(
+                "simulation failed: {}\n{}",
+                err,
+                simulation.logs.unwrap_or_default().join("\n")
+            );
+        }
+        let units_consumed = simulation
+            .units_consumed
+            .context("simulation did not report compute units")?;
+        Ok(unit_limit_with_margin(
+            units_consumed,
+            self.args.compute_unit_margin,
+        ))
+    }
+}
+
+/// The `percentile` of sorted `fees`, `None` when there are no samples.
+pub fn percentile_of(fees: &[u64], percentile: u8) -> Option<u64> {
+    if fees.is_empty() {
+        return None;
+    }
+    let index = (fees.len() - 1) * percentile as usize / 100;
+    Some(fees[index])
+}
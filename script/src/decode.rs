@@ -0,0 +1,972 @@
+//! Decoding of `stateless-asks` instructions, for working out why a fill failed.
+//!
+//! Each account is labelled with the role the processor reads it in. Every account the
+//! instruction determines, the PDAs, ATAs and program ids, is recomputed from the others, so a
+//! wrong account shows up as a mismatch rather than as an opaque `InvalidAccountData`.
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use borsh::BorshDeserialize;
+use clap::ValueEnum;
+use solana_program::{pubkey::Pubkey, system_program, sysvar};
+use solana_sdk::transaction::VersionedTransaction;
+use solana_zk_offers::{
+    metadata,
+    offer_batch::compute_offer_batch_address,
+    quote::FeePayer,
+    sealed_bid::{
+        compute_sealed_auction_address, compute_sealed_bid_address, compute_sealed_vault,
+    },
+    zk_offers::{
+        compute_collection_offer_authority, compute_escrow_offer_address, compute_escrow_vault,
+        compute_offer_authority, compute_offer_router, compute_routed_offer_address, OfferStruct,
+        PlatformFee, PublicValuesStruct, CANONICAL_PUBLIC_VALUES_LEN,
+    },
+};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::native_mint;
+use stateless_asks::instruction::{StatelessOfferInstruction, TOKEN_AUTH_RULES_PROGRAM_ID};
+use std::fmt;
+
+/// How the input of the decode command is encoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Encoding {
+    /// Base58, unless the input contains characters only base64 uses.
+    Auto,
+    Base58,
+    Base64,
+}
+
+/// Decode a base58 or base64 string.
+pub fn decode_bytes(input: &str, encoding: Encoding) -> Result<Vec<u8>> {
+    let input = input.trim();
+    let is_base58 = match encoding {
+        Encoding::Auto => input.chars().all(is_base58_char),
+        Encoding::Base58 => true,
+        Encoding::Base64 => false,
+    };
+    if is_base58 {
+        bs58::decode(input).into_vec().context("invalid base58")
+    } else {
+        STANDARD.decode(input).context("invalid base64")
+    }
+}
+
+fn is_base58_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() && !matches!(c, '0' | 'O' | 'I' | 'l')
+}
+
+/// An account of a decoded instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodedAccount {
+    /// The role the processor reads the account in.
+    pub role: String,
+    /// `None` for accounts loaded from an address lookup table.
+    pub key: Option<Pubkey>,
+    /// The account the instruction data and the other accounts determine, if any.
+    pub expected: Option<Pubkey>,
+}
+
+impl DecodedAccount {
+    pub fn is_mismatch(&self) -> bool {
+        matches!((self.key, self.expected), (Some(key), Some(expected)) if key != expected)
+    }
+}
+
+/// What the instruction data decoded as.
+pub enum Payload {
+    Instruction(StatelessOfferInstruction),
+    /// The Borsh [`OfferStruct`] earlier clients sent as instruction data.
+    Offer(OfferStruct),
+}
+
+pub struct DecodedInstruction {
+    /// Position of the instruction in its transaction.
+    pub index: Option<usize>,
+    pub program_id: Option<Pubkey>,
+    pub payload: Payload,
+    pub accounts: Vec<DecodedAccount>,
+    /// Problems other than mismatched accounts, such as a wrong bump or account count.
+    pub notes: Vec<String>,
+}
+
+impl DecodedInstruction {
+    pub fn mismatches(&self) -> impl Iterator<Item = &DecodedAccount> {
+        self.accounts.iter().filter(|account| account.is_mismatch())
+    }
+
+    /// Whether nothing looks wrong with the instruction.
+    pub fn is_consistent(&self) -> bool {
+        self.notes.is_empty() && self.mismatches().next().is_none()
+    }
+
+    /// The key of the first account labelled `role`.
+    pub fn account(&self, role: &str) -> Option<Pubkey> {
+        self.accounts
+            .iter()
+            .find(|account| account.role == role)
+            .and_then(|account| account.key)
+    }
+}
+
+impl fmt::Display for DecodedInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.index {
+            Some(index) => write!(f, "instruction #{}: ", index)?,
+            None => write!(f, "instruction: ")?,
+        }
+        match &self.payload {
+            Payload::Instruction(instruction) => {
+                writeln!(f, "{:?}", instruction)?;
+                if let Some(public_values) = instruction.public_values() {
+                    writeln!(f, "  public values: {:?}", public_values)?;
+                }
+            }
+            Payload::Offer(offer) => {
+                writeln!(f, "OfferStruct")?;
+                writeln!(f, "  public values: {:?}", offer.public_values)?;
+                writeln!(f, "  bump seed: {}", offer.bump_seed())?;
+            }
+        }
+        if let Some(program_id) = &self.program_id {
+            writeln!(f, "  program: {}", program_id)?;
+        }
+        let width = self
+            .accounts
+            .iter()
+            .map(|account| account.role.len())
+            .max()
+            .unwrap_or(0);
+        for (index, account) in self.accounts.iter().enumerate() {
+            let key = account
+                .key
+                .map_or("<address lookup table>".to_string(), |key| key.to_string());
+            write!(f, "  #{:<3} {:<width$} {}", index, account.role, key)?;
+            match account.expected {
+                Some(expected) if account.is_mismatch() => {
+                    write!(f, "  MISMATCH, expected {}", expected)?
+                }
+                Some(_) if account.key.is_some() => write!(f, "  ok")?,
+                _ => {}
+            }
+            writeln!(f)?;
+        }
+        for note in &self.notes {
+            writeln!(f, "  ! {}", note)?;
+        }
+        Ok(())
+    }
+}
+
+/// Decode the stateless-asks instructions of a bincode serialized transaction.
+///
+/// Without `program_id`, every instruction whose data decodes is reported, and PDAs are derived
+/// from the program the instruction invokes.
+pub fn decode_transaction(
+    program_id: Option<&Pubkey>,
+    transaction: &[u8],
+) -> Result<Vec<DecodedInstruction>> {
+    let transaction: VersionedTransaction =
+        bincode::deserialize(transaction).context("not a serialized transaction")?;
+    let keys = transaction.message.static_account_keys();
+    let mut decoded = Vec::new();
+    for (index, instruction) in transaction.message.instructions().iter().enumerate() {
+        let Some(invoked) = keys.get(instruction.program_id_index as usize) else {
+            bail!("instruction #{} invokes a missing account", index);
+        };
+        if program_id.is_some_and(|program_id| program_id != invoked) {
+            continue;
+        }
+        let accounts: Vec<Option<Pubkey>> = instruction
+            .accounts
+            .iter()
+            .map(|&account| keys.get(account as usize).copied())
+            .collect();
+        match decode_instruction(Some(invoked), &instruction.data, &accounts) {
+            Ok(mut instruction) => {
+                instruction.index = Some(index);
+                decoded.push(instruction);
+            }
+            Err(err) if program_id.is_some() => {
+                return Err(err.context(format!("instruction #{}", index)))
+            }
+            // Some other program's instruction.
+            Err(_) => {}
+        }
+    }
+    Ok(decoded)
+}
+
+/// Decode instruction data and label `accounts`, which are `None` when unknown.
+///
+/// PDAs are only recomputed when `program_id` is given.
+pub fn decode_instruction(
+    program_id: Option<&Pubkey>,
+    data: &[u8],
+    accounts: &[Option<Pubkey>],
+) -> Result<DecodedInstruction> {
+    let instruction = match StatelessOfferInstruction::unpack(data) {
+        Ok(instruction) => instruction,
+        Err(err) => {
+            return match OfferStruct::try_from_slice(data) {
+                Ok(offer) => Ok(decode_offer_struct(program_id, offer)),
+                Err(_) => bail!("not a stateless-asks instruction: {}", err),
+            }
+        }
+    };
+    let mut labeller = Labeller::new(accounts);
+    match &instruction {
+        StatelessOfferInstruction::AcceptOffer {
+            metadata_bump_seed,
+            maker_size,
+            taker_size,
+            bump_seed,
+            platform_fee,
+            ..
+        } => label_accept_offer(
+            &mut labeller,
+            program_id,
+            &AcceptTerms {
+                metadata_bump_seed: *metadata_bump_seed,
+                maker_size: *maker_size,
+                taker_size: *taker_size,
+                bump_seed: *bump_seed,
+                platform_fee: *platform_fee,
+                collection: None,
+                programmable: false,
+            },
+        ),
+        StatelessOfferInstruction::AcceptProgrammableOffer {
+            metadata_bump_seed,
+            maker_size,
+            taker_size,
+            bump_seed,
+            platform_fee,
+            ..
+        } => label_accept_offer(
+            &mut labeller,
+            program_id,
+            &AcceptTerms {
+                metadata_bump_seed: *metadata_bump_seed,
+                maker_size: *maker_size,
+                taker_size: *taker_size,
+                bump_seed: *bump_seed,
+                platform_fee: *platform_fee,
+                collection: None,
+                programmable: true,
+            },
+        ),
+        StatelessOfferInstruction::AcceptCollectionOffer {
+            collection,
+            verified_creators_only,
+            metadata_bump_seed,
+            maker_size,
+            taker_size,
+            bump_seed,
+            platform_fee,
+            ..
+        } => label_accept_offer(
+            &mut labeller,
+            program_id,
+            &AcceptTerms {
+                metadata_bump_seed: Some(*metadata_bump_seed),
+                maker_size: *maker_size,
+                taker_size: *taker_size,
+                bump_seed: *bump_seed,
+                platform_fee: *platform_fee,
+                collection: Some((*collection, *verified_creators_only)),
+                programmable: false,
+            },
+        ),
+        StatelessOfferInstruction::InitEscrowOffer {
+            nonce,
+            bump_seed,
+            public_values,
+        } => {
+            let public_values = canonical_public_values(public_values)?;
+            let maker_wallet = labeller.key(0);
+            let maker_mint = Some(public_values.maker_mint);
+            let escrow_offer = program_id
+                .zip(maker_wallet)
+                .map(|(program_id, maker_wallet)| {
+                    compute_escrow_offer_address(program_id, &maker_wallet, *nonce)
+                });
+            labeller.check_bump("escrow offer", escrow_offer, *bump_seed);
+            labeller.label("maker_wallet", None);
+            labeller.label("maker_src_account", ata(maker_wallet, maker_mint));
+            let escrow_offer = labeller.label("escrow_offer", escrow_offer.map(|(key, _)| key));
+            labeller.label(
+                "vault",
+                escrow_offer.map(|escrow_offer| {
+                    compute_escrow_vault(&escrow_offer, &public_values.maker_mint)
+                }),
+            );
+            labeller.label("maker_mint", maker_mint);
+            labeller.label("token_program", Some(spl_token::id()));
+            labeller.label(
+                "associated_token_program",
+                Some(spl_associated_token_account::id()),
+            );
+            labeller.label("system_program", Some(system_program::id()));
+        }
+        StatelessOfferInstruction::AcceptEscrowOffer {
+            metadata_bump_seed, ..
+        } => {
+            let maker_mint = labeller.key(7);
+            let taker_mint = labeller.key(8);
+            let is_native = taker_mint == Some(native_mint::id());
+            labeller.label("maker_wallet", None);
+            labeller.label("taker_wallet", None);
+            let escrow_offer = labeller.label("escrow_offer", None);
+            labeller.label(
+                "vault",
+                escrow_offer
+                    .zip(maker_mint)
+                    .map(|(escrow_offer, maker_mint)| {
+                        compute_escrow_vault(&escrow_offer, &maker_mint)
+                    }),
+            );
+            label_counterparty(&mut labeller, is_native);
+            labeller.label("token_program", Some(spl_token::id()));
+            if is_native {
+                labeller.label("system_program", Some(system_program::id()));
+            }
+            label_fees(
+                &mut labeller,
+                *metadata_bump_seed,
+                false,
+                FillFee::InOfferAccount,
+                maker_mint,
+                taker_mint,
+            );
+        }
+        StatelessOfferInstruction::CloseEscrowOffer => {
+            // The mint is only recorded in the escrow offer account.
+            labeller.label("maker_wallet", None);
+            labeller.label("escrow_offer", None);
+            labeller.label("vault", None);
+            labeller.label("maker_src_account", None);
+            labeller.label("token_program", Some(spl_token::id()));
+        }
+        StatelessOfferInstruction::InitRoutedOffer {
+            nonce,
+            bump_seed,
+            public_values,
+        } => {
+            canonical_public_values(public_values)?;
+            let routed_offer = program_id
+                .zip(labeller.key(0))
+                .map(|(program_id, maker_wallet)| {
+                    compute_routed_offer_address(program_id, &maker_wallet, *nonce)
+                });
+            labeller.check_bump("routed offer", routed_offer, *bump_seed);
+            labeller.label("maker_wallet", None);
+            labeller.label("routed_offer", routed_offer.map(|(key, _)| key));
+            labeller.label("system_program", Some(system_program::id()));
+        }
+        StatelessOfferInstruction::AcceptRoutedOffer {
+            metadata_bump_seed,
+            router_bump_seed,
+            ..
+        } => {
+            let maker_wallet = labeller.key(0);
+            let maker_mint = labeller.key(7);
+            let taker_mint = labeller.key(8);
+            let is_native = taker_mint == Some(native_mint::id());
+            let router = program_id.map(compute_offer_router);
+            labeller.check_bump("offer router", router, *router_bump_seed);
+            labeller.label("maker_wallet", None);
+            labeller.label("taker_wallet", None);
+            labeller.label("routed_offer", None);
+            labeller.label("maker_src_account", ata(maker_wallet, maker_mint));
+            label_counterparty(&mut labeller, is_native);
+            labeller.label("offer_router", router.map(|(key, _)| key));
+            labeller.label("token_program", Some(spl_token::id()));
+            if is_native {
+                labeller.label("system_program", Some(system_program::id()));
+            }
+            label_fees(
+                &mut labeller,
+                *metadata_bump_seed,
+                false,
+                FillFee::InOfferAccount,
+                maker_mint,
+                taker_mint,
+            );
+        }
+        StatelessOfferInstruction::CloseRoutedOffer => {
+            labeller.label("maker_wallet", None);
+            labeller.label("routed_offer", None);
+        }
+        StatelessOfferInstruction::InitSealedAuction {
+            nonce,
+            bump_seed,
+            public_values,
+            ..
+        } => {
+            let public_values = canonical_public_values(public_values)?;
+            let maker_wallet = labeller.key(0);
+            let maker_mint = Some(public_values.maker_mint);
+            let auction = program_id
+                .zip(maker_wallet)
+                .map(|(program_id, maker_wallet)| {
+                    compute_sealed_auction_address(program_id, &maker_wallet, *nonce)
+                });
+            labeller.check_bump("sealed auction", auction, *bump_seed);
+            labeller.label("maker_wallet", None);
+            labeller.label("maker_src_account", ata(maker_wallet, maker_mint));
+            let auction = labeller.label("auction", auction.map(|(key, _)| key));
+            labeller.label(
+                "vault",
+                auction.map(|auction| compute_sealed_vault(&auction, &public_values.maker_mint)),
+            );
+            labeller.label("maker_mint", maker_mint);
+            labeller.label("token_program", Some(spl_token::id()));
+            labeller.label(
+                "associated_token_program",
+                Some(spl_associated_token_account::id()),
+            );
+            labeller.label("system_program", Some(system_program::id()));
+        }
+        StatelessOfferInstruction::CommitSealedBid { bump_seed, .. } => {
+            let bidder = labeller.key(0);
+            let taker_mint = labeller.key(5);
+            let bid = sealed_bid_address(program_id, labeller.key(1), bidder);
+            labeller.check_bump("sealed bid", bid, *bump_seed);
+            labeller.label("bidder", None);
+            labeller.label("auction", None);
+            let bid = labeller.label("bid", bid.map(|(key, _)| key));
+            labeller.label("bid_vault", ata(bid, taker_mint));
+            labeller.label("bidder_src_account", ata(bidder, taker_mint));
+            labeller.label("taker_mint", None);
+            labeller.label("token_program", Some(spl_token::id()));
+            labeller.label(
+                "associated_token_program",
+                Some(spl_associated_token_account::id()),
+            );
+            labeller.label("system_program", Some(system_program::id()));
+        }
+        StatelessOfferInstruction::RevealSealedBid { .. } => {
+            let bidder = labeller.key(0);
+            let taker_mint = labeller.key(5);
+            let bid = sealed_bid_address(program_id, labeller.key(1), bidder);
+            labeller.label("bidder", None);
+            labeller.label("auction", None);
+            let bid = labeller.label("bid", bid.map(|(key, _)| key));
+            labeller.label("bid_vault", ata(bid, taker_mint));
+            labeller.label("bidder_src_account", ata(bidder, taker_mint));
+            labeller.label("taker_mint", None);
+            labeller.label("token_program", Some(spl_token::id()));
+        }
+        StatelessOfferInstruction::SettleSealedAuction { metadata_bump_seed } => {
+            let maker_wallet = labeller.key(0);
+            let auction = labeller.key(1);
+            let maker_mint = labeller.key(5);
+            let taker_mint = labeller.key(6);
+            labeller.label("maker_wallet", None);
+            labeller.label("auction", None);
+            labeller.label("vault", ata(auction, maker_mint));
+            labeller.label("maker_src_account", ata(maker_wallet, maker_mint));
+            labeller.label("maker_dst_account", ata(maker_wallet, taker_mint));
+            labeller.label("maker_mint", None);
+            labeller.label("taker_mint", None);
+            labeller.label("token_program", Some(spl_token::id()));
+            // The best bid follows when one was revealed, which only the auction account records.
+            if labeller.remaining() > 0 {
+                let bidder = labeller.key(8);
+                let bid = sealed_bid_address(program_id, auction, bidder);
+                labeller.label("bidder", None);
+                let bid = labeller.label("bid", bid.map(|(key, _)| key));
+                labeller.label("bid_vault", ata(bid, taker_mint));
+                labeller.label("bidder_dst_account", ata(bidder, maker_mint));
+                label_fees(
+                    &mut labeller,
+                    *metadata_bump_seed,
+                    false,
+                    FillFee::InOfferAccount,
+                    maker_mint,
+                    taker_mint,
+                );
+            }
+        }
+        StatelessOfferInstruction::CloseSealedBid => {
+            let bidder = labeller.key(0);
+            let taker_mint = labeller.key(6);
+            let bid = sealed_bid_address(program_id, labeller.key(1), bidder);
+            labeller.label("bidder", None);
+            labeller.label("auction", None);
+            let bid = labeller.label("bid", bid.map(|(key, _)| key));
+            labeller.label("bid_vault", ata(bid, taker_mint));
+            labeller.label("bidder_src_account", ata(bidder, taker_mint));
+            // The maker is only recorded in the bid account.
+            labeller.label("maker_dst_account", None);
+            labeller.label("taker_mint", None);
+            labeller.label("token_program", Some(spl_token::id()));
+        }
+        StatelessOfferInstruction::PostOfferBatch {
+            bump_seed,
+            vkey_hash,
+            root,
+            ..
+        } => {
+            let offer_batch = program_id
+                .map(|program_id| compute_offer_batch_address(program_id, vkey_hash, root));
+            labeller.check_bump("offer batch", offer_batch, *bump_seed);
+            labeller.label("poster", None);
+            labeller.label("offer_batch", offer_batch.map(|(key, _)| key));
+            labeller.label("system_program", Some(system_program::id()));
+        }
+        StatelessOfferInstruction::AcceptBatchedOffer {
+            metadata_bump_seed,
+            bump_seed,
+            public_values,
+            ..
+        } => {
+            let public_values = canonical_public_values(public_values)?;
+            // The root is only recorded in the batch account, so it cannot be recomputed.
+            labeller.label_prefix("offer_batch", None);
+            label_accept_offer(
+                &mut labeller,
+                program_id,
+                &AcceptTerms {
+                    metadata_bump_seed: *metadata_bump_seed,
+                    maker_size: public_values.maker_size,
+                    taker_size: public_values.taker_size.unwrap_or_default(),
+                    bump_seed: *bump_seed,
+                    platform_fee: public_values.platform_fee,
+                    collection: None,
+                    programmable: false,
+                },
+            );
+        }
+        StatelessOfferInstruction::CloseOfferBatch => {
+            labeller.label("poster", None);
+            labeller.label("offer_batch", None);
+        }
+    }
+    labeller.finish();
+    Ok(DecodedInstruction {
+        index: None,
+        program_id: program_id.copied(),
+        payload: Payload::Instruction(instruction),
+        accounts: labeller.accounts,
+        notes: labeller.notes,
+    })
+}
+
+/// Labels accounts in the order a processor reads them.
+struct Labeller<'a> {
+    keys: &'a [Option<Pubkey>],
+    /// Accounts before this one were read ahead of a nested instruction's accounts, see
+    /// [`Labeller::label_prefix`].
+    base: usize,
+    next: usize,
+    accounts: Vec<DecodedAccount>,
+    notes: Vec<String>,
+}
+
+impl<'a> Labeller<'a> {
+    fn new(keys: &'a [Option<Pubkey>]) -> Self {
+        Self {
+            keys,
+            base: 0,
+            next: 0,
+            accounts: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    /// The account at `index`, if present and known.
+    fn key(&self, index: usize) -> Option<Pubkey> {
+        self.keys.get(self.base + index).copied().flatten()
+    }
+
+    /// The account the next [`Labeller::label`] call labels.
+    fn peek(&self) -> Option<Pubkey> {
+        self.key(self.next)
+    }
+
+    fn remaining(&self) -> usize {
+        self.keys.len().saturating_sub(self.base + self.next)
+    }
+
+    /// Label the next account, returning its key.
+    fn label(&mut self, role: impl Into<String>, expected: Option<Pubkey>) -> Option<Pubkey> {
+        let role = role.into();
+        let index = self.base + self.next;
+        self.next += 1;
+        match self.keys.get(index) {
+            Some(&key) => {
+                self.accounts.push(DecodedAccount {
+                    role,
+                    key,
+                    expected,
+                });
+                key
+            }
+            None => {
+                self.note(format!("missing account #{} ({})", index, role));
+                None
+            }
+        }
+    }
+
+    /// Label the next account, then index the following accounts from zero, for instructions
+    /// whose accounts are those of another instruction behind one of their own.
+    fn label_prefix(&mut self, role: &str, expected: Option<Pubkey>) -> Option<Pubkey> {
+        let key = self.label(role, expected);
+        self.base += self.next;
+        self.next = 0;
+        key
+    }
+
+    fn note(&mut self, note: String) {
+        self.notes.push(note);
+    }
+
+    fn check_bump(&mut self, name: &str, address: Option<(Pubkey, u8)>, bump_seed: u8) {
+        if let Some((_, expected)) = address {
+            if expected != bump_seed {
+                self.note(format!(
+                    "{} bump seed is {}, expected {}",
+                    name, bump_seed, expected
+                ));
+            }
+        }
+    }
+
+    /// Label whatever the processor does not read.
+    fn finish(&mut self) {
+        let extra = self.remaining();
+        for _ in 0..extra {
+            self.label("unused", None);
+        }
+        if extra > 0 {
+            self.note(format!("{} accounts the processor does not read", extra));
+        }
+    }
+}
+
+/// The bid account of `bidder` in `auction`.
+fn sealed_bid_address(
+    program_id: Option<&Pubkey>,
+    auction: Option<Pubkey>,
+    bidder: Option<Pubkey>,
+) -> Option<(Pubkey, u8)> {
+    match (program_id, auction, bidder) {
+        (Some(program_id), Some(auction), Some(bidder)) => {
+            Some(compute_sealed_bid_address(program_id, &auction, &bidder))
+        }
+        _ => None,
+    }
+}
+
+/// Decode the canonical public values an instruction carries.
+fn canonical_public_values(
+    bytes: &[u8; CANONICAL_PUBLIC_VALUES_LEN],
+) -> Result<PublicValuesStruct> {
+    PublicValuesStruct::from_canonical_bytes(bytes)
+        .context("public values are not canonically encoded")
+}
+
+fn ata(wallet: Option<Pubkey>, mint: Option<Pubkey>) -> Option<Pubkey> {
+    wallet
+        .zip(mint)
+        .map(|(wallet, mint)| get_associated_token_address(&wallet, &mint))
+}
+
+/// The terms of an accept instruction that decide its accounts.
+struct AcceptTerms {
+    metadata_bump_seed: Option<u8>,
+    maker_size: u64,
+    taker_size: u64,
+    bump_seed: u8,
+    platform_fee: Option<PlatformFee>,
+    /// The collection and whether only verified creators are paid.
+    collection: Option<(Pubkey, bool)>,
+    programmable: bool,
+}
+
+/// The accounts of `process_accept_offer`.
+fn label_accept_offer(labeller: &mut Labeller, program_id: Option<&Pubkey>, terms: &AcceptTerms) {
+    let maker_wallet = labeller.key(0);
+    let maker_src_account = labeller.key(2);
+    let taker_dst_account = labeller.key(5);
+    let maker_mint = labeller.key(6);
+    let taker_mint = labeller.key(7);
+    let is_native = taker_mint == Some(native_mint::id());
+    let authority = match (program_id, maker_wallet, maker_mint, terms.collection) {
+        (Some(program_id), Some(maker_wallet), Some(maker_mint), Some((collection, verified))) => {
+            Some(compute_collection_offer_authority(
+                program_id,
+                &maker_wallet,
+                &maker_mint,
+                &collection,
+                terms.maker_size,
+                terms.taker_size,
+                verified,
+                terms.platform_fee.as_ref(),
+            ))
+        }
+        (Some(program_id), Some(maker_wallet), Some(maker_mint), None) => {
+            taker_mint.map(|taker_mint| {
+                compute_offer_authority(
+                    program_id,
+                    &maker_wallet,
+                    &maker_mint,
+                    &taker_mint,
+                    terms.maker_size,
+                    terms.taker_size,
+                    terms.platform_fee.as_ref(),
+                )
+            })
+        }
+        _ => None,
+    };
+    labeller.check_bump("transfer authority", authority, terms.bump_seed);
+    labeller.label("maker_wallet", None);
+    labeller.label("taker_wallet", None);
+    labeller.label("maker_src_account", ata(maker_wallet, maker_mint));
+    label_counterparty(labeller, is_native);
+    labeller.label("transfer_authority", authority.map(|(key, _)| key));
+    labeller.label("token_program", Some(spl_token::id()));
+    if is_native {
+        labeller.label("system_program", Some(system_program::id()));
+    }
+    if terms.programmable {
+        label_programmable(labeller, maker_mint, maker_src_account, taker_dst_account);
+    }
+    label_fees(
+        labeller,
+        terms.metadata_bump_seed,
+        terms.collection.is_some(),
+        FillFee::Committed(terms.platform_fee),
+        maker_mint,
+        taker_mint,
+    );
+}
+
+/// Label the destination and source accounts of both legs and the two mints, which follow the
+/// maker's source in every fill.
+fn label_counterparty(labeller: &mut Labeller, is_native: bool) {
+    let maker_wallet = labeller.key(0);
+    let taker_wallet = labeller.key(1);
+    let maker_mint = labeller.key(labeller.next + 3);
+    let taker_mint = labeller.key(labeller.next + 4);
+    let (maker_dst_account, taker_src_account) = if is_native {
+        (maker_wallet, taker_wallet)
+    } else {
+        (ata(maker_wallet, taker_mint), ata(taker_wallet, taker_mint))
+    };
+    labeller.label("maker_dst_account", maker_dst_account);
+    labeller.label("taker_src_account", taker_src_account);
+    labeller.label("taker_dst_account", ata(taker_wallet, maker_mint));
+    labeller.label("maker_mint", None);
+    labeller.label("taker_mint", None);
+}
+
+/// The Token Metadata accounts moving a programmable NFT.
+fn label_programmable(
+    labeller: &mut Labeller,
+    maker_mint: Option<Pubkey>,
+    maker_src_account: Option<Pubkey>,
+    taker_dst_account: Option<Pubkey>,
+) {
+    let token_metadata_program = metadata::id();
+    let token_record = |token_account: Option<Pubkey>| {
+        maker_mint.zip(token_account).map(|(mint, token_account)| {
+            token_metadata_pda(&[mint.as_ref(), b"token_record", token_account.as_ref()])
+        })
+    };
+    labeller.label("token_metadata_program", Some(token_metadata_program));
+    labeller.label(
+        "nft_metadata",
+        maker_mint.map(|mint| metadata::find_metadata_address(&mint).0),
+    );
+    labeller.label(
+        "edition",
+        maker_mint.map(|mint| token_metadata_pda(&[mint.as_ref(), b"edition"])),
+    );
+    labeller.label("owner_token_record", token_record(maker_src_account));
+    labeller.label("destination_token_record", token_record(taker_dst_account));
+    labeller.label("transfer_system_program", Some(system_program::id()));
+    labeller.label("sysvar_instructions", Some(sysvar::instructions::id()));
+    labeller.label(
+        "associated_token_program",
+        Some(spl_associated_token_account::id()),
+    );
+    // Token Metadata takes its own program id in place of an absent rule set.
+    let has_rule_set = labeller.peek() != Some(token_metadata_program);
+    let (rules_program, rules) = if has_rule_set {
+        (TOKEN_AUTH_RULES_PROGRAM_ID, None)
+    } else {
+        (token_metadata_program, Some(token_metadata_program))
+    };
+    labeller.label("authorization_rules_program", Some(rules_program));
+    labeller.label("authorization_rules", rules);
+}
+
+/// A Token Metadata PDA, whose seeds all start with the metadata prefix and program id.
+fn token_metadata_pda(seeds: &[&[u8]]) -> Pubkey {
+    let program_id = metadata::id();
+    let mut prefixed: Vec<&[u8]> = vec![b"metadata", program_id.as_ref()];
+    prefixed.extend_from_slice(seeds);
+    Pubkey::find_program_address(&prefixed, &program_id).0
+}
+
+/// The platform fee of a fill.
+enum FillFee {
+    Committed(Option<PlatformFee>),
+    /// Escrowed and routed offers keep the fee in their offer account.
+    InOfferAccount,
+}
+
+/// The metadata, creator and platform fee accounts `pay_fees` reads.
+fn label_fees(
+    labeller: &mut Labeller,
+    metadata_bump_seed: Option<u8>,
+    is_collection: bool,
+    fee: FillFee,
+    maker_mint: Option<Pubkey>,
+    taker_mint: Option<Pubkey>,
+) {
+    let is_native = taker_mint == Some(native_mint::id());
+    let mut fee_payer = FeePayer::Taker;
+    if let Some(bump_seed) = metadata_bump_seed {
+        let metadata_of = |mint: Option<Pubkey>| {
+            mint.and_then(|mint| {
+                Pubkey::create_program_address(
+                    &[
+                        b"metadata",
+                        metadata::id().as_ref(),
+                        mint.as_ref(),
+                        &[bump_seed],
+                    ],
+                    &metadata::id(),
+                )
+                .ok()
+            })
+        };
+        let maker_metadata = metadata_of(maker_mint);
+        let taker_metadata = metadata_of(taker_mint);
+        let metadata = labeller.peek();
+        if metadata.is_some() && metadata == taker_metadata {
+            fee_payer = FeePayer::Maker;
+        } else if metadata.is_some() && metadata != maker_metadata {
+            labeller.note(format!(
+                "the metadata account is not the metadata of either mint with bump seed {}",
+                bump_seed
+            ));
+        }
+        let expected = if is_collection || fee_payer == FeePayer::Maker {
+            taker_metadata
+        } else {
+            maker_metadata
+        };
+        labeller.label("metadata", expected);
+        let fee_mint = match fee_payer {
+            FeePayer::Taker => taker_mint,
+            FeePayer::Maker => maker_mint,
+        };
+        // Native creators are paid in their wallet, SPL creators in the ATA following it.
+        let accounts_per_creator = if is_native { 1 } else { 2 };
+        let platform_fee_accounts = match &fee {
+            FillFee::Committed(platform_fee) => platform_fee.is_some() as usize,
+            FillFee::InOfferAccount => labeller.remaining() % accounts_per_creator,
+        };
+        let creators =
+            labeller.remaining().saturating_sub(platform_fee_accounts) / accounts_per_creator;
+        for creator in 0..creators {
+            // Native creators and a native platform fee are all plain wallets, so the last one
+            // cannot be told apart without the fee recorded in the offer account.
+            let role = match fee {
+                FillFee::InOfferAccount if is_native && creator + 1 == creators => {
+                    format!("creator {} or platform_fee_account", creator)
+                }
+                _ => format!("creator {}", creator),
+            };
+            let wallet = labeller.label(role, None);
+            if !is_native {
+                labeller.label(
+                    format!("creator {} token account", creator),
+                    ata(wallet, fee_mint),
+                );
+            }
+        }
+    }
+    match fee {
+        FillFee::Committed(Some(platform_fee)) => {
+            let recipient = Some(platform_fee.recipient);
+            let expected = match fee_payer {
+                FeePayer::Maker => ata(recipient, maker_mint),
+                FeePayer::Taker if is_native => recipient,
+                FeePayer::Taker => ata(recipient, taker_mint),
+            };
+            labeller.label("platform_fee_account", expected);
+        }
+        FillFee::Committed(None) => {}
+        FillFee::InOfferAccount => {
+            if labeller.remaining() == 1 {
+                labeller.label("platform_fee_account", None);
+            }
+        }
+    }
+}
+
+fn decode_offer_struct(program_id: Option<&Pubkey>, offer: OfferStruct) -> DecodedInstruction {
+    // The instruction accounts are copied from the blob, so check the blob itself.
+    let keys = offer.accounts().map(|(_, key)| Some(key));
+    let mut labeller = Labeller::new(&keys);
+    labeller.note(
+        "the data is an OfferStruct, which the program rejects; fills are built with the \
+         stateless_asks::instruction builders"
+            .to_string(),
+    );
+    label_offer_struct(&mut labeller, program_id, &offer);
+    DecodedInstruction {
+        index: None,
+        program_id: program_id.copied(),
+        accounts: labeller.accounts,
+        notes: labeller.notes,
+        payload: Payload::Offer(offer),
+    }
+}
+
+/// The accounts an [`OfferStruct`] advertises, checked against its public values.
+fn label_offer_struct(labeller: &mut Labeller, program_id: Option<&Pubkey>, offer: &OfferStruct) {
+    let public_values = &offer.public_values;
+    let maker_wallet = labeller.key(0);
+    let taker_wallet = labeller.key(1);
+    let maker_mint = Some(public_values.maker_mint);
+    let taker_mint = public_values
+        .taker_mint
+        .or_else(|| public_values.is_native.then(native_mint::id));
+    if let (Some(program_id), Some(maker_wallet), Some(taker_mint), Some(taker_size)) = (
+        program_id,
+        maker_wallet,
+        taker_mint,
+        public_values.taker_size,
+    ) {
+        let authority = compute_offer_authority(
+            program_id,
+            &maker_wallet,
+            &public_values.maker_mint,
+            &taker_mint,
+            public_values.maker_size,
+            taker_size,
+            public_values.platform_fee.as_ref(),
+        );
+        labeller.check_bump("transfer authority", Some(authority), offer.bump_seed());
+    }
+    let (maker_dst_account, taker_src_account) = if public_values.is_native {
+        (maker_wallet, taker_wallet)
+    } else {
+        (ata(maker_wallet, taker_mint), ata(taker_wallet, taker_mint))
+    };
+    labeller.label("maker_wallet", None);
+    labeller.label("taker_wallet", None);
+    labeller.label("maker_src_account", ata(maker_wallet, maker_mint));
+    labeller.label("maker_dst_account", maker_dst_account);
+    labeller.label("taker_src_account", taker_src_account);
+    labeller.label("taker_dst_account", ata(taker_wallet, maker_mint));
+}
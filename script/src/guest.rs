@@ -0,0 +1,99 @@
+//! Inputs read by the offer program running in the zkVM.
+
+use anyhow::{bail, Result};
+use solana_program::pubkey::Pubkey;
+use solana_zk_offers::zk_offers::{GuestMode, PublicValuesStruct};
+use sp1_sdk::{HashableKey, SP1Proof, SP1ProofWithPublicValues, SP1Stdin, SP1VerifyingKey};
+
+/// Write the offer inputs in the order the guest reads them: the maker mint, the maker size and
+/// the platform fee basis points, followed by the fee recipient when the fee is non-zero.
+pub fn offer_stdin(public_values: &PublicValuesStruct) -> SP1Stdin {
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&GuestMode::Offer);
+    stdin.write(&public_values.maker_mint.to_string());
+    stdin.write(&public_values.maker_size);
+    match &public_values.platform_fee {
+        Some(platform_fee) if platform_fee.basis_points > 0 => {
+            stdin.write(&platform_fee.basis_points);
+            stdin.write(&platform_fee.recipient.to_string());
+        }
+        _ => stdin.write(&0u16),
+    }
+    stdin
+}
+
+/// Write the inputs opening a sealed bid: the auction, the bidder, the canonically encoded auction
+/// terms, the amount and the salt the bid was committed with.
+///
+/// The salt stays private to the prover, only the commitment it produces is made public.
+pub fn sealed_bid_reveal_stdin(
+    auction: &Pubkey,
+    bidder: &Pubkey,
+    public_values: &PublicValuesStruct,
+    amount: u64,
+    salt: &[u8; 32],
+) -> SP1Stdin {
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&GuestMode::SealedBidReveal);
+    stdin.write(&auction.to_string());
+    stdin.write(&bidder.to_string());
+    stdin.write_vec(public_values.to_canonical_bytes().to_vec());
+    stdin.write(&amount);
+    stdin.write(salt);
+    stdin
+}
+
+/// Write the inputs of a solvency proof: the maker wallet, mint and size, the slot and bank hash
+/// of the snapshot and the raw data of the maker's token account at that slot.
+pub fn solvency_stdin(
+    maker_wallet: &Pubkey,
+    maker_mint: &Pubkey,
+    maker_size: u64,
+    slot: u64,
+    bank_hash: &[u8; 32],
+    token_account_data: Vec<u8>,
+) -> SP1Stdin {
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&GuestMode::Solvency);
+    stdin.write(&maker_wallet.to_string());
+    stdin.write(&maker_mint.to_string());
+    stdin.write(&maker_size);
+    stdin.write(&slot);
+    stdin.write(bank_hash);
+    stdin.write_vec(token_account_data);
+    stdin
+}
+
+/// Write the inputs of a batch proof: the Borsh encoded terms of every offer in the batch, in the
+/// order of their leaves.
+pub fn offer_batch_stdin(offers: &[PublicValuesStruct]) -> SP1Stdin {
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&GuestMode::OfferBatch);
+    stdin.write_vec(borsh::to_vec(offers).expect("Failed to serialize offers"));
+    stdin
+}
+
+/// Write the inputs of an aggregation: the verification key hashes and public values of the
+/// proofs, followed by the proofs themselves, which the guest verifies in the same order.
+///
+/// Only compressed proofs can be verified inside the zkVM, any other kind is rejected.
+pub fn aggregation_stdin(
+    proofs: &[(SP1ProofWithPublicValues, SP1VerifyingKey)],
+) -> Result<SP1Stdin> {
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&GuestMode::Aggregate);
+    let vkeys: Vec<[u32; 8]> = proofs.iter().map(|(_, vk)| vk.hash_u32()).collect();
+    stdin.write(&vkeys);
+    let public_values: Vec<Vec<u8>> = proofs
+        .iter()
+        .map(|(proof, _)| proof.public_values.to_vec())
+        .collect();
+    stdin.write(&public_values);
+    for (index, (proof, vk)) in proofs.iter().enumerate() {
+        let SP1Proof::Compressed(compressed) = &proof.proof else {
+            bail!("proof {} is not a compressed proof", index);
+        };
+        stdin.write_proof(compressed.clone(), vk.vk.clone());
+    }
+    Ok(stdin)
+}
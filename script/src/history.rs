@@ -0,0 +1,493 @@
+//! Offer history reconstructed from `getTransaction` responses, for the offline indexer.
+//!
+//! Fills are read from the [`FillEvent`]s the program logs or, in transactions from before fills
+//! logged events, from the decoded accept instructions. Cancellations are SPL `Revoke`s and closed
+//! escrowed or routed offers. Delegations are SPL approvals to program derived addresses, which
+//! every offer authority and the offer router are. Token accounts are resolved to their mint with
+//! the token balances of the transaction, so no account has to be fetched.
+//!
+//! Approvals and revokes are everywhere on chain, so they are only offer records in transactions
+//! that invoke the program, or when they approve the offer router. The others are kept aside
+//! until [`match_offer_approvals`] ties them to a delegate the program fills through.
+
+use anyhow::{bail, Context, Result};
+use solana_program::pubkey::Pubkey;
+use solana_sdk::instruction::CompiledInstruction;
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiInnerInstructions,
+    UiInstruction, UiLoadedAddresses, UiMessage, UiTransaction, UiTransactionStatusMeta,
+    UiTransactionTokenBalance,
+};
+use solana_zk_offers::{
+    events::{parse_fill_events, FeePaid, FillEvent},
+    zk_offers::compute_offer_router,
+};
+use spl_token::instruction::TokenInstruction;
+use stateless_asks::instruction::StatelessOfferInstruction;
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+use crate::decode::{decode_instruction, DecodedInstruction, Payload};
+
+/// A filled offer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fill {
+    /// The transfer authority of a stateless offer, or the escrow or routed offer account.
+    pub offer: Pubkey,
+    pub maker_wallet: Pubkey,
+    pub taker_wallet: Pubkey,
+    pub maker_mint: Pubkey,
+    pub taker_mint: Pubkey,
+    /// Only recorded in the offer account for escrowed and routed offers, so `None` when those
+    /// were filled without a fill event.
+    pub maker_size: Option<u64>,
+    pub taker_size: Option<u64>,
+    /// The payout, known only from a fill event.
+    pub payout: Option<Payout>,
+}
+
+/// What each side received of a fill, and the fees paid out of it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Payout {
+    pub taker_received: u64,
+    pub maker_received: u64,
+    pub fee_mint: Pubkey,
+    pub creator_fees: Vec<FeePaid>,
+    pub platform_fee: Option<FeePaid>,
+}
+
+impl From<FillEvent> for Fill {
+    fn from(event: FillEvent) -> Self {
+        Self {
+            offer: event.offer,
+            maker_wallet: event.maker_wallet,
+            taker_wallet: event.taker_wallet,
+            maker_mint: event.maker_mint,
+            taker_mint: event.taker_mint,
+            maker_size: Some(event.maker_size),
+            taker_size: Some(event.taker_size),
+            payout: Some(Payout {
+                taker_received: event.taker_received,
+                maker_received: event.maker_received,
+                fee_mint: event.fee_mint,
+                creator_fees: event.creator_fees,
+                platform_fee: event.platform_fee,
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CancellationKind {
+    /// An SPL `Revoke` of a token account's delegation.
+    Revoke,
+    CloseEscrowOffer,
+    CloseRoutedOffer,
+}
+
+impl CancellationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CancellationKind::Revoke => "revoke",
+            CancellationKind::CloseEscrowOffer => "close_escrow_offer",
+            CancellationKind::CloseRoutedOffer => "close_routed_offer",
+        }
+    }
+}
+
+/// A withdrawn offer or delegation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cancellation {
+    pub kind: CancellationKind,
+    pub wallet: Pubkey,
+    /// The revoked token account, or the closed offer account.
+    pub account: Pubkey,
+    /// Unknown for routed offers, whose mint is only recorded in the closed account.
+    pub mint: Option<Pubkey>,
+}
+
+/// An SPL approval to a program derived address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Delegation {
+    pub wallet: Pubkey,
+    pub token_account: Pubkey,
+    pub delegate: Pubkey,
+    /// Unknown when the token account is not in the token balances of the transaction.
+    pub mint: Option<Pubkey>,
+    pub amount: u64,
+    /// Whether the delegate is the offer router rather than the authority of a single offer.
+    pub to_router: bool,
+}
+
+/// What one transaction did to offers.
+#[derive(Clone, Debug, Default)]
+pub struct TransactionHistory {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    /// Why the transaction failed. Failed transactions have no other records.
+    pub err: Option<String>,
+    pub fills: Vec<Fill>,
+    pub cancellations: Vec<Cancellation>,
+    pub delegations: Vec<Delegation>,
+    /// Approvals to program derived addresses in a transaction that does not invoke the program,
+    /// not yet known to be offers.
+    pub unmatched_delegations: Vec<Delegation>,
+    /// Revokes in a transaction that does not invoke the program, not yet known to withdraw an
+    /// offer.
+    pub unmatched_revokes: Vec<Cancellation>,
+}
+
+/// Parse a `getTransaction` response, either the JSON-RPC envelope or its `result`.
+pub fn parse_transaction_dump(json: &str) -> Result<EncodedConfirmedTransactionWithStatusMeta> {
+    let mut value: serde_json::Value = serde_json::from_str(json).context("invalid JSON")?;
+    if let Some(result) = value.get_mut("result") {
+        value = result.take();
+    }
+    if value.is_null() {
+        bail!("transaction not found");
+    }
+    serde_json::from_value(value).context("not a getTransaction response")
+}
+
+/// Extract the fills, cancellations and delegations of a transaction.
+pub fn transaction_history(
+    program_id: &Pubkey,
+    transaction: &EncodedConfirmedTransactionWithStatusMeta,
+) -> Result<TransactionHistory> {
+    let meta = transaction.transaction.meta.as_ref();
+    let (signature, keys, instructions) =
+        resolve_instructions(&transaction.transaction.transaction, meta)?;
+    let mut history = TransactionHistory {
+        signature,
+        slot: transaction.slot,
+        block_time: transaction.block_time,
+        err: meta
+            .and_then(|meta| meta.err.as_ref())
+            .map(|err| err.to_string()),
+        ..Default::default()
+    };
+    if history.err.is_some() {
+        return Ok(history);
+    }
+    let mints = token_account_mints(&keys, meta);
+    let logs: Option<&Vec<String>> = meta.and_then(|meta| meta.log_messages.as_ref().into());
+    let events = logs.map_or_else(Vec::new, |logs| parse_fill_events(program_id, logs));
+    let has_events = !events.is_empty();
+    history.fills.extend(events.into_iter().map(Fill::from));
+
+    let router = compute_offer_router(program_id).0;
+    let invokes_program = instructions
+        .iter()
+        .any(|instruction| instruction.program_id == *program_id);
+    for instruction in instructions {
+        if instruction.program_id == *program_id {
+            let accounts: Vec<Option<Pubkey>> =
+                instruction.accounts.iter().copied().map(Some).collect();
+            // Instructions of other program versions.
+            let Ok(decoded) = decode_instruction(Some(program_id), &instruction.data, &accounts)
+            else {
+                continue;
+            };
+            if !has_events {
+                history.fills.extend(instruction_fill(&decoded));
+            }
+            history.cancellations.extend(closed_offer(&decoded, &mints));
+        } else if instruction.program_id == spl_token::id() {
+            let Ok(token_instruction) = TokenInstruction::unpack(&instruction.data) else {
+                continue;
+            };
+            let accounts = &instruction.accounts;
+            let approval = match token_instruction {
+                TokenInstruction::Approve { amount } => accounts
+                    .get(..3)
+                    .map(|a| (a[0], mints.get(&a[0]).copied(), a[1], a[2], amount)),
+                TokenInstruction::ApproveChecked { amount, .. } => accounts
+                    .get(..4)
+                    .map(|a| (a[0], Some(a[1]), a[2], a[3], amount)),
+                TokenInstruction::Revoke => {
+                    if let [token_account, wallet, ..] = accounts[..] {
+                        let revoke = Cancellation {
+                            kind: CancellationKind::Revoke,
+                            wallet,
+                            account: token_account,
+                            mint: mints.get(&token_account).copied(),
+                        };
+                        if invokes_program {
+                            history.cancellations.push(revoke);
+                        } else {
+                            history.unmatched_revokes.push(revoke);
+                        }
+                    }
+                    None
+                }
+                _ => None,
+            };
+            if let Some((token_account, mint, delegate, wallet, amount)) = approval {
+                if !delegate.is_on_curve() {
+                    let delegation = Delegation {
+                        wallet,
+                        token_account,
+                        delegate,
+                        mint,
+                        amount,
+                        to_router: delegate == router,
+                    };
+                    if invokes_program || delegation.to_router {
+                        history.delegations.push(delegation);
+                    } else {
+                        history.unmatched_delegations.push(delegation);
+                    }
+                }
+            }
+        }
+    }
+    Ok(history)
+}
+
+/// Move the unmatched approvals and revokes of `histories` that concern an offer to their
+/// delegations and cancellations.
+///
+/// An approval is an offer delegation when the program fills an offer through its delegate in one
+/// of `histories`. A revoke withdraws an offer when its token account is delegated for an offer
+/// in one of `histories`. What is left unmatched is not an offer record.
+pub fn match_offer_approvals(histories: &mut [TransactionHistory]) {
+    let delegates: HashSet<Pubkey> = histories
+        .iter()
+        .flat_map(|history| history.fills.iter().map(|fill| fill.offer))
+        .collect();
+    for history in histories.iter_mut() {
+        let (matched, unmatched): (Vec<_>, Vec<_>) =
+            std::mem::take(&mut history.unmatched_delegations)
+                .into_iter()
+                .partition(|delegation| delegates.contains(&delegation.delegate));
+        history.delegations.extend(matched);
+        history.unmatched_delegations = unmatched;
+    }
+
+    let delegated_accounts: HashSet<Pubkey> = histories
+        .iter()
+        .flat_map(|history| {
+            history
+                .delegations
+                .iter()
+                .map(|delegation| delegation.token_account)
+        })
+        .collect();
+    for history in histories.iter_mut() {
+        let (matched, unmatched): (Vec<_>, Vec<_>) = std::mem::take(&mut history.unmatched_revokes)
+            .into_iter()
+            .partition(|revoke| delegated_accounts.contains(&revoke.account));
+        history.cancellations.extend(matched);
+        history.unmatched_revokes = unmatched;
+    }
+}
+
+/// An instruction of a transaction, top level or inner, with its accounts resolved.
+struct ResolvedInstruction {
+    program_id: Pubkey,
+    accounts: Vec<Pubkey>,
+    data: Vec<u8>,
+}
+
+/// The signature, account keys and instructions of a transaction encoded as `base64`, `base58`
+/// or raw `json`.
+fn resolve_instructions(
+    transaction: &EncodedTransaction,
+    meta: Option<&UiTransactionStatusMeta>,
+) -> Result<(String, Vec<Pubkey>, Vec<ResolvedInstruction>)> {
+    let (signature, mut keys, compiled) = match (transaction.decode(), transaction) {
+        (Some(transaction), _) => (
+            transaction
+                .signatures
+                .first()
+                .map(|signature| signature.to_string()),
+            transaction.message.static_account_keys().to_vec(),
+            transaction.message.instructions().to_vec(),
+        ),
+        (
+            None,
+            EncodedTransaction::Json(UiTransaction {
+                signatures,
+                message: UiMessage::Raw(message),
+            }),
+        ) => (
+            signatures.first().cloned(),
+            parse_keys(&message.account_keys)?,
+            message
+                .instructions
+                .iter()
+                .map(|instruction| {
+                    Ok(CompiledInstruction {
+                        program_id_index: instruction.program_id_index,
+                        accounts: instruction.accounts.clone(),
+                        data: bs58::decode(&instruction.data)
+                            .into_vec()
+                            .context("invalid instruction data")?,
+                    })
+                })
+                .collect::<Result<_>>()?,
+        ),
+        _ => bail!("unsupported transaction encoding, use base64 or json"),
+    };
+    let signature = signature.context("transaction has no signature")?;
+
+    // Accounts loaded from lookup tables follow the static keys, writable ones first.
+    if let Some(loaded) =
+        meta.and_then(|meta| Option::<&UiLoadedAddresses>::from(meta.loaded_addresses.as_ref()))
+    {
+        keys.extend(parse_keys(&loaded.writable)?);
+        keys.extend(parse_keys(&loaded.readonly)?);
+    }
+
+    let mut inner: HashMap<u8, Vec<CompiledInstruction>> = HashMap::new();
+    if let Some(inner_instructions) = meta.and_then(|meta| {
+        Option::<&Vec<UiInnerInstructions>>::from(meta.inner_instructions.as_ref())
+    }) {
+        for instructions in inner_instructions {
+            for instruction in &instructions.instructions {
+                // Parsed inner instructions only come with the jsonParsed encoding.
+                if let UiInstruction::Compiled(instruction) = instruction {
+                    inner
+                        .entry(instructions.index)
+                        .or_default()
+                        .push(CompiledInstruction {
+                            program_id_index: instruction.program_id_index,
+                            accounts: instruction.accounts.clone(),
+                            data: bs58::decode(&instruction.data)
+                                .into_vec()
+                                .context("invalid inner instruction data")?,
+                        });
+                }
+            }
+        }
+    }
+
+    let mut resolved = Vec::new();
+    for (index, instruction) in compiled.into_iter().enumerate() {
+        let inner = inner.remove(&(index as u8)).unwrap_or_default();
+        for instruction in std::iter::once(instruction).chain(inner) {
+            resolved.push(resolve(&keys, instruction)?);
+        }
+    }
+    Ok((signature, keys, resolved))
+}
+
+fn parse_keys(keys: &[String]) -> Result<Vec<Pubkey>> {
+    keys.iter()
+        .map(|key| Pubkey::from_str(key).with_context(|| format!("invalid pubkey {}", key)))
+        .collect()
+}
+
+fn resolve(keys: &[Pubkey], instruction: CompiledInstruction) -> Result<ResolvedInstruction> {
+    let key = |index: u8| {
+        keys.get(index as usize)
+            .copied()
+            .with_context(|| format!("missing account #{}", index))
+    };
+    Ok(ResolvedInstruction {
+        program_id: key(instruction.program_id_index)?,
+        accounts: instruction
+            .accounts
+            .iter()
+            .map(|&index| key(index))
+            .collect::<Result<_>>()?,
+        data: instruction.data,
+    })
+}
+
+/// The mint of every token account in the token balances of the transaction.
+fn token_account_mints(
+    keys: &[Pubkey],
+    meta: Option<&UiTransactionStatusMeta>,
+) -> HashMap<Pubkey, Pubkey> {
+    let mut mints = HashMap::new();
+    let Some(meta) = meta else {
+        return mints;
+    };
+    for balances in [&meta.pre_token_balances, &meta.post_token_balances] {
+        let Some(balances) = Option::<&Vec<UiTransactionTokenBalance>>::from(balances.as_ref())
+        else {
+            continue;
+        };
+        for balance in balances {
+            if let (Some(account), Ok(mint)) = (
+                keys.get(balance.account_index as usize),
+                Pubkey::from_str(&balance.mint),
+            ) {
+                mints.insert(*account, mint);
+            }
+        }
+    }
+    mints
+}
+
+/// The fill of an accept instruction, for transactions without fill events.
+fn instruction_fill(decoded: &DecodedInstruction) -> Option<Fill> {
+    let Payload::Instruction(instruction) = &decoded.payload else {
+        return None;
+    };
+    let (offer_role, sizes) = match instruction {
+        StatelessOfferInstruction::AcceptOffer {
+            maker_size,
+            taker_size,
+            ..
+        }
+        | StatelessOfferInstruction::AcceptProgrammableOffer {
+            maker_size,
+            taker_size,
+            ..
+        }
+        | StatelessOfferInstruction::AcceptCollectionOffer {
+            maker_size,
+            taker_size,
+            ..
+        } => ("transfer_authority", Some((*maker_size, *taker_size))),
+        StatelessOfferInstruction::AcceptEscrowOffer { .. } => ("escrow_offer", None),
+        StatelessOfferInstruction::AcceptRoutedOffer { .. } => ("routed_offer", None),
+        _ => return None,
+    };
+    Some(Fill {
+        offer: decoded.account(offer_role)?,
+        maker_wallet: decoded.account("maker_wallet")?,
+        taker_wallet: decoded.account("taker_wallet")?,
+        maker_mint: decoded.account("maker_mint")?,
+        taker_mint: decoded.account("taker_mint")?,
+        maker_size: sizes.map(|(maker_size, _)| maker_size),
+        taker_size: sizes.map(|(_, taker_size)| taker_size),
+        payout: None,
+    })
+}
+
+/// The cancellation of a close instruction.
+fn closed_offer(
+    decoded: &DecodedInstruction,
+    mints: &HashMap<Pubkey, Pubkey>,
+) -> Option<Cancellation> {
+    let Payload::Instruction(instruction) = &decoded.payload else {
+        return None;
+    };
+    let (kind, account, mint) = match instruction {
+        StatelessOfferInstruction::CloseEscrowOffer => (
+            CancellationKind::CloseEscrowOffer,
+            decoded.account("escrow_offer")?,
+            decoded
+                .account("vault")
+                .and_then(|vault| mints.get(&vault).copied()),
+        ),
+        StatelessOfferInstruction::CloseRoutedOffer => (
+            CancellationKind::CloseRoutedOffer,
+            decoded.account("routed_offer")?,
+            None,
+        ),
+        _ => return None,
+    };
+    Some(Cancellation {
+        kind,
+        wallet: decoded.account("maker_wallet")?,
+        account,
+        mint,
+    })
+}
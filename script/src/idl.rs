@@ -0,0 +1,889 @@
+//! An Anchor-compatible IDL of the stateless-asks program.
+//!
+//! The IDL follows the Anchor 0.30 layout. Instruction discriminators are the program's eight
+//! byte instruction header, so clients generated from it encode instructions the program
+//! accepts. Anchor cannot express accounts whose presence depends on the arguments or on the
+//! NFT metadata, so those are marked optional with the condition in their docs, and the
+//! variable number of creator accounts is described on the metadata account.
+//!
+//! Instruction arguments and the account and event types are read from their Borsh schemas,
+//! and the errors from [`UtilError`], so only the docs and accounts are written by hand.
+
+use borsh::{
+    schema::{BorshSchemaContainer, Definition, Fields},
+    schema_container_of, BorshSchema,
+};
+use serde::Serialize;
+use solana_program::{pubkey::Pubkey, system_program, sysvar};
+use solana_zk_offers::{
+    events::{FeePaid, FillEvent, FILL_EVENT_DISCRIMINATOR},
+    metadata,
+    zk_offers::{PlatformFee, PublicValuesStruct, CANONICAL_PUBLIC_VALUES_LEN},
+};
+use stateless_asks::{
+    errors::UtilError,
+    instruction::{
+        instruction_schema, StatelessOfferInstruction, TakerGuard, INSTRUCTION_HEADER_LEN,
+        TOKEN_AUTH_RULES_PROGRAM_ID,
+    },
+    state::{AccountKey, EscrowOffer, OfferBatch, RoutedOffer, SealedAuction, SealedBid},
+};
+
+/// Where the IDL is checked in, relative to the repository root.
+pub const IDL_PATH: &str = "program/idl/stateless_asks.json";
+
+#[derive(Clone, Serialize)]
+pub struct Idl {
+    pub metadata: IdlMetadata,
+    pub instructions: Vec<IdlInstruction>,
+    pub events: Vec<IdlEvent>,
+    pub errors: Vec<IdlError>,
+    pub types: Vec<IdlTypeDef>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct IdlMetadata {
+    pub name: String,
+    pub version: String,
+    pub spec: String,
+    pub description: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct IdlInstruction {
+    pub name: String,
+    pub docs: Vec<String>,
+    pub discriminator: Vec<u8>,
+    pub accounts: Vec<IdlAccount>,
+    pub args: Vec<IdlField>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct IdlAccount {
+    pub name: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub docs: Vec<String>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub writable: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub signer: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub optional: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pda: Option<IdlPda>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct IdlPda {
+    pub seeds: Vec<IdlSeed>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub program: Option<IdlSeed>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum IdlSeed {
+    Const { value: Vec<u8> },
+    Account { path: String },
+    Arg { path: String },
+}
+
+#[derive(Clone, Serialize)]
+pub struct IdlField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: IdlType,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(untagged)]
+pub enum IdlType {
+    Primitive(&'static str),
+    Option { option: Box<IdlType> },
+    Vec { vec: Box<IdlType> },
+    Array { array: (Box<IdlType>, usize) },
+    Defined { defined: IdlDefined },
+}
+
+#[derive(Clone, Serialize)]
+pub struct IdlDefined {
+    pub name: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct IdlEvent {
+    pub name: String,
+    pub discriminator: Vec<u8>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct IdlError {
+    pub code: u32,
+    pub name: String,
+    pub msg: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct IdlTypeDef {
+    pub name: String,
+    pub docs: Vec<String>,
+    #[serde(rename = "type")]
+    pub ty: IdlTypeDefTy,
+}
+
+#[derive(Clone, Serialize)]
+pub struct IdlTypeDefTy {
+    pub kind: &'static str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<IdlField>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<IdlEnumVariant>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct IdlEnumVariant {
+    pub name: String,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+/// The IDL as pretty-printed JSON, as checked in at [`IDL_PATH`].
+pub fn idl_json() -> String {
+    let mut json = serde_json::to_string_pretty(&idl()).expect("IDL serializes");
+    json.push('\n');
+    json
+}
+
+pub fn idl() -> Idl {
+    Idl {
+        metadata: IdlMetadata {
+            name: "stateless_asks".to_string(),
+            version: "0.1.0".to_string(),
+            spec: "0.1.0".to_string(),
+            description: "Stateless and escrowed token offers".to_string(),
+        },
+        instructions: instructions(),
+        events: vec![IdlEvent {
+            name: "FillEvent".to_string(),
+            discriminator: FILL_EVENT_DISCRIMINATOR.to_vec(),
+        }],
+        errors: errors(),
+        types: types(),
+    }
+}
+
+fn instructions() -> Vec<IdlInstruction> {
+    let public_values = [0; CANONICAL_PUBLIC_VALUES_LEN];
+    vec![
+        instruction(
+            "accept_offer",
+            "Fill an offer whose maker delegated maker_size to the transfer authority.",
+            StatelessOfferInstruction::AcceptOffer {
+                metadata_bump_seed: None,
+                maker_size: 0,
+                taker_size: 0,
+                bump_seed: 0,
+                platform_fee: None,
+                taker_guard: TakerGuard::default(),
+            },
+            [
+                fill_accounts(false),
+                vec![conditional_system_program()],
+                fee_accounts(),
+            ]
+            .concat(),
+        ),
+        instruction(
+            "accept_programmable_offer",
+            "Fill an offer whose maker leg is a programmable NFT, moved through Token Metadata.",
+            StatelessOfferInstruction::AcceptProgrammableOffer {
+                metadata_bump_seed: None,
+                maker_size: 0,
+                taker_size: 0,
+                bump_seed: 0,
+                platform_fee: None,
+                taker_guard: TakerGuard::default(),
+            },
+            [
+                fill_accounts(true),
+                vec![conditional_system_program()],
+                programmable_accounts(),
+                fee_accounts(),
+            ]
+            .concat(),
+        ),
+        instruction(
+            "accept_collection_offer",
+            "Fill a collection-wide bid with any verified member of the collection.",
+            StatelessOfferInstruction::AcceptCollectionOffer {
+                collection: Pubkey::default(),
+                verified_creators_only: false,
+                metadata_bump_seed: 0,
+                maker_size: 0,
+                taker_size: 0,
+                bump_seed: 0,
+                platform_fee: None,
+                taker_guard: TakerGuard::default(),
+            },
+            [fill_accounts(false), fee_accounts()].concat(),
+        ),
+        instruction(
+            "init_escrow_offer",
+            "Move maker_size into the vault of a new escrow offer account.",
+            StatelessOfferInstruction::InitEscrowOffer {
+                nonce: 0,
+                bump_seed: 0,
+                public_values,
+            },
+            vec![
+                account("maker_wallet").writable().signer(),
+                account("maker_src_account").writable(),
+                account("escrow_offer").writable().pda(
+                    vec![
+                        seed_const(b"escrow_offer"),
+                        seed_account("maker_wallet"),
+                        seed_arg("nonce"),
+                    ],
+                    None,
+                ),
+                vault(),
+                account("maker_mint"),
+                token_program(),
+                account("associated_token_program")
+                    .address(&spl_associated_token_account::id()),
+                account("system_program").address(&system_program::id()),
+            ],
+        ),
+        instruction(
+            "accept_escrow_offer",
+            "Fill an escrowed offer, then close its vault and escrow offer account.",
+            StatelessOfferInstruction::AcceptEscrowOffer {
+                metadata_bump_seed: None,
+                taker_guard: TakerGuard::default(),
+            },
+            [
+                vec![
+                    account("maker_wallet").writable(),
+                    account("taker_wallet").writable().signer(),
+                    account("escrow_offer").writable(),
+                    vault(),
+                ],
+                counterparty_accounts(),
+                vec![token_program(), conditional_system_program()],
+                fee_accounts(),
+            ]
+            .concat(),
+        ),
+        instruction(
+            "close_escrow_offer",
+            "Return the vault to the maker and close the escrow offer.",
+            StatelessOfferInstruction::CloseEscrowOffer,
+            vec![
+                account("maker_wallet").writable().signer(),
+                account("escrow_offer").writable(),
+                vault(),
+                account("maker_src_account").writable(),
+                token_program(),
+            ],
+        ),
+        instruction(
+            "init_routed_offer",
+            "Record an offer filled through the maker's delegation to the offer router.",
+            StatelessOfferInstruction::InitRoutedOffer {
+                nonce: 0,
+                bump_seed: 0,
+                public_values,
+            },
+            vec![
+                account("maker_wallet").writable().signer(),
+                account("routed_offer").writable().pda(
+                    vec![
+                        seed_const(b"routed_offer"),
+                        seed_account("maker_wallet"),
+                        seed_arg("nonce"),
+                    ],
+                    None,
+                ),
+                account("system_program").address(&system_program::id()),
+            ],
+        ),
+        instruction(
+            "accept_routed_offer",
+            "Fill a routed offer and close its account.",
+            StatelessOfferInstruction::AcceptRoutedOffer {
+                metadata_bump_seed: None,
+                router_bump_seed: 0,
+                taker_guard: TakerGuard::default(),
+            },
+            [
+                vec![
+                    account("maker_wallet").writable(),
+                    account("taker_wallet").writable().signer(),
+                    account("routed_offer").writable(),
+                    account("maker_src_account").writable(),
+                ],
+                counterparty_accounts(),
+                vec![
+                    account("offer_router").pda(vec![seed_const(b"offer_router")], None),
+                    token_program(),
+                    conditional_system_program(),
+                ],
+                fee_accounts(),
+            ]
+            .concat(),
+        ),
+        instruction(
+            "close_routed_offer",
+            "Withdraw a routed offer.",
+            StatelessOfferInstruction::CloseRoutedOffer,
+            vec![
+                account("maker_wallet").writable().signer(),
+                account("routed_offer").writable(),
+            ],
+        ),
+        instruction(
+            "init_sealed_auction",
+            "Move maker_size into the vault of a new sealed-bid auction.",
+            StatelessOfferInstruction::InitSealedAuction {
+                nonce: 0,
+                bump_seed: 0,
+                public_values,
+                bid_vkey_hash: [0; 32],
+                commit_end_slot: 0,
+                reveal_end_slot: 0,
+            },
+            vec![
+                account("maker_wallet").writable().signer(),
+                account("maker_src_account").writable(),
+                account("auction").writable().pda(
+                    vec![
+                        seed_const(b"sealed_auction"),
+                        seed_account("maker_wallet"),
+                        seed_arg("nonce"),
+                    ],
+                    None,
+                ),
+                token_vault("vault", "auction", "maker_mint"),
+                account("maker_mint"),
+                token_program(),
+                account("associated_token_program")
+                    .address(&spl_associated_token_account::id()),
+                account("system_program").address(&system_program::id()),
+            ],
+        ),
+        instruction(
+            "commit_sealed_bid",
+            "Record the commitment to a sealed bid.",
+            StatelessOfferInstruction::CommitSealedBid {
+                bump_seed: 0,
+                commitment: [0; 32],
+                deposit: 0,
+            },
+            vec![
+                account("bidder").writable().signer(),
+                account("auction"),
+                sealed_bid(),
+                token_vault("bid_vault", "bid", "taker_mint"),
+                account("bidder_src_account").writable(),
+                account("taker_mint"),
+                token_program(),
+                account("associated_token_program")
+                    .address(&spl_associated_token_account::id()),
+                account("system_program").address(&system_program::id()),
+            ],
+        ),
+        instruction(
+            "reveal_sealed_bid",
+            "Open a sealed bid with a Groth16 proof and return the deposit above the amount.",
+            StatelessOfferInstruction::RevealSealedBid {
+                amount: 0,
+                proof: vec![],
+            },
+            vec![
+                account("bidder").writable().signer(),
+                account("auction").writable(),
+                sealed_bid(),
+                token_vault("bid_vault", "bid", "taker_mint"),
+                account("bidder_src_account").writable(),
+                account("taker_mint"),
+                token_program(),
+            ],
+        ),
+        instruction(
+            "settle_sealed_auction",
+            "Fill an auction with its best bid, or return the vault to the maker without one.",
+            StatelessOfferInstruction::SettleSealedAuction {
+                metadata_bump_seed: None,
+            },
+            [
+                vec![
+                    account("maker_wallet").writable(),
+                    account("auction").writable(),
+                    token_vault("vault", "auction", "maker_mint"),
+                    account("maker_src_account").writable(),
+                    account("maker_dst_account").writable(),
+                    account("maker_mint"),
+                    account("taker_mint"),
+                    token_program(),
+                    account("bidder").writable().optional().doc(
+                        "The best bidder. It and the following bid accounts are present when a \
+                         bid was revealed.",
+                    ),
+                    account("bid").writable().optional(),
+                    account("bid_vault").writable().optional(),
+                    account("bidder_dst_account").writable().optional(),
+                ],
+                fee_accounts(),
+            ]
+            .concat(),
+        ),
+        instruction(
+            "close_sealed_bid",
+            "Return a revealed bid that is not the best, or forfeit an unrevealed one.",
+            StatelessOfferInstruction::CloseSealedBid,
+            vec![
+                account("bidder")
+                    .writable()
+                    .signer()
+                    .doc("Signs unless the bid is forfeited."),
+                account("auction"),
+                account("bid").writable(),
+                token_vault("bid_vault", "bid", "taker_mint"),
+                account("bidder_src_account").writable(),
+                account("maker_dst_account")
+                    .writable()
+                    .doc("The maker's ATA for the taker mint, receiving a forfeited deposit."),
+                account("taker_mint"),
+                token_program(),
+            ],
+        ),
+        instruction(
+            "post_offer_batch",
+            "Record the Merkle root of a batch of offers proven with one Groth16 proof.",
+            StatelessOfferInstruction::PostOfferBatch {
+                bump_seed: 0,
+                vkey_hash: [0; 32],
+                root: [0; 32],
+                offer_count: 0,
+                proof: vec![],
+            },
+            vec![
+                account("poster").writable().signer(),
+                account("offer_batch").writable().pda(
+                    vec![
+                        seed_const(b"offer_batch"),
+                        seed_arg("vkey_hash"),
+                        seed_arg("root"),
+                    ],
+                    None,
+                ),
+                account("system_program").address(&system_program::id()),
+            ],
+        ),
+        instruction(
+            "accept_batched_offer",
+            "Fill a delegated offer whose terms are proven part of a posted batch.",
+            StatelessOfferInstruction::AcceptBatchedOffer {
+                metadata_bump_seed: None,
+                bump_seed: 0,
+                public_values,
+                merkle_proof: vec![],
+                taker_guard: TakerGuard::default(),
+            },
+            [
+                vec![account("offer_batch")],
+                fill_accounts(false),
+                vec![conditional_system_program()],
+                fee_accounts(),
+            ]
+            .concat(),
+        ),
+        instruction(
+            "close_offer_batch",
+            "Return the rent of an offer batch to its poster.",
+            StatelessOfferInstruction::CloseOfferBatch,
+            vec![
+                account("poster").writable().signer(),
+                account("offer_batch").writable(),
+            ],
+        ),
+    ]
+}
+
+/// The IDL of the instruction `sample` is a variant of, with its args read from the
+/// [`instruction_schema`] so they always follow the fields the program decodes.
+fn instruction(
+    name: &str,
+    docs: &str,
+    sample: StatelessOfferInstruction,
+    accounts: Vec<IdlAccount>,
+) -> IdlInstruction {
+    IdlInstruction {
+        name: name.to_string(),
+        docs: vec![docs.to_string()],
+        discriminator: sample.pack()[..INSTRUCTION_HEADER_LEN].to_vec(),
+        accounts,
+        args: instruction_args(sample.discriminator()),
+    }
+}
+
+/// The fields of the instruction variant with `discriminator`, in encoding order.
+fn instruction_args(discriminator: u8) -> Vec<IdlField> {
+    let schema = instruction_schema();
+    let Some(Definition::Enum { variants, .. }) = schema.get_definition(schema.declaration())
+    else {
+        panic!("the instruction schema is not an enum");
+    };
+    let (_, _, declaration) = variants
+        .iter()
+        .find(|(value, _, _)| *value == discriminator as i64)
+        .expect("every discriminator has a variant");
+    match schema.get_definition(declaration) {
+        Some(Definition::Struct {
+            fields: Fields::NamedFields(fields),
+        }) => fields
+            .iter()
+            .map(|(name, declaration)| field(name, schema_type(&schema, declaration)))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// The IDL type of a declaration in `schema`.
+fn schema_type(schema: &BorshSchemaContainer, declaration: &str) -> IdlType {
+    const PRIMITIVES: [&str; 5] = ["bool", "u8", "u16", "u32", "u64"];
+    if let Some(name) = PRIMITIVES.iter().find(|name| **name == declaration) {
+        return primitive(*name);
+    }
+    match (declaration, schema.get_definition(declaration)) {
+        ("Pubkey", _) => pubkey(),
+        ("Vec<u8>", _) => primitive("bytes"),
+        (_, Some(Definition::Enum { variants, .. }))
+            if variants.len() == 2 && variants[0].1 == "None" && variants[1].1 == "Some" =>
+        {
+            option(schema_type(schema, &variants[1].2))
+        }
+        (
+            _,
+            Some(Definition::Sequence {
+                length_width: 0,
+                length_range,
+                elements,
+            }),
+        ) => array(schema_type(schema, elements), *length_range.end() as usize),
+        (_, Some(Definition::Sequence { elements, .. })) => vec_of(schema_type(schema, elements)),
+        (_, Some(_)) => defined(declaration),
+        (_, None) => panic!("{} is not in the schema", declaration),
+    }
+}
+
+/// The accounts every delegated fill starts with.
+fn fill_accounts(taker_pays_rent: bool) -> Vec<IdlAccount> {
+    let taker_wallet = account("taker_wallet").signer();
+    let taker_wallet = if taker_pays_rent {
+        taker_wallet.writable()
+    } else {
+        taker_wallet
+    };
+    [
+        vec![
+            account("maker_wallet"),
+            taker_wallet,
+            account("maker_src_account").writable(),
+        ],
+        counterparty_accounts(),
+        vec![
+            account("transfer_authority").doc(
+                "Delegate of the maker source account, derived from the prefix stateless_offer, \
+                 the maker wallet and the public values digest of the fill terms. Collection \
+                 offers replace the prefix and put the collection in place of the taker mint, \
+                 and batched offers derive it from the prefix batched_offer, the maker wallet, \
+                 the batch root and the digest of the committed terms.",
+            ),
+            token_program(),
+        ],
+    ]
+    .concat()
+}
+
+/// The accounts of both legs after the maker's source.
+fn counterparty_accounts() -> Vec<IdlAccount> {
+    vec![
+        account("maker_dst_account")
+            .writable()
+            .doc("The maker wallet when the taker pays in native SOL."),
+        account("taker_src_account")
+            .writable()
+            .doc("The taker wallet when the taker pays in native SOL."),
+        account("taker_dst_account").writable(),
+        account("maker_mint"),
+        account("taker_mint"),
+    ]
+}
+
+fn programmable_accounts() -> Vec<IdlAccount> {
+    vec![
+        account("token_metadata_program").address(&metadata::id()),
+        account("nft_metadata").writable(),
+        account("edition"),
+        account("owner_token_record").writable(),
+        account("destination_token_record").writable(),
+        account("transfer_system_program").address(&system_program::id()),
+        account("sysvar_instructions").address(&sysvar::instructions::id()),
+        account("associated_token_program").address(&spl_associated_token_account::id()),
+        account("authorization_rules_program").doc(&format!(
+            "{}, or the Token Metadata program when the NFT has no rule set.",
+            TOKEN_AUTH_RULES_PROGRAM_ID
+        )),
+        account("authorization_rules")
+            .doc("The Token Metadata program when the NFT has no rule set."),
+    ]
+}
+
+/// Royalty and platform fee accounts, which close every fill.
+fn fee_accounts() -> Vec<IdlAccount> {
+    vec![
+        account("metadata").optional().doc(
+            "Token Metadata account of the NFT leg, present when metadata_bump_seed is set. \
+             Each paid creator wallet follows it, with its ATA for the paying mint unless the \
+             fees are paid in native SOL.",
+        ),
+        account("platform_fee_account").writable().optional().doc(
+            "Platform fee recipient, or its ATA for SPL fees. Present when a platform fee is \
+             committed, after the creator accounts.",
+        ),
+    ]
+}
+
+fn conditional_system_program() -> IdlAccount {
+    account("system_program")
+        .optional()
+        .address(&system_program::id())
+        .doc("Present when the taker pays in native SOL.")
+}
+
+fn token_program() -> IdlAccount {
+    account("token_program").address(&spl_token::id())
+}
+
+/// The escrow offer account's ATA for the maker mint.
+fn vault() -> IdlAccount {
+    token_vault("vault", "escrow_offer", "maker_mint")
+}
+
+/// The ATA of the program account `owner` for `mint`.
+fn token_vault(name: &str, owner: &str, mint: &str) -> IdlAccount {
+    account(name).writable().pda(
+        vec![
+            seed_account(owner),
+            seed_const(spl_token::id().as_ref()),
+            seed_account(mint),
+        ],
+        Some(seed_const(spl_associated_token_account::id().as_ref())),
+    )
+}
+
+fn sealed_bid() -> IdlAccount {
+    account("bid").writable().pda(
+        vec![
+            seed_const(b"sealed_bid"),
+            seed_account("auction"),
+            seed_account("bidder"),
+        ],
+        None,
+    )
+}
+
+fn errors() -> Vec<IdlError> {
+    (0..)
+        .map_while(UtilError::from_code)
+        .map(|error| IdlError {
+            code: error as u32,
+            name: format!("{:?}", error),
+            msg: error.to_string(),
+        })
+        .collect()
+}
+
+fn types() -> Vec<IdlTypeDef> {
+    vec![
+        schema_type_def::<PlatformFee>("A fee taken by the venue running the offer book."),
+        schema_type_def::<PublicValuesStruct>(
+            "The offer terms recorded in offer accounts. Instructions carry them in the \
+             fixed-width encoding of PublicValuesStruct::to_canonical_bytes instead.",
+        ),
+        schema_type_def::<TakerGuard>(
+            "Bounds a taker puts on a fill, checked once the fees are known.",
+        ),
+        schema_type_def::<FeePaid>("A fee paid out of a fill."),
+        schema_type_def::<FillEvent>("A filled offer, logged by every fill."),
+        schema_type_def::<AccountKey>(
+            "Leading byte of every program account, telling the kinds of account apart.",
+        ),
+        schema_type_def::<EscrowOffer>(
+            "An escrowed offer, holding the terms its vault is released against.",
+        ),
+        schema_type_def::<RoutedOffer>(
+            "An offer filled through the program-wide offer router.",
+        ),
+        schema_type_def::<SealedAuction>(
+            "A sealed-bid auction of an escrowed maker_size, with taker_size as the reserve.",
+        ),
+        schema_type_def::<SealedBid>(
+            "One bidder's commitment to a sealed auction, holding its deposit in its vault.",
+        ),
+        schema_type_def::<OfferBatch>(
+            "A batch of offers proven in one guest run, recorded by the Merkle root of their terms.",
+        ),
+    ]
+}
+
+/// The IDL definition of `T`, with its fields or variants read from its Borsh schema.
+fn schema_type_def<T: BorshSchema>(docs: &str) -> IdlTypeDef {
+    let schema = schema_container_of::<T>();
+    let (kind, fields, variants) = match schema.get_definition(schema.declaration()) {
+        Some(Definition::Struct {
+            fields: Fields::NamedFields(fields),
+        }) => (
+            "struct",
+            fields
+                .iter()
+                .map(|(name, declaration)| field(name, schema_type(&schema, declaration)))
+                .collect(),
+            vec![],
+        ),
+        Some(Definition::Enum { variants, .. }) => (
+            "enum",
+            vec![],
+            variants
+                .iter()
+                .map(|(_, name, _)| IdlEnumVariant { name: name.clone() })
+                .collect(),
+        ),
+        _ => panic!("{} is neither a struct nor an enum", schema.declaration()),
+    };
+    IdlTypeDef {
+        name: schema.declaration().clone(),
+        docs: vec![docs.to_string()],
+        ty: IdlTypeDefTy {
+            kind,
+            fields,
+            variants,
+        },
+    }
+}
+
+fn account(name: &str) -> IdlAccount {
+    IdlAccount {
+        name: name.to_string(),
+        docs: vec![],
+        writable: false,
+        signer: false,
+        optional: false,
+        address: None,
+        pda: None,
+    }
+}
+
+impl IdlAccount {
+    fn writable(self) -> Self {
+        Self {
+            writable: true,
+            ..self
+        }
+    }
+
+    fn signer(self) -> Self {
+        Self {
+            signer: true,
+            ..self
+        }
+    }
+
+    fn optional(self) -> Self {
+        Self {
+            optional: true,
+            ..self
+        }
+    }
+
+    fn doc(mut self, doc: &str) -> Self {
+        self.docs.push(doc.to_string());
+        self
+    }
+
+    fn address(self, address: &Pubkey) -> Self {
+        Self {
+            address: Some(address.to_string()),
+            ..self
+        }
+    }
+
+    fn pda(self, seeds: Vec<IdlSeed>, program: Option<IdlSeed>) -> Self {
+        Self {
+            pda: Some(IdlPda { seeds, program }),
+            ..self
+        }
+    }
+}
+
+fn seed_const(value: &[u8]) -> IdlSeed {
+    IdlSeed::Const {
+        value: value.to_vec(),
+    }
+}
+
+fn seed_account(path: &str) -> IdlSeed {
+    IdlSeed::Account {
+        path: path.to_string(),
+    }
+}
+
+fn seed_arg(path: &str) -> IdlSeed {
+    IdlSeed::Arg {
+        path: path.to_string(),
+    }
+}
+
+fn field(name: &str, ty: IdlType) -> IdlField {
+    IdlField {
+        name: name.to_string(),
+        ty,
+    }
+}
+
+fn primitive(name: &'static str) -> IdlType {
+    IdlType::Primitive(name)
+}
+
+fn pubkey() -> IdlType {
+    IdlType::Primitive("pubkey")
+}
+
+fn option(ty: IdlType) -> IdlType {
+    IdlType::Option {
+        option: Box::new(ty),
+    }
+}
+
+fn array(ty: IdlType, len: usize) -> IdlType {
+    IdlType::Array {
+        array: (Box::new(ty), len),
+    }
+}
+
+fn vec_of(ty: IdlType) -> IdlType {
+    IdlType::Vec { vec: Box::new(ty) }
+}
+
+fn defined(name: &str) -> IdlType {
+    IdlType::Defined {
+        defined: IdlDefined {
+            name: name.to_string(),
+        },
+    }
+}
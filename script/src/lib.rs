@@ -0,0 +1,13 @@
+//! Shared helpers for the zk-offers scripts.
+
+pub mod accounts;
+pub mod cache;
+pub mod compute_budget;
+pub mod decode;
+pub mod guest;
+pub mod history;
+pub mod idl;
+pub mod offer_book;
+pub mod prover;
+#[cfg(feature = "server")]
+pub mod server;
@@ -0,0 +1,494 @@
+//! An in-memory order book of proved offers.
+//!
+//! Offers are submitted as an SP1 proof of their public values plus the maker wallet. Before an
+//! offer is stored, the proof is verified, the transfer authority PDA is recomputed from the
+//! committed terms and the maker's token account is checked to delegate exactly `maker_size` to
+//! that authority. The delegation itself is the maker's signature over the offer: only the owner
+//! of the token account can approve it.
+//!
+//! A delegation does not show the maker holds what they delegated, so the book also reads the
+//! balance of the token account itself, and only lists offers the maker's balance covers. Makers can
+//! follow up with a solvency proof of their balance at a recent slot, and the offer is then listed
+//! as proven funded at that slot.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use borsh::BorshDeserialize;
+use serde::{Deserialize, Serialize};
+use solana_program::{
+    instruction::Instruction, message::Message, pubkey::Pubkey, slot_hashes::SlotHashes,
+    sysvar::slot_hashes,
+};
+use solana_zk_offers::{
+    health::{check_offer_health, OfferIssue, OfferTerms},
+    metadata::{decode_metadata, find_metadata_address},
+    native::{finish_native_fill, prepare_native_fill, wrapped_sol_account},
+    solvency::MakerSolvency,
+    zk_offers::{compute_offer_authority, PlatformFee, PublicValuesStruct},
+};
+use sp1_sdk::{SP1ProofWithPublicValues, SP1VerifyingKey};
+use spl_associated_token_account::get_associated_token_address;
+use std::{collections::BTreeMap, str::FromStr, sync::RwLock};
+use thiserror::Error;
+
+use crate::{accounts::AccountSource, compute_budget::ComputeBudgeter, prover::OfferProver};
+
+#[derive(Error, Debug)]
+pub enum OfferBookError {
+    #[error("invalid pubkey {0}")]
+    InvalidPubkey(String),
+    #[error("invalid proof encoding: {0}")]
+    InvalidProofEncoding(String),
+    #[error("proof verification failed: {0}")]
+    InvalidProof(String),
+    #[error("public values do not decode as PublicValuesStruct")]
+    InvalidPublicValues,
+    #[error("offer does not commit to a taker mint")]
+    MissingTakerMint,
+    #[error("offer does not commit to a taker size and none was submitted")]
+    MissingTakerSize,
+    #[error("submitted taker size does not match the committed one")]
+    TakerSizeMismatch,
+    #[error("platform fee exceeds the program cap or has no recipient")]
+    InvalidPlatformFee,
+    #[error("account {0} not found")]
+    AccountNotFound(Pubkey),
+    #[error("failed to fetch account: {0}")]
+    AccountSource(String),
+    #[error("delegation check failed: {0}")]
+    Delegation(String),
+    #[error("unknown offer {0}")]
+    UnknownOffer(Pubkey),
+    #[error("metadata of {0} does not match the offer")]
+    InvalidMetadata(Pubkey),
+    #[error("failed to budget compute units: {0}")]
+    ComputeBudget(String),
+    #[error("public values do not decode as MakerSolvency")]
+    InvalidSolvency,
+    #[error("solvency proof is for another maker, mint or size than the offer")]
+    SolvencyMismatch,
+    #[error("maker did not hold the maker size at slot {0}")]
+    Insolvent(u64),
+    #[error("bank hash of slot {0} does not match the cluster's recent slot hashes")]
+    UnknownBankHash(u64),
+}
+
+/// The body of an offer submission.
+#[derive(Clone, Debug, Deserialize)]
+pub struct OfferSubmission {
+    /// Base58 maker wallet.
+    pub maker_wallet: String,
+    /// Taker size, required when the proof does not commit to one.
+    pub taker_size: Option<u64>,
+    /// Base64 bincode-encoded `SP1ProofWithPublicValues`.
+    pub proof: String,
+}
+
+/// The body of a solvency proof submission for a stored offer.
+#[derive(Clone, Debug, Deserialize)]
+pub struct SolvencySubmission {
+    /// Base64 bincode-encoded `SP1ProofWithPublicValues` committing a `MakerSolvency`.
+    pub proof: String,
+}
+
+/// A validated offer, keyed by its transfer authority.
+#[derive(Clone, Debug)]
+pub struct Offer {
+    pub authority: Pubkey,
+    pub bump_seed: u8,
+    pub maker_wallet: Pubkey,
+    pub maker_src_account: Pubkey,
+    pub maker_dst_account: Pubkey,
+    pub maker_mint: Pubkey,
+    pub taker_mint: Pubkey,
+    pub is_native: bool,
+    pub maker_size: u64,
+    pub taker_size: u64,
+    pub platform_fee: Option<PlatformFee>,
+    /// Latest slot the maker proved to hold `maker_size` at.
+    pub funded_at_slot: Option<u64>,
+}
+
+impl Offer {
+    pub fn terms(&self) -> OfferTerms {
+        OfferTerms {
+            maker_wallet: self.maker_wallet,
+            maker_mint: self.maker_mint,
+            taker_mint: self.taker_mint,
+            maker_size: self.maker_size,
+            taker_size: self.taker_size,
+            platform_fee: self.platform_fee,
+        }
+    }
+}
+
+/// JSON representation of an [`Offer`].
+#[derive(Clone, Debug, Serialize)]
+pub struct OfferView {
+    pub id: String,
+    pub maker_wallet: String,
+    pub maker_src_account: String,
+    pub maker_dst_account: String,
+    pub maker_mint: String,
+    pub taker_mint: String,
+    pub is_native: bool,
+    pub maker_size: u64,
+    pub taker_size: u64,
+    pub platform_fee_basis_points: Option<u16>,
+    pub platform_fee_recipient: Option<String>,
+    pub funded_at_slot: Option<u64>,
+}
+
+impl From<&Offer> for OfferView {
+    fn from(offer: &Offer) -> Self {
+        Self {
+            id: offer.authority.to_string(),
+            maker_wallet: offer.maker_wallet.to_string(),
+            maker_src_account: offer.maker_src_account.to_string(),
+            maker_dst_account: offer.maker_dst_account.to_string(),
+            maker_mint: offer.maker_mint.to_string(),
+            taker_mint: offer.taker_mint.to_string(),
+            is_native: offer.is_native,
+            maker_size: offer.maker_size,
+            taker_size: offer.taker_size,
+            platform_fee_basis_points: offer.platform_fee.map(|fee| fee.basis_points),
+            platform_fee_recipient: offer.platform_fee.map(|fee| fee.recipient.to_string()),
+            funded_at_slot: offer.funded_at_slot,
+        }
+    }
+}
+
+pub fn parse_pubkey(s: &str) -> Result<Pubkey, OfferBookError> {
+    Pubkey::from_str(s).map_err(|_| OfferBookError::InvalidPubkey(s.to_string()))
+}
+
+pub struct OfferBook {
+    program_id: Pubkey,
+    prover: OfferProver,
+    vk: SP1VerifyingKey,
+    accounts: Box<dyn AccountSource>,
+    compute_budget: Option<ComputeBudgeter>,
+    offers: RwLock<BTreeMap<Pubkey, Offer>>,
+}
+
+impl OfferBook {
+    pub fn new(
+        program_id: Pubkey,
+        prover: OfferProver,
+        vk: SP1VerifyingKey,
+        accounts: Box<dyn AccountSource>,
+    ) -> Self {
+        Self {
+            program_id,
+            prover,
+            vk,
+            accounts,
+            compute_budget: None,
+            offers: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Prepend compute budget instructions to the accept messages.
+    pub fn with_compute_budget(mut self, compute_budget: ComputeBudgeter) -> Self {
+        self.compute_budget = Some(compute_budget);
+        self
+    }
+
+    /// Validate a submission and add it to the book.
+    pub fn submit(&self, submission: &OfferSubmission) -> Result<Offer, OfferBookError> {
+        let maker_wallet = parse_pubkey(&submission.maker_wallet)?;
+        let proof = self.verify_proof(&submission.proof)?;
+        let public_values =
+            PublicValuesStruct::from_canonical_bytes(proof.public_values.as_slice())
+                .ok_or(OfferBookError::InvalidPublicValues)?;
+
+        let offer = self.offer_from_public_values(maker_wallet, &public_values, submission)?;
+        self.check_delegation(&offer)?;
+        self.offers
+            .write()
+            .expect("offer book lock poisoned")
+            .insert(offer.authority, offer.clone());
+        Ok(offer)
+    }
+
+    /// Mark `id` as funded at the slot of a solvency proof.
+    ///
+    /// The proof must be for the offer's maker, mint and size and show the maker solvent, and its
+    /// bank hash must be one of the cluster's recent slot hashes. An older proof than the one
+    /// already recorded is accepted but leaves the offer unchanged.
+    pub fn prove_solvency(
+        &self,
+        id: &Pubkey,
+        submission: &SolvencySubmission,
+    ) -> Result<Offer, OfferBookError> {
+        let offer = self.offer(id)?;
+        let proof = self.verify_proof(&submission.proof)?;
+        let solvency = MakerSolvency::try_from_slice(proof.public_values.as_slice())
+            .map_err(|_| OfferBookError::InvalidSolvency)?;
+        if solvency.maker_wallet != offer.maker_wallet
+            || solvency.token_account != offer.maker_src_account
+            || solvency.maker_mint != offer.maker_mint
+            || solvency.maker_size != offer.maker_size
+        {
+            return Err(OfferBookError::SolvencyMismatch);
+        }
+        if !solvency.solvent {
+            return Err(OfferBookError::Insolvent(solvency.slot));
+        }
+        self.check_bank_hash(solvency.slot, &solvency.bank_hash)?;
+
+        let mut offers = self.offers.write().expect("offer book lock poisoned");
+        let offer = offers
+            .get_mut(id)
+            .ok_or(OfferBookError::UnknownOffer(*id))?;
+        if offer.funded_at_slot < Some(solvency.slot) {
+            offer.funded_at_slot = Some(solvency.slot);
+        }
+        Ok(offer.clone())
+    }
+
+    /// All stored offers selling `maker_mint` for `taker_mint`.
+    pub fn offers_for_pair(&self, maker_mint: &Pubkey, taker_mint: &Pubkey) -> Vec<Offer> {
+        self.offers
+            .read()
+            .expect("offer book lock poisoned")
+            .values()
+            .filter(|offer| offer.maker_mint == *maker_mint && offer.taker_mint == *taker_mint)
+            .cloned()
+            .collect()
+    }
+
+    pub fn offer(&self, id: &Pubkey) -> Result<Offer, OfferBookError> {
+        self.offers
+            .read()
+            .expect("offer book lock poisoned")
+            .get(id)
+            .cloned()
+            .ok_or(OfferBookError::UnknownOffer(*id))
+    }
+
+    /// Build the unsigned message a taker signs to fill `id`.
+    ///
+    /// The delegation is re-checked first and the offer is dropped from the book if it went
+    /// stale since it was submitted.
+    pub fn accept_message(&self, id: &Pubkey, taker: &Pubkey) -> Result<Message, OfferBookError> {
+        let offer = self.offer(id)?;
+        if let Err(err) = self.check_delegation(&offer) {
+            self.offers
+                .write()
+                .expect("offer book lock poisoned")
+                .remove(id);
+            return Err(err);
+        }
+        let taker_src_account = if offer.is_native {
+            *taker
+        } else {
+            get_associated_token_address(taker, &offer.taker_mint)
+        };
+        let taker_dst_account = get_associated_token_address(taker, &offer.maker_mint);
+        let nft_metadata = self.nft_metadata(&offer)?;
+        let instruction = match &nft_metadata {
+            Some((mint, metadata_data)) => {
+                stateless_asks::instruction::accept_offer_with_metadata_account(
+                    &self.program_id,
+                    &offer.maker_wallet,
+                    taker,
+                    &offer.maker_src_account,
+                    &offer.maker_dst_account,
+                    &taker_src_account,
+                    &taker_dst_account,
+                    &offer.maker_mint,
+                    &offer.taker_mint,
+                    &offer.authority,
+                    &spl_token::id(),
+                    metadata_data,
+                    offer.is_native,
+                    offer.platform_fee.as_ref(),
+                    offer.maker_size,
+                    offer.taker_size,
+                    offer.bump_seed,
+                )
+                .map_err(|_| OfferBookError::InvalidMetadata(*mint))?
+            }
+            None => stateless_asks::instruction::accept_offer(
+                &self.program_id,
+                &offer.maker_wallet,
+                taker,
+                &offer.maker_src_account,
+                &offer.maker_dst_account,
+                &taker_src_account,
+                &taker_dst_account,
+                &offer.maker_mint,
+                &offer.taker_mint,
+                &offer.authority,
+                &spl_token::id(),
+                offer.is_native,
+                offer.platform_fee.as_ref(),
+                offer.maker_size,
+                offer.taker_size,
+                offer.bump_seed,
+            ),
+        };
+        if offer.maker_mint != spl_token::native_mint::id() {
+            return self.message(taker, vec![instruction]);
+        }
+
+        // SOL offers pay out wrapped SOL, so create the receiving accounts and unwrap after,
+        // unless the taker already held wrapped SOL the unwrap would take with it.
+        let mut fee_recipients: Vec<Pubkey> = nft_metadata
+            .as_ref()
+            .and_then(|(_, data)| decode_metadata(data).ok())
+            .and_then(|metadata| metadata.data.creators)
+            .into_iter()
+            .flatten()
+            .map(|creator| creator.address)
+            .collect();
+        fee_recipients.extend(offer.platform_fee.map(|fee| fee.recipient));
+        let wrapped_account_existed = self
+            .accounts
+            .account_data(&wrapped_sol_account(taker))
+            .map_err(|e| OfferBookError::AccountSource(e.to_string()))?
+            .is_some();
+        let mut instructions = prepare_native_fill(taker, &fee_recipients);
+        instructions.push(instruction);
+        instructions.extend(finish_native_fill(taker, wrapped_account_existed));
+        self.message(taker, instructions)
+    }
+
+    /// Compile `instructions` paid by `taker`, with the compute budget applied if configured.
+    fn message(
+        &self,
+        taker: &Pubkey,
+        instructions: Vec<Instruction>,
+    ) -> Result<Message, OfferBookError> {
+        let instructions = match &self.compute_budget {
+            Some(compute_budget) => compute_budget
+                .apply(taker, instructions)
+                .map_err(|e| OfferBookError::ComputeBudget(format!("{:#}", e)))?,
+            None => instructions,
+        };
+        Ok(Message::new(&instructions, Some(taker)))
+    }
+
+    /// The Token Metadata account data of whichever leg is an NFT, maker leg first.
+    fn nft_metadata(&self, offer: &Offer) -> Result<Option<(Pubkey, Vec<u8>)>, OfferBookError> {
+        let mints = if offer.is_native {
+            vec![offer.maker_mint]
+        } else {
+            vec![offer.maker_mint, offer.taker_mint]
+        };
+        for mint in mints {
+            let (metadata_key, _) = find_metadata_address(&mint);
+            let data = self
+                .accounts
+                .account_data(&metadata_key)
+                .map_err(|e| OfferBookError::AccountSource(e.to_string()))?;
+            if let Some(data) = data {
+                return Ok(Some((mint, data)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn offer_from_public_values(
+        &self,
+        maker_wallet: Pubkey,
+        public_values: &PublicValuesStruct,
+        submission: &OfferSubmission,
+    ) -> Result<Offer, OfferBookError> {
+        let taker_mint = match (public_values.taker_mint, public_values.is_native) {
+            (Some(taker_mint), _) => taker_mint,
+            (None, true) => spl_token::native_mint::id(),
+            (None, false) => return Err(OfferBookError::MissingTakerMint),
+        };
+        let taker_size = match (public_values.taker_size, submission.taker_size) {
+            (Some(committed), Some(submitted)) if committed != submitted => {
+                return Err(OfferBookError::TakerSizeMismatch)
+            }
+            (Some(taker_size), _) | (None, Some(taker_size)) => taker_size,
+            (None, None) => return Err(OfferBookError::MissingTakerSize),
+        };
+        if matches!(&public_values.platform_fee, Some(fee) if !fee.is_valid()) {
+            return Err(OfferBookError::InvalidPlatformFee);
+        }
+        let is_native = taker_mint == spl_token::native_mint::id();
+        let (authority, bump_seed) = compute_offer_authority(
+            &self.program_id,
+            &maker_wallet,
+            &public_values.maker_mint,
+            &taker_mint,
+            public_values.maker_size,
+            taker_size,
+            public_values.platform_fee.as_ref(),
+        );
+        let maker_dst_account = if is_native {
+            maker_wallet
+        } else {
+            get_associated_token_address(&maker_wallet, &taker_mint)
+        };
+        Ok(Offer {
+            authority,
+            bump_seed,
+            maker_wallet,
+            maker_src_account: get_associated_token_address(
+                &maker_wallet,
+                &public_values.maker_mint,
+            ),
+            maker_dst_account,
+            maker_mint: public_values.maker_mint,
+            taker_mint,
+            is_native,
+            maker_size: public_values.maker_size,
+            taker_size,
+            platform_fee: public_values.platform_fee,
+            funded_at_slot: None,
+        })
+    }
+
+    /// Decode a base64 bincode-encoded proof and verify it against the offer program.
+    fn verify_proof(&self, proof: &str) -> Result<SP1ProofWithPublicValues, OfferBookError> {
+        let proof_bytes = STANDARD
+            .decode(proof)
+            .map_err(|e| OfferBookError::InvalidProofEncoding(e.to_string()))?;
+        let proof: SP1ProofWithPublicValues = bincode::deserialize(&proof_bytes)
+            .map_err(|e| OfferBookError::InvalidProofEncoding(e.to_string()))?;
+        self.prover
+            .verify(&proof, &self.vk)
+            .map_err(|e| OfferBookError::InvalidProof(e.to_string()))?;
+        Ok(proof)
+    }
+
+    /// Check `bank_hash` against the SlotHashes sysvar, which lists the last 512 slots.
+    fn check_bank_hash(&self, slot: u64, bank_hash: &[u8; 32]) -> Result<(), OfferBookError> {
+        let data = self
+            .accounts
+            .account_data(&slot_hashes::id())
+            .map_err(|e| OfferBookError::AccountSource(e.to_string()))?
+            .ok_or(OfferBookError::AccountNotFound(slot_hashes::id()))?;
+        let slot_hashes: SlotHashes = bincode::deserialize(&data)
+            .map_err(|e| OfferBookError::AccountSource(e.to_string()))?;
+        match slot_hashes.get(&slot) {
+            Some(hash) if hash.to_bytes() == *bank_hash => Ok(()),
+            _ => Err(OfferBookError::UnknownBankHash(slot)),
+        }
+    }
+
+    fn check_delegation(&self, offer: &Offer) -> Result<(), OfferBookError> {
+        let data = self
+            .accounts
+            .account_data(&offer.maker_src_account)
+            .map_err(|e| OfferBookError::AccountSource(e.to_string()))?;
+        let health = check_offer_health(
+            &self.program_id,
+            &offer.terms(),
+            &offer.maker_src_account,
+            data.as_deref(),
+        );
+        match health.issues.first() {
+            None => Ok(()),
+            Some(OfferIssue::MissingAccount) => {
+                Err(OfferBookError::AccountNotFound(offer.maker_src_account))
+            }
+            Some(issue) => Err(OfferBookError::Delegation(issue.to_string())),
+        }
+    }
+}
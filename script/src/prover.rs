@@ -0,0 +1,290 @@
+//! Prover backend selection for the zk-offers scripts.
+//!
+//! Every binary used to call `ProverClient::new()` and prove locally. This module lets the caller
+//! pick between a `local` prover, a `mock` prover (no real proving, usable in CI) and the Succinct
+//! prover `network`. Network proof request IDs are written to disk before polling starts so that
+//! a crashed script picks up the same request instead of paying for a new one. The ID is removed
+//! once the request is fulfilled or the network reports it failed, unfulfillable or expired, so a
+//! dead request is never resumed. A polling error leaves it for the next run to resume.
+
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use sha2::{Digest, Sha256};
+use sp1_sdk::{
+    proto::network::ProofMode, NetworkProver, ProverClient, SP1ProofWithPublicValues,
+    SP1ProvingKey, SP1Stdin, SP1VerifyingKey,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::cache::ProofCache;
+
+/// The backend used to generate proofs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ProverBackend {
+    /// Prove on this machine.
+    Local,
+    /// Produce mock proofs without running the prover.
+    Mock,
+    /// Prove on the Succinct prover network.
+    Network,
+}
+
+/// The kind of proof to generate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ProofKind {
+    Core,
+    Compressed,
+    Plonk,
+    Groth16,
+}
+
+impl ProofKind {
+    fn network_mode(self) -> ProofMode {
+        match self {
+            ProofKind::Core => ProofMode::Core,
+            ProofKind::Compressed => ProofMode::Compressed,
+            ProofKind::Plonk => ProofMode::Plonk,
+            ProofKind::Groth16 => ProofMode::Groth16,
+        }
+    }
+}
+
+/// Command-line arguments shared by every binary that generates proofs.
+#[derive(Clone, Debug, Args)]
+pub struct ProverArgs {
+    /// Prover backend, `local`, `mock` or `network`.
+    #[clap(long = "prover", env = "SP1_PROVER", value_enum, default_value = "local")]
+    pub backend: ProverBackend,
+
+    /// Directory where pending network proof request IDs are persisted.
+    #[clap(long, env = "SP1_PROOF_REQUESTS_DIR", default_value = "proof-requests")]
+    pub proof_requests_dir: PathBuf,
+
+    /// Directory holding cached proving keys and proofs.
+    #[clap(long, env = "SP1_PROOF_CACHE_DIR", default_value = "proof-cache")]
+    pub proof_cache_dir: PathBuf,
+
+    /// Always set up and prove from scratch, ignoring the proof cache.
+    #[clap(long)]
+    pub no_proof_cache: bool,
+}
+
+/// A prover client bound to the backend selected on the command line.
+pub struct OfferProver {
+    backend: ProverBackend,
+    client: ProverClient,
+    requests_dir: PathBuf,
+    cache_dir: Option<PathBuf>,
+}
+
+impl OfferProver {
+    pub fn new(args: &ProverArgs) -> Self {
+        let client = match args.backend {
+            ProverBackend::Local => ProverClient::local(),
+            ProverBackend::Mock => ProverClient::mock(),
+            ProverBackend::Network => ProverClient::network(),
+        };
+        Self {
+            backend: args.backend,
+            client,
+            requests_dir: args.proof_requests_dir.clone(),
+            cache_dir: (!args.no_proof_cache).then(|| args.proof_cache_dir.clone()),
+        }
+    }
+
+    pub fn backend(&self) -> ProverBackend {
+        self.backend
+    }
+
+    pub fn client(&self) -> &ProverClient {
+        &self.client
+    }
+
+    /// Set up the program, reusing cached keys for an unchanged ELF.
+    pub fn setup(&self, elf: &[u8]) -> (SP1ProvingKey, SP1VerifyingKey) {
+        let cache = self.cache(elf);
+        if let Some(keys) = cache.as_ref().and_then(ProofCache::load_keys) {
+            tracing::info!("Using cached proving key");
+            return keys;
+        }
+        let (pk, vk) = self.client.setup(elf);
+        if let Some(cache) = cache {
+            if let Err(err) = cache.store_keys(&pk, &vk) {
+                tracing::warn!("Failed to cache proving key: {:#}", err);
+            }
+        }
+        (pk, vk)
+    }
+
+    /// Generate a proof of the given kind, reusing a cached proof for identical inputs.
+    ///
+    /// On the network backend the request ID is persisted under the requests directory, keyed by
+    /// the ELF, stdin and proof kind, and an existing ID is resumed instead of requesting again.
+    /// The ID is dropped once the request is fulfilled or fails.
+    pub fn prove(
+        &self,
+        pk: &SP1ProvingKey,
+        stdin: SP1Stdin,
+        kind: ProofKind,
+    ) -> Result<SP1ProofWithPublicValues> {
+        let mock = self.backend == ProverBackend::Mock;
+        let cache = self.cache(&pk.elf);
+        if let Some(cache) = &cache {
+            if let Some(proof) = cache.load_proof(&pk.vk, &stdin, kind, mock)? {
+                tracing::info!("Using cached proof");
+                return Ok(proof);
+            }
+        }
+        let proof = self.prove_uncached(pk, stdin.clone(), kind)?;
+        if let Some(cache) = &cache {
+            if let Err(err) = cache.store_proof(&pk.vk, &stdin, kind, mock, &proof) {
+                tracing::warn!("Failed to cache proof: {:#}", err);
+            }
+        }
+        Ok(proof)
+    }
+
+    fn prove_uncached(
+        &self,
+        pk: &SP1ProvingKey,
+        stdin: SP1Stdin,
+        kind: ProofKind,
+    ) -> Result<SP1ProofWithPublicValues> {
+        match self.backend {
+            ProverBackend::Network => self.prove_network(pk, stdin, kind),
+            ProverBackend::Local | ProverBackend::Mock => {
+                let builder = self.client.prove(pk, stdin);
+                match kind {
+                    ProofKind::Core => builder.run(),
+                    ProofKind::Compressed => builder.compressed().run(),
+                    ProofKind::Plonk => builder.plonk().run(),
+                    ProofKind::Groth16 => builder.groth16().run(),
+                }
+            }
+        }
+    }
+
+    pub fn verify(&self, proof: &SP1ProofWithPublicValues, vk: &SP1VerifyingKey) -> Result<()> {
+        self.client.verify(proof, vk)?;
+        Ok(())
+    }
+
+    fn cache(&self, elf: &[u8]) -> Option<ProofCache> {
+        let dir = self.cache_dir.as_ref()?;
+        match ProofCache::open(dir, elf) {
+            Ok(cache) => Some(cache),
+            Err(err) => {
+                tracing::warn!("Proof cache disabled: {:#}", err);
+                None
+            }
+        }
+    }
+
+    fn prove_network(
+        &self,
+        pk: &SP1ProvingKey,
+        stdin: SP1Stdin,
+        kind: ProofKind,
+    ) -> Result<SP1ProofWithPublicValues> {
+        let request = ProofRequest::new(&self.requests_dir, &pk.elf, &stdin, kind)?;
+        let network = NetworkProver::new();
+        let runtime = tokio::runtime::Runtime::new()?;
+
+        let proof_id = match request.pending_id() {
+            Some(proof_id) => {
+                tracing::info!("Resuming proof request {}", proof_id);
+                proof_id
+            }
+            None => {
+                let proof_id =
+                    runtime.block_on(network.request_proof(&pk.elf, stdin, kind.network_mode()))?;
+                request.record(&proof_id)?;
+                tracing::info!("Created proof request {}", proof_id);
+                proof_id
+            }
+        };
+
+        let proof: Result<SP1ProofWithPublicValues> =
+            runtime.block_on(network.wait_proof(&proof_id, None));
+        if request.settle(&proof) {
+            proof.with_context(|| format!("proof request {} failed", proof_id))
+        } else {
+            proof.with_context(|| {
+                format!(
+                    "failed to poll proof request {}, run again to resume it",
+                    proof_id
+                )
+            })
+        }
+    }
+}
+
+/// The file persisting the ID of a network proof request, keyed by the ELF, stdin and proof kind
+/// it proves.
+pub struct ProofRequest {
+    path: PathBuf,
+}
+
+impl ProofRequest {
+    pub fn new(dir: &Path, elf: &[u8], stdin: &SP1Stdin, kind: ProofKind) -> Result<Self> {
+        let mut hasher = Sha256::new();
+        hasher.update(elf);
+        hasher.update(bincode::serialize(stdin).context("failed to serialize stdin")?);
+        hasher.update([kind as u8]);
+        let key = hex::encode(hasher.finalize());
+        Ok(Self {
+            path: dir.join(format!("{}.request", key)),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The ID of a request made for these inputs and not settled yet, if any.
+    pub fn pending_id(&self) -> Option<String> {
+        let proof_id = fs::read_to_string(&self.path).ok()?;
+        let proof_id = proof_id.trim();
+        (!proof_id.is_empty()).then(|| proof_id.to_string())
+    }
+
+    /// Persist `proof_id` so that a crashed script resumes it.
+    pub fn record(&self, proof_id: &str) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).context("failed to create proof requests directory")?;
+        }
+        fs::write(&self.path, proof_id).context("failed to persist proof request ID")
+    }
+
+    /// Drop the persisted ID once the request is fulfilled or has failed.
+    pub fn forget(&self) {
+        let _ = fs::remove_file(&self.path);
+    }
+
+    /// Forget the request if waiting on it settled it, and tell whether it did.
+    ///
+    /// A proof, or an error with which the network reports the request failed, unfulfillable or
+    /// expired, settles it. Any other error is taken for a transport or polling failure, and the
+    /// ID is kept so that the next run resumes the request it already paid for.
+    pub fn settle<T>(&self, outcome: &Result<T>) -> bool {
+        let settled = match outcome {
+            Ok(_) => true,
+            Err(err) => is_settled_failure(err),
+        };
+        if settled {
+            self.forget();
+        }
+        settled
+    }
+}
+
+/// Whether `err`, returned while waiting on a network request, is the network's final answer.
+fn is_settled_failure(err: &anyhow::Error) -> bool {
+    let message = format!("{:#}", err).to_lowercase();
+    ["generation failed", "unfulfillable", "unclaimed", "expired"]
+        .iter()
+        .any(|failure| message.contains(failure))
+}
@@ -0,0 +1,128 @@
+//! HTTP routes in front of an [`OfferBook`].
+//!
+//! Routes:
+//! - `POST /offers` submits an [`OfferSubmission`] and returns the stored offer.
+//! - `GET /offers?maker_mint=..&taker_mint=..` lists the offers for a mint pair.
+//! - `GET /offers/:id/accept?taker=..` returns a base64 bincode-encoded message ready to sign.
+//! - `POST /offers/:id/solvency` submits a [`SolvencySubmission`] and returns the updated offer.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::offer_book::{
+    parse_pubkey, OfferBook, OfferBookError, OfferSubmission, OfferView, SolvencySubmission,
+};
+
+/// The offer book routes, serving `book`.
+pub fn router(book: Arc<OfferBook>) -> Router {
+    Router::new()
+        .route("/offers", post(submit_offer).get(list_offers))
+        .route("/offers/:id/accept", get(accept_offer))
+        .route("/offers/:id/solvency", post(prove_solvency))
+        .with_state(book)
+}
+
+#[derive(Deserialize)]
+struct PairQuery {
+    maker_mint: String,
+    taker_mint: String,
+}
+
+#[derive(Deserialize)]
+struct AcceptQuery {
+    taker: String,
+}
+
+#[derive(Serialize)]
+struct AcceptResponse {
+    offer: OfferView,
+    message: String,
+}
+
+struct ApiError(OfferBookError);
+
+impl From<OfferBookError> for ApiError {
+    fn from(err: OfferBookError) -> Self {
+        Self(err)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match self.0 {
+            OfferBookError::UnknownOffer(_) => StatusCode::NOT_FOUND,
+            OfferBookError::AccountSource(_) | OfferBookError::ComputeBudget(_) => {
+                StatusCode::BAD_GATEWAY
+            }
+            _ => StatusCode::BAD_REQUEST,
+        };
+        let body = serde_json::json!({ "error": self.0.to_string() });
+        (status, Json(body)).into_response()
+    }
+}
+
+async fn submit_offer(
+    State(book): State<Arc<OfferBook>>,
+    Json(submission): Json<OfferSubmission>,
+) -> Result<(StatusCode, Json<OfferView>), ApiError> {
+    let offer = tokio::task::spawn_blocking(move || book.submit(&submission))
+        .await
+        .expect("offer validation panicked")?;
+    tracing::info!("Stored offer {}", offer.authority);
+    Ok((StatusCode::CREATED, Json(OfferView::from(&offer))))
+}
+
+async fn prove_solvency(
+    State(book): State<Arc<OfferBook>>,
+    Path(id): Path<String>,
+    Json(submission): Json<SolvencySubmission>,
+) -> Result<Json<OfferView>, ApiError> {
+    let id = parse_pubkey(&id)?;
+    let offer = tokio::task::spawn_blocking(move || book.prove_solvency(&id, &submission))
+        .await
+        .expect("solvency validation panicked")?;
+    tracing::info!(
+        "Offer {} proven funded at slot {:?}",
+        offer.authority,
+        offer.funded_at_slot
+    );
+    Ok(Json(OfferView::from(&offer)))
+}
+
+async fn list_offers(
+    State(book): State<Arc<OfferBook>>,
+    Query(query): Query<PairQuery>,
+) -> Result<Json<Vec<OfferView>>, ApiError> {
+    let maker_mint = parse_pubkey(&query.maker_mint)?;
+    let taker_mint = parse_pubkey(&query.taker_mint)?;
+    let offers = book.offers_for_pair(&maker_mint, &taker_mint);
+    Ok(Json(offers.iter().map(OfferView::from).collect()))
+}
+
+async fn accept_offer(
+    State(book): State<Arc<OfferBook>>,
+    Path(id): Path<String>,
+    Query(query): Query<AcceptQuery>,
+) -> Result<Json<AcceptResponse>, ApiError> {
+    let id = parse_pubkey(&id)?;
+    let taker = parse_pubkey(&query.taker)?;
+    let (offer, message) = tokio::task::spawn_blocking(move || {
+        let message = book.accept_message(&id, &taker)?;
+        Ok::<_, OfferBookError>((book.offer(&id)?, message))
+    })
+    .await
+    .expect("accept message builder panicked")?;
+    let message = bincode::serialize(&message).expect("Failed to serialize message");
+    Ok(Json(AcceptResponse {
+        offer: OfferView::from(&offer),
+        message: STANDARD.encode(message),
+    }))
+}
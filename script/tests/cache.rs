@@ -0,0 +1,29 @@
+//! Checks that opening the proof cache only prunes the entries it created for other ELFs.
+
+use sha2::{Digest, Sha256};
+use std::fs;
+use zk_offers_script::cache::ProofCache;
+
+#[test]
+fn opening_the_cache_leaves_foreign_entries_alone() {
+    let root = std::env::temp_dir().join(format!("proof-cache-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("notes")).unwrap();
+    fs::write(root.join("notes/todo.txt"), "keep me").unwrap();
+    fs::write(root.join("README"), "keep me").unwrap();
+
+    ProofCache::open(&root, b"old elf").unwrap();
+    let old_entry = root.join(hex::encode(Sha256::digest(b"old elf")));
+    assert!(old_entry.is_dir());
+
+    ProofCache::open(&root, b"new elf").unwrap();
+    assert!(!old_entry.exists());
+    assert!(root.join(hex::encode(Sha256::digest(b"new elf"))).is_dir());
+    assert_eq!(
+        fs::read_to_string(root.join("notes/todo.txt")).unwrap(),
+        "keep me"
+    );
+    assert!(root.join("README").is_file());
+
+    fs::remove_dir_all(&root).unwrap();
+}
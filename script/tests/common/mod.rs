@@ -0,0 +1,159 @@
+//! The offer shared by the script tests, and an offer book serving it.
+#![allow(dead_code)]
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_program::{
+    instruction::Instruction, program_option::COption, program_pack::Pack, pubkey::Pubkey,
+};
+use solana_zk_offers::zk_offers::{compute_offer_authority, PlatformFee, PublicValuesStruct};
+use sp1_sdk::{SP1Proof, SP1ProofWithPublicValues, SP1PublicValues, SP1Stdin};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::state::{Account, AccountState};
+use stateless_asks::instruction::accept_offer;
+use std::path::PathBuf;
+use zk_offers_script::{
+    accounts::StaticAccountSource,
+    offer_book::{OfferBook, OfferSubmission},
+    prover::{OfferProver, ProverArgs, ProverBackend},
+};
+
+/// The checked-in guest, whose verification key the offer book checks proofs against.
+pub const ZKVM_ELF: &[u8] = include_bytes!("../../../elf/riscv32im-succinct-zkvm-elf");
+
+pub const MAKER_SIZE: u64 = 1;
+pub const TAKER_SIZE: u64 = 1_000;
+
+/// A maker offering `MAKER_SIZE` of a fresh mint for `TAKER_SIZE` of another.
+pub struct Offer {
+    pub program_id: Pubkey,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub maker_mint: Pubkey,
+    pub taker_mint: Pubkey,
+    pub platform_fee: Option<PlatformFee>,
+}
+
+impl Offer {
+    pub fn new() -> Self {
+        Self {
+            program_id: Pubkey::new_unique(),
+            maker: Pubkey::new_unique(),
+            taker: Pubkey::new_unique(),
+            maker_mint: Pubkey::new_unique(),
+            taker_mint: Pubkey::new_unique(),
+            platform_fee: None,
+        }
+    }
+
+    /// [`new`](Self::new) charging a 1% platform fee.
+    pub fn with_platform_fee() -> Self {
+        Self {
+            platform_fee: Some(PlatformFee {
+                basis_points: 100,
+                recipient: Pubkey::new_unique(),
+            }),
+            ..Self::new()
+        }
+    }
+
+    pub fn maker_src_account(&self) -> Pubkey {
+        get_associated_token_address(&self.maker, &self.maker_mint)
+    }
+
+    pub fn authority(&self) -> (Pubkey, u8) {
+        compute_offer_authority(
+            &self.program_id,
+            &self.maker,
+            &self.maker_mint,
+            &self.taker_mint,
+            MAKER_SIZE,
+            TAKER_SIZE,
+            self.platform_fee.as_ref(),
+        )
+    }
+
+    /// The fill of the offer, signed with `bump_seed` for its transfer authority.
+    pub fn accept_offer_with_bump(&self, bump_seed: u8) -> Instruction {
+        accept_offer(
+            &self.program_id,
+            &self.maker,
+            &self.taker,
+            &self.maker_src_account(),
+            &get_associated_token_address(&self.maker, &self.taker_mint),
+            &get_associated_token_address(&self.taker, &self.taker_mint),
+            &get_associated_token_address(&self.taker, &self.maker_mint),
+            &self.maker_mint,
+            &self.taker_mint,
+            &self.authority().0,
+            &spl_token::id(),
+            false,
+            self.platform_fee.as_ref(),
+            MAKER_SIZE,
+            TAKER_SIZE,
+            bump_seed,
+        )
+    }
+
+    pub fn accept_offer(&self) -> Instruction {
+        self.accept_offer_with_bump(self.authority().1)
+    }
+
+    pub fn public_values(&self) -> PublicValuesStruct {
+        PublicValuesStruct::fill_terms(
+            self.maker_mint,
+            self.taker_mint,
+            MAKER_SIZE,
+            TAKER_SIZE,
+            self.platform_fee,
+        )
+    }
+
+    /// The maker's token account holding `amount`, with `MAKER_SIZE` delegated to the offer.
+    pub fn maker_src_data(&self, amount: u64) -> Vec<u8> {
+        let mut data = vec![0; Account::LEN];
+        Account {
+            mint: self.maker_mint,
+            owner: self.maker,
+            amount,
+            delegate: COption::Some(self.authority().0),
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: MAKER_SIZE,
+            close_authority: COption::None,
+        }
+        .pack_into_slice(&mut data);
+        data
+    }
+
+    /// A submission of the offer with a mock proof of its terms, which the mock prover accepts
+    /// without running the guest.
+    pub fn submission(&self) -> OfferSubmission {
+        let proof = SP1ProofWithPublicValues {
+            proof: SP1Proof::Core(vec![]),
+            stdin: SP1Stdin::new(),
+            public_values: SP1PublicValues::from(
+                self.public_values().to_canonical_bytes().as_slice(),
+            ),
+            sp1_version: String::new(),
+        };
+        OfferSubmission {
+            maker_wallet: self.maker.to_string(),
+            taker_size: None,
+            proof: STANDARD.encode(bincode::serialize(&proof).unwrap()),
+        }
+    }
+
+    /// An offer book of the offer's program reading the maker's token account holding `amount`.
+    pub fn offer_book(&self, amount: u64) -> OfferBook {
+        let mut accounts = StaticAccountSource::default();
+        accounts.insert(self.maker_src_account(), self.maker_src_data(amount));
+        let prover = OfferProver::new(&ProverArgs {
+            backend: ProverBackend::Mock,
+            proof_requests_dir: PathBuf::from("proof-requests"),
+            proof_cache_dir: PathBuf::from("proof-cache"),
+            no_proof_cache: true,
+        });
+        let (_, vk) = prover.setup(ZKVM_ELF);
+        OfferBook::new(self.program_id, prover, vk, Box::new(accounts))
+    }
+}
@@ -0,0 +1,83 @@
+//! Checks the priority fee strategies and the compute budget instructions prepended to
+//! transactions.
+
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+use solana_zk_offers::compute_budget::{
+    self, set_compute_unit_limit, set_compute_unit_price, unit_limit_with_margin,
+    with_compute_budget, MAX_COMPUTE_UNIT_LIMIT,
+};
+use zk_offers_script::compute_budget::{percentile_of, PriorityFee};
+
+#[test]
+fn priority_fees_parse_from_the_command_line() {
+    assert_eq!("none".parse(), Ok(PriorityFee::None));
+    assert_eq!("fixed:1000".parse(), Ok(PriorityFee::Fixed(1_000)));
+    assert_eq!("recent".parse(), Ok(PriorityFee::Recent(50)));
+    assert_eq!("recent:90".parse(), Ok(PriorityFee::Recent(90)));
+    assert_eq!("recent:100".parse(), Ok(PriorityFee::Recent(100)));
+    for invalid in [
+        "",
+        "fixed",
+        "fixed:",
+        "fixed:-1",
+        "recent:101",
+        "recent:x",
+        "none:1",
+        "max",
+    ] {
+        assert!(invalid.parse::<PriorityFee>().is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn percentiles_pick_from_the_sorted_samples() {
+    let fees = [10, 20, 30, 40, 50];
+    assert_eq!(percentile_of(&fees, 0), Some(10));
+    assert_eq!(percentile_of(&fees, 50), Some(30));
+    assert_eq!(percentile_of(&fees, 75), Some(40));
+    assert_eq!(percentile_of(&fees, 100), Some(50));
+    assert_eq!(percentile_of(&[7], 90), Some(7));
+    assert_eq!(percentile_of(&[], 50), None);
+}
+
+#[test]
+fn margins_are_capped_at_the_highest_limit() {
+    assert_eq!(unit_limit_with_margin(100_000, 10), 110_000);
+    assert_eq!(unit_limit_with_margin(100_000, 0), 100_000);
+    assert_eq!(
+        unit_limit_with_margin(1_300_000, 10),
+        MAX_COMPUTE_UNIT_LIMIT
+    );
+    assert_eq!(
+        unit_limit_with_margin(u64::MAX, u32::MAX),
+        MAX_COMPUTE_UNIT_LIMIT
+    );
+}
+
+#[test]
+fn budget_instructions_replace_earlier_ones() {
+    let fill = Instruction::new_with_bytes(
+        Pubkey::new_unique(),
+        &[1],
+        vec![AccountMeta::new(Pubkey::new_unique(), true)],
+    );
+    let placeholder = vec![set_compute_unit_limit(1), fill.clone()];
+
+    assert_eq!(
+        with_compute_budget(placeholder.clone(), Some(200_000), Some(5)),
+        vec![
+            set_compute_unit_limit(200_000),
+            set_compute_unit_price(5),
+            fill.clone()
+        ]
+    );
+    assert_eq!(
+        with_compute_budget(placeholder.clone(), None, Some(5)),
+        vec![set_compute_unit_price(5), fill.clone()]
+    );
+    assert_eq!(with_compute_budget(placeholder, None, None), vec![fill]);
+    assert_eq!(set_compute_unit_limit(1).program_id, compute_budget::id());
+}
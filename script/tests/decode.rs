@@ -0,0 +1,165 @@
+//! Checks that the decoder labels the accounts of built instructions without false mismatches,
+//! and flags the ones a broken client gets wrong.
+
+mod common;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use common::{Offer, MAKER_SIZE, TAKER_SIZE};
+use solana_program::{instruction::Instruction, message::Message, pubkey::Pubkey};
+use solana_sdk::transaction::{Transaction, VersionedTransaction};
+use solana_zk_offers::metadata::{Creator, Data, Metadata};
+use spl_associated_token_account::get_associated_token_address;
+use stateless_asks::instruction::accept_offer_with_metadata_account;
+use zk_offers_script::decode::{
+    decode_bytes, decode_instruction, decode_transaction, DecodedInstruction, Encoding,
+};
+
+impl Offer {
+    fn metadata_data(&self) -> Vec<u8> {
+        borsh::to_vec(&Metadata {
+            key: 4,
+            update_authority: Pubkey::new_unique(),
+            mint: self.maker_mint,
+            data: Data {
+                name: String::new(),
+                symbol: String::new(),
+                uri: String::new(),
+                seller_fee_basis_points: 500,
+                creators: Some(vec![
+                    Creator {
+                        address: Pubkey::new_unique(),
+                        verified: true,
+                        share: 60,
+                    },
+                    Creator {
+                        address: Pubkey::new_unique(),
+                        verified: false,
+                        share: 40,
+                    },
+                ]),
+            },
+            primary_sale_happened: true,
+            is_mutable: false,
+            edition_nonce: None,
+            token_standard: None,
+            collection: None,
+        })
+        .unwrap()
+    }
+
+    fn accept_offer_with_metadata(&self) -> Instruction {
+        let (authority, bump_seed) = self.authority();
+        accept_offer_with_metadata_account(
+            &self.program_id,
+            &self.maker,
+            &self.taker,
+            &get_associated_token_address(&self.maker, &self.maker_mint),
+            &get_associated_token_address(&self.maker, &self.taker_mint),
+            &get_associated_token_address(&self.taker, &self.taker_mint),
+            &get_associated_token_address(&self.taker, &self.maker_mint),
+            &self.maker_mint,
+            &self.taker_mint,
+            &authority,
+            &spl_token::id(),
+            &self.metadata_data(),
+            false,
+            self.platform_fee.as_ref(),
+            MAKER_SIZE,
+            TAKER_SIZE,
+            bump_seed,
+        )
+        .unwrap()
+    }
+}
+
+fn decode(program_id: &Pubkey, instruction: &Instruction) -> DecodedInstruction {
+    let accounts: Vec<Option<Pubkey>> = instruction
+        .accounts
+        .iter()
+        .map(|account| Some(account.pubkey))
+        .collect();
+    decode_instruction(Some(program_id), &instruction.data, &accounts).unwrap()
+}
+
+fn roles(decoded: &DecodedInstruction) -> Vec<&str> {
+    decoded
+        .accounts
+        .iter()
+        .map(|account| account.role.as_str())
+        .collect()
+}
+
+#[test]
+fn built_accept_offer_is_consistent() {
+    let offer = Offer::with_platform_fee();
+    let decoded = decode(&offer.program_id, &offer.accept_offer_with_metadata());
+    assert!(decoded.is_consistent(), "{}", decoded);
+    assert_eq!(
+        roles(&decoded),
+        [
+            "maker_wallet",
+            "taker_wallet",
+            "maker_src_account",
+            "maker_dst_account",
+            "taker_src_account",
+            "taker_dst_account",
+            "maker_mint",
+            "taker_mint",
+            "transfer_authority",
+            "token_program",
+            "metadata",
+            "creator 0",
+            "creator 0 token account",
+            "creator 1",
+            "creator 1 token account",
+            "platform_fee_account",
+        ]
+    );
+}
+
+#[test]
+fn wrong_accounts_are_flagged() {
+    let offer = Offer::with_platform_fee();
+    let mut instruction = offer.accept_offer_with_metadata();
+    // The maker's wallet instead of its ATA, and a creator paid in the wrong mint.
+    instruction.accounts[2].pubkey = offer.maker;
+    instruction.accounts[12].pubkey =
+        get_associated_token_address(&instruction.accounts[11].pubkey, &offer.maker_mint);
+    let decoded = decode(&offer.program_id, &instruction);
+    let mismatches: Vec<&str> = decoded
+        .mismatches()
+        .map(|account| account.role.as_str())
+        .collect();
+    assert_eq!(mismatches, ["maker_src_account", "creator 0 token account"]);
+}
+
+#[test]
+fn wrong_bump_and_missing_accounts_are_noted() {
+    let offer = Offer::with_platform_fee();
+    let mut instruction = offer.accept_offer_with_bump(offer.authority().1.wrapping_sub(1));
+    instruction.accounts.pop();
+    let decoded = decode(&offer.program_id, &instruction);
+    assert_eq!(decoded.mismatches().count(), 0);
+    assert_eq!(decoded.notes.len(), 2, "{}", decoded);
+}
+
+#[test]
+fn transactions_decode_from_base64_and_base58() {
+    let offer = Offer::with_platform_fee();
+    let instruction = offer.accept_offer_with_metadata();
+    let transaction = VersionedTransaction::from(Transaction::new_unsigned(Message::new(
+        &[instruction],
+        Some(&offer.taker),
+    )));
+    let bytes = bincode::serialize(&transaction).unwrap();
+    for encoded in [STANDARD.encode(&bytes), bs58::encode(&bytes).into_string()] {
+        let decoded = decode_transaction(
+            Some(&offer.program_id),
+            &decode_bytes(&encoded, Encoding::Auto).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].index, Some(0));
+        assert!(decoded[0].is_consistent(), "{}", decoded[0]);
+    }
+}
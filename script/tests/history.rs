@@ -0,0 +1,208 @@
+//! Checks that the indexer reads fills, cancellations and delegations out of `getTransaction`
+//! responses.
+
+mod common;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use common::{Offer, MAKER_SIZE, TAKER_SIZE};
+use serde_json::json;
+use solana_program::{instruction::Instruction, message::Message, pubkey::Pubkey};
+use solana_sdk::transaction::Transaction;
+use solana_zk_offers::events::{FeePaid, FillEvent};
+use zk_offers_script::history::{
+    match_offer_approvals, parse_transaction_dump, transaction_history, CancellationKind,
+    TransactionHistory,
+};
+
+impl Offer {
+    fn approve(&self) -> Instruction {
+        spl_token::instruction::approve(
+            &spl_token::id(),
+            &self.maker_src_account(),
+            &self.authority().0,
+            &self.maker,
+            &[],
+            MAKER_SIZE,
+        )
+        .unwrap()
+    }
+
+    fn revoke(&self) -> Instruction {
+        spl_token::instruction::revoke(
+            &spl_token::id(),
+            &self.maker_src_account(),
+            &self.maker,
+            &[],
+        )
+        .unwrap()
+    }
+
+    fn fill_event(&self) -> FillEvent {
+        FillEvent {
+            offer: self.authority().0,
+            maker_wallet: self.maker,
+            taker_wallet: self.taker,
+            maker_mint: self.maker_mint,
+            taker_mint: self.taker_mint,
+            maker_size: MAKER_SIZE,
+            taker_size: TAKER_SIZE,
+            taker_received: MAKER_SIZE,
+            maker_received: 950,
+            fee_mint: self.taker_mint,
+            creator_fees: vec![FeePaid {
+                recipient: Pubkey::new_unique(),
+                amount: 50,
+            }],
+            platform_fee: None,
+        }
+    }
+
+    /// The history of a transaction of `instructions` signed by the maker, whose source token
+    /// account is listed in the token balances.
+    fn history(
+        &self,
+        instructions: &[Instruction],
+        logs: Vec<String>,
+        err: Option<serde_json::Value>,
+    ) -> TransactionHistory {
+        let message = Message::new(instructions, Some(&self.maker));
+        let account_index = message
+            .account_keys
+            .iter()
+            .position(|key| *key == self.maker_src_account())
+            .unwrap();
+        let transaction = Transaction::new_unsigned(message);
+        let status = match &err {
+            Some(err) => json!({ "Err": err }),
+            None => json!({ "Ok": null }),
+        };
+        let dump = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": {
+                "slot": 100,
+                "blockTime": 1_700_000_000,
+                "version": "legacy",
+                "transaction": [STANDARD.encode(bincode::serialize(&transaction).unwrap()), "base64"],
+                "meta": {
+                    "err": err,
+                    "status": status,
+                    "fee": 5000,
+                    "preBalances": [],
+                    "postBalances": [],
+                    "logMessages": logs,
+                    "preTokenBalances": [{
+                        "accountIndex": account_index,
+                        "mint": self.maker_mint.to_string(),
+                        "owner": self.maker.to_string(),
+                        "programId": spl_token::id().to_string(),
+                        "uiTokenAmount": {
+                            "uiAmount": 1.0,
+                            "decimals": 0,
+                            "amount": "1",
+                            "uiAmountString": "1",
+                        },
+                    }],
+                    "postTokenBalances": [],
+                },
+            },
+        });
+        let transaction = parse_transaction_dump(&dump.to_string()).unwrap();
+        transaction_history(&self.program_id, &transaction).unwrap()
+    }
+}
+
+#[test]
+fn fills_without_events_are_read_from_instructions() {
+    let offer = Offer::new();
+    let history = offer.history(&[offer.approve(), offer.accept_offer()], vec![], None);
+    assert_eq!(history.slot, 100);
+    assert!(history.err.is_none());
+
+    assert_eq!(history.delegations.len(), 1);
+    let delegation = &history.delegations[0];
+    assert_eq!(delegation.wallet, offer.maker);
+    assert_eq!(delegation.token_account, offer.maker_src_account());
+    assert_eq!(delegation.delegate, offer.authority().0);
+    assert_eq!(delegation.mint, Some(offer.maker_mint));
+    assert_eq!(delegation.amount, MAKER_SIZE);
+    assert!(!delegation.to_router);
+
+    assert_eq!(history.fills.len(), 1);
+    let fill = &history.fills[0];
+    assert_eq!(fill.offer, offer.authority().0);
+    assert_eq!(fill.maker_wallet, offer.maker);
+    assert_eq!(fill.taker_wallet, offer.taker);
+    assert_eq!(fill.maker_mint, offer.maker_mint);
+    assert_eq!(fill.taker_mint, offer.taker_mint);
+    assert_eq!(fill.maker_size, Some(MAKER_SIZE));
+    assert_eq!(fill.taker_size, Some(TAKER_SIZE));
+    assert!(fill.payout.is_none());
+}
+
+#[test]
+fn fill_events_replace_instruction_fills() {
+    let offer = Offer::new();
+    let event = offer.fill_event();
+    let logs = vec![
+        format!("Program {} invoke [1]", offer.program_id),
+        format!("Program data: {}", STANDARD.encode(event.to_log_data())),
+        format!("Program {} success", offer.program_id),
+    ];
+    let history = offer.history(&[offer.accept_offer()], logs, None);
+    assert_eq!(history.fills.len(), 1);
+    let payout = history.fills[0].payout.as_ref().unwrap();
+    assert_eq!(payout.maker_received, event.maker_received);
+    assert_eq!(payout.creator_fees, event.creator_fees);
+}
+
+#[test]
+fn revokes_are_cancellations_and_failed_transactions_are_skipped() {
+    let offer = Offer::new();
+    let history = offer.history(&[offer.revoke(), offer.accept_offer()], vec![], None);
+    assert_eq!(history.cancellations.len(), 1);
+    let cancellation = &history.cancellations[0];
+    assert_eq!(cancellation.kind, CancellationKind::Revoke);
+    assert_eq!(cancellation.wallet, offer.maker);
+    assert_eq!(cancellation.account, offer.maker_src_account());
+    assert_eq!(cancellation.mint, Some(offer.maker_mint));
+
+    let err = json!({ "InstructionError": [1, { "Custom": 1 }] });
+    let history = offer.history(&[offer.approve(), offer.accept_offer()], vec![], Some(err));
+    assert!(history.err.is_some());
+    assert!(history.fills.is_empty());
+    assert!(history.delegations.is_empty());
+}
+
+#[test]
+fn approvals_outside_program_transactions_wait_for_a_fill() {
+    let offer = Offer::new();
+    let other = Offer::new();
+    let mut histories = vec![
+        offer.history(&[offer.approve()], vec![], None),
+        other.history(&[other.approve()], vec![], None),
+        offer.history(&[offer.accept_offer()], vec![], None),
+        offer.history(&[offer.revoke()], vec![], None),
+        other.history(&[other.revoke()], vec![], None),
+    ];
+    assert!(histories[0].delegations.is_empty());
+    assert_eq!(histories[0].unmatched_delegations.len(), 1);
+    assert!(histories[3].cancellations.is_empty());
+    assert_eq!(histories[3].unmatched_revokes.len(), 1);
+
+    match_offer_approvals(&mut histories);
+    // The approval is filled through, so it and the revoke of its token account are offer
+    // records. The approval to an address the program never fills through is not.
+    assert_eq!(histories[0].delegations.len(), 1);
+    assert_eq!(histories[0].delegations[0].delegate, offer.authority().0);
+    assert!(histories[0].unmatched_delegations.is_empty());
+    assert_eq!(histories[3].cancellations.len(), 1);
+    assert_eq!(
+        histories[3].cancellations[0].account,
+        offer.maker_src_account()
+    );
+    assert!(histories[1].delegations.is_empty());
+    assert_eq!(histories[1].unmatched_delegations.len(), 1);
+    assert!(histories[4].cancellations.is_empty());
+    assert_eq!(histories[4].unmatched_revokes.len(), 1);
+}
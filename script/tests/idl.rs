@@ -0,0 +1,155 @@
+//! Checks that the checked-in IDL matches the program interface.
+
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+use solana_zk_offers::zk_offers::PublicValuesStruct;
+use stateless_asks::instruction::{
+    accept_offer, close_escrow_offer, close_offer_batch, close_routed_offer, close_sealed_bid,
+    commit_sealed_bid, init_escrow_offer, init_routed_offer, init_sealed_auction, post_offer_batch,
+    reveal_sealed_bid, INSTRUCTION_HEADER_LEN,
+};
+use zk_offers_script::idl::{idl, idl_json, IDL_PATH};
+
+#[test]
+fn checked_in_idl_is_up_to_date() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("..")
+        .join(IDL_PATH);
+    let checked_in = std::fs::read_to_string(&path).expect("IDL is checked in");
+    assert!(
+        checked_in == idl_json(),
+        "{} is stale, regenerate it with `cargo run --bin idl`",
+        IDL_PATH
+    );
+}
+
+/// Built without any of the accounts the IDL marks optional.
+fn built_instructions() -> Vec<Instruction> {
+    let program_id = Pubkey::new_unique();
+    let [maker, taker, account, mint] = [(); 4].map(|_| Pubkey::new_unique());
+    let token_program = spl_token::id();
+    let public_values = PublicValuesStruct {
+        maker_mint: mint,
+        taker_mint: Some(Pubkey::new_unique()),
+        is_native: false,
+        maker_size: 1,
+        taker_size: Some(1_000),
+        platform_fee: None,
+    };
+    vec![
+        accept_offer(
+            &program_id,
+            &maker,
+            &taker,
+            &account,
+            &account,
+            &account,
+            &account,
+            &mint,
+            &mint,
+            &account,
+            &token_program,
+            false,
+            None,
+            1,
+            1_000,
+            0,
+        ),
+        init_escrow_offer(
+            &program_id,
+            &maker,
+            &account,
+            &token_program,
+            public_values.clone(),
+            0,
+            0,
+        ),
+        close_escrow_offer(&program_id, &maker, &account, &mint, &token_program),
+        init_routed_offer(&program_id, &maker, &account, public_values.clone(), 0, 0),
+        close_routed_offer(&program_id, &maker, &account),
+        init_sealed_auction(
+            &program_id,
+            &maker,
+            &account,
+            &token_program,
+            public_values,
+            0,
+            0,
+            [0; 32],
+            1,
+            2,
+        ),
+        commit_sealed_bid(
+            &program_id,
+            &taker,
+            &account,
+            &account,
+            &mint,
+            &token_program,
+            0,
+            [0; 32],
+            1_000,
+        ),
+        reveal_sealed_bid(
+            &program_id,
+            &taker,
+            &account,
+            &account,
+            &mint,
+            &token_program,
+            1_000,
+            vec![],
+        ),
+        close_sealed_bid(
+            &program_id,
+            &taker,
+            &maker,
+            &account,
+            &account,
+            &mint,
+            &token_program,
+        ),
+        post_offer_batch(
+            &program_id,
+            &maker,
+            &account,
+            0,
+            [0; 32],
+            [0; 32],
+            1,
+            vec![],
+        ),
+        close_offer_batch(&program_id, &maker, &account),
+    ]
+}
+
+#[test]
+fn idl_accounts_match_the_instruction_builders() {
+    let idl = idl();
+    for instruction in built_instructions() {
+        let discriminator = &instruction.data[..INSTRUCTION_HEADER_LEN];
+        let idl_instruction = idl
+            .instructions
+            .iter()
+            .find(|idl_instruction| idl_instruction.discriminator == discriminator)
+            .expect("every instruction is in the IDL");
+        let accounts: Vec<_> = idl_instruction
+            .accounts
+            .iter()
+            .filter(|account| !account.optional)
+            .collect();
+        assert_eq!(
+            accounts.len(),
+            instruction.accounts.len(),
+            "{}",
+            idl_instruction.name
+        );
+        for (account, meta) in accounts.iter().zip(&instruction.accounts) {
+            let name = format!("{}.{}", idl_instruction.name, account.name);
+            assert_eq!(account.writable, meta.is_writable, "{}", name);
+            assert_eq!(account.signer, meta.is_signer, "{}", name);
+            if let Some(address) = &account.address {
+                assert_eq!(*address, meta.pubkey.to_string(), "{}", name);
+            }
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Checks which submissions the offer book stores and lists.
+
+mod common;
+
+use common::{Offer, MAKER_SIZE, TAKER_SIZE};
+use zk_offers_script::offer_book::{OfferBookError, OfferSubmission};
+
+#[test]
+fn submitted_offers_are_listed_for_their_pair() {
+    let offer = Offer::new();
+    let book = offer.offer_book(MAKER_SIZE);
+    let stored = book.submit(&offer.submission()).unwrap();
+    assert_eq!(stored.authority, offer.authority().0);
+    assert_eq!(stored.maker_src_account, offer.maker_src_account());
+    assert_eq!(stored.taker_size, TAKER_SIZE);
+
+    let listed = book.offers_for_pair(&offer.maker_mint, &offer.taker_mint);
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].authority, stored.authority);
+    assert!(book
+        .offers_for_pair(&offer.taker_mint, &offer.maker_mint)
+        .is_empty());
+    assert_eq!(
+        book.offer(&stored.authority).unwrap().maker_wallet,
+        offer.maker
+    );
+}
+
+#[test]
+fn offers_the_maker_balance_does_not_cover_are_rejected() {
+    // The delegation alone is in order, only the balance behind it is missing.
+    let offer = Offer::new();
+    let book = offer.offer_book(MAKER_SIZE - 1);
+    let err = book.submit(&offer.submission()).unwrap_err();
+    assert!(
+        matches!(&err, OfferBookError::Delegation(issue) if issue.contains("balance")),
+        "{}",
+        err
+    );
+    assert!(book
+        .offers_for_pair(&offer.maker_mint, &offer.taker_mint)
+        .is_empty());
+}
+
+#[test]
+fn submitted_taker_sizes_must_match_the_committed_one() {
+    let offer = Offer::new();
+    let book = offer.offer_book(MAKER_SIZE);
+    let submission = OfferSubmission {
+        taker_size: Some(TAKER_SIZE + 1),
+        ..offer.submission()
+    };
+    assert!(matches!(
+        book.submit(&submission),
+        Err(OfferBookError::TakerSizeMismatch)
+    ));
+}
@@ -0,0 +1,80 @@
+//! Checks how network proof request IDs are keyed, resumed and dropped.
+
+use anyhow::anyhow;
+use sp1_sdk::SP1Stdin;
+use std::fs;
+use zk_offers_script::prover::{ProofKind, ProofRequest};
+
+fn stdin(value: u64) -> SP1Stdin {
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&value);
+    stdin
+}
+
+#[test]
+fn requests_are_keyed_by_elf_stdin_and_kind() {
+    let dir = std::env::temp_dir().join("proof-requests");
+    let path = |elf: &[u8], stdin: &SP1Stdin, kind| {
+        ProofRequest::new(&dir, elf, stdin, kind)
+            .unwrap()
+            .path()
+            .to_path_buf()
+    };
+    let request = path(b"elf", &stdin(1), ProofKind::Core);
+    assert_eq!(request, path(b"elf", &stdin(1), ProofKind::Core));
+    assert_eq!(request.parent().unwrap(), dir);
+    assert_eq!(request.extension().unwrap(), "request");
+    assert_ne!(request, path(b"other elf", &stdin(1), ProofKind::Core));
+    assert_ne!(request, path(b"elf", &stdin(2), ProofKind::Core));
+    assert_ne!(request, path(b"elf", &stdin(1), ProofKind::Groth16));
+}
+
+#[test]
+fn requests_are_resumed_until_settled() {
+    let dir = std::env::temp_dir().join(format!("proof-requests-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let request = ProofRequest::new(&dir, b"elf", &stdin(1), ProofKind::Plonk).unwrap();
+    assert_eq!(request.pending_id(), None);
+
+    // Recording creates the directory, and a later run resumes the same request.
+    request.record("0xabc").unwrap();
+    let resumed = ProofRequest::new(&dir, b"elf", &stdin(1), ProofKind::Plonk).unwrap();
+    assert_eq!(resumed.pending_id().as_deref(), Some("0xabc"));
+
+    // Once the request is fulfilled or fails, the next run requests a new proof.
+    resumed.forget();
+    assert_eq!(request.pending_id(), None);
+    request.forget();
+
+    // A file left empty by a crash mid-write holds no request to resume.
+    fs::write(request.path(), "\n").unwrap();
+    assert_eq!(request.pending_id(), None);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn requests_survive_polling_errors() {
+    let dir = std::env::temp_dir().join(format!("proof-requests-settle-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let request = ProofRequest::new(&dir, b"elf", &stdin(1), ProofKind::Groth16).unwrap();
+    request.record("0xabc").unwrap();
+
+    // A dropped connection while polling leaves the paid request for the next run to resume.
+    let polling_error: anyhow::Result<()> = Err(anyhow!("error sending request: connection reset"));
+    assert!(!request.settle(&polling_error));
+    let resumed = ProofRequest::new(&dir, b"elf", &stdin(1), ProofKind::Groth16).unwrap();
+    assert_eq!(resumed.pending_id().as_deref(), Some("0xabc"));
+
+    // The network reporting the request failed settles it.
+    let failed: anyhow::Result<()> = Err(anyhow!("Proof generation failed: unexecutable"));
+    assert!(resumed.settle(&failed));
+    assert_eq!(request.pending_id(), None);
+
+    // So does a fulfilled request.
+    request.record("0xdef").unwrap();
+    assert!(request.settle(&Ok(())));
+    assert_eq!(resumed.pending_id(), None);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,81 @@
+//! Checks the offer book routes end to end, with the server feature:
+//! ```shell
+//! cargo test --features server --test server
+//! ```
+#![cfg(feature = "server")]
+
+mod common;
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{header::CONTENT_TYPE, Request, StatusCode},
+    Router,
+};
+use common::{Offer, MAKER_SIZE};
+use serde_json::Value;
+use std::sync::Arc;
+use tower::ServiceExt;
+use zk_offers_script::server::router;
+
+/// Send `request` to `app` and return the status and JSON body of the response.
+async fn call(app: &Router, request: Request<Body>) -> (StatusCode, Value) {
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn post_offer(offer: &Offer) -> Request<Body> {
+    let submission = offer.submission();
+    let body = serde_json::json!({
+        "maker_wallet": submission.maker_wallet,
+        "taker_size": submission.taker_size,
+        "proof": submission.proof,
+    });
+    Request::post("/offers")
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+fn list_offers(offer: &Offer) -> Request<Body> {
+    Request::get(format!(
+        "/offers?maker_mint={}&taker_mint={}",
+        offer.maker_mint, offer.taker_mint
+    ))
+    .body(Body::empty())
+    .unwrap()
+}
+
+#[tokio::test]
+async fn posted_offers_are_listed_for_their_pair() {
+    let offer = Offer::new();
+    let app = router(Arc::new(offer.offer_book(MAKER_SIZE)));
+
+    let (status, stored) = call(&app, post_offer(&offer)).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(stored["id"], offer.authority().0.to_string());
+    assert_eq!(stored["maker_wallet"], offer.maker.to_string());
+
+    let (status, listed) = call(&app, list_offers(&offer)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(listed, Value::Array(vec![stored]));
+}
+
+#[tokio::test]
+async fn posted_offers_the_maker_balance_does_not_cover_are_rejected() {
+    let offer = Offer::new();
+    let app = router(Arc::new(offer.offer_book(MAKER_SIZE - 1)));
+
+    let (status, body) = call(&app, post_offer(&offer)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        body["error"].as_str().unwrap().contains("balance"),
+        "{}",
+        body
+    );
+
+    let (status, listed) = call(&app, list_offers(&offer)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(listed, Value::Array(vec![]));
+}
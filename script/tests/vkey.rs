@@ -0,0 +1,18 @@
+//! Checks that the guest verification key pinned for the on-chain program is the one of the ELF
+//! the scripts prove with.
+
+use solana_zk_offers::{sealed_bid::format_vkey_hash, zk_offers::OFFER_VKEY_HASH};
+use sp1_sdk::{HashableKey, ProverClient};
+
+const ZKVM_ELF: &[u8] = include_bytes!("../../elf/riscv32im-succinct-zkvm-elf");
+
+#[test]
+fn pinned_vkey_hash_matches_the_checked_in_elf() {
+    // Setting up computes the real verification key, the mock prover only skips proving.
+    let (_, vk) = ProverClient::mock().setup(ZKVM_ELF);
+    assert_eq!(
+        format_vkey_hash(&OFFER_VKEY_HASH),
+        vk.bytes32(),
+        "update OFFER_VKEY_HASH in lib/src/lib.rs with the hash the vkey script prints"
+    );
+}